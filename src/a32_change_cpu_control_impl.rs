@@ -0,0 +1,124 @@
+use super::*;
+
+pub fn a32_change_cpu_control_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut args = split_args(token_stream).into_iter();
+  let scratch = scratch_arg(args.next())?;
+  let mut errors = Errors::default();
+
+  let mut mode: Option<u8> = None;
+  let mut irq_masked: Option<bool> = None;
+  let mut fiq_masked: Option<bool> = None;
+  let mut seen: Vec<String> = Vec::new();
+  for arg in args {
+    let Some((name, value)) = get_named_arg(&arg) else {
+      errors.push(Error::new(
+        args_span(&arg),
+        "arguments must be of the form `name = value`",
+      ));
+      continue;
+    };
+    if !["mode", "irq_masked", "fiq_masked"].contains(&name.as_str()) {
+      errors.push(Error::new(
+        args_span(&arg),
+        format!("unknown argument `{name}`"),
+      ));
+      continue;
+    }
+    if seen.contains(&name) {
+      errors
+        .push(Error::new(args_span(&arg), format!("`{name}` is given twice")));
+    }
+    seen.push(name.clone());
+    match (name.as_str(), value) {
+      ("mode", [tree]) if cpu_mode_bits(&tree.to_string()).is_some() => {
+        mode = cpu_mode_bits(&tree.to_string())
+      }
+      ("mode", _) => errors.push(Error::new(
+        args_span(value),
+        format!("`mode` must be one of: {}", cpu_mode_names()),
+      )),
+      (_, [tree]) if get_bool(tree).is_some() => {
+        if name == "irq_masked" {
+          irq_masked = get_bool(tree);
+        } else {
+          fiq_masked = get_bool(tree);
+        }
+      }
+      _ => errors.push(Error::new(
+        args_span(value),
+        format!("`{name}` must be set as `true` or `false`"),
+      )),
+    }
+  }
+  if seen.is_empty() {
+    errors.push(Error::call_site(
+      "nothing to change, give at least one of `mode`, `irq_masked`, or \
+       `fiq_masked`",
+    ));
+  }
+  errors.finish()?;
+
+  let lines = change_cpu_control_lines(&scratch, mode, irq_masked, fiq_masked);
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
+
+/// Gets the scratch register, which must be the first argument.
+pub fn scratch_arg(arg: Option<Vec<TokenTree>>) -> Result<String, Error> {
+  let arg =
+    arg.ok_or_else(|| Error::call_site("missing the scratch register"))?;
+  let scratch = match arg.as_slice() {
+    [tree] => get_reg_text(tree),
+    _ => None,
+  }
+  .ok_or_else(|| {
+    Error::new(
+      args_span(&arg),
+      "the first argument must be the scratch register, as a string literal \
+       or a register name",
+    )
+  })?;
+  check_reg(&scratch, args_span(&arg))?;
+  if matches!(parse_reg(&scratch), Some(13 | 15)) {
+    return Err(Error::new(
+      args_span(&arg),
+      format!(
+        "can't use `{scratch}` as the scratch register, using `sp` or `pc` \
+         with `mrs`/`msr` is unpredictable"
+      ),
+    ));
+  }
+  Ok(scratch)
+}
+
+/// The `mrs`/`bic`/`orr`/`msr` lines that change only the given CPSR control
+/// bits, leaving the rest as they are at runtime.
+pub fn change_cpu_control_lines(
+  scratch: &str, mode: Option<u8>, irq_masked: Option<bool>,
+  fiq_masked: Option<bool>,
+) -> Vec<String> {
+  // Each field is the bits it covers and the bits of it that should be set.
+  let fields = [
+    mode.map(|bits| (0b1_1111, bits)),
+    irq_masked.map(|masked| (0b1000_0000, (masked as u8) << 7)),
+    fiq_masked.map(|masked| (0b0100_0000, (masked as u8) << 6)),
+  ];
+  let mut clear = 0_u8;
+  let mut set = 0_u8;
+  for (field, bits) in fields.into_iter().flatten() {
+    clear |= field & !bits;
+    set |= bits;
+  }
+
+  let mut lines = vec![format!("mrs {scratch}, CPSR")];
+  if clear != 0 {
+    lines.push(format!("bic {scratch}, {scratch}, #{clear:#X}"));
+  }
+  if set != 0 {
+    lines.push(format!("orr {scratch}, {scratch}, #{set:#X}"));
+  }
+  lines.push(format!("msr CPSR_c, {scratch}"));
+  lines
+}
@@ -1,9 +1,81 @@
 use super::*;
 
-pub fn a32_fake_blx_impl(token_stream: TokenStream) -> TokenStream {
-  let reg_name = one_str_literal_or_panic(token_stream);
+pub fn a32_fake_blx_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let trees: Vec<TokenTree> = token_stream.into_iter().collect();
+  let comma = trees
+    .iter()
+    .position(|tree| matches!(tree, TokenTree::Punct(p) if *p == ','));
+  let (reg_trees, arch) = match comma {
+    None => (trees.as_slice(), Arch::V4),
+    Some(i) => (&trees[..i], parse_arch_arg(&trees[i], &trees[i + 1..])?),
+  };
+  let (reg_name, span) =
+    one_reg(TokenStream::from_iter(reg_trees.iter().cloned()))?;
+  check_target(&reg_name, span, arch >= Arch::V5T)?;
 
-  TokenStream::from(TokenTree::Literal(Literal::string(&format!(
-    "add lr, pc, #0\nbx {reg_name}"
-  ))))
+  if arch >= Arch::V5T {
+    return Ok(TokenStream::from(str_literal_at(
+      &format!("blx {reg_name}"),
+      span,
+    )));
+  }
+  Ok(TokenStream::from(str_literal_at(
+    &format!("add lr, pc, #0\nbx {reg_name}"),
+    span,
+  )))
+}
+
+pub fn t32_fake_blx_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let (reg_name, span) = one_reg(token_stream)?;
+  check_target(&reg_name, span, false)?;
+
+  // `bl` is the only way to set `lr` with the t32 bit, so it "calls" the `bx`
+  // two instructions ahead, and the callee returns to the `bl` over the `bx`.
+  // A `b .+4` would be shorter, but LLVM can't assemble it.
+  Ok(TokenStream::from(str_literal_at(
+    &format!("bl .+8\nbl .+6\nbx {reg_name}"),
+    span,
+  )))
+}
+
+/// Checks that a register can hold the target address of a call.
+///
+/// Unless `lr_ok`, `lr` is rejected since it's overwritten with the return
+/// address before the branch that uses the target.
+fn check_target(reg_name: &str, span: Span, lr_ok: bool) -> Result<(), Error> {
+  check_reg(reg_name, span)?;
+  let why = match parse_reg(reg_name) {
+    Some(13) => "it's the stack pointer, not a code address",
+    Some(14) if !lr_ok => {
+      "`lr` is overwritten with the return address before the `bx`"
+    }
+    Some(15) => "it's the program counter, not a code address",
+    _ => return Ok(()),
+  };
+  Err(Error::new(span, format!("`{reg_name}` can't be the target, {why}")))
+}
+
+/// Parses the `arch = ...` that can follow the register.
+fn parse_arch_arg(
+  comma: &TokenTree, rest: &[TokenTree],
+) -> Result<Arch, Error> {
+  match get_named_arg(rest) {
+    Some((name, [tree])) if name == "arch" => Arch::from_option(tree),
+    Some((name, _)) if name == "arch" => Err(Error::new(
+      args_span(&rest[2..]),
+      "`arch` must be one arch name, eg: `v5te`",
+    )),
+    Some((other, _)) => {
+      Err(Error::new(args_span(rest), format!("unknown argument `{other}`")))
+    }
+    // a trailing comma, or a second register
+    None => Err(Error::new(
+      rest.first().map_or(comma.span(), TokenTree::span),
+      ONE_REG_ONLY,
+    )),
+  }
 }
@@ -1,9 +1,52 @@
 use super::*;
 
-pub fn a32_read_spsr_to_impl(token_stream: TokenStream) -> TokenStream {
-  let reg_name = one_str_literal_or_panic(token_stream);
+pub fn a32_read_spsr_to_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  read_status_reg(token_stream, "SPSR")
+}
+
+pub fn a32_read_cpsr_to_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  read_status_reg(token_stream, "CPSR")
+}
+
+pub fn a32_get_cpu_mode_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let (reg_name, span) = read_target(token_stream, "CPSR")?;
+  Ok(TokenStream::from(str_literal_at(
+    &format!("mrs {reg_name}, CPSR\nand {reg_name}, {reg_name}, #0x1F"),
+    span,
+  )))
+}
+
+/// Reads the status register named (`CPSR` or `SPSR`) with `mrs`.
+fn read_status_reg(
+  token_stream: TokenStream, status_reg: &str,
+) -> Result<TokenStream, Error> {
+  let (reg_name, span) = read_target(token_stream, status_reg)?;
+  Ok(TokenStream::from(str_literal_at(
+    &format!("mrs {reg_name}, {status_reg}"),
+    span,
+  )))
+}
 
-  TokenStream::from(TokenTree::Literal(Literal::string(&format!(
-    "mrs {reg_name}, SPSR"
-  ))))
+/// Gets the register that a status register is read into.
+fn read_target(
+  token_stream: TokenStream, status_reg: &str,
+) -> Result<(String, Span), Error> {
+  let (reg_name, span) = one_reg(token_stream)?;
+  check_reg(&reg_name, span)?;
+  if matches!(parse_reg(&reg_name), Some(13 | 15)) {
+    return Err(Error::new(
+      span,
+      format!(
+        "can't read {status_reg} into `{reg_name}`, using `sp` or `pc` with \
+         `mrs` is unpredictable"
+      ),
+    ));
+  }
+  Ok((reg_name, span))
 }
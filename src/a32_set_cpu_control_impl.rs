@@ -1,60 +1,143 @@
 use super::*;
 
-pub fn a32_set_cpu_control_impl(token_stream: TokenStream) -> TokenStream {
-  let mut stream_iter = token_stream.into_iter();
-  // CPSR low bits are: `I F T MMMMM`, and T must always be left as 0.
+pub fn a32_set_cpu_control_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let (mode, irq_masked, fiq_masked) = parse_cpu_control(token_stream)?;
+  Ok(TokenStream::from_iter(Some(TokenTree::Literal(Literal::string(
+    &set_cpu_control_asm(mode, irq_masked, fiq_masked),
+  )))))
+}
+
+pub fn cpu_control_bits_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let (mode, irq_masked, fiq_masked) = parse_cpu_control(token_stream)?;
+  Ok(TokenStream::from(TokenTree::Literal(Literal::u32_suffixed(
+    cpu_control_byte(mode, irq_masked, fiq_masked).into(),
+  ))))
+}
 
-  // processor modes bits reference:
-  // https://developer.arm.com/documentation/ddi0406/c/System-Level-Architecture/The-System-Level-Programmers--Model/ARM-processor-modes-and-ARM-core-registers/ARM-processor-modes?lang=en
-  let mode =
-    match stream_iter.next().expect("too few tokens").to_string().as_str() {
-      "User" | "usr" => "10000",
-      "FIQ" | "fiq" => "10001",
-      "IRQ" | "irq" => "10010",
-      "Supervisor" | "svc" => "10011",
-      "System" | "sys" => "11111",
+/// Parses `{mode_name}` and then any `irq_masked = {bool}` and
+/// `fiq_masked = {bool}` settings, in either order.
+///
+/// An omitted setting defaults to `true`. Every problem is reported, not just
+/// the first one.
+fn parse_cpu_control(
+  token_stream: TokenStream,
+) -> Result<(u8, bool, bool), Error> {
+  let mut stream_iter = token_stream.into_iter().peekable();
+  let mut errors = Errors::default();
+
+  let mode_tree =
+    stream_iter.next().ok_or_else(|| Error::call_site("too few tokens"))?;
+  let mode = match cpu_mode_bits(&mode_tree.to_string()) {
+    Some(mode) => mode,
+    None => {
+      errors.push(Error::new(
+        mode_tree.span(),
+        format!(
+          "First argument must be a valid cpu mode name, got `{mode_tree}`, \
+           expected one of: {}",
+          cpu_mode_names()
+        ),
+      ));
+      0
+    }
+  };
+  // A missing comma is recorded, but the settings are still checked as though
+  // it had simply been forgotten.
+  match stream_iter.peek() {
+    Some(TokenTree::Punct(p)) if *p == ',' => {
+      stream_iter.next();
+    }
+    Some(other) => errors
+      .push(Error::new(other.span(), "must have comma after the first arg")),
+    None => (),
+  }
+
+  let mut irq_masked: Option<bool> = None;
+  let mut fiq_masked: Option<bool> = None;
+  for arg in split_args(stream_iter.collect()) {
+    let Some((name, value)) = get_named_arg(&arg) else {
+      errors.push(Error::new(
+        args_span(&arg),
+        "arguments must be of the form `name = value`",
+      ));
+      continue;
+    };
+    let setting = match name.as_str() {
+      "irq_masked" => &mut irq_masked,
+      "fiq_masked" => &mut fiq_masked,
       other => {
-        panic!("First argument must be a valid cpu mode name, got `{other}`")
+        errors.push(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        ));
+        continue;
       }
     };
-  assert_eq!(
-    stream_iter.next().expect("too few tokens").to_string(),
-    ",",
-    "must have comma after the first arg"
-  );
-
-  assert_eq!(
-    stream_iter.next().expect("too few tokens").to_string(),
-    "irq_masked",
-    "second setting must be `irq_masked`"
-  );
-  assert_eq!(
-    stream_iter.next().expect("too few tokens").to_string(),
-    "=",
-    "after `irq_masked` must be a `=`"
-  );
-  let i = get_bool(&stream_iter.next().expect("too few tokens"))
-    .expect("`irq_masked` must be set as `true` or `false`") as u8;
-  assert_eq!(
-    stream_iter.next().expect("too few tokens").to_string(),
-    ",",
-    "must have comma after the second arg"
-  );
-
-  assert_eq!(
-    stream_iter.next().expect("too few tokens").to_string(),
-    "fiq_masked",
-    "third setting must be `fiq_masked`"
-  );
-  assert_eq!(
-    stream_iter.next().expect("too few tokens").to_string(),
-    "=",
-    "after `fiq_masked` must be a `=`"
-  );
-  let f = get_bool(&stream_iter.next().expect("too few tokens"))
-    .expect("`fiq_masked` must be set as `true` or `false`") as u8;
-  assert!(stream_iter.next().is_none(), "too many tokens");
-  TokenStream::from_iter(Some(TokenTree::Literal(Literal::string(&format!(
-    "msr CPSR_c, #0b{i}{f}0{mode}"
-  )))))
+    if setting.is_some() {
+      errors
+        .push(Error::new(args_span(&arg), format!("`{name}` is given twice")));
+    }
+    match value {
+      [tree] if get_bool(tree).is_some() => *setting = get_bool(tree),
+      _ => errors.push(Error::new(
+        args_span(value),
+        format!("`{name}` must be set as `true` or `false`"),
+      )),
+    }
+  }
+
+  errors.finish()?;
+  Ok((mode, irq_masked.unwrap_or(true), fiq_masked.unwrap_or(true)))
+}
+
+/// The long name, short name, and CPSR mode bits of each CPU mode.
+// processor modes bits reference:
+// https://developer.arm.com/documentation/ddi0406/c/System-Level-Architecture/The-System-Level-Programmers--Model/ARM-processor-modes-and-ARM-core-registers/ARM-processor-modes?lang=en
+const CPU_MODES: &[(&str, &str, u8)] = &[
+  ("User", "usr", 0b10000),
+  ("FIQ", "fiq", 0b10001),
+  ("IRQ", "irq", 0b10010),
+  ("Supervisor", "svc", 0b10011),
+  ("Abort", "abt", 0b10111),
+  ("Undefined", "und", 0b11011),
+  ("System", "sys", 0b11111),
+];
+
+/// Gets the CPSR mode bits for the long or short name of a mode.
+pub fn cpu_mode_bits(name: &str) -> Option<u8> {
+  CPU_MODES
+    .iter()
+    .find(|(long, short, _)| name == *long || name == *short)
+    .map(|(.., bits)| *bits)
+}
+
+/// All of the accepted mode names, for error messages.
+pub fn cpu_mode_names() -> String {
+  CPU_MODES
+    .iter()
+    .map(|(long, short, _)| format!("`{long}`/`{short}`"))
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+/// The low byte of the CPSR, holding all of the control bits.
+pub fn cpu_control_byte(
+  mode_bits: u8, irq_masked: bool, fiq_masked: bool,
+) -> u8 {
+  // CPSR low bits are: `I F T MMMMM`, and T must always be left as 0.
+  let i = irq_masked as u8;
+  let f = fiq_masked as u8;
+  (i << 7) | (f << 6) | mode_bits
+}
+
+/// The `msr` instruction that sets all of the CPSR control bits.
+pub fn set_cpu_control_asm(
+  mode_bits: u8, irq_masked: bool, fiq_masked: bool,
+) -> String {
+  let byte = cpu_control_byte(mode_bits, irq_masked, fiq_masked);
+  format!("msr CPSR_c, #{byte:#010b}")
 }
@@ -0,0 +1,71 @@
+use super::*;
+
+pub fn a32_set_interrupt_masks_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut args = split_args(token_stream).into_iter();
+  let scratch = scratch_arg(args.next())?;
+  let mut errors = Errors::default();
+
+  // `None` is not given, `Some(None)` is `keep`.
+  let mut irq_masked: Option<Option<bool>> = None;
+  let mut fiq_masked: Option<Option<bool>> = None;
+  for arg in args {
+    let Some((name, value)) = get_named_arg(&arg) else {
+      errors.push(Error::new(
+        args_span(&arg),
+        "arguments must be of the form `name = value`",
+      ));
+      continue;
+    };
+    let setting = match name.as_str() {
+      "irq_masked" => &mut irq_masked,
+      "fiq_masked" => &mut fiq_masked,
+      other => {
+        errors.push(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        ));
+        continue;
+      }
+    };
+    if setting.is_some() {
+      errors
+        .push(Error::new(args_span(&arg), format!("`{name}` is given twice")));
+    }
+    match value {
+      [TokenTree::Ident(i)] if i.to_string() == "keep" => *setting = Some(None),
+      [tree] if get_bool(tree).is_some() => *setting = Some(get_bool(tree)),
+      _ => {
+        errors.push(Error::new(
+          args_span(value),
+          format!("`{name}` must be set as `true`, `false`, or `keep`"),
+        ));
+        // it was still given, so it shouldn't also be reported as missing.
+        *setting = Some(None);
+      }
+    }
+  }
+  for (name, setting) in
+    [("irq_masked", irq_masked), ("fiq_masked", fiq_masked)]
+  {
+    if setting.is_none() {
+      errors.push(Error::call_site(format!(
+        "missing `{name} = true`, `false`, or `keep`"
+      )));
+    }
+  }
+  errors.finish()?;
+
+  let (irq_masked, fiq_masked) = (irq_masked.flatten(), fiq_masked.flatten());
+  if irq_masked.is_none() && fiq_masked.is_none() {
+    return Err(Error::call_site(
+      "both masks are `keep`, so there's nothing to change",
+    ));
+  }
+  // The mode is never given, so the mode bits written back are always the
+  // ones that were just read.
+  let lines = change_cpu_control_lines(&scratch, None, irq_masked, fiq_masked);
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
@@ -0,0 +1,93 @@
+use super::*;
+
+pub fn a32_with_mode_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let mut args = split_args(token_stream).into_iter();
+  let mode_arg =
+    args.next().ok_or_else(|| Error::call_site(NOT_ENOUGH_INPUT))?;
+  let mode = match mode_arg.as_slice() {
+    [tree] => cpu_mode_bits(&tree.to_string()),
+    _ => None,
+  }
+  .ok_or_else(|| {
+    Error::new(
+      args_span(&mode_arg),
+      format!("the mode must be one of: {}", cpu_mode_names()),
+    )
+  })?;
+  if mode == cpu_mode_bits("usr").unwrap() {
+    return Err(Error::new(
+      args_span(&mode_arg),
+      "can't come back from User mode, since it can't change the mode itself",
+    ));
+  }
+
+  let mut scratch: Option<(String, Span)> = None;
+  let mut body: Option<Group> = None;
+  for arg in args {
+    if let Some((name, value)) = get_named_arg(&arg) {
+      match name.as_str() {
+        "scratch" => {
+          scratch = Some((get_reg_arg(&name, value)?, args_span(value)))
+        }
+        other => {
+          return Err(Error::new(
+            args_span(&arg),
+            format!("unknown argument `{other}`"),
+          ))
+        }
+      }
+      continue;
+    }
+    match arg.as_slice() {
+      [TokenTree::Group(g)] if g.delimiter() == Delimiter::Brace => {
+        if body.is_some() {
+          return Err(Error::new(g.span(), "the body is given twice"));
+        }
+        body = Some(g.clone());
+      }
+      _ => {
+        return Err(Error::new(
+          args_span(&arg),
+          "expected `scratch = \"rN\"` or the body, eg: `{ ... }`",
+        ))
+      }
+    }
+  }
+  let (scratch, scratch_span) =
+    scratch.ok_or_else(|| Error::call_site("missing `scratch = \"rN\"`"))?;
+  // r8 and up are banked in FIQ mode, so going to or from FIQ mode would
+  // restore from a different register than the one the CPSR was saved in.
+  if matches!(parse_reg(&scratch), Some(8..)) {
+    return Err(Error::new(
+      scratch_span,
+      format!(
+        "`scratch` can't be `{scratch}`, it must be one of `r0`-`r7` so that \
+         it's the same register in every mode"
+      ),
+    ));
+  }
+  let body = body.ok_or_else(|| Error::call_site("missing the body"))?;
+
+  let mut out_buffer: Vec<TokenTree> = Vec::new();
+  out_buffer.push(TokenTree::Literal(Literal::string(&format!(
+    "mrs {scratch}, CPSR\n{}\n",
+    set_cpu_control_asm(mode, true, true)
+  ))));
+  out_buffer.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+  extend_concat_as_lines(&mut out_buffer, body.stream());
+  out_buffer.push(TokenTree::Literal(Literal::string(&format!(
+    "msr CPSR_c, {scratch}\n"
+  ))));
+
+  let concat_expr = vec![
+    TokenTree::Ident(Ident::new("concat", Span::call_site())),
+    TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+    TokenTree::Group(Group::new(
+      Delimiter::Parenthesis,
+      TokenStream::from_iter(out_buffer),
+    )),
+  ];
+  Ok(TokenStream::from_iter(concat_expr))
+}
@@ -1,9 +1,81 @@
 use super::*;
 
-pub fn a32_write_spsr_from_impl(token_stream: TokenStream) -> TokenStream {
-  let reg_name = one_str_literal_or_panic(token_stream);
+pub fn a32_write_spsr_from_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  write_status_reg(token_stream, "SPSR")
+}
+
+pub fn a32_write_cpsr_from_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  write_status_reg(token_stream, "CPSR")
+}
+
+/// Writes the status register named (`CPSR` or `SPSR`) with `msr`.
+fn write_status_reg(
+  token_stream: TokenStream, status_reg: &str,
+) -> Result<TokenStream, Error> {
+  let args = split_args(token_stream);
+  let (reg_arg, fields_arg) = match args.as_slice() {
+    [reg_arg] => (reg_arg, None),
+    [reg_arg, fields_arg] => (reg_arg, Some(fields_arg)),
+    _ => {
+      return Err(Error::call_site(format!(
+        "expected a register, and optionally the {status_reg} fields to write"
+      )))
+    }
+  };
+  let span = args_span(reg_arg);
+  let reg_name = match reg_arg.as_slice() {
+    [tree] => get_reg_text(tree),
+    _ => None,
+  }
+  .ok_or_else(|| {
+    Error::new(span, "the register must be a string literal or a register name")
+  })?;
+  check_reg(&reg_name, span)?;
+  if matches!(parse_reg(&reg_name), Some(13 | 15)) {
+    return Err(Error::new(
+      span,
+      format!(
+        "can't write {status_reg} from `{reg_name}`, using `sp` or `pc` with \
+         `msr` is unpredictable"
+      ),
+    ));
+  }
+  let fields = match fields_arg {
+    None => String::from("cxsf"),
+    Some(fields_arg) => psr_fields(fields_arg)?,
+  };
+
+  Ok(TokenStream::from(str_literal_at(
+    &format!("msr {status_reg}_{fields}, {reg_name}"),
+    span,
+  )))
+}
 
-  TokenStream::from(TokenTree::Literal(Literal::string(&format!(
-    "msr SPSR, {reg_name}"
-  ))))
+/// Gets the field mask, in the usual `cxsf` order.
+fn psr_fields(arg: &[TokenTree]) -> Result<String, Error> {
+  let span = args_span(arg);
+  let text = match arg {
+    [tree] => get_str_literal_content(tree),
+    _ => None,
+  }
+  .ok_or_else(|| {
+    Error::new(span, "the fields must be a string literal, eg: `\"cf\"`")
+  })?;
+  if text.is_empty() {
+    return Err(Error::new(span, "the fields can't be empty"));
+  }
+  if let Some(bad) = text.chars().find(|c| !"cxsf".contains(*c)) {
+    return Err(Error::new(
+      span,
+      format!("{bad:?} is not a field, expected some of `c`, `x`, `s`, `f`"),
+    ));
+  }
+  if let Some(dup) = text.chars().find(|c| text.matches(*c).count() > 1) {
+    return Err(Error::new(span, format!("the `{dup}` field is given twice")));
+  }
+  Ok("cxsf".chars().filter(|c| text.contains(*c)).collect())
 }
@@ -0,0 +1,84 @@
+use super::*;
+
+pub fn adjust_ptr_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let args = split_args(token_stream);
+  let (reg_name, offset, scratch) = match args.as_slice() {
+    [reg, offset] => (reg, offset, None),
+    [reg, offset, scratch] => (reg, offset, Some(scratch)),
+    _ => {
+      return Err(Error::call_site(
+        "expected a register, an offset, and optionally a scratch",
+      ))
+    }
+  };
+  let reg_span = args_span(reg_name);
+  let reg_name = match reg_name.as_slice() {
+    [tree] => get_str_literal_content(tree),
+    _ => None,
+  }
+  .ok_or_else(|| {
+    Error::new(reg_span, "the register must be a string literal")
+  })?;
+  check_reg(&reg_name, reg_span)?;
+  let offset_span = args_span(offset);
+  let offset = get_int(offset).ok_or_else(|| {
+    Error::new(offset_span, "the offset must be an integer literal")
+  })?;
+  let scratch = match scratch {
+    None => None,
+    Some(scratch) => match get_named_arg(scratch) {
+      Some((name, value)) if name == "scratch" => {
+        Some((get_reg_arg(&name, value)?, args_span(value)))
+      }
+      _ => {
+        return Err(Error::new(
+          args_span(scratch),
+          "the third argument must be `scratch = \"rN\"`",
+        ))
+      }
+    },
+  };
+
+  let op = if offset < 0 { "sub" } else { "add" };
+  let magnitude: u32 = offset
+    .unsigned_abs()
+    .try_into()
+    .map_err(|_| Error::new(offset_span, "the offset must fit in 32 bits"))?;
+
+  let asm = if a32_imm_encodable(magnitude) {
+    format!("{op} {reg_name}, {reg_name}, #{magnitude}")
+  } else {
+    // Take the (even aligned) 8-bit window holding the highest set bit, and
+    // see if what's left over is also an immediate.
+    let top_bit = 31 - magnitude.leading_zeros();
+    let window_start = top_bit.saturating_sub(7).next_multiple_of(2);
+    let high = magnitude & (0xFF << window_start);
+    let low = magnitude - high;
+    if a32_imm_encodable(low) {
+      format!(
+        "{op} {reg_name}, {reg_name}, #{high}\n{op} {reg_name}, {reg_name}, #{low}"
+      )
+    } else {
+      let (scratch, scratch_span) = scratch.ok_or_else(|| {
+        Error::new(
+          offset_span,
+          format!("the offset {offset} needs a `scratch = \"rN\"` register"),
+        )
+      })?;
+      if regs_alias(&scratch, &reg_name) {
+        return Err(Error::new(
+          scratch_span,
+          "`scratch` must not be the pointer register",
+        ));
+      }
+      format!(
+        "ldr {scratch}, ={magnitude:#X}\n{op} {reg_name}, {reg_name}, {scratch}"
+      )
+    }
+  };
+
+  Ok(TokenStream::from(str_literal_at(&asm, out_span)))
+}
@@ -0,0 +1,96 @@
+use super::*;
+
+pub fn align_dispatch_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut ptr: Option<String> = None;
+  let mut other: Option<String> = None;
+  let mut scratch: Option<(String, Span)> = None;
+  let mut word: Option<u32> = None;
+  let mut half: Option<u32> = None;
+  let mut byte: Option<u32> = None;
+  for arg in split_args(token_stream) {
+    let (name, value) = get_named_arg(&arg).ok_or_else(|| {
+      Error::new(
+        args_span(&arg),
+        "arguments must be of the form `name = value`",
+      )
+    })?;
+    match name.as_str() {
+      "ptr" => ptr = Some(get_reg_arg(&name, value)?),
+      "other" => other = Some(get_reg_arg(&name, value)?),
+      "scratch" => {
+        scratch = Some((get_reg_arg(&name, value)?, args_span(value)))
+      }
+      "labels" => {
+        let group = match value {
+          [tree] => get_group(tree.clone()),
+          _ => None,
+        }
+        .ok_or_else(|| {
+          Error::new(
+            args_span(value),
+            "`labels` must be a group, eg: `{ word = 1, half = 2, byte = 3 }`",
+          )
+        })?;
+        for label_arg in split_args(group.stream()) {
+          let (label_name, label_value) = get_named_arg(&label_arg)
+            .ok_or_else(|| {
+              Error::new(
+                args_span(&label_arg),
+                "labels must be of the form `name = number`",
+              )
+            })?;
+          let number = Some(get_int_arg(&label_name, label_value)?);
+          match label_name.as_str() {
+            "word" => word = number,
+            "half" => half = number,
+            "byte" => byte = number,
+            other => {
+              return Err(Error::new(
+                args_span(&label_arg),
+                format!(
+                  "unknown label `{other}`, expected `word`, `half`, or `byte`"
+                ),
+              ))
+            }
+          }
+        }
+      }
+      other => {
+        return Err(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        ))
+      }
+    }
+  }
+  let ptr = ptr.ok_or_else(|| Error::call_site("missing `ptr = \"rN\"`"))?;
+  let other =
+    other.ok_or_else(|| Error::call_site("missing `other = \"rN\"`"))?;
+  let (scratch, scratch_span) =
+    scratch.ok_or_else(|| Error::call_site("missing `scratch = \"rN\"`"))?;
+  let word =
+    word.ok_or_else(|| Error::call_site("missing the `word` label"))?;
+  let half =
+    half.ok_or_else(|| Error::call_site("missing the `half` label"))?;
+  if regs_alias(&scratch, &ptr) || regs_alias(&scratch, &other) {
+    return Err(Error::new(
+      scratch_span,
+      "`scratch` must differ from `ptr` and `other`",
+    ));
+  }
+
+  let mut lines = vec![
+    format!("orr {scratch}, {ptr}, {other}"),
+    format!("tst {scratch}, #3"),
+    format!("beq {word}f"),
+    format!("tst {scratch}, #1"),
+    format!("beq {half}f"),
+  ];
+  if let Some(byte) = byte {
+    lines.push(format!("b {byte}f"));
+  }
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
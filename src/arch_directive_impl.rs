@@ -0,0 +1,155 @@
+use super::*;
+
+/// Architecture names for `.arch`.
+const ARCH_NAMES: &[&str] = &[
+  "armv2",
+  "armv2a",
+  "armv3",
+  "armv3m",
+  "armv4",
+  "armv4t",
+  "armv5",
+  "armv5t",
+  "armv5te",
+  "armv5tej",
+  "armv6",
+  "armv6j",
+  "armv6k",
+  "armv6z",
+  "armv6kz",
+  "armv6zk",
+  "armv6t2",
+  "armv6-m",
+  "armv6s-m",
+  "armv7",
+  "armv7-a",
+  "armv7ve",
+  "armv7-r",
+  "armv7-m",
+  "armv7e-m",
+  "armv8-a",
+  "armv8-r",
+  "armv8-m.base",
+  "armv8-m.main",
+];
+
+/// CPU names for `.cpu`.
+const CPU_NAMES: &[&str] = &[
+  "arm7tdmi",
+  "arm7tdmi-s",
+  "arm710t",
+  "arm720t",
+  "arm8",
+  "arm810",
+  "arm9",
+  "arm9tdmi",
+  "arm920",
+  "arm920t",
+  "arm922t",
+  "arm940t",
+  "arm946e-s",
+  "arm966e-s",
+  "arm968e-s",
+  "arm926ej-s",
+  "arm10tdmi",
+  "arm1020t",
+  "arm1020e",
+  "arm1022e",
+  "arm1136j-s",
+  "arm1136jf-s",
+  "arm1156t2-s",
+  "arm1176jzf-s",
+  "mpcore",
+  "strongarm",
+  "xscale",
+  "iwmmxt",
+  "cortex-m0",
+  "cortex-m0plus",
+  "cortex-m1",
+  "cortex-m3",
+  "cortex-m4",
+  "cortex-m7",
+  "cortex-a5",
+  "cortex-a7",
+  "cortex-a8",
+  "cortex-a9",
+  "cortex-a15",
+  "cortex-r4",
+  "cortex-r5",
+];
+
+/// FPU names for `.fpu`.
+const FPU_NAMES: &[&str] = &[
+  "none",
+  "softvfp",
+  "vfp",
+  "vfpv2",
+  "vfpv3",
+  "vfpv3-d16",
+  "vfpv3xd",
+  "vfpv4",
+  "vfpv4-d16",
+  "fpv4-sp-d16",
+  "fpv5-d16",
+  "fpv5-sp-d16",
+  "neon",
+  "neon-vfpv4",
+];
+
+pub fn arch_directive_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let args = split_args(token_stream);
+  if args.is_empty() {
+    return Err(Error::call_site(NOT_ENOUGH_INPUT));
+  }
+  let mut errors = Errors::default();
+  let mut seen: Vec<String> = Vec::new();
+  let mut out = String::new();
+  for arg in args {
+    let Some((key, value)) = get_named_arg(&arg) else {
+      errors.push(Error::new(
+        args_span(&arg),
+        "arguments must be of the form `name = value`",
+      ));
+      continue;
+    };
+    let names = match key.as_str() {
+      "arch" => ARCH_NAMES,
+      "cpu" => CPU_NAMES,
+      "fpu" => FPU_NAMES,
+      other => {
+        errors.push(Error::new(
+          args_span(&arg),
+          format!(
+            "unknown argument `{other}`, expected `arch`, `cpu`, or `fpu`"
+          ),
+        ));
+        continue;
+      }
+    };
+    if seen.contains(&key) {
+      errors.push(Error::new(
+        args_span(&arg),
+        format!("`{key}` is given more than once"),
+      ));
+      continue;
+    }
+    seen.push(key.clone());
+    let Some(name) = errors.check(get_str_arg(&key, value)) else {
+      continue;
+    };
+    if !names.contains(&name.as_str()) {
+      let mut msg = format!("unknown {key} name `{name}`");
+      if let Some(closest) = closest_match(&name, names) {
+        msg.push_str(&format!(", did you mean `{closest}`?"));
+      }
+      errors.push(Error::new(args_span(value), msg));
+      continue;
+    }
+    out.push_str(&format!(".{key} {name}\n"));
+  }
+  errors.finish()?;
+  Ok(TokenStream::from(str_literal_at(&out, out_span)))
+}
@@ -0,0 +1,80 @@
+use super::*;
+
+pub fn asciz_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let args = split_args(token_stream);
+  let (label, text_arg) = match args.as_slice() {
+    [text_arg] => (None, text_arg),
+    [label_arg, text_arg] => {
+      let label = match label_arg.as_slice() {
+        [tree] => get_str_literal_content(tree),
+        _ => None,
+      }
+      .ok_or_else(|| {
+        Error::new(args_span(label_arg), "the label must be a string literal")
+      })?;
+      if !is_symbol_name(&label) {
+        return Err(Error::new(
+          args_span(label_arg),
+          format!("`{label}` is not a valid symbol name"),
+        ));
+      }
+      (Some(label), text_arg)
+    }
+    [] => return Err(Error::call_site(NOT_ENOUGH_INPUT)),
+    [_, _, extra, ..] => {
+      return Err(Error::new(
+        args_span(extra),
+        "expected at most a label and the text",
+      ))
+    }
+  };
+  let text = match text_arg.as_slice() {
+    [tree] => get_str_literal_content(tree),
+    _ => None,
+  }
+  .ok_or_else(|| {
+    Error::new(args_span(text_arg), "the text must be a string literal")
+  })?;
+  // `.asciz` adds its own terminating NUL, and one inside the text would just
+  // cut the string short for any C-style reader.
+  if text.contains('\0') {
+    return Err(Error::new(
+      args_span(text_arg),
+      "the text can't contain a NUL, `.asciz` adds one at the end",
+    ));
+  }
+
+  let directive = format!(".asciz \"{}\"\n", gas_escape(&text));
+  let out = match label {
+    Some(label) => format!("{label}:\n{directive}.size {label}, . - {label}\n"),
+    None => directive,
+  };
+  Ok(TokenStream::from(str_literal_at(&out, out_span)))
+}
+
+/// Escapes text to go between the quotes of a GAS string directive.
+///
+/// Printable ascii is kept as is (other than `"`, `\`, and the braces of the
+/// `asm!` template), and every other byte of the UTF-8 is written as a three
+/// digit octal escape. Octal is used because GAS reads *every* hex digit after
+/// `\x`, so a `\xNN` escape followed by text like `"ab"` would be read as a
+/// single (wrong) value.
+fn gas_escape(text: &str) -> String {
+  let mut out = String::with_capacity(text.len());
+  for byte in text.bytes() {
+    match byte {
+      b'"' => out.push_str("\\\""),
+      b'\\' => out.push_str("\\\\"),
+      b'\n' => out.push_str("\\n"),
+      b'\t' => out.push_str("\\t"),
+      b'\r' => out.push_str("\\r"),
+      // the text goes in an `asm!` template, so braces are escaped for that.
+      b'{' => out.push_str("{{"),
+      b'}' => out.push_str("}}"),
+      b' '..=b'~' => out.push(char::from(byte)),
+      _ => out.push_str(&format!("\\{byte:03o}")),
+    }
+  }
+  out
+}
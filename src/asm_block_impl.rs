@@ -0,0 +1,125 @@
+use super::*;
+
+/// Which instruction set the assembler is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IsaState {
+  A32,
+  T32,
+}
+impl IsaState {
+  pub fn name(self) -> &'static str {
+    match self {
+      Self::A32 => "a32",
+      Self::T32 => "t32",
+    }
+  }
+
+  /// The state that a line switches to, if it's a state directive.
+  pub fn of_directive(line: &str) -> Option<Self> {
+    let words: Vec<String> = strip_line(line)
+      .split_whitespace()
+      .map(str::to_ascii_lowercase)
+      .collect();
+    match words.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+      [".arm"] | [".code", "32"] | [".code32"] => Some(Self::A32),
+      [".thumb"] | [".code", "16"] | [".code16"] => Some(Self::T32),
+      _ => None,
+    }
+  }
+}
+
+pub fn asm_block_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  let mut start: Option<IsaState> = None;
+  let mut end: Option<IsaState> = None;
+  let mut body: Option<Group> = None;
+  for arg in split_args(token_stream) {
+    let Some((name, value)) = get_named_arg(&arg) else {
+      match arg.as_slice() {
+        [TokenTree::Group(g)] if body.is_none() => body = Some(g.clone()),
+        _ => {
+          return Err(Error::new(
+            args_span(&arg),
+            "arguments must be `start = ...`, `end = ...`, and the body group",
+          ))
+        }
+      }
+      continue;
+    };
+    let state = match value {
+      [TokenTree::Ident(i)] if i.to_string() == "a32" => Some(IsaState::A32),
+      [TokenTree::Ident(i)] if i.to_string() == "t32" => Some(IsaState::T32),
+      _ => None,
+    };
+    match name.as_str() {
+      "start" | "end" => {
+        let state = state.ok_or_else(|| {
+          Error::new(
+            args_span(value),
+            format!("`{name}` must be `a32` or `t32`"),
+          )
+        })?;
+        if name == "start" {
+          start = Some(state);
+        } else {
+          end = Some(state);
+        }
+      }
+      other => {
+        return Err(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        ))
+      }
+    }
+  }
+  let start = start.ok_or_else(|| Error::call_site("missing `start = ...`"))?;
+  let end = end.ok_or_else(|| Error::call_site("missing `end = ...`"))?;
+  let body =
+    body.ok_or_else(|| Error::call_site("missing the group of lines"))?;
+
+  // Anything that can't be folded to text is skipped, so this is best-effort.
+  let mut state = start;
+  for element in split_args(body.stream()) {
+    let Ok(text) = fold_to_str(TokenStream::from_iter(element.iter().cloned()))
+    else {
+      continue;
+    };
+    for line in text.split(['\n', ';']) {
+      let Some(new_state) = IsaState::of_directive(line) else { continue };
+      if new_state == state {
+        return Err(Error::new(
+          args_span(&element),
+          format!(
+            "`{}` switches to {}, but the code is already {}",
+            line.trim(),
+            new_state.name(),
+            state.name()
+          ),
+        ));
+      }
+      state = new_state;
+    }
+  }
+  if state != end {
+    return Err(Error::new(
+      body.span(),
+      format!(
+        "the block should end as {}, but it ends as {}",
+        end.name(),
+        state.name()
+      ),
+    ));
+  }
+
+  let mut out_buffer: Vec<TokenTree> = Vec::new();
+  extend_concat_as_lines(&mut out_buffer, body.stream());
+  let concat_expr = vec![
+    TokenTree::Ident(Ident::new("concat", Span::call_site())),
+    TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+    TokenTree::Group(Group::new(
+      Delimiter::Parenthesis,
+      TokenStream::from_iter(out_buffer),
+    )),
+  ];
+  Ok(TokenStream::from_iter(concat_expr))
+}
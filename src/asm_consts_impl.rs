@@ -0,0 +1,158 @@
+use super::*;
+
+pub fn asm_consts_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let (options, token_stream) = take_options(token_stream, &["deny_unused"]);
+  let mut deny_unused = false;
+  for (_name, value) in &options {
+    deny_unused = get_bool(value).ok_or_else(|| {
+      Error::new(value.span(), "`deny_unused` must be `true` or `false`")
+    })?;
+  }
+  let args = split_args(token_stream);
+  let groups: Vec<&Group> = args
+    .iter()
+    .filter_map(|arg| match arg.as_slice() {
+      [TokenTree::Group(g)] => Some(g),
+      _ => None,
+    })
+    .collect();
+  let (decls, body) =
+    match groups.as_slice() {
+      [decls, body] if args.len() == 2 => (decls, body),
+      _ => return Err(Error::call_site(
+        "expected a group of `NAME = value` declarations and then a group of \
+         lines",
+      )),
+    };
+
+  let mut consts: Vec<(String, u32, Span)> = Vec::new();
+  for decl in split_args(decls.stream()) {
+    let (name, value) = match decl.as_slice() {
+      [TokenTree::Ident(name), TokenTree::Punct(eq), value @ ..]
+        if *eq == '=' =>
+      {
+        (name, value)
+      }
+      _ => {
+        return Err(Error::new(
+          args_span(&decl),
+          "declarations must be of the form `NAME = value`",
+        ))
+      }
+    };
+    let name_str = name.to_string();
+    if consts.iter().any(|(n, ..)| *n == name_str) {
+      return Err(Error::new(
+        name.span(),
+        format!("`{name_str}` is declared twice"),
+      ));
+    }
+    let bits = get_int(value)
+      .and_then(|v| {
+        u32::try_from(v)
+          .ok()
+          .or_else(|| i32::try_from(v).ok().map(|v| v as u32))
+      })
+      .ok_or_else(|| {
+        Error::new(
+          args_span(value),
+          format!("`{name_str}` must be a 32-bit integer literal"),
+        )
+      })?;
+    consts.push((name_str, bits, name.span()));
+  }
+
+  let mut used = vec![false; consts.len()];
+  let mut out_buffer: Vec<TokenTree> = Vec::new();
+  let mut lines = Vec::new();
+  for tree in body.stream() {
+    let Some(content) = get_str_literal_content(&tree) else {
+      lines.push(tree);
+      continue;
+    };
+    let mut new_content = Vec::new();
+    for line in content.split('\n') {
+      new_content.push(substitute_line(line, &consts, &mut used, tree.span())?);
+    }
+    lines.push(str_literal_at(&new_content.join("\n"), tree.span()));
+  }
+  if deny_unused {
+    let mut errors = Errors::default();
+    for ((name, _, span), used) in consts.iter().zip(&used) {
+      if !used {
+        errors.push(Error::new(*span, format!("`{name}` is never used")));
+      }
+    }
+    errors.finish()?;
+  }
+
+  extend_concat_as_lines(&mut out_buffer, lines);
+  let concat_expr = vec![
+    TokenTree::Ident(Ident::new("concat", Span::call_site())),
+    TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+    TokenTree::Group(Group::new(
+      Delimiter::Parenthesis,
+      TokenStream::from_iter(out_buffer),
+    )),
+  ];
+  Ok(TokenStream::from_iter(concat_expr))
+}
+
+/// Replaces each `#NAME` in a line with the value, then checks that the values
+/// can be immediates of the line's instruction.
+fn substitute_line(
+  line: &str, consts: &[(String, u32, Span)], used: &mut [bool], span: Span,
+) -> Result<String, Error> {
+  let mut out = String::new();
+  let mut substituted = Vec::new();
+  let mut rest = line;
+  while let Some(i) = rest.find('#') {
+    out.push_str(&rest[..=i]);
+    rest = &rest[i + 1..];
+    let name_len = rest
+      .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+      .unwrap_or(rest.len());
+    let name = &rest[..name_len];
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+      continue;
+    }
+    let index =
+      consts.iter().position(|(n, ..)| n == name).ok_or_else(|| {
+        Error::new(span, format!("`#{name}` is used but never declared"))
+      })?;
+    used[index] = true;
+    let (_, value, _) = &consts[index];
+    out.push_str(&format!("{value:#X}"));
+    substituted.push((name, *value));
+    rest = &rest[name_len..];
+  }
+  out.push_str(rest);
+
+  // only the mnemonics with a data-processing immediate are checked.
+  let mnemonic = strip_line(&out).split_whitespace().next().unwrap_or("");
+  let Some((base, _)) = split_mnemonic(mnemonic) else { return Ok(out) };
+  for (name, value) in substituted {
+    // the assembler will swap in the opposite instruction if that works.
+    let ok = match base {
+      "mov" | "mvn" | "and" | "bic" | "adc" | "sbc" => {
+        a32_imm_encodable(value) || a32_imm_encodable(!value)
+      }
+      "cmp" | "cmn" | "add" | "sub" => {
+        a32_imm_encodable(value) || a32_imm_encodable(value.wrapping_neg())
+      }
+      "tst" | "teq" | "orr" | "eor" | "rsb" | "rsc" => a32_imm_encodable(value),
+      _ => true,
+    };
+    if !ok {
+      return Err(Error::new(
+        span,
+        format!(
+          "`{name}` is {value:#X}, which can't be an immediate of `{base}`"
+        ),
+      ));
+    }
+  }
+  Ok(out)
+}
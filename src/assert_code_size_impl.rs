@@ -0,0 +1,51 @@
+use super::*;
+
+pub fn assert_code_size_le_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let args = split_args(token_stream);
+  let [name_arg, size_arg] = args.as_slice() else {
+    return Err(Error::new(
+      args_span(&args.concat()),
+      "expected a symbol name and a size, eg: `\"my_stub\", 32`",
+    ));
+  };
+  let mut errors = Errors::default();
+  let name = errors.check(symbol_name_arg(name_arg));
+  let size = match get_int(size_arg) {
+    Some(size @ 1..=0xFFFF_FFFF) => Some(size),
+    _ => {
+      errors.push(Error::new(
+        args_span(size_arg),
+        "the size must be a positive integer literal",
+      ));
+      None
+    }
+  };
+  errors.finish()?;
+  let (name, size) = (name.unwrap(), size.unwrap());
+
+  // LLVM's `.err` ignores any message, so `.error` is used to get the message
+  // shown with both LLVM and GNU assemblers.
+  let out = format!(
+    ".if (. - {name}) > {size}\n.error \"{name} exceeds {size} bytes\"\n.endif\n"
+  );
+  Ok(TokenStream::from(str_literal_at(&out, out_span)))
+}
+
+pub fn size_marker_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let args = split_args(token_stream);
+  let [name_arg] = args.as_slice() else {
+    return Err(Error::new(
+      args_span(&args.concat()),
+      "expected only a symbol name",
+    ));
+  };
+  let name = symbol_name_arg(name_arg)?;
+  let out = format!("{name}:\n");
+  Ok(TokenStream::from(str_literal_at(&out, out_span)))
+}
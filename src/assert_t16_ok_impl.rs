@@ -0,0 +1,30 @@
+use super::*;
+
+pub fn assert_t16_ok_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let mut token_iter = token_stream.into_iter();
+  let body_group = token_iter
+    .next()
+    .and_then(get_group)
+    .ok_or_else(|| Error::call_site("must have a group for the body"))?;
+  if let Some(extra) = token_iter.next() {
+    return Err(Error::new(extra.span(), "too many tokens"));
+  }
+
+  lint_body(&body_group.stream(), Lint::T16, None)?;
+
+  let mut out_buffer: Vec<TokenTree> = Vec::new();
+  extend_concat_as_lines(&mut out_buffer, body_group.stream());
+
+  let concat_expr = vec![
+    TokenTree::Ident(Ident::new("concat", Span::call_site())),
+    TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+    TokenTree::Group(Group::new(
+      Delimiter::Parenthesis,
+      TokenStream::from_iter(out_buffer),
+    )),
+  ];
+
+  Ok(TokenStream::from_iter(concat_expr))
+}
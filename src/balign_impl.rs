@@ -0,0 +1,69 @@
+use super::*;
+
+pub fn balign_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let args = split_args(token_stream);
+  let (align_arg, fill_arg, max_skip_arg) = match args.as_slice() {
+    [] => return Err(Error::call_site(NOT_ENOUGH_INPUT)),
+    [align] => (align, None, None),
+    [align, fill] => (align, Some(fill), None),
+    [align, fill, max_skip] => (align, Some(fill), Some(max_skip)),
+    [.., extra] => {
+      return Err(Error::new(
+        args_span(extra),
+        "too many arguments, expected the alignment, fill, and max skip",
+      ))
+    }
+  };
+  let align = match get_int(align_arg) {
+    Some(align) => check_align(align, args_span(align_arg))?,
+    None => {
+      return Err(Error::new(
+        args_span(align_arg),
+        "the alignment must be an integer literal",
+      ))
+    }
+  };
+
+  let mut line = format!(".balign {align}");
+  if let Some(fill_arg) = fill_arg {
+    line.push(',');
+    // An empty fill uses the default fill, which still allows a max skip.
+    if !fill_arg.is_empty() {
+      let fill = get_int(fill_arg)
+        .and_then(|fill| u8::try_from(fill).ok())
+        .ok_or_else(|| {
+          Error::new(
+            args_span(fill_arg),
+            "the fill must be a byte value, from 0 to 0xFF",
+          )
+        })?;
+      line.push_str(&format!(" {fill:#04X}"));
+    }
+  }
+  if let Some(max_skip_arg) = max_skip_arg {
+    let max_skip: String =
+      max_skip_arg.iter().map(TokenTree::to_string).collect();
+    if max_skip.is_empty() {
+      return Err(Error::call_site("the max skip is empty"));
+    }
+    line.push_str(&format!(", {max_skip}"));
+  }
+  line.push('\n');
+  Ok(TokenStream::from(str_literal_at(&line, out_span)))
+}
+
+/// Checks that an alignment is a power of two `.balign` accepts.
+pub fn check_align(align: i128, span: Span) -> Result<u32, Error> {
+  u32::try_from(align)
+    .ok()
+    .filter(|align| align.is_power_of_two() && *align <= 32768)
+    .ok_or_else(|| {
+      Error::new(
+        span,
+        format!(
+          "the alignment must be a power of two from 1 to 32768, got {align}"
+        ),
+      )
+    })
+}
@@ -0,0 +1,77 @@
+use super::*;
+
+pub fn bkpt_debug_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut args = split_args(token_stream).into_iter();
+  let imm_arg =
+    args.next().ok_or_else(|| Error::call_site(NOT_ENOUGH_INPUT))?;
+  let imm = get_int(&imm_arg).ok_or_else(|| {
+    Error::new(
+      args_span(&imm_arg),
+      "the first argument must be the breakpoint number, as an integer \
+       literal",
+    )
+  })?;
+
+  let mut state = IsaState::A32;
+  // A proc-macro is built with the same `debug-assertions` setting as the
+  // crate using it, unless the profile overrides it for build dependencies.
+  let mut debug_only = true;
+  for arg in args {
+    if let Some((name, value)) = get_named_arg(&arg) {
+      match name.as_str() {
+        "debug_only" => {
+          debug_only = match value {
+            [tree] => get_bool(tree),
+            _ => None,
+          }
+          .ok_or_else(|| {
+            Error::new(
+              args_span(value),
+              "`debug_only` must be set as `true` or `false`",
+            )
+          })?
+        }
+        other => {
+          return Err(Error::new(
+            args_span(&arg),
+            format!("unknown argument `{other}`"),
+          ))
+        }
+      }
+      continue;
+    }
+    state = match arg.as_slice() {
+      [TokenTree::Ident(i)] if i.to_string() == "a32" => IsaState::A32,
+      [TokenTree::Ident(i)] if i.to_string() == "t32" => IsaState::T32,
+      _ => {
+        return Err(Error::new(
+          args_span(&arg),
+          "expected `a32`, `t32`, or `debug_only = bool`",
+        ))
+      }
+    };
+  }
+  let max = match state {
+    IsaState::A32 => 0xFFFF,
+    IsaState::T32 => 0xFF,
+  };
+  if !(0..=max).contains(&imm) {
+    return Err(Error::new(
+      args_span(&imm_arg),
+      format!(
+        "the breakpoint number must be 0 to {max:#X} in {}, got {imm}",
+        state.name()
+      ),
+    ));
+  }
+
+  let line = if debug_only && !cfg!(debug_assertions) {
+    String::new()
+  } else {
+    format!("bkpt #{imm:#04X}")
+  };
+  Ok(TokenStream::from(str_literal_at(&line, out_span)))
+}
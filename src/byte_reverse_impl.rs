@@ -0,0 +1,121 @@
+use super::*;
+
+pub fn byte_reverse_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let (dst, src, scratch) = byte_reverse_args(token_stream)?;
+  let lines = match scratch {
+    None => vec![format!("rev {dst}, {src}")],
+    // With bytes `ABCD`: the scratch becomes `A^C, B^D, C^A, D^B` with the
+    // second byte cleared, then `ror #8` gives `DABC` and the eor with the
+    // scratch `lsr #8` fixes the middle two bytes into `DCBA`.
+    Some(t) => vec![
+      format!("eor {t}, {src}, {src}, ror #16"),
+      format!("bic {t}, {t}, #0xFF0000"),
+      format!("mov {dst}, {src}, ror #8"),
+      format!("eor {dst}, {dst}, {t}, lsr #8"),
+    ],
+  };
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
+
+pub fn byte_reverse16_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let (dst, src, scratch) = byte_reverse_args(token_stream)?;
+  let lines = match scratch {
+    None => vec![format!("rev16 {dst}, {src}")],
+    Some(t) => vec![
+      format!("and {t}, {src}, #0xFF"),
+      format!("mov {dst}, {src}, lsr #8"),
+      format!("and {dst}, {dst}, #0xFF"),
+      format!("orr {dst}, {dst}, {t}, lsl #8"),
+    ],
+  };
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
+
+/// Gets the destination, source, and scratch registers.
+///
+/// The scratch is only required (and checked) when not using `arch = v6`, and
+/// it's `None` with `v6`.
+fn byte_reverse_args(
+  token_stream: TokenStream,
+) -> Result<(String, String, Option<String>), Error> {
+  let mut args = split_args(token_stream).into_iter();
+  let first = args.next().ok_or_else(|| Error::call_site(NOT_ENOUGH_INPUT))?;
+  let (dst, src) = match first.as_slice() {
+    [_] => {
+      let reg = select_reg(&first)?;
+      (reg.clone(), reg)
+    }
+    _ => get_reg_assign(&first)?,
+  };
+  for reg in [&dst, &src] {
+    if parse_reg(reg) == Some(13) {
+      return Err(Error::new(
+        args_span(&first),
+        format!("`{reg}` can't be used, it's the stack pointer"),
+      ));
+    }
+  }
+
+  let mut scratch: Option<(String, Span)> = None;
+  let mut v6 = false;
+  for arg in args {
+    let (name, value) = get_named_arg(&arg).ok_or_else(|| {
+      Error::new(
+        args_span(&arg),
+        "expected `scratch = \"rN\"` or `arch = v4`/`arch = v6`",
+      )
+    })?;
+    match name.as_str() {
+      "scratch" => {
+        let reg = get_reg_arg(&name, value)?;
+        if matches!(parse_reg(&reg), Some(13 | 15)) {
+          return Err(Error::new(
+            args_span(value),
+            format!("`scratch` can't be `{reg}`"),
+          ));
+        }
+        scratch = Some((reg, args_span(value)));
+      }
+      "arch" => {
+        v6 = match value {
+          [TokenTree::Ident(i)] if i.to_string() == "v4" => false,
+          [TokenTree::Ident(i)] if i.to_string() == "v6" => true,
+          _ => {
+            return Err(Error::new(
+              args_span(value),
+              "`arch` must be `v4` or `v6`",
+            ))
+          }
+        }
+      }
+      other => {
+        return Err(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        ))
+      }
+    }
+  }
+
+  if v6 {
+    return Ok((dst, src, None));
+  }
+  let (scratch, scratch_span) = scratch.ok_or_else(|| {
+    Error::call_site(
+      "missing `scratch = \"rN\"`, which is needed unless `arch = v6`",
+    )
+  })?;
+  if regs_alias(&scratch, &dst) || regs_alias(&scratch, &src) {
+    return Err(Error::new(
+      scratch_span,
+      "`scratch` must differ from the source and destination",
+    ));
+  }
+  Ok((dst, src, Some(scratch)))
+}
@@ -0,0 +1,78 @@
+use super::*;
+
+pub fn cfi_fn_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  let mut saved: Vec<u8> = Vec::new();
+  let mut frame: u32 = 0;
+  let mut body: Option<Group> = None;
+  for arg in split_args(token_stream) {
+    if let Some((name, value)) = get_named_arg(&arg) {
+      match name.as_str() {
+        "saves" => {
+          let saves = get_str_arg(&name, value)?;
+          saved = parse_reg_list(&name, &saves, args_span(value))?.1;
+        }
+        "frame" => {
+          frame = get_int_arg(&name, value)?;
+          if !frame.is_multiple_of(4) {
+            return Err(Error::new(
+              args_span(value),
+              format!("`frame` must be a multiple of 4, got {frame}"),
+            ));
+          }
+        }
+        other => {
+          return Err(Error::new(
+            args_span(&arg),
+            format!("unknown argument `{other}`"),
+          ))
+        }
+      }
+      continue;
+    }
+    match arg.as_slice() {
+      [TokenTree::Group(g)] if g.delimiter() == Delimiter::Brace => {
+        if body.is_some() {
+          return Err(Error::new(g.span(), "the body is given twice"));
+        }
+        body = Some(g.clone());
+      }
+      _ => {
+        return Err(Error::new(
+          args_span(&arg),
+          "expected `saves = \"...\"`, `frame = N`, or the body, eg: `{ ... }`",
+        ))
+      }
+    }
+  }
+  let body = body.ok_or_else(|| Error::call_site("missing the body"))?;
+
+  // `push` stores the lowest register at the lowest address, so after the push
+  // the last register is just below the CFA, and the `frame` bytes are then
+  // taken off below all of them.
+  saved.sort_unstable();
+  let pushed = 4 * saved.len() as u32;
+  let mut directives = String::from(".cfi_startproc\n");
+  if pushed + frame > 0 {
+    directives.push_str(&format!(".cfi_def_cfa_offset {}\n", pushed + frame));
+  }
+  for (i, reg) in saved.iter().enumerate() {
+    let offset = pushed - 4 * i as u32;
+    directives.push_str(&format!(".cfi_offset r{reg}, -{offset}\n"));
+  }
+
+  let mut out_buffer: Vec<TokenTree> = Vec::new();
+  out_buffer.push(TokenTree::Literal(Literal::string(&directives)));
+  out_buffer.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+  extend_concat_as_lines(&mut out_buffer, body.stream());
+  out_buffer.push(TokenTree::Literal(Literal::string(".cfi_endproc\n")));
+
+  let concat_expr = vec![
+    TokenTree::Ident(Ident::new("concat", Span::call_site())),
+    TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+    TokenTree::Group(Group::new(
+      Delimiter::Parenthesis,
+      TokenStream::from_iter(out_buffer),
+    )),
+  ];
+  Ok(TokenStream::from_iter(concat_expr))
+}
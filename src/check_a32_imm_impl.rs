@@ -0,0 +1,53 @@
+use super::*;
+
+pub fn check_a32_imm_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let args = split_args(token_stream);
+  let (value_arg, alt) = match args.as_slice() {
+    [value_arg] => (value_arg, None),
+    [value_arg, alt] => (value_arg, Some(alt)),
+    _ => {
+      return Err(Error::call_site(
+        "expected an integer literal, and optionally `mvn` or `cmn`",
+      ))
+    }
+  };
+  let span = args_span(value_arg);
+  let value = get_int(value_arg)
+    .ok_or_else(|| Error::new(span, "the value must be an integer literal"))?;
+  let text: String = value_arg.iter().map(TokenTree::to_string).collect();
+  let bits = u32::try_from(value)
+    .or_else(|_| i32::try_from(value).map(|v| v as u32))
+    .map_err(|_| {
+      Error::new(span, format!("`{text}` doesn't fit in 32 bits"))
+    })?;
+  let (alt_bits, alt_name) = match alt.map(|alt| alt.as_slice()) {
+    None => (None, ""),
+    Some([TokenTree::Ident(i)]) if i.to_string() == "mvn" => {
+      (Some(!bits), ", nor can its complement (for `mvn`)")
+    }
+    Some([TokenTree::Ident(i)]) if i.to_string() == "cmn" => {
+      (Some(bits.wrapping_neg()), ", nor can its negation (for `cmn`)")
+    }
+    Some(alt) => {
+      return Err(Error::new(
+        args_span(alt),
+        "the second argument must be `mvn` or `cmn`",
+      ))
+    }
+  };
+
+  if a32_imm_encodable(bits) || alt_bits.is_some_and(a32_imm_encodable) {
+    Ok(TokenStream::from(str_literal_at("", out_span)))
+  } else {
+    Err(Error::new(
+      span,
+      format!(
+        "`{text}` ({bits:#X}) can't be an `a32` immediate{alt_name}, it must \
+         be an 8-bit value rotated right by an even amount"
+      ),
+    ))
+  }
+}
@@ -0,0 +1,106 @@
+use super::*;
+
+/// The binary search steps: if the value is below the limit, shift it left and
+/// add the shift to the count.
+const CLZ_STEPS: &[(u32, u32)] =
+  &[(0x10000, 16), (0x100_0000, 8), (0x1000_0000, 4), (0x4000_0000, 2)];
+
+pub fn clz_emulate_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut args = split_args(token_stream).into_iter();
+  let first = args.next().ok_or_else(|| Error::call_site(NOT_ENOUGH_INPUT))?;
+  let (dst, src) = get_reg_assign(&first)?;
+  for reg in [&dst, &src] {
+    if parse_reg(reg) == Some(13) {
+      return Err(Error::new(
+        args_span(&first),
+        format!("`{reg}` can't be used, it's the stack pointer"),
+      ));
+    }
+  }
+
+  let mut scratch: Option<(String, Span)> = None;
+  let mut v5 = false;
+  for arg in args {
+    let (name, value) = get_named_arg(&arg).ok_or_else(|| {
+      Error::new(
+        args_span(&arg),
+        "expected `scratch = \"rN\"` or `arch = v4`/`arch = v5`",
+      )
+    })?;
+    match name.as_str() {
+      "scratch" => {
+        let reg = get_reg_arg(&name, value)?;
+        if matches!(parse_reg(&reg), Some(13 | 15)) {
+          return Err(Error::new(
+            args_span(value),
+            format!("`scratch` can't be `{reg}`"),
+          ));
+        }
+        scratch = Some((reg, args_span(value)));
+      }
+      "arch" => {
+        v5 = match value {
+          [TokenTree::Ident(i)] if i.to_string() == "v4" => false,
+          [TokenTree::Ident(i)] if i.to_string() == "v5" => true,
+          _ => {
+            return Err(Error::new(
+              args_span(value),
+              "`arch` must be `v4` or `v5`",
+            ))
+          }
+        }
+      }
+      other => {
+        return Err(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        ))
+      }
+    }
+  }
+
+  if v5 {
+    return Ok(TokenStream::from(str_literal_at(
+      &format!("clz {dst}, {src}"),
+      out_span,
+    )));
+  }
+  let (scratch, scratch_span) = scratch.ok_or_else(|| {
+    Error::call_site(
+      "missing `scratch = \"rN\"`, which is needed unless `arch = v5`",
+    )
+  })?;
+  if regs_alias(&dst, &src) {
+    return Err(Error::new(
+      args_span(&first),
+      format!(
+        "the destination and source must differ, `{dst}` is the count while \
+         the source is still needed"
+      ),
+    ));
+  }
+  if regs_alias(&scratch, &dst) || regs_alias(&scratch, &src) {
+    return Err(Error::new(
+      scratch_span,
+      "`scratch` must differ from the source and destination",
+    ));
+  }
+
+  // The value is copied to the scratch and shifted up as the count goes up.
+  // When the input is 0 every step counts, for 31, so one more is added then.
+  let mut lines =
+    vec![format!("mov {scratch}, {src}"), format!("mov {dst}, #0")];
+  for (limit, shift) in CLZ_STEPS {
+    lines.push(format!("cmp {scratch}, #{limit:#X}"));
+    lines.push(format!("movlo {scratch}, {scratch}, lsl #{shift}"));
+    lines.push(format!("addlo {dst}, {dst}, #{shift}"));
+  }
+  lines.push(format!("cmp {scratch}, #0x80000000"));
+  lines.push(format!("addlo {dst}, {dst}, #1"));
+  lines.push(format!("cmp {scratch}, #0"));
+  lines.push(format!("addeq {dst}, {dst}, #1"));
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
@@ -0,0 +1,145 @@
+use super::*;
+
+/// A named CP15 register: its names, `CRn`, `CRm`, and `opcode2`, and if it can
+/// be read and written.
+///
+/// `opcode1` is 0 for all of these, as it is for every CP15 register of ARMv4
+/// and ARMv5.
+struct Cp15Reg {
+  names: &'static [&'static str],
+  crn: u8,
+  crm: u8,
+  op2: u8,
+  read: bool,
+  write: bool,
+}
+
+const fn reg(
+  names: &'static [&'static str], crn: u8, crm: u8, op2: u8, read: bool,
+  write: bool,
+) -> Cp15Reg {
+  Cp15Reg { names, crn, crm, op2, read, write }
+}
+
+/// The named CP15 registers, laid out as on the ARM946E-S. The cache and TCM
+/// registers vary between cores, so check the core's manual for those.
+const CP15_REGS: &[Cp15Reg] = &[
+  reg(&["MainId", "Midr", "Id"], 0, 0, 0, true, false),
+  reg(&["CacheType"], 0, 0, 1, true, false),
+  reg(&["TcmSize"], 0, 0, 2, true, false),
+  reg(&["Control", "ControlRegister"], 1, 0, 0, true, true),
+  reg(&["TranslationTableBase", "Ttb"], 2, 0, 0, true, true),
+  reg(&["DomainAccess"], 3, 0, 0, true, true),
+  reg(&["DataFaultStatus", "Fsr"], 5, 0, 0, true, true),
+  reg(&["InstructionFaultStatus", "Ifsr"], 5, 0, 1, true, true),
+  reg(&["FaultAddress", "Far"], 6, 0, 0, true, true),
+  reg(&["WaitForInterrupt"], 7, 0, 4, false, true),
+  reg(&["InvalidateICache"], 7, 5, 0, false, true),
+  reg(&["InvalidateICacheLine"], 7, 5, 1, false, true),
+  reg(&["InvalidateDCache"], 7, 6, 0, false, true),
+  reg(&["InvalidateDCacheLine"], 7, 6, 1, false, true),
+  reg(&["InvalidateCaches"], 7, 7, 0, false, true),
+  reg(&["CleanDCacheLine"], 7, 10, 1, false, true),
+  reg(&["DrainWriteBuffer"], 7, 10, 4, false, true),
+  reg(&["CleanInvalidateDCacheLine"], 7, 14, 1, false, true),
+  reg(&["DtcmRegion"], 9, 1, 0, true, true),
+  reg(&["ItcmRegion"], 9, 1, 1, true, true),
+];
+
+pub fn cp15_read_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  cp15_access(token_stream, false)
+}
+
+pub fn cp15_write_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  cp15_access(token_stream, true)
+}
+
+/// An `mrc` or `mcr` between a core register and a CP15 register.
+fn cp15_access(
+  token_stream: TokenStream, write: bool,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let args = split_args(token_stream);
+  let (reg_arg, cp_args) =
+    args.split_first().ok_or_else(|| Error::call_site(NOT_ENOUGH_INPUT))?;
+  let reg = select_reg(reg_arg)?;
+
+  let (crn, crm, op2) = match cp_args {
+    [] => return Err(Error::call_site("missing the CP15 register")),
+    [name_arg] => {
+      let (name, span) = match name_arg.as_slice() {
+        [TokenTree::Ident(i)] => (i.to_string(), i.span()),
+        _ => {
+          return Err(Error::new(
+            args_span(name_arg),
+            "the CP15 register must be a name, eg: `Control`, or `cN, cN, N`",
+          ))
+        }
+      };
+      let cp = CP15_REGS
+        .iter()
+        .find(|cp| cp.names.contains(&name.as_str()))
+        .ok_or_else(|| {
+          let names: Vec<String> =
+            CP15_REGS.iter().map(|cp| format!("`{}`", cp.names[0])).collect();
+          Error::new(
+            span,
+            format!(
+              "unknown CP15 register `{name}`, expected one of: {}, or `cN, \
+               cN, N` for any other",
+              names.join(", ")
+            ),
+          )
+        })?;
+      if write && !cp.write {
+        return Err(Error::new(span, format!("`{name}` can't be written")));
+      }
+      if !write && !cp.read {
+        return Err(Error::new(span, format!("`{name}` can't be read")));
+      }
+      (cp.crn, cp.crm, cp.op2)
+    }
+    [crn, crm, op2] => {
+      (cp_reg_arg("CRn", crn)?, cp_reg_arg("CRm", crm)?, op2_arg(op2)?)
+    }
+    _ => {
+      return Err(Error::new(
+        args_span(&cp_args[0]),
+        "the CP15 register must be a name, eg: `Control`, or `cN, cN, N`",
+      ))
+    }
+  };
+  let op = if write { "mcr" } else { "mrc" };
+  Ok(TokenStream::from(str_literal_at(
+    &format!("{op} p15, 0, {reg}, c{crn}, c{crm}, {op2}"),
+    out_span,
+  )))
+}
+
+/// Gets a `c0` through `c15` coprocessor register.
+fn cp_reg_arg(name: &str, arg: &[TokenTree]) -> Result<u8, Error> {
+  match arg {
+    [TokenTree::Ident(i)] => i
+      .to_string()
+      .strip_prefix('c')
+      .filter(|digits| !(digits.len() > 1 && digits.starts_with('0')))
+      .and_then(|digits| digits.parse().ok()),
+    _ => None,
+  }
+  .filter(|n| *n <= 15)
+  .ok_or_else(|| {
+    Error::new(args_span(arg), format!("`{name}` must be one of `c0`-`c15`"))
+  })
+}
+
+/// Gets the `opcode2` value, 0 through 7.
+fn op2_arg(arg: &[TokenTree]) -> Result<u8, Error> {
+  get_int(arg)
+    .and_then(|n| u8::try_from(n).ok())
+    .filter(|n| *n <= 7)
+    .ok_or_else(|| {
+      Error::new(args_span(arg), "`opcode2` must be an integer from 0 to 7")
+    })
+}
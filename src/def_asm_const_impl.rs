@@ -0,0 +1,71 @@
+use super::*;
+
+pub fn def_asm_const_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let args = split_args(token_stream);
+  let is_assign = |arg: &Vec<TokenTree>| matches!(arg.get(1), Some(TokenTree::Punct(p)) if *p == '=');
+  let defs: Vec<(&TokenTree, &[TokenTree])> = match args.as_slice() {
+    [] => return Err(Error::call_site(NOT_ENOUGH_INPUT)),
+    [name, value] if !is_assign(name) => match name.as_slice() {
+      [name] => vec![(name, value.as_slice())],
+      _ => {
+        return Err(Error::new(
+          args_span(name),
+          "the name must be a string literal",
+        ))
+      }
+    },
+    _ => args
+      .iter()
+      .map(|arg| match arg.as_slice() {
+        [name, TokenTree::Punct(eq), value @ ..] if *eq == '=' => {
+          Ok((name, value))
+        }
+        _ => Err(Error::new(
+          args_span(arg),
+          "definitions must be of the form `\"NAME\" = value`",
+        )),
+      })
+      .collect::<Result<_, _>>()?,
+  };
+
+  let mut names: Vec<String> = Vec::new();
+  let mut lines = String::new();
+  for (name_tree, value) in defs {
+    let name = get_str_literal_content(name_tree).ok_or_else(|| {
+      Error::new(name_tree.span(), "the name must be a string literal")
+    })?;
+    if !is_symbol_name(&name) {
+      return Err(Error::new(
+        name_tree.span(),
+        format!(
+          "`{name}` can't be an assembler symbol, it must be letters, digits, \
+           `_`, `.`, and `$`, and not start with a digit"
+        ),
+      ));
+    }
+    if names.contains(&name) {
+      return Err(Error::new(
+        name_tree.span(),
+        format!("`{name}` is defined twice"),
+      ));
+    }
+    let bits = get_int(value)
+      .and_then(|v| {
+        u32::try_from(v)
+          .ok()
+          .or_else(|| i32::try_from(v).ok().map(|v| v as u32))
+      })
+      .ok_or_else(|| {
+        Error::new(
+          args_span(value),
+          format!("`{name}` must be a 32-bit integer literal"),
+        )
+      })?;
+    lines.push_str(&format!(".equ {name}, {bits:#X}\n"));
+    names.push(name);
+  }
+  Ok(TokenStream::from(str_literal_at(&lines, out_span)))
+}
@@ -0,0 +1,122 @@
+use super::*;
+
+pub fn div_const_u32_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut dst: Option<String> = None;
+  let mut src: Option<String> = None;
+  let mut by: Option<(u32, Span)> = None;
+  let mut scratch: Option<[(String, Span); 2]> = None;
+  for arg in split_args(token_stream) {
+    let (name, value) = get_named_arg(&arg).ok_or_else(|| {
+      Error::new(
+        args_span(&arg),
+        "arguments must be of the form `name = value`",
+      )
+    })?;
+    match name.as_str() {
+      "dst" => dst = Some(get_reg_arg(&name, value)?),
+      "src" => src = Some(get_reg_arg(&name, value)?),
+      "by" => by = Some((get_int_arg(&name, value)?, args_span(value))),
+      "scratch" => {
+        let scratch_form = || {
+          Error::new(
+            args_span(value),
+            "`scratch` must be two string literals, eg: `[\"r2\", \"r3\"]`",
+          )
+        };
+        let group = match value {
+          [tree] => get_group(tree.clone()),
+          _ => None,
+        }
+        .ok_or_else(scratch_form)?;
+        let regs = split_args(group.stream())
+          .iter()
+          .map(|arg| {
+            let reg = get_str_arg(&name, arg).map_err(|_| scratch_form())?;
+            check_reg(&reg, args_span(arg))?;
+            Ok((reg, args_span(arg)))
+          })
+          .collect::<Result<Vec<(String, Span)>, Error>>()?;
+        scratch = Some(regs.try_into().map_err(|_| scratch_form())?);
+      }
+      other => {
+        return Err(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        ))
+      }
+    }
+  }
+  let dst = dst.ok_or_else(|| Error::call_site("missing `dst = \"rN\"`"))?;
+  let src = src.ok_or_else(|| Error::call_site("missing `src = \"rN\"`"))?;
+  let (by, by_span) = by.ok_or_else(|| Error::call_site("missing `by = N`"))?;
+  if by == 0 {
+    return Err(Error::new(by_span, "can't divide by zero"));
+  }
+
+  let lines = if by.is_power_of_two() {
+    let shift = by.trailing_zeros();
+    if shift == 0 {
+      vec![format!("mov {dst}, {src}")]
+    } else {
+      vec![format!("mov {dst}, {src}, lsr #{shift}")]
+    }
+  } else {
+    let [(lo, lo_span), (hi, hi_span)] = scratch.ok_or_else(|| {
+      Error::call_site("missing `scratch = [\"rN\", \"rM\"]`")
+    })?;
+    let alias_span = if regs_alias(&lo, &src) {
+      Some(lo_span)
+    } else if regs_alias(&hi, &src) || regs_alias(&hi, &lo) {
+      Some(hi_span)
+    } else {
+      None
+    };
+    if let Some(span) = alias_span {
+      return Err(Error::new(
+        span,
+        "`src` and both `scratch` registers must all be different",
+      ));
+    }
+    let (magic, shift, needs_add) = magic_u32(by);
+    let mut lines = vec![
+      format!("ldr {hi}, ={magic:#X}"),
+      format!("umull {lo}, {hi}, {src}, {hi}"),
+    ];
+    if needs_add {
+      // the magic number is really 33 bits, so the top bit is added back in
+      // without overflowing: `q = (((n - q) >> 1) + q) >> shift`
+      lines.push(format!("sub {lo}, {src}, {hi}"));
+      lines.push(format!("add {hi}, {hi}, {lo}, lsr #1"));
+    }
+    lines.push(format!("mov {dst}, {hi}, lsr #{shift}"));
+    lines
+  };
+
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
+
+/// Computes the round-up reciprocal of a divisor that's not a power of two.
+///
+/// Returns the magic multiplier, the shift to apply to the high word of the
+/// product, and if the "add" fixup step is needed.
+fn magic_u32(d: u32) -> (u32, u32, bool) {
+  let floor_log_2_d = 31 - d.leading_zeros();
+  let d = u64::from(d);
+  let numerator = 1_u64 << (32 + floor_log_2_d);
+  let mut proposed_m = numerator / d;
+  let rem = numerator - proposed_m * d;
+  let needs_add = if d - rem < (1 << floor_log_2_d) {
+    false
+  } else {
+    proposed_m += proposed_m;
+    let twice_rem = rem + rem;
+    if twice_rem >= d {
+      proposed_m += 1;
+    }
+    true
+  };
+  ((proposed_m + 1) as u32, floor_log_2_d, needs_add)
+}
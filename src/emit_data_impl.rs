@@ -0,0 +1,66 @@
+use super::*;
+
+/// The most values put on one line.
+const VALUES_PER_LINE: usize = 8;
+
+pub fn emit_words_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  emit_data(token_stream, ".word", "word", 32)
+}
+
+pub fn emit_halfwords_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  emit_data(token_stream, ".hword", "halfword", 16)
+}
+
+pub fn emit_bytes_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  emit_data(token_stream, ".byte", "byte", 8)
+}
+
+/// Data directives of the values, which must each fit in `bits` as either a
+/// signed or unsigned value.
+fn emit_data(
+  token_stream: TokenStream, directive: &str, size_name: &str, bits: u32,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let min = -(1_i128 << (bits - 1));
+  let max = (1_i128 << bits) - 1;
+  let mut errors = Errors::default();
+  let mut values: Vec<i128> = Vec::new();
+  let args = split_args(token_stream).into_iter().filter(|arg| !arg.is_empty());
+  for (i, arg) in args.enumerate() {
+    let Some(value) = get_int(&arg) else {
+      errors.push(Error::new(
+        args_span(&arg),
+        format!("the value at index {i} must be an integer literal"),
+      ));
+      continue;
+    };
+    if !(min..=max).contains(&value) {
+      let text: String = arg.iter().map(TokenTree::to_string).collect();
+      errors.push(Error::new(
+        args_span(&arg),
+        format!(
+          "the value at index {i}, `{text}`, doesn't fit in a {size_name} \
+           ({min} to {max})"
+        ),
+      ));
+      continue;
+    }
+    values.push(value);
+  }
+  errors.finish()?;
+
+  let lines: Vec<String> = values
+    .chunks(VALUES_PER_LINE)
+    .map(|chunk| {
+      let chunk: Vec<String> = chunk.iter().map(i128::to_string).collect();
+      format!("{directive} {}\n", chunk.join(", "))
+    })
+    .collect();
+  Ok(TokenStream::from(str_literal_at(&lines.concat(), out_span)))
+}
@@ -0,0 +1,51 @@
+use super::*;
+
+/// Each exception kind's names, and the instruction that returns from it.
+///
+/// The `lr` of an exception points some way past the instruction to return
+/// to, depending on how far along the pipeline was when it hit.
+const EXCEPTION_RETURNS: &[(&[&str], &str)] = &[
+  (&["Irq"], "subs pc, lr, #4"),
+  (&["Fiq"], "subs pc, lr, #4"),
+  (&["Swi", "Svc"], "movs pc, lr"),
+  (&["Undefined"], "movs pc, lr"),
+  (&["PrefetchAbort"], "subs pc, lr, #4"),
+  (&["DataAbort"], "subs pc, lr, #8"),
+];
+
+pub fn exception_return_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let trees: Vec<TokenTree> = token_stream.into_iter().collect();
+  let ident = match trees.as_slice() {
+    [TokenTree::Ident(i)] => i,
+    [] => return Err(Error::call_site(NOT_ENOUGH_INPUT)),
+    _ => {
+      return Err(Error::new(
+        args_span(&trees),
+        "expected one exception kind, eg: `Irq`",
+      ))
+    }
+  };
+  let name = ident.to_string();
+  let line = EXCEPTION_RETURNS
+    .iter()
+    .find(|(names, _)| names.contains(&name.as_str()))
+    .map(|(_, line)| *line)
+    .ok_or_else(|| {
+      let kinds: Vec<String> = EXCEPTION_RETURNS
+        .iter()
+        .map(|(names, _)| {
+          names.iter().map(|n| format!("`{n}`")).collect::<Vec<_>>().join("/")
+        })
+        .collect();
+      Error::new(
+        ident.span(),
+        format!(
+          "unknown exception kind `{name}`, expected one of: {}",
+          kinds.join(", ")
+        ),
+      )
+    })?;
+  Ok(TokenStream::from(str_literal_at(line, ident.span())))
+}
@@ -0,0 +1,103 @@
+use super::*;
+
+pub fn fn_footer_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  let mut args = split_args(token_stream).into_iter();
+  let (name, span) = fn_name_arg(args.next())?;
+  let footer = fn_footer_text(&name, args)?;
+  Ok(TokenStream::from(str_literal_at(&footer, span)))
+}
+
+pub fn asm_fn_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  let mut args = split_args(token_stream).into_iter();
+  let (name, _) = fn_name_arg(args.next())?;
+  let mut state = IsaState::A32;
+  let mut header_args: Vec<Vec<TokenTree>> = Vec::new();
+  let mut footer_args: Vec<Vec<TokenTree>> = Vec::new();
+  let mut body: Option<Group> = None;
+  for arg in args {
+    if let Some((arg_name, _)) = get_named_arg(&arg) {
+      if arg_name == "pool" {
+        footer_args.push(arg);
+      } else {
+        header_args.push(arg);
+      }
+      continue;
+    }
+    match arg.as_slice() {
+      [TokenTree::Ident(i)] if i.to_string() == "a32" => state = IsaState::A32,
+      [TokenTree::Ident(i)] if i.to_string() == "t32" => state = IsaState::T32,
+      [TokenTree::Group(g)] if g.delimiter() == Delimiter::Brace => {
+        if body.is_some() {
+          return Err(Error::new(g.span(), "the body is given twice"));
+        }
+        body = Some(g.clone());
+      }
+      _ => {
+        return Err(Error::new(
+          args_span(&arg),
+          "expected `a32`, `t32`, `name = value`, or the body, eg: `{ ... }`",
+        ))
+      }
+    }
+  }
+  let header = fn_header_lines(&name, state, header_args)?;
+  let footer = fn_footer_text(&name, footer_args)?;
+  let body = body.ok_or_else(|| Error::call_site("missing the body"))?;
+
+  let mut out_buffer: Vec<TokenTree> = Vec::new();
+  out_buffer.push(TokenTree::Literal(Literal::string(&format!(
+    "{}\n",
+    header.join("\n")
+  ))));
+  out_buffer.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+  extend_concat_as_lines(&mut out_buffer, body.stream());
+  out_buffer.push(TokenTree::Literal(Literal::string(&footer)));
+
+  let concat_expr = vec![
+    TokenTree::Ident(Ident::new("concat", Span::call_site())),
+    TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+    TokenTree::Group(Group::new(
+      Delimiter::Parenthesis,
+      TokenStream::from_iter(out_buffer),
+    )),
+  ];
+  Ok(TokenStream::from_iter(concat_expr))
+}
+
+/// The `.size` line that ends a function, and the `.ltorg` before it when the
+/// `pool` argument is set.
+fn fn_footer_text(
+  name: &str, args: impl IntoIterator<Item = Vec<TokenTree>>,
+) -> Result<String, Error> {
+  let mut pool = false;
+  for arg in args {
+    let (arg_name, value) = get_named_arg(&arg).ok_or_else(|| {
+      Error::new(
+        args_span(&arg),
+        "arguments must be of the form `name = value`",
+      )
+    })?;
+    match arg_name.as_str() {
+      "pool" => {
+        pool = match value {
+          [tree] => get_bool(tree),
+          _ => None,
+        }
+        .ok_or_else(|| {
+          Error::new(
+            args_span(value),
+            "`pool` must be set as `true` or `false`",
+          )
+        })?
+      }
+      other => {
+        return Err(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        ))
+      }
+    }
+  }
+  let size = format!(".size {name}, . - {name}\n");
+  Ok(if pool { format!(".ltorg\n{size}") } else { size })
+}
@@ -0,0 +1,113 @@
+use super::*;
+
+pub fn a32_fn_header_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  fn_header(token_stream, IsaState::A32)
+}
+
+pub fn t32_fn_header_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  fn_header(token_stream, IsaState::T32)
+}
+
+fn fn_header(
+  token_stream: TokenStream, state: IsaState,
+) -> Result<TokenStream, Error> {
+  let mut args = split_args(token_stream).into_iter();
+  let (name, span) = fn_name_arg(args.next())?;
+  let lines = fn_header_lines(&name, state, args)?;
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), span)))
+}
+
+/// The `.global`, `.type`, alignment, and label lines that start a function,
+/// using the `global` and `align` arguments.
+pub fn fn_header_lines(
+  name: &str, state: IsaState, args: impl IntoIterator<Item = Vec<TokenTree>>,
+) -> Result<Vec<String>, Error> {
+  let min_align: u32 = match state {
+    IsaState::A32 => 4,
+    IsaState::T32 => 2,
+  };
+  let mut global = true;
+  let mut align = min_align;
+  for arg in args {
+    let (arg_name, value) = get_named_arg(&arg).ok_or_else(|| {
+      Error::new(
+        args_span(&arg),
+        "arguments must be of the form `name = value`",
+      )
+    })?;
+    match arg_name.as_str() {
+      "global" => {
+        global = match value {
+          [tree] => get_bool(tree),
+          _ => None,
+        }
+        .ok_or_else(|| {
+          Error::new(
+            args_span(value),
+            "`global` must be set as `true` or `false`",
+          )
+        })?
+      }
+      "align" => {
+        align = get_int_arg(&arg_name, value)?;
+        if !align.is_power_of_two() || align < min_align {
+          return Err(Error::new(
+            args_span(value),
+            format!(
+              "`align` must be a power of two of at least {min_align} for \
+               {} code, got {align}",
+              state.name()
+            ),
+          ));
+        }
+      }
+      other => {
+        return Err(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        ))
+      }
+    }
+  }
+
+  let mut lines: Vec<String> = Vec::new();
+  if global {
+    lines.push(format!(".global {name}"));
+  }
+  lines.push(format!(".type {name}, %function"));
+  lines.push(format!(".balign {align}"));
+  if state == IsaState::T32 {
+    lines.push(".thumb_func".to_string());
+  }
+  lines.push(format!("{name}:"));
+  Ok(lines)
+}
+
+/// Gets the function name, which must be the first argument.
+pub fn fn_name_arg(
+  arg: Option<Vec<TokenTree>>,
+) -> Result<(String, Span), Error> {
+  let arg = arg.ok_or_else(|| Error::call_site(NOT_ENOUGH_INPUT))?;
+  let span = args_span(&arg);
+  let name = match arg.as_slice() {
+    [tree] => get_str_literal_content(tree),
+    _ => None,
+  }
+  .ok_or_else(|| {
+    Error::new(
+      span,
+      "the first argument must be the function name, as a string literal",
+    )
+  })?;
+  if !is_symbol_name(&name) {
+    return Err(Error::new(
+      span,
+      format!("`{name}` is not a valid symbol name"),
+    ));
+  }
+  Ok((name, span))
+}
@@ -0,0 +1,165 @@
+use super::*;
+
+pub fn gas_macro_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut args = split_args(token_stream).into_iter();
+  let name_arg =
+    args.next().ok_or_else(|| Error::call_site(NOT_ENOUGH_INPUT))?;
+  let name = match name_arg.as_slice() {
+    [tree] => get_str_literal_content(tree),
+    _ => None,
+  }
+  .ok_or_else(|| {
+    Error::new(
+      args_span(&name_arg),
+      "the first argument must be the macro name, as a string literal",
+    )
+  })?;
+  if !is_symbol_name(&name) {
+    return Err(Error::new(
+      args_span(&name_arg),
+      format!("`{name}` is not a valid macro name"),
+    ));
+  }
+
+  let mut params: Vec<String> = Vec::new();
+  let mut body: Option<Group> = None;
+  for arg in args {
+    if let Some((arg_name, value)) = get_named_arg(&arg) {
+      match arg_name.as_str() {
+        "params" => params = param_list(value)?,
+        other => {
+          return Err(Error::new(
+            args_span(&arg),
+            format!("unknown argument `{other}`"),
+          ))
+        }
+      }
+      continue;
+    }
+    match arg.as_slice() {
+      [TokenTree::Group(g)] if g.delimiter() == Delimiter::Brace => {
+        if body.is_some() {
+          return Err(Error::new(g.span(), "the body is given twice"));
+        }
+        body = Some(g.clone());
+      }
+      _ => {
+        return Err(Error::new(
+          args_span(&arg),
+          "expected `params = [...]` or the body, eg: `{ ... }`",
+        ))
+      }
+    }
+  }
+  let body = body.ok_or_else(|| Error::call_site("missing the body"))?;
+
+  let mut errors = Errors::default();
+  let mut lines: Vec<String> = Vec::new();
+  for line_arg in split_args(body.stream()) {
+    let Some(line) = (match line_arg.as_slice() {
+      [tree] => get_str_literal_content(tree),
+      _ => None,
+    }) else {
+      errors.push(Error::new(
+        args_span(&line_arg),
+        "the body lines must be string literals, so that the parameter uses \
+         can be checked",
+      ));
+      continue;
+    };
+    for used in param_uses(&line) {
+      if !params.contains(&used) {
+        errors.push(Error::new(
+          args_span(&line_arg),
+          format!("`\\{used}` is used, but `{used}` is not a parameter"),
+        ));
+      }
+    }
+    lines.extend(line.lines().map(str::to_string));
+  }
+  errors.finish()?;
+
+  let mut out = format!(".macro {name}");
+  for param in &params {
+    out.push(' ');
+    out.push_str(param);
+  }
+  out.push('\n');
+  for line in lines {
+    // the lines are plain assembly, so braces (eg: register lists) are
+    // escaped for the `asm!` template.
+    out.push_str(&line.replace('{', "{{").replace('}', "}}"));
+    out.push('\n');
+  }
+  out.push_str(".endm\n");
+  Ok(TokenStream::from(str_literal_at(&out, out_span)))
+}
+
+/// Gets the parameter names of a `params = ["a", "b"]` argument.
+fn param_list(value: &[TokenTree]) -> Result<Vec<String>, Error> {
+  let [TokenTree::Group(g)] = value else {
+    return Err(Error::new(
+      args_span(value),
+      "`params` must be a list of string literals, eg: `[\"a\", \"b\"]`",
+    ));
+  };
+  if g.delimiter() != Delimiter::Bracket {
+    return Err(Error::new(
+      g.span(),
+      "`params` must be a list of string literals, eg: `[\"a\", \"b\"]`",
+    ));
+  }
+  let mut errors = Errors::default();
+  let mut params: Vec<String> = Vec::new();
+  for param_arg in split_args(g.stream()) {
+    let Some(param) = (match param_arg.as_slice() {
+      [tree] => get_str_literal_content(tree),
+      _ => None,
+    }) else {
+      errors.push(Error::new(
+        args_span(&param_arg),
+        "parameter names must be string literals",
+      ));
+      continue;
+    };
+    let is_ident = param
+      .starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+      && param.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !is_ident {
+      errors.push(Error::new(
+        args_span(&param_arg),
+        format!("`{param}` is not a valid parameter name"),
+      ));
+    } else if params.contains(&param) {
+      errors.push(Error::new(
+        args_span(&param_arg),
+        format!("the parameter `{param}` is given more than once"),
+      ));
+    } else {
+      params.push(param);
+    }
+  }
+  errors.finish()?;
+  Ok(params)
+}
+
+/// Gets the names used as `\name` in a line.
+///
+/// The assembler's own `\@` (the macro call count) and `\()` (an empty
+/// separator) aren't parameter uses, so they're skipped.
+fn param_uses(line: &str) -> Vec<String> {
+  let mut uses = Vec::new();
+  let mut rest = line;
+  while let Some(i) = rest.find('\\') {
+    rest = &rest[i + 1..];
+    let len = rest
+      .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+      .unwrap_or(rest.len());
+    if len > 0 {
+      uses.push(rest[..len].to_string());
+    }
+    rest = &rest[len..];
+  }
+  uses
+}
@@ -0,0 +1,79 @@
+use super::*;
+
+pub fn in_section_impl(
+  attr: TokenStream, item: TokenStream,
+) -> Result<TokenStream, Error> {
+  let mut args = split_args(attr).into_iter();
+  let name_arg = args.next().ok_or_else(|| {
+    Error::call_site("expected a section name, eg: `#[in_section(\".iwram\")]`")
+  })?;
+  let section_name = match name_arg.as_slice() {
+    [tree] => get_str_literal_content(tree),
+    _ => None,
+  }
+  .ok_or_else(|| {
+    Error::new(
+      args_span(&name_arg),
+      "the section name must be a string literal",
+    )
+  })?;
+  if let Some(problem) = section_name_problem(&section_name) {
+    return Err(Error::new(
+      args_span(&name_arg),
+      format!("`{section_name}` is not a valid section name: {problem}"),
+    ));
+  }
+  let instruction_set = match args.next() {
+    None => None,
+    Some(arg) => match arg.as_slice() {
+      [TokenTree::Ident(i)] if i.to_string() == "a32" => Some("a32"),
+      [TokenTree::Ident(i)] if i.to_string() == "t32" => Some("t32"),
+      _ => {
+        return Err(Error::new(
+          args_span(&arg),
+          "the instruction set must be `a32` or `t32`",
+        ))
+      }
+    },
+  };
+  if let Some(extra) = args.next() {
+    return Err(Error::new(
+      args_span(&extra),
+      "too many arguments, expected a section name and an instruction set",
+    ));
+  }
+  check_is_fn(&item)?;
+
+  let mut attrs = format!("#[link_section = {section_name:?}]");
+  if let Some(set) = instruction_set {
+    write!(attrs, "#[instruction_set(arm::{set})]").unwrap();
+  }
+  let mut out = TokenStream::from_str(&attrs).unwrap();
+  out.extend(item);
+  Ok(out)
+}
+
+/// Checks that an item is a function, looking past any attributes,
+/// visibility, and qualifiers.
+fn check_is_fn(item: &TokenStream) -> Result<(), Error> {
+  const NOT_FN: &str = "`#[in_section]` can only be used on a function";
+  let mut trees = item.clone().into_iter();
+  while let Some(tree) = trees.next() {
+    match &tree {
+      // an attribute, which is `#` and then the bracketed part
+      TokenTree::Punct(p) if *p == '#' => {
+        trees.next();
+      }
+      TokenTree::Ident(i) => match i.to_string().as_str() {
+        "fn" => return Ok(()),
+        "pub" | "const" | "async" | "unsafe" | "extern" | "default" => (),
+        _ => return Err(Error::new(i.span(), NOT_FN)),
+      },
+      // the `(crate)` of `pub(crate)`, and the ABI of `extern "C"`
+      TokenTree::Group(g) if g.delimiter() == Delimiter::Parenthesis => (),
+      TokenTree::Literal(_) => (),
+      other => return Err(Error::new(other.span(), NOT_FN)),
+    }
+  }
+  Err(Error::call_site(NOT_FN))
+}
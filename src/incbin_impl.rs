@@ -0,0 +1,98 @@
+use super::*;
+
+pub fn incbin_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut args = split_args(token_stream).into_iter();
+  let path_arg =
+    args.next().ok_or_else(|| Error::call_site(NOT_ENOUGH_INPUT))?;
+  let path = match path_arg.as_slice() {
+    [tree] => get_str_literal_content(tree),
+    _ => None,
+  }
+  .ok_or_else(|| {
+    Error::new(
+      args_span(&path_arg),
+      "the first argument must be the file path, as a string literal",
+    )
+  })?;
+  // The path is placed in quotes as is, so it can't hold anything that would
+  // end the quotes early or be read as an escape.
+  if path.is_empty() || path.contains(['"', '\\', '\n']) {
+    return Err(Error::new(
+      args_span(&path_arg),
+      format!(
+        "{path:?} can't be used as an `.incbin` path, it must be non-empty \
+         and can't have `\"`, `\\`, or newlines"
+      ),
+    ));
+  }
+
+  let mut symbol: Option<String> = None;
+  let mut section = ".rodata".to_string();
+  let mut flags = "a".to_string();
+  let mut align: u32 = 4;
+  for arg in args {
+    let (arg_name, value) = get_named_arg(&arg).ok_or_else(|| {
+      Error::new(
+        args_span(&arg),
+        "arguments must be of the form `name = value`",
+      )
+    })?;
+    match arg_name.as_str() {
+      "symbol" => {
+        let name = get_str_arg(&arg_name, value)?;
+        if !is_symbol_name(&name) {
+          return Err(Error::new(
+            args_span(value),
+            format!("`{name}` is not a valid symbol name"),
+          ));
+        }
+        symbol = Some(name);
+      }
+      "section" => {
+        section = get_str_arg(&arg_name, value)?;
+        if let Some(problem) = section_name_problem(&section) {
+          return Err(Error::new(
+            args_span(value),
+            format!("`{section}` is not a valid section name: {problem}"),
+          ));
+        }
+      }
+      "flags" => {
+        flags = get_str_arg(&arg_name, value)?;
+        check_section_flags(&flags, args_span(value))?;
+      }
+      "align" => {
+        align = match get_int(value) {
+          Some(align) => check_align(align, args_span(value))?,
+          None => {
+            return Err(Error::new(
+              args_span(value),
+              "`align` must be an integer literal",
+            ))
+          }
+        }
+      }
+      other => {
+        return Err(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        ))
+      }
+    }
+  }
+  let symbol =
+    symbol.ok_or_else(|| Error::call_site("missing `symbol = \"name\"`"))?;
+
+  let lines = [
+    format!(r#".section {section},"{flags}",%progbits"#),
+    format!(".balign {align}"),
+    format!(".global {symbol}"),
+    format!("{symbol}:"),
+    format!(r#".incbin "{path}""#),
+    format!(".global {symbol}_end"),
+    format!("{symbol}_end:"),
+    format!(".size {symbol}, {symbol}_end - {symbol}"),
+  ];
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
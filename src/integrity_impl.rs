@@ -0,0 +1,74 @@
+use super::*;
+
+pub fn with_integrity_word_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let args = split_args(token_stream);
+  let (label_arg, body) = match args.as_slice() {
+    [label_arg, body] => (label_arg, body),
+    _ => {
+      return Err(Error::call_site(
+        "expected `label = \"name\"` and then a grouping of lines",
+      ))
+    }
+  };
+  let (label, label_span) = match get_named_arg(label_arg) {
+    Some((name, value)) if name == "label" => {
+      (get_str_arg(&name, value)?, args_span(value))
+    }
+    _ => {
+      return Err(Error::new(
+        args_span(label_arg),
+        "the first argument must be `label = \"name\"`",
+      ))
+    }
+  };
+  if !is_symbol_name(&label) {
+    return Err(Error::new(
+      label_span,
+      format!("`{label}` is not a valid label name"),
+    ));
+  }
+  let text = body_text(body)?;
+  let crc = crc32(text.as_bytes());
+
+  let out =
+    format!("{text}b {label}_end\n{label}:\n.word {crc:#010X}\n{label}_end:\n");
+  Ok(TokenStream::from(str_literal_at(&out, out_span)))
+}
+
+pub fn integrity_value_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let body: Vec<TokenTree> = token_stream.into_iter().collect();
+  let crc = crc32(body_text(&body)?.as_bytes());
+  Ok(TokenStream::from(TokenTree::Literal(Literal::u32_suffixed(crc))))
+}
+
+/// Folds a grouping of lines to the text that the lines would assemble from.
+fn body_text(body: &[TokenTree]) -> Result<String, Error> {
+  let group = match body {
+    [tree] => get_group(tree.clone()),
+    _ => None,
+  }
+  .ok_or_else(|| {
+    Error::new(args_span(body), "the lines must be in a single grouping")
+  })?;
+  let mut lines = Vec::new();
+  extend_concat_as_lines(&mut lines, group.stream());
+  fold_to_str(TokenStream::from_iter(lines))
+}
+
+/// The common CRC-32 (as used by zip, png, etc).
+fn crc32(bytes: &[u8]) -> u32 {
+  let mut crc = u32::MAX;
+  for &byte in bytes {
+    crc ^= u32::from(byte);
+    for _ in 0..8 {
+      let mask = (crc & 1).wrapping_neg();
+      crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+    }
+  }
+  !crc
+}
@@ -0,0 +1,104 @@
+use super::*;
+
+pub fn irp_each_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let args = split_args(token_stream);
+  let [var_arg, list_arg, body_arg] = args.as_slice() else {
+    return Err(Error::new(
+      args_span(&args.concat()),
+      "expected the loop variable, the list, and the body, eg: `\"reg\", \
+       [\"r0\", \"r1\"], { ... }`",
+    ));
+  };
+
+  let var = match var_arg.as_slice() {
+    [tree] => get_str_literal_content(tree),
+    _ => None,
+  }
+  .ok_or_else(|| {
+    Error::new(args_span(var_arg), "the loop variable must be a string literal")
+  })?;
+  let is_ident = var.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+    && var.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+  if !is_ident {
+    return Err(Error::new(
+      args_span(var_arg),
+      format!("`{var}` is not a valid loop variable name"),
+    ));
+  }
+
+  let list = match list_arg.as_slice() {
+    [TokenTree::Group(g)] if g.delimiter() == Delimiter::Bracket => g,
+    _ => {
+      return Err(Error::new(
+        args_span(list_arg),
+        "the list must be in brackets, eg: `[\"r0\", \"r1\"]`",
+      ))
+    }
+  };
+  let mut errors = Errors::default();
+  let mut items: Vec<String> = Vec::new();
+  let item_args = split_args(list.stream());
+  if item_args.is_empty() {
+    errors.push(Error::new(list.span(), "the list can't be empty"));
+  }
+  for item_arg in item_args {
+    let item = match item_arg.as_slice() {
+      [tree] => get_str_literal_content(tree),
+      _ => None,
+    }
+    .or_else(|| get_int(&item_arg).map(|i| i.to_string()));
+    match item {
+      // the list is comma and space separated, so an item can't have those.
+      Some(item)
+        if !item.is_empty()
+          && !item.contains(|c: char| c == ',' || c.is_whitespace()) =>
+      {
+        items.push(item)
+      }
+      _ => errors.push(Error::new(
+        args_span(&item_arg),
+        "list items must be integer literals or string literals without \
+         commas or whitespace",
+      )),
+    }
+  }
+
+  let body = match body_arg.as_slice() {
+    [TokenTree::Group(g)] if g.delimiter() == Delimiter::Brace => g,
+    _ => {
+      return Err(Error::new(
+        args_span(body_arg),
+        "expected the body, eg: `{ ... }`",
+      ))
+    }
+  };
+  let mut lines: Vec<String> = Vec::new();
+  for line_arg in split_args(body.stream()) {
+    let line = match line_arg.as_slice() {
+      [tree] => get_str_literal_content(tree),
+      _ => None,
+    };
+    match line {
+      Some(line) => lines.extend(line.lines().map(str::to_string)),
+      None => errors.push(Error::new(
+        args_span(&line_arg),
+        format!(
+          "the body lines must be string literals, other macros can't be \
+           used since their output can't use `\\{var}`"
+        ),
+      )),
+    }
+  }
+  errors.finish()?;
+
+  let mut out = format!(".irp {var}, {}\n", items.join(", "));
+  for line in lines {
+    // the lines are plain assembly, so braces (eg: register lists) are
+    // escaped for the `asm!` template.
+    out.push_str(&line.replace('{', "{{").replace('}', "}}"));
+    out.push('\n');
+  }
+  out.push_str(".endr\n");
+  Ok(TokenStream::from(str_literal_at(&out, out_span)))
+}
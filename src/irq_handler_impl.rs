@@ -0,0 +1,162 @@
+use super::*;
+
+pub fn irq_handler_prologue_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let steps = irq_handler_steps(token_stream)?;
+  let lines: Vec<&str> = steps.iter().map(|(save, _)| save.as_str()).collect();
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
+
+pub fn irq_handler_epilogue_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let steps = irq_handler_steps(token_stream)?;
+  let lines: Vec<&str> =
+    steps.iter().rev().map(|(_, restore)| restore.as_str()).collect();
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
+
+/// The registers saved when no `save` list is given: the ones a called
+/// function is allowed to clobber, plus `lr`.
+const DEFAULT_SAVE: &str = "r0-r3, r12, lr";
+
+/// Parses the arguments, then gives each step of the prologue paired with the
+/// step of the epilogue that undoes it.
+///
+/// The prologue does the steps in order, and the epilogue does them in
+/// reverse.
+fn irq_handler_steps(
+  token_stream: TokenStream,
+) -> Result<Vec<(String, String)>, Error> {
+  let mut save: Option<(String, Span)> = None;
+  let mut spsr: Option<(String, Span)> = None;
+  let mut sys_mode = false;
+  let mut fiq_masked = false;
+  for arg in split_args(token_stream) {
+    let (name, value) = get_named_arg(&arg).ok_or_else(|| {
+      Error::new(
+        args_span(&arg),
+        "arguments must be of the form `name = value`",
+      )
+    })?;
+    match name.as_str() {
+      "save" => save = Some((get_str_arg(&name, value)?, args_span(value))),
+      "spsr" => spsr = Some((get_reg_arg(&name, value)?, args_span(value))),
+      "sys_mode" | "fiq_masked" => {
+        let setting = match value {
+          [tree] => get_bool(tree),
+          _ => None,
+        }
+        .ok_or_else(|| {
+          Error::new(
+            args_span(value),
+            format!("`{name}` must be set as `true` or `false`"),
+          )
+        })?;
+        if name == "sys_mode" {
+          sys_mode = setting;
+        } else {
+          fiq_masked = setting;
+        }
+      }
+      other => {
+        return Err(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        ))
+      }
+    }
+  }
+  let (save, save_span) =
+    save.unwrap_or_else(|| (DEFAULT_SAVE.to_string(), Span::call_site()));
+  let (save_list, saved) = parse_reg_list("save", &save, save_span)?;
+
+  let mut steps = vec![(
+    format!("push {{{{{save_list}}}}}"),
+    format!("pop {{{{{save_list}}}}}"),
+  )];
+  if let Some((spsr, spsr_span)) = spsr {
+    let n = parse_reg(&spsr).filter(|n| *n <= 12).ok_or_else(|| {
+      Error::new(
+        spsr_span,
+        format!("`spsr` can't be `{spsr}`, it must be one of `r0`-`r12`"),
+      )
+    })?;
+    if !saved.contains(&n) {
+      return Err(Error::new(
+        spsr_span,
+        format!(
+          "`{spsr}` must also be in the `save` list, or the interrupted \
+           code's value of it is lost"
+        ),
+      ));
+    }
+    steps.push((format!("mrs {spsr}, SPSR"), format!("msr SPSR_cxsf, {spsr}")));
+    steps.push((format!("push {{{{{spsr}}}}}"), format!("pop {{{{{spsr}}}}}")));
+  }
+  if sys_mode {
+    let sys = cpu_mode_bits("sys").unwrap();
+    let irq = cpu_mode_bits("irq").unwrap();
+    steps.push((
+      set_cpu_control_asm(sys, true, fiq_masked),
+      set_cpu_control_asm(irq, true, fiq_masked),
+    ));
+    // System mode shares `lr` with User mode, so the body calling any function
+    // would lose the `lr` of the interrupted code.
+    steps.push(("push {{lr}}".to_string(), "pop {{lr}}".to_string()));
+  }
+  Ok(steps)
+}
+
+/// Parses a register list such as `r0-r3, r12, lr`, giving the list as it
+/// should be written in a `push` or `pop`, along with each register number in
+/// it. The `name` of the argument the list came from is used in errors.
+pub fn parse_reg_list(
+  name: &str, text: &str, span: Span,
+) -> Result<(String, Vec<u8>), Error> {
+  let mut items: Vec<String> = Vec::new();
+  let mut regs: Vec<u8> = Vec::new();
+  for item in text.split(',').map(str::trim) {
+    let (first, last) = item.split_once('-').unwrap_or((item, item));
+    let (first, last) = (first.trim(), last.trim());
+    let (Some(lo), Some(hi)) = (parse_reg(first), parse_reg(last)) else {
+      return Err(Error::new(
+        span,
+        format!(
+          "`{item}` in the `{name}` list is not a register or a range of \
+           registers, eg: `r0-r3`"
+        ),
+      ));
+    };
+    if lo > hi {
+      return Err(Error::new(
+        span,
+        format!("the range `{item}` in the `{name}` list is backwards"),
+      ));
+    }
+    for n in lo..=hi {
+      if n == 13 || n == 15 {
+        return Err(Error::new(
+          span,
+          format!("the `{name}` list can't hold `sp` or `pc`"),
+        ));
+      }
+      if regs.contains(&n) {
+        return Err(Error::new(
+          span,
+          format!("`r{n}` is in the `{name}` list twice"),
+        ));
+      }
+      regs.push(n);
+    }
+    if first == last {
+      items.push(first.to_string());
+    } else {
+      items.push(format!("{first}-{last}"));
+    }
+  }
+  Ok((items.join(", "), regs))
+}
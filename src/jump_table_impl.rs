@@ -0,0 +1,96 @@
+use super::*;
+
+pub fn jump_table_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut args = split_args(token_stream).into_iter();
+  let reg_arg =
+    args.next().ok_or_else(|| Error::call_site(NOT_ENOUGH_INPUT))?;
+  let index = select_reg(&reg_arg)?;
+  if parse_reg(&index) == Some(13) {
+    return Err(Error::new(
+      args_span(&reg_arg),
+      format!("`{index}` can't be used, it's the stack pointer"),
+    ));
+  }
+
+  let list_arg =
+    args.next().ok_or_else(|| Error::call_site(NOT_ENOUGH_INPUT))?;
+  let list = match list_arg.as_slice() {
+    [TokenTree::Group(g)] if g.delimiter() == Delimiter::Bracket => g,
+    _ => {
+      return Err(Error::new(
+        args_span(&list_arg),
+        "the second argument must be the case labels in brackets, eg: \
+         `[\"case_zero\", \"case_one\"]`",
+      ))
+    }
+  };
+  let label_args = split_args(list.stream());
+  if label_args.is_empty() {
+    return Err(Error::new(list.span(), "the case list can't be empty"));
+  }
+  let mut errors = Errors::default();
+  let mut labels: Vec<String> = Vec::new();
+  for label_arg in &label_args {
+    if let Some(label) = errors.check(symbol_name_arg(label_arg)) {
+      labels.push(label);
+    }
+  }
+
+  let mut default: Option<String> = None;
+  for arg in args {
+    if let Some((name, value)) = get_named_arg(&arg) {
+      match name.as_str() {
+        "default" => default = errors.check(symbol_name_arg(value)),
+        other => errors.push(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        )),
+      }
+      continue;
+    }
+    match arg.as_slice() {
+      [TokenTree::Ident(i)] if i.to_string() == "a32" => (),
+      [TokenTree::Ident(i)] if i.to_string() == "t32" => {
+        errors.push(Error::new(
+          i.span(),
+          "`t32` isn't supported yet, there's no `add pc` with a shifted \
+           register in t32, so the table needs a different approach",
+        ))
+      }
+      _ => errors.push(Error::new(
+        args_span(&arg),
+        "expected `default = \"label\"` or the state, `a32`",
+      )),
+    }
+  }
+  errors.finish()?;
+  let default = default.ok_or_else(|| {
+    Error::call_site(
+      "missing `default = \"label\"`, where an out of range index branches to",
+    )
+  })?;
+  let count = labels.len();
+  if !a32_imm_encodable(count as u32) {
+    return Err(Error::new(
+      list.span(),
+      format!(
+        "{count} cases can't be the immediate of the bounds check `cmp`, it \
+         must be an 8-bit value rotated right by an even amount"
+      ),
+    ));
+  }
+
+  // Reading `pc` gives the address of the `addlo` plus 8, which is the first
+  // entry of the table. That skips the `b` to the default, which is then only
+  // reached when the `addlo` doesn't run.
+  let mut lines = vec![
+    format!("cmp {index}, #{count}"),
+    format!("addlo pc, pc, {index}, lsl #2"),
+    format!("b {default}"),
+  ];
+  lines.extend(labels.iter().map(|label| format!("b {label}")));
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
@@ -0,0 +1,192 @@
+use super::*;
+
+pub fn extract_lane_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let args = LaneArgs::parse(token_stream, &["dst", "src", "signed", "arch"])?;
+  let dst = args.reg("dst")?;
+  let src = args.reg("src")?;
+  let (width, lane) = args.width_and_lane()?;
+  let pos = lane * width;
+
+  let lines = if args.arch >= Arch::V6 {
+    let op = match (args.signed, width) {
+      (false, 8) => "uxtb",
+      (false, _) => "uxth",
+      (true, 8) => "sxtb",
+      (true, _) => "sxth",
+    };
+    if pos == 0 {
+      vec![format!("{op} {dst}, {src}")]
+    } else {
+      vec![format!("{op} {dst}, {src}, ror #{pos}")]
+    }
+  } else {
+    // Shift the lane up to the top of the register, then back down to the
+    // bottom, which clears (or sign extends) the other bits.
+    let shr = if args.signed { "asr" } else { "lsr" };
+    let up = 32 - pos - width;
+    let down = 32 - width;
+    if up == 0 {
+      vec![format!("mov {dst}, {src}, {shr} #{down}")]
+    } else {
+      vec![
+        format!("mov {dst}, {src}, lsl #{up}"),
+        format!("mov {dst}, {dst}, {shr} #{down}"),
+      ]
+    }
+  };
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
+
+pub fn insert_lane_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let args = LaneArgs::parse(token_stream, &["dst", "src", "scratch"])?;
+  let dst = args.reg("dst")?;
+  let src = args.reg("src")?;
+  let scratch = args.reg("scratch")?;
+  let (width, lane) = args.width_and_lane()?;
+  let pos = lane * width;
+  if regs_alias(&dst, &src) {
+    return Err(Error::new(args.span("src"), "`src` must differ from `dst`"));
+  }
+  if regs_alias(&scratch, &dst) || regs_alias(&scratch, &src) {
+    return Err(Error::new(
+      args.span("scratch"),
+      "`scratch` must differ from `dst` and `src`",
+    ));
+  }
+
+  // The lane mask isn't always encodable as one immediate, but each byte of
+  // it always is, so the lane is cleared one byte at a time.
+  let mut lines: Vec<String> = (0..width / 8)
+    .map(|byte| {
+      format!("bic {dst}, {dst}, #{:#X}", 0xFF_u32 << (pos + byte * 8))
+    })
+    .collect();
+  // Shifting `src` up then down clears any bits of it above the lane width.
+  lines.push(format!("mov {scratch}, {src}, lsl #{}", 32 - width));
+  let down = 32 - width - pos;
+  if down == 0 {
+    lines.push(format!("orr {dst}, {dst}, {scratch}"));
+  } else {
+    lines.push(format!("orr {dst}, {dst}, {scratch}, lsr #{down}"));
+  }
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
+
+/// The named arguments of the lane macros.
+struct LaneArgs {
+  regs: Vec<(String, String, Span)>,
+  width: Option<(u32, Span)>,
+  lane: Option<(u32, Span)>,
+  signed: bool,
+  arch: Arch,
+}
+impl LaneArgs {
+  /// Parses the arguments. Registers, `width`, and `lane` are always allowed,
+  /// the other names only if they're listed.
+  fn parse(token_stream: TokenStream, allowed: &[&str]) -> Result<Self, Error> {
+    let mut out = Self {
+      regs: Vec::new(),
+      width: None,
+      lane: None,
+      signed: false,
+      arch: Arch::V4,
+    };
+    for arg in split_args(token_stream) {
+      let (name, value) = get_named_arg(&arg).ok_or_else(|| {
+        Error::new(
+          args_span(&arg),
+          "arguments must be of the form `name = value`",
+        )
+      })?;
+      match name.as_str() {
+        "width" => {
+          out.width = Some((get_int_arg(&name, value)?, args_span(value)))
+        }
+        "lane" => {
+          out.lane = Some((get_int_arg(&name, value)?, args_span(value)))
+        }
+        "signed" if allowed.contains(&"signed") => {
+          out.signed = match value {
+            [tree] => get_bool(tree),
+            _ => None,
+          }
+          .ok_or_else(|| {
+            Error::new(
+              args_span(value),
+              "`signed` must be set as `true` or `false`",
+            )
+          })?
+        }
+        "arch" if allowed.contains(&"arch") => {
+          out.arch = match value {
+            [tree] => Arch::from_option(tree)?,
+            _ => {
+              return Err(Error::new(
+                args_span(value),
+                "`arch` must be one arch name, eg: `v6`",
+              ))
+            }
+          }
+        }
+        reg if allowed.contains(&reg) => {
+          let text = get_reg_arg(&name, value)?;
+          out.regs.push((name, text, args_span(value)));
+        }
+        other => {
+          return Err(Error::new(
+            args_span(&arg),
+            format!("unknown argument `{other}`"),
+          ))
+        }
+      }
+    }
+    Ok(out)
+  }
+
+  fn reg(&self, name: &str) -> Result<String, Error> {
+    self
+      .regs
+      .iter()
+      .rev()
+      .find(|(n, ..)| n == name)
+      .map(|(_, text, _)| text.clone())
+      .ok_or_else(|| Error::call_site(format!("missing `{name} = \"rN\"`")))
+  }
+
+  fn span(&self, name: &str) -> Span {
+    self
+      .regs
+      .iter()
+      .rev()
+      .find(|(n, ..)| n == name)
+      .map_or(Span::call_site(), |(.., span)| *span)
+  }
+
+  /// Gets the width and lane, checking that the lane fits in a register.
+  fn width_and_lane(&self) -> Result<(u32, u32), Error> {
+    let (width, width_span) =
+      self.width.ok_or_else(|| Error::call_site("missing `width = N`"))?;
+    let (lane, lane_span) =
+      self.lane.ok_or_else(|| Error::call_site("missing `lane = N`"))?;
+    if width != 8 && width != 16 {
+      return Err(Error::new(width_span, "`width` must be 8 or 16"));
+    }
+    if lane >= 32 / width {
+      return Err(Error::new(
+        lane_span,
+        format!(
+          "lane {lane} of width {width} is past the end of the register, \
+           `lane` must be less than {}",
+          32 / width
+        ),
+      ));
+    }
+    Ok((width, lane))
+  }
+}
@@ -7,34 +7,114 @@
 //! These macros help you get your assembly written, but they have nearly no
 //! ability to help ensure that your assembly is correct. In rare cases where
 //! something can be statically known to be "obviously" wrong (eg: an invalid
-//! register name is picked for a specific instruction) the macro will panic.
+//! register name is picked for a specific instruction) the macro will emit a
+//! `compile_error!` instead of the assembly.
 
 extern crate proc_macro;
+use a32_change_cpu_control_impl::{change_cpu_control_lines, scratch_arg};
+use a32_set_cpu_control_impl::{
+  cpu_mode_bits, cpu_mode_names, set_cpu_control_asm,
+};
+use asm_block_impl::IsaState;
+use balign_impl::check_align;
 use core::{
   fmt::Write,
   str::FromStr,
   sync::atomic::{AtomicU64, Ordering},
 };
+use fn_header_impl::{fn_header_lines, fn_name_arg};
+use irq_handler_impl::parse_reg_list;
+use lint::*;
 use proc_macro::{
   Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream,
   TokenTree,
 };
+use put_fn_in_section_impl::{
+  check_section_flags, check_section_type, section_name_problem,
+};
+use reg_select_impl::{get_reg_assign, select_reg};
+use t32_with_a32_scope_impl::check_no_state_switch;
 use util::*;
+use weak_symbol_impl::symbol_name_arg;
+use when_impl::{
+  check_label_not_defined, cmp_test_with, flags_test_with, parse_label,
+};
 
+mod a32_change_cpu_control_impl;
 mod a32_fake_blx_impl;
 mod a32_read_spsr_to_impl;
 mod a32_set_cpu_control_impl;
+mod a32_set_interrupt_masks_impl;
+mod a32_with_mode_impl;
 mod a32_write_spsr_from_impl;
+mod adjust_ptr_impl;
+mod align_dispatch_impl;
+mod arch_directive_impl;
+mod asciz_impl;
+mod asm_block_impl;
+mod asm_consts_impl;
+mod assert_code_size_impl;
+mod assert_t16_ok_impl;
+mod balign_impl;
+mod bkpt_debug_impl;
+mod byte_reverse_impl;
+mod cfi_fn_impl;
+mod check_a32_imm_impl;
+mod clz_emulate_impl;
+mod cp15_impl;
+mod def_asm_const_impl;
+mod div_const_u32_impl;
+mod emit_data_impl;
+mod exception_return_impl;
+mod fn_footer_impl;
+mod fn_header_impl;
+mod gas_macro_impl;
+mod in_section_impl;
+mod incbin_impl;
+mod integrity_impl;
+mod irp_each_impl;
+mod irq_handler_impl;
+mod jump_table_impl;
+mod lane_impl;
+mod lint;
+mod literal_pool_impl;
+mod load_const_impl;
+mod loop_impl;
+mod mmio_bits_impl;
+mod mul_const_impl;
+mod nested_irq_impl;
+mod poll_with_timeout_impl;
+mod prng_step_impl;
 mod put_fn_in_section_impl;
+mod reg_abs_impl;
+mod reg_select_impl;
+mod region_impl;
+mod reserve_zeroed_impl;
+mod sat_arith_impl;
+mod set_mode_stacks_impl;
+mod set_symbol_impl;
+mod svc_reentry_impl;
+mod swap_regs_impl;
+mod swi_call_impl;
+mod swp_swap_impl;
+mod t32_execute_a32_impl;
 mod t32_with_a32_scope_impl;
+mod task_frame_impl;
 mod util;
+mod vector_table_impl;
+mod weak_symbol_impl;
 mod when_impl;
+mod with_masked_interrupts_impl;
+mod with_reg_aliases_impl;
+mod with_section_impl;
 
 /// Reads SPSR to the register given.
 ///
 /// ## Input
 /// A single string literal that's an actual register name (eg: `"r0"`), or an
-/// assembly register substitution name (eg: `"{temp}"`).
+/// assembly register substitution name (eg: `"{temp}"`). The register can't
+/// be `sp` or `pc`. An actual register name can also be given without quotes
+/// (eg: `r0`).
 ///
 /// ## Output
 /// This expands to one line of assembly using the [`mrs`][mrs_docs] instruction
@@ -50,17 +130,65 @@ mod when_impl;
 #[proc_macro]
 pub fn a32_read_spsr_to(token_stream: TokenStream) -> TokenStream {
   a32_read_spsr_to_impl::a32_read_spsr_to_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Reads CPSR to the register given.
+///
+/// ## Input
+/// The same as [`a32_read_spsr_to!`]: one register, which can't be `sp` or
+/// `pc`.
+///
+/// ## Output
+/// This expands to one line of assembly using the [`mrs`][mrs_docs] instruction
+/// to read CPSR to the named register. Unlike SPSR, CPSR can be read in any
+/// mode.
+///
+/// [mrs_docs]: https://developer.arm.com/documentation/dui0473/m/arm-and-thumb-instructions/mrs--system-coprocessor-register-to-arm-register-
+#[proc_macro]
+pub fn a32_read_cpsr_to(token_stream: TokenStream) -> TokenStream {
+  a32_read_spsr_to_impl::a32_read_cpsr_to_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Reads the current CPU mode to the register given.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// a32_get_cpu_mode!("r0")
+/// # ;
+/// # assert_eq!(s, "mrs r0, CPSR\nand r0, r0, #0x1F");
+/// ```
+///
+/// * The input is the same as with [`a32_read_cpsr_to!`]. The register is
+///   used for both reading CPSR and masking it, so no scratch is needed.
+/// * The output is the 5 mode bits of CPSR: `0b10000` User, `0b10001` FIQ,
+///   `0b10010` IRQ, `0b10011` Supervisor, `0b10111` Abort, `0b11011`
+///   Undefined, or `0b11111` System. These are the same bits as the mode part
+///   of [`cpu_control_bits!`], so the two can be compared with a mask.
+#[proc_macro]
+pub fn a32_get_cpu_mode(token_stream: TokenStream) -> TokenStream {
+  a32_read_spsr_to_impl::a32_get_cpu_mode_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
 }
 
 /// Writes SPSR from the register given.
 ///
 /// ## Input
-/// A single string literal that's an actual register name (eg: `"r0"`), or an
-/// assembly register substitution name (eg: `"{temp}"`).
+/// * A string literal that's an actual register name (eg: `"r0"`), or an
+///   assembly register substitution name (eg: `"{temp}"`). The register can't
+///   be `sp` or `pc`. An actual register name can also be given without quotes
+///   (eg: `r0`).
+/// * Optionally, a string literal of the fields of SPSR to write, some of `c`
+///   (control), `x` (extension), `s` (status), and `f` (flags), each at most
+///   once. The default is all of them.
 ///
 /// ## Output
 /// This expands to one line of assembly using the [`msr`][msr_docs] instruction
-/// to write SPSR from the named register.
+/// to write SPSR from the named register, such as `msr SPSR_cxsf, r0`. The
+/// fields are always written in the `cxsf` order.
 ///
 /// ## Assembly Safety
 /// * The `mrs` docs warn you not to use `mrs` to access SPSR when in User or
@@ -72,6 +200,33 @@ pub fn a32_read_spsr_to(token_stream: TokenStream) -> TokenStream {
 #[proc_macro]
 pub fn a32_write_spsr_from(token_stream: TokenStream) -> TokenStream {
   a32_write_spsr_from_impl::a32_write_spsr_from_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Writes CPSR from the register given.
+///
+/// ## Input
+/// The same as [`a32_write_spsr_from!`]: one register, which can't be `sp` or
+/// `pc`, and then optionally the fields to write (eg: `"f"` for only the
+/// flags). The default is all of them.
+///
+/// ## Output
+/// This expands to one line of assembly using the [`msr`][msr_docs] instruction
+/// to write CPSR from the named register, such as `msr CPSR_cxsf, r0`.
+///
+/// ## Assembly Safety
+/// * In User mode only the flags can be written. Writes to the other fields
+///   (including the `c` field's mode and interrupt mask bits) are silently
+///   ignored by the CPU, they don't cause an exception.
+/// * Changing the mode changes which `sp` and `lr` (and more, for FIQ) are in
+///   use, which the compiler doesn't know about. Generally only the flags
+///   should be written inside an `asm!` that the compiler expects to return.
+///
+/// [msr_docs]: https://developer.arm.com/documentation/dui0489/i/arm-and-thumb-instructions/msr--arm-register-to-system-coprocessor-register-
+#[proc_macro]
+pub fn a32_write_cpsr_from(token_stream: TokenStream) -> TokenStream {
+  a32_write_spsr_from_impl::a32_write_cpsr_from_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
 }
 
 /// ARMv4T lacks the actual `blx` instruction, so this performs a "fake"
@@ -79,7 +234,16 @@ pub fn a32_write_spsr_from(token_stream: TokenStream) -> TokenStream {
 ///
 /// ## Input
 /// A single string literal that's an actual register name (eg: `"r0"`), or an
-/// assembly register substitution name (eg: `"{temp}"`).
+/// assembly register substitution name (eg: `"{temp}"`). An actual register
+/// name can also be given without quotes (eg: `r0`).
+///
+/// The register can't be `sp`, `lr`, or `pc` (or `r13`, `r14`, `r15`), since
+/// none of those can hold the target address: in particular `lr` would be
+/// overwritten with the return address before the branch. Substitutions can't
+/// be checked, so they're always allowed.
+///
+/// The register can be followed by an architecture, such as `, arch = v5te`.
+/// The default is `v4`.
 ///
 /// ## Output
 /// Emits a string literal of `a32` code like the following:
@@ -88,10 +252,49 @@ pub fn a32_write_spsr_from(token_stream: TokenStream) -> TokenStream {
 /// bx <reg>
 /// ```
 ///
+/// When the architecture is `v5t` or later, the real instruction exists, so
+/// the output is just `blx <reg>` instead (and `lr` is allowed as the target).
+///
 /// This assembly is only correct in `a32` state.
 #[proc_macro]
 pub fn a32_fake_blx(token_stream: TokenStream) -> TokenStream {
   a32_fake_blx_impl::a32_fake_blx_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// ARMv4T lacks the actual `blx` instruction, so this performs a "fake"
+/// `blx`-styled operation from `t32` code.
+///
+/// ## Input
+/// The same as with [`a32_fake_blx!`], without the architecture: a register
+/// name or substitution, which can't be `sp`, `lr`, or `pc`.
+///
+/// ## Output
+/// Emits a string literal of `t32` code like the following:
+/// ```arm
+/// bl .+8  // set `lr` to the next `bl` (with the t32 bit), and go to the `bx`
+/// bl .+6  // the callee returns here, so skip over the `bx`
+/// bx <reg>
+/// ```
+///
+/// Like with a real `blx`, `lr` is clobbered.
+///
+/// ## Why not `mov lr, pc`?
+/// In `t32` state, reading `pc` gives the address of the current instruction
+/// plus 4 (two halfword instructions ahead), not plus 8 like in `a32` state.
+/// So after a `mov lr, pc` (at address `A`) and `bx <reg>` (at `A+2`), `lr`
+/// does hold `A+4`, the right return address. However, the low bit of `lr` is
+/// then clear, so the callee's `bx lr` would return in `a32` state and run the
+/// `t32` code as garbage. Setting the low bit needs an `add` that `t32` can't
+/// do to `lr` on ARMv4T without another register. Instead, `bl` always sets
+/// `lr` to the next instruction with the low bit set, so it's used to "call"
+/// the `bx`, with a second `bl` as the return point to skip past the `bx`.
+///
+/// This assembly is only correct in `t32` state.
+#[proc_macro]
+pub fn t32_fake_blx(token_stream: TokenStream) -> TokenStream {
+  a32_fake_blx_impl::t32_fake_blx_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
 }
 
 /// Emits a `.section` directive to place the code in a section name you pick.
@@ -99,79 +302,3298 @@ pub fn a32_fake_blx(token_stream: TokenStream) -> TokenStream {
 /// Use this *before* the label for the function you're writing.
 ///
 /// ## Input
-/// A string literal that's a valid section name. It should be alphanumeric,
-/// also `.` is allowed.
+/// * A string literal that's a valid section name. It can use ascii
+///   alphanumerics, `.`, `_`, `-`, and `$`, and it can't be empty or start
+///   with a digit. Starting with `.` is fine, as with `.text.foo`.
+/// * Optionally, a string literal of the section flags, each at most once:
+///   `a` (allocated), `w` (writable), `x` (executable), `M` (mergeable), `S`
+///   (strings), and `T` (thread local). The default is `"ax"`.
+/// * Optionally after the flags, a string literal of the section type, one of
+///   `%progbits`, `%nobits`, or `%note`. The default is `%progbits`.
 ///
 /// ## Output
-/// Emits a `.section` directive with the section name you specify and also
-/// properly marks the section as `allocated` and `executable`.
+/// Emits a `.section` directive with the section name, flags, and type. With
+/// only a section name the section is marked as `allocated` and `executable`:
+/// `.section NAME,"ax",%progbits`.
 #[proc_macro]
 pub fn put_fn_in_section(token_stream: TokenStream) -> TokenStream {
   put_fn_in_section_impl::put_fn_in_section_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
 }
 
-/// Places `.code 32` at the start and `.code 16` at the end of the input
-/// sequence.
+/// Emits the directives and label that start an `a32` function.
+///
+/// Use this after [`put_fn_in_section!`] (or any other way of picking the
+/// section).
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// a32_fn_header!("my_handler")
+/// # ;
+/// # assert_eq!(s, ".global my_handler\n.type my_handler, %function\n.balign 4\nmy_handler:");
+/// ```
 ///
 /// ## Input
-/// The input sequence should be zero or more expressions (comma separated) that
-/// could each normally be used within an `asm!` block.
+/// * The function name, as a string literal that's a valid symbol name.
+/// * `global = bool` (optional, default `true`): if the function can be linked
+///   to from other object files.
+/// * `align = N` (optional, default 4): the alignment, which must be a power of
+///   two and at least 4.
 ///
 /// ## Output
-/// A single `concat!` expression, with newlines inserted for each input
-/// expression, and with the `.code` directives at the start and end.
+/// A string literal of lines: a `.global` (unless `global = false`), a `.type`
+/// marking the name as a function, a `.balign`, and then the label.
+#[proc_macro]
+pub fn a32_fn_header(token_stream: TokenStream) -> TokenStream {
+  fn_header_impl::a32_fn_header_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Emits the directives and label that start a `t32` function.
 ///
-/// ## Safety
-/// You **must* use this within `t32` code only. You **must not** use this
-/// within an `a32` encoded assembly block. It will leave the assembler in a bad
-/// state after the assembly string, which is UB.
+/// Use this after [`put_fn_in_section!`] (or any other way of picking the
+/// section).
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// t32_fn_header!("my_thumb_fn")
+/// # ;
+/// # assert_eq!(s, ".global my_thumb_fn\n.type my_thumb_fn, %function\n.balign 2\n.thumb_func\nmy_thumb_fn:");
+/// ```
+///
+/// ## Input
+/// The same as with [`a32_fn_header!`], except that the default alignment is
+/// 2, and `align` must be at least 2.
+///
+/// ## Output
+/// The same lines as with [`a32_fn_header!`], with a `.thumb_func` just before
+/// the label. That marks the symbol as `t32` code, so the linker sets the low
+/// bit of its address, and calls to it from `a32` code switch state.
+///
+/// ## Assembly Safety
+/// `.thumb_func` only marks the symbol, the assembler must already be in `t32`
+/// mode (`.thumb` or `.code 16`) for the code after it.
 #[proc_macro]
-pub fn t32_with_a32_scope(token_stream: TokenStream) -> TokenStream {
-  t32_with_a32_scope_impl::t32_with_a32_scope_impl(token_stream)
+pub fn t32_fn_header(token_stream: TokenStream) -> TokenStream {
+  fn_header_impl::t32_fn_header_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
 }
 
-/// Generates the asm string to set the CPU control bits.
+/// Emits the `.size` directive that ends a function.
 ///
-/// Input must be of the form:
-/// ```text
-/// {mode_name}, irq_masked = {bool}, fiq_masked = {bool}
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// fn_footer!("my_handler")
+/// # ;
+/// # assert_eq!(s, ".size my_handler, . - my_handler\n");
 /// ```
 ///
-/// Valid mode names are the long name or short name of a CPU mode:
-/// * User / usr
-/// * FIQ / fiq
-/// * IRQ / irq
-/// * Supervisor / svc
-/// * System / sys
+/// ## Input
+/// * The function name, as a string literal that's a valid symbol name.
+/// * `pool = bool` (optional, default `false`): if the literal pool should be
+///   placed at the end of the function.
+///
+/// ## Output
+/// A string literal of a `.size` line that sets the size of the function's
+/// symbol to the distance from its label, so that the linker and other tools
+/// know how big the function is. With `pool = true`, there's an `.ltorg` line
+/// before it.
+///
+/// Use this at the end of a function started with [`a32_fn_header!`] or
+/// [`t32_fn_header!`], or use [`asm_fn!`] to do both at once.
 ///
 /// ## Assembly Safety
-/// This instruction can only be used in `a32` code.
+/// With `pool = true`, the last line of the function must be one that never
+/// falls through, such as a return.
 #[proc_macro]
-pub fn a32_set_cpu_control(token_stream: TokenStream) -> TokenStream {
-  a32_set_cpu_control_impl::a32_set_cpu_control_impl(token_stream)
+pub fn fn_footer(token_stream: TokenStream) -> TokenStream {
+  fn_footer_impl::fn_footer_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
 }
 
-/// Emits code that will perform the test and skip past some lines if the test
-/// does not pass.
+/// Wraps some lines as a whole function, with a header and footer that use the
+/// same name.
 ///
 /// **Usage Example:**
 /// ```rust
 /// # use bracer::*;
 /// # let s =
-/// when!(("r0" != "#0")[1]{
-///   "add r1, r2, r3",
-///   "add r0, r1, r4",
+/// asm_fn!("my_handler", {
+///   "mov r0, #0",
+///   "bx lr",
 /// })
 /// # ;
+/// # assert_eq!(s, ".global my_handler\n.type my_handler, %function\n.balign 4\nmy_handler:\nmov r0, #0\nbx lr\n.size my_handler, . - my_handler\n");
 /// ```
 ///
-/// * The test to perform must be in one grouping.
-/// * The number literal for the numeric label placed at the end of the block
-///   must be another grouping.
-/// * The lines to execute when the test passes must be in a separate grouping.
-/// * The macro *does not* care what grouping markers you use, `()`, `[]`, and
-///   `{}` are all fine.
+/// ## Input
+/// * The function name, as a string literal that's a valid symbol name.
+/// * `a32` or `t32` (optional, default `a32`): which header to use.
+/// * The `global` and `align` arguments of [`a32_fn_header!`], and the `pool`
+///   argument of [`fn_footer!`], all optional.
+/// * The body, as a braced group of string literals, one line each.
+///
+/// ## Output
+/// A `concat!` expression of the lines of [`a32_fn_header!`] (or
+/// [`t32_fn_header!`]), the body, and then the lines of [`fn_footer!`].
+///
+/// ## Assembly Safety
+/// Pick the section first, such as with [`put_fn_in_section!`]. With `t32`,
+/// the assembler must already be in `t32` mode.
 #[proc_macro]
-pub fn when(token_stream: TokenStream) -> TokenStream {
-  when_impl::when_impl(token_stream)
+pub fn asm_fn(token_stream: TokenStream) -> TokenStream {
+  fn_footer_impl::asm_fn_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Emits a `.balign` directive, checking that the alignment is a power of two.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// balign!(4)
+/// # ;
+/// # assert_eq!(s, ".balign 4\n");
+/// # let s =
+/// balign!(32, 0xFF, 16)
+/// # ;
+/// # assert_eq!(s, ".balign 32, 0xFF, 16\n");
+/// ```
+///
+/// ## Input
+/// * The alignment, as an integer literal that's a power of two from 1 to
+///   32768. `.balign` itself would take any number, such as 3, so this catches
+///   typos.
+/// * The fill (optional): the byte value to pad with, from 0 to 0xFF. This can
+///   be left empty (as in `balign!(8, , 4)`) to use the default fill while
+///   still giving a max skip.
+/// * The max skip (optional): the most bytes to skip, passed on as written. If
+///   aligning would take more than this, no alignment is done.
+///
+/// ## Output
+/// A string literal of one `.balign` line.
+#[proc_macro]
+pub fn balign(token_stream: TokenStream) -> TokenStream {
+  balign_impl::balign_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Emits the lines to reserve a zeroed static buffer, such as a stack.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// reserve_zeroed!("irq_stack", 0x400, section = ".bss.iwram", align = 8)
+/// # ;
+/// # assert_eq!(s, ".section .bss.iwram,\"aw\",%nobits\n.balign 8\n.global irq_stack\nirq_stack:\n.space 0x400\n.size irq_stack, . - irq_stack");
+/// ```
+///
+/// ## Input
+/// * The symbol name, as a string literal that's a valid symbol name.
+/// * The size in bytes, as a positive integer literal.
+/// * `section = "name"` (optional, default `".bss"`): the section to put the
+///   buffer in.
+/// * `flags = "flags"` (optional, default `"aw"`): the section flags, the same
+///   as with [`put_fn_in_section!`].
+/// * `align = N` (optional, default 4): the alignment, the same as with
+///   [`balign!`].
+///
+/// ## Output
+/// A string literal of lines: a `.section` of type `%nobits`, a `.balign`, a
+/// `.global` and label for the symbol, a `.space` of the size, and a `.size`
+/// for the symbol.
+///
+/// A `%nobits` section takes no space in the output file, the buffer is zeroed
+/// at startup like other `.bss` data. That only happens if the startup code
+/// zeroes this section, so pick a section that it covers.
+///
+/// ## Assembly Safety
+/// This changes the section, so use it after the code that's in the current
+/// section, or switch back afterwards.
+#[proc_macro]
+pub fn reserve_zeroed(token_stream: TokenStream) -> TokenStream {
+  reserve_zeroed_impl::reserve_zeroed_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Emits the lines to include the bytes of a file, with symbols for the start
+/// and end of the data.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// incbin!("assets/tiles.bin", symbol = "tiles_data", section = ".rodata", align = 4)
+/// # ;
+/// # assert_eq!(s, ".section .rodata,\"a\",%progbits\n.balign 4\n.global tiles_data\ntiles_data:\n.incbin \"assets/tiles.bin\"\n.global tiles_data_end\ntiles_data_end:\n.size tiles_data, tiles_data_end - tiles_data");
+/// ```
+///
+/// ## Input
+/// * The path of the file, as a string literal. This is passed on as written,
+///   so it can't have `"`, `\`, or newlines.
+/// * `symbol = "name"`: the symbol for the start of the data. The end of the
+///   data gets the same name with `_end` added.
+/// * `section = "name"` (optional, default `".rodata"`): the section to put the
+///   data in.
+/// * `flags = "flags"` (optional, default `"a"`): the section flags, the same
+///   as with [`put_fn_in_section!`].
+/// * `align = N` (optional, default 4): the alignment, the same as with
+///   [`balign!`].
+///
+/// ## Output
+/// A string literal of lines: a `.section`, a `.balign`, a `.global` and label
+/// for the start, the `.incbin`, a `.global` and label for the end, and a
+/// `.size` for the start symbol.
+///
+/// From Rust, declare both symbols as `extern` statics and take the bytes
+/// between their addresses.
+///
+/// ## Working Directory
+/// A relative path is found from the directory the assembler runs in, *not*
+/// the file that has the `global_asm!`. When Cargo builds, that's the
+/// directory `rustc` is run in, which is normally the workspace root. Since
+/// this macro needs a string literal, the path can't be built with `env!`, so
+/// either use a path relative to the workspace root or an absolute one.
+/// Changes to the file also aren't tracked, so Cargo won't rebuild when only
+/// the file changes unless a build script says to.
+///
+/// ## Assembly Safety
+/// This changes the section, so use it after the code that's in the current
+/// section, or switch back afterwards.
+#[proc_macro]
+pub fn incbin(token_stream: TokenStream) -> TokenStream {
+  incbin_impl::incbin_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Emits `.word` directives of some values.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// emit_words!(0x100, 0x200, 0x300)
+/// # ;
+/// # assert_eq!(s, ".word 256, 512, 768\n");
+/// ```
+///
+/// ## Input
+/// Any number of integer literals, of any base, that each fit in 32 bits
+/// (signed or unsigned).
+///
+/// ## Output
+/// A string literal of `.word` lines with up to 8 values each, or an empty
+/// string literal if there are no values. It's a compile error naming the
+/// index of any value that doesn't fit.
+#[proc_macro]
+pub fn emit_words(token_stream: TokenStream) -> TokenStream {
+  emit_data_impl::emit_words_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Emits `.hword` directives of some values.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// emit_halfwords!(0x7FFF, -1, 0b1_0000)
+/// # ;
+/// # assert_eq!(s, ".hword 32767, -1, 16\n");
+/// ```
+///
+/// ## Input
+/// Any number of integer literals, of any base, that each fit in 16 bits
+/// (signed or unsigned).
+///
+/// ## Output
+/// The same as with [`emit_words!`], but with `.hword` lines.
+#[proc_macro]
+pub fn emit_halfwords(token_stream: TokenStream) -> TokenStream {
+  emit_data_impl::emit_halfwords_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Emits `.byte` directives of some values.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// emit_bytes!(1, 2, 3, 255)
+/// # ;
+/// # assert_eq!(s, ".byte 1, 2, 3, 255\n");
+/// ```
+///
+/// ## Input
+/// Any number of integer literals, of any base, that each fit in 8 bits
+/// (signed or unsigned).
+///
+/// ## Output
+/// The same as with [`emit_words!`], but with `.byte` lines.
+#[proc_macro]
+pub fn emit_bytes(token_stream: TokenStream) -> TokenStream {
+  emit_data_impl::emit_bytes_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Emits an `.asciz` directive of some text.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// asciz!("hello: %d\n")
+/// # ;
+/// # assert_eq!(s, ".asciz \"hello: %d\\n\"\n");
+/// # let s =
+/// asciz!("msg_hello", "hello")
+/// # ;
+/// # assert_eq!(s, "msg_hello:\n.asciz \"hello\"\n.size msg_hello, . - msg_hello\n");
+/// ```
+///
+/// ## Input
+/// * (Optional) The label to put on the text, as a string literal.
+/// * The text, as a string literal. It can't contain a NUL.
+///
+/// ## Output
+/// A string literal of the `.asciz` line, with the text escaped for the
+/// assembler. Printable ascii is kept, `"` and `\` are escaped, newlines,
+/// tabs, and carriage returns use `\n`, `\t`, and `\r`, and every other byte
+/// (including each byte of non-ascii characters in UTF-8) is an octal escape.
+/// Braces are doubled, since the output is `asm!` template text.
+///
+/// With a label, the directive is placed after `label:` and followed by
+/// `.size label, . - label`.
+#[proc_macro]
+pub fn asciz(token_stream: TokenStream) -> TokenStream {
+  asciz_impl::asciz_impl(token_stream).unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Emits `.weak` directives for some symbols.
+///
+/// A weak symbol can be defined by other code, and that definition is used in
+/// place of the weak one. This is how to give a default that can be replaced,
+/// such as a default interrupt handler.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// weak_symbol!("default_irq_handler")
+/// # ;
+/// # assert_eq!(s, ".weak default_irq_handler\n");
+/// # let s =
+/// weak_symbol!("a", "b", "c")
+/// # ;
+/// # assert_eq!(s, ".weak a\n.weak b\n.weak c\n");
+/// ```
+///
+/// ## Input
+/// One or more symbol names, as string literals.
+///
+/// ## Output
+/// A string literal with one `.weak` line per symbol.
+#[proc_macro]
+pub fn weak_symbol(token_stream: TokenStream) -> TokenStream {
+  weak_symbol_impl::weak_symbol_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Emits the directive to set the ELF visibility of a symbol.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// symbol_visibility!("helper", hidden)
+/// # ;
+/// # assert_eq!(s, ".hidden helper\n");
+/// ```
+///
+/// ## Input
+/// * The symbol name, as a string literal.
+/// * The visibility: `hidden`, `protected`, or `internal`.
+///
+/// ## Output
+/// A string literal of the `.hidden`, `.protected`, or `.internal` line.
+#[proc_macro]
+pub fn symbol_visibility(token_stream: TokenStream) -> TokenStream {
+  weak_symbol_impl::symbol_visibility_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Defines a symbol as an alias of another symbol.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// set_symbol!("__aeabi_memcpy", "fast_memcpy")
+/// # ;
+/// # assert_eq!(s, ".global __aeabi_memcpy\n.set __aeabi_memcpy, fast_memcpy\n");
+/// # let s =
+/// set_symbol!("memcpy_alias", "fast_memcpy", global = false, function = true)
+/// # ;
+/// # assert_eq!(s, ".type memcpy_alias, %function\n.set memcpy_alias, fast_memcpy\n");
+/// ```
+///
+/// ## Input
+/// * The alias name, as a string literal.
+/// * The target name, as a string literal. It can't be the alias itself.
+/// * `global = bool` (optional, default `true`): if the alias is exported with
+///   `.global`.
+/// * `function = bool` (optional, default `false`): if the alias is marked
+///   with `.type alias, %function`. Set this when the target is a function, so
+///   that calls to the alias from `t32` code interwork correctly.
+///
+/// ## Output
+/// A string literal of the `.global` and `.type` lines (as set), then `.set
+/// alias, target`.
+#[proc_macro]
+pub fn set_symbol(token_stream: TokenStream) -> TokenStream {
+  set_symbol_impl::set_symbol_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Defines an assembler macro with `.macro` and `.endm`.
+///
+/// The macro is expanded by the assembler itself, so it can be used with
+/// `.rept` and `.irp`, and used any number of times without making the
+/// `global_asm!` text longer.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// gas_macro!("save_regs", params = ["first", "last"], {
+///   r"stmfd sp!, {\first-\last}",
+/// })
+/// # ;
+/// # assert_eq!(s, ".macro save_regs first last\nstmfd sp!, {{\\first-\\last}}\n.endm\n");
+/// ```
+///
+/// ## Input
+/// * The macro name, as a string literal.
+/// * `params = ["a", "b", ...]` (optional): the parameter names, which must be
+///   identifiers.
+/// * The body, as a braced group of string literals, one or more lines each. A
+///   parameter is used as `\name` (so `"\\name"` or `r"\name"` in Rust).
+///
+/// ## Output
+/// A string literal of the `.macro` line, the body lines, and `.endm`.
+///
+/// The body lines are plain assembly rather than `asm!` template text, so
+/// their braces are escaped for you. It's a compile error to use `\name` in
+/// the body when `name` isn't a parameter, other than the assembler's own `\@`
+/// and `\()`.
+#[proc_macro]
+pub fn gas_macro(token_stream: TokenStream) -> TokenStream {
+  gas_macro_impl::gas_macro_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Repeats some lines for each item of a list with `.irp` and `.endr`.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// irp_each!("reg", ["r0", "r1", "r2", "r3"], {
+///   "mov \\reg, #0",
+/// })
+/// # ;
+/// # assert_eq!(s, ".irp reg, r0, r1, r2, r3\nmov \\reg, #0\n.endr\n");
+/// ```
+///
+/// ## Input
+/// * The loop variable name, as a string literal. It must be an identifier.
+/// * The list, in brackets. Each item is an integer literal or a string
+///   literal without commas or whitespace. The list can't be empty.
+/// * The body, as a braced group of string literals, one or more lines each.
+///   The current item is used as `\name` (so `"\\name"` or `r"\name"` in
+///   Rust).
+///
+/// ## Output
+/// A string literal of the `.irp` line, the body lines, and `.endr`. As with
+/// [`gas_macro!`], the braces of the body lines are escaped for you.
+///
+/// Other bracer macros can't be used in the body, since what they output can't
+/// use the loop variable anyway.
+#[proc_macro]
+pub fn irp_each(token_stream: TokenStream) -> TokenStream {
+  irp_each_impl::irp_each_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Wraps a function body in the CFI directives that describe its stack frame.
+///
+/// Without these, a debugger can't unwind the stack through the function, so
+/// a backtrace from code that it calls stops there.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// cfi_fn!(saves = "r4-r7, lr", frame = 16, {
+///   "push {{r4-r7, lr}}",
+///   "sub sp, sp, #16",
+///   "bl do_work",
+///   "add sp, sp, #16",
+///   "pop {{r4-r7, lr}}",
+///   "bx lr",
+/// })
+/// # ;
+/// # assert_eq!(s, ".cfi_startproc\n.cfi_def_cfa_offset 36\n.cfi_offset r4, -20\n.cfi_offset r5, -16\n.cfi_offset r6, -12\n.cfi_offset r7, -8\n.cfi_offset r14, -4\npush {{r4-r7, lr}}\nsub sp, sp, #16\nbl do_work\nadd sp, sp, #16\npop {{r4-r7, lr}}\nbx lr\n.cfi_endproc\n");
+/// ```
+///
+/// ## Input
+/// * `saves = "..."` (optional): the registers that the body pushes, in the
+///   same form as the `save` list of [`irq_handler_prologue!`].
+/// * `frame = N` (optional, default 0): the bytes, a multiple of 4, that the
+///   body then takes off of `sp` after the push.
+/// * The body, as a braced group of lines. Other bracer macros can be used as
+///   lines.
+///
+/// ## Output
+/// A `concat!` expression of `.cfi_startproc`, a `.cfi_def_cfa_offset` of the
+/// whole frame size, a `.cfi_offset` for each saved register, the body, and
+/// then `.cfi_endproc`.
+///
+/// The directives are all placed before the body, so they describe the frame
+/// as it is *after* the prologue. That's what's needed for a backtrace from
+/// anything the body calls, but unwinding from within the prologue or
+/// epilogue themselves will be off. The body is **not** checked against the
+/// declared `saves` and `frame`, so keep them in sync by hand.
+#[proc_macro]
+pub fn cfi_fn(token_stream: TokenStream) -> TokenStream {
+  cfi_fn_impl::cfi_fn_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Fails the assembly if the code since a label is more than some size.
+///
+/// This is for code that must fit in a fixed slot, such as a vector stub or a
+/// small trampoline copied into a buffer.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s = concat!(
+/// size_marker!("my_stub"),
+/// "ldr pc, =handler\n",
+/// assert_code_size_le!("my_stub", 32),
+/// # );
+/// # assert_eq!(s, "my_stub:\nldr pc, =handler\n.if (. - my_stub) > 32\n.error \"my_stub exceeds 32 bytes\"\n.endif\n");
+/// ```
+///
+/// ## Input
+/// * The label at the start of the code, as a string literal.
+/// * The most bytes the code can be, as a positive integer literal.
+///
+/// ## Output
+/// A string literal of an `.if` block that gives an assembler `.error` if the
+/// code from the label up to here is too big.
+///
+/// Place it right after the code, in the same section as the label. Any
+/// literal pool that should be counted must come before it.
+#[proc_macro]
+pub fn assert_code_size_le(token_stream: TokenStream) -> TokenStream {
+  assert_code_size_impl::assert_code_size_le_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Emits a label to measure code from with [`assert_code_size_le!`].
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// size_marker!("my_stub")
+/// # ;
+/// # assert_eq!(s, "my_stub:\n");
+/// ```
+///
+/// ## Input
+/// The label name, as a string literal.
+///
+/// ## Output
+/// A string literal of the label line. If the code already starts with a
+/// label (such as from [`a32_fn_header!`]), that can be used instead.
+#[proc_macro]
+pub fn size_marker(token_stream: TokenStream) -> TokenStream {
+  assert_code_size_impl::size_marker_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Emits `.arch`, `.cpu`, and `.fpu` directives, checking the names.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// arch_directive!(arch = "armv5te")
+/// # ;
+/// # assert_eq!(s, ".arch armv5te\n");
+/// # let s =
+/// arch_directive!(cpu = "arm7tdmi", fpu = "softvfp")
+/// # ;
+/// # assert_eq!(s, ".cpu arm7tdmi\n.fpu softvfp\n");
+/// ```
+///
+/// ## Input
+/// One or more of `arch = "..."`, `cpu = "..."`, and `fpu = "..."`, each at
+/// most once. The names are checked against the ones the assembler accepts
+/// (as of LLVM's ARM assembler), and an unknown name is an error that
+/// suggests the closest known name.
+///
+/// ## Output
+/// A string literal with a directive line for each argument, in the order
+/// given.
+#[proc_macro]
+pub fn arch_directive(token_stream: TokenStream) -> TokenStream {
+  arch_directive_impl::arch_directive_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Branches to one of a list of labels by an index in a register.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// jump_table!("r0", ["case_zero", "case_one", "case_two"], default = "case_bad")
+/// # ;
+/// # assert_eq!(s, "cmp r0, #3\naddlo pc, pc, r0, lsl #2\nb case_bad\nb case_zero\nb case_one\nb case_two");
+/// ```
+///
+/// ## Input
+/// * The index register. It can't be `sp` or `pc`.
+/// * The case labels, in brackets. Can't be empty.
+/// * `default = "label"`: where an index past the end of the list branches
+///   to. The index is compared unsigned, so a negative index also goes here.
+/// * (Optional) The state, `a32`. Only `a32` is supported, since `t32` can't
+///   add a shifted register to `pc`, so giving `t32` is an error.
+///
+/// ## Output
+/// A string literal of the bounds check, the computed `add` to `pc`, and then
+/// a `b` line for the default and for each case.
+///
+/// ## Assembly Safety
+/// The lines must stay together, since the `add` relies on the table being
+/// right after it. This sets the CPU flags.
+#[proc_macro]
+pub fn jump_table(token_stream: TokenStream) -> TokenStream {
+  jump_table_impl::jump_table_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Counts the leading zero bits of a register, without needing `clz`.
+///
+/// ARMv4 doesn't have `clz` (it's from ARMv5), so by default this is a branch
+/// free binary search.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// clz_emulate!("r0" = "r1", scratch = "r2")
+/// # ;
+/// # assert_eq!(s, "mov r2, r1\nmov r0, #0\ncmp r2, #0x10000\nmovlo r2, r2, lsl #16\naddlo r0, r0, #16\ncmp r2, #0x1000000\nmovlo r2, r2, lsl #8\naddlo r0, r0, #8\ncmp r2, #0x10000000\nmovlo r2, r2, lsl #4\naddlo r0, r0, #4\ncmp r2, #0x40000000\nmovlo r2, r2, lsl #2\naddlo r0, r0, #2\ncmp r2, #0x80000000\naddlo r0, r0, #1\ncmp r2, #0\naddeq r0, r0, #1");
+/// # let s =
+/// clz_emulate!("r0" = "r1", arch = v5)
+/// # ;
+/// # assert_eq!(s, "clz r0, r1");
+/// ```
+///
+/// ## Input
+/// * The destination and source registers, as `"dst" = "src"`. Neither can be
+///   `sp` or `pc`.
+/// * `scratch = "rN"`: a register to shift the value in. It's required unless
+///   `arch = v5`, and then it's ignored.
+/// * `arch = v4` or `arch = v5` (optional, default `v4`): with `v5` the output
+///   is just `clz`.
+///
+/// ## Output
+/// A string literal of the lines. The count is 0 to 32, with 32 for an input
+/// of 0. For `v4` the destination, source, and scratch must all be different
+/// registers, the source is left as it was, and the CPU flags are changed.
+#[proc_macro]
+pub fn clz_emulate(token_stream: TokenStream) -> TokenStream {
+  clz_emulate_impl::clz_emulate_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Reverses the byte order of a register, without needing `rev`.
+///
+/// ARMv4 doesn't have `rev` (it's from ARMv6), so by default this is the
+/// classic `eor`/`bic`/`ror` sequence.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// byte_reverse!("r0" = "r1", scratch = "r2")
+/// # ;
+/// # assert_eq!(s, "eor r2, r1, r1, ror #16\nbic r2, r2, #0xFF0000\nmov r0, r1, ror #8\neor r0, r0, r2, lsr #8");
+/// # let s =
+/// byte_reverse!("r0", arch = v6)
+/// # ;
+/// # assert_eq!(s, "rev r0, r0");
+/// ```
+///
+/// ## Input
+/// * The destination and source registers, as `"dst" = "src"`, or just one
+///   register to reverse it in place. Neither can be `sp` or `pc`.
+/// * `scratch = "rN"`: a register for the middle step, which must differ from
+///   the destination and source. It's required unless `arch = v6`, and then
+///   it's ignored.
+/// * `arch = v4` or `arch = v6` (optional, default `v4`): with `v6` the output
+///   is just `rev`.
+///
+/// ## Output
+/// A string literal of the lines. The CPU flags are not changed.
+#[proc_macro]
+pub fn byte_reverse(token_stream: TokenStream) -> TokenStream {
+  byte_reverse_impl::byte_reverse_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Swaps the two bytes of a halfword, without needing `rev16`.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// byte_reverse16!("r0" = "r1", scratch = "r2")
+/// # ;
+/// # assert_eq!(s, "and r2, r1, #0xFF\nmov r0, r1, lsr #8\nand r0, r0, #0xFF\norr r0, r0, r2, lsl #8");
+/// # let s =
+/// byte_reverse16!("r0" = "r1", arch = v6)
+/// # ;
+/// # assert_eq!(s, "rev16 r0, r1");
+/// ```
+///
+/// ## Input
+/// The same as with [`byte_reverse!`], and with `arch = v6` the output is just
+/// `rev16`.
+///
+/// ## Output
+/// A string literal of the lines. The `v4` lines only read the low halfword
+/// of the source, and clear the upper halfword of the destination. `rev16`
+/// swaps the bytes of *both* halfwords, so the two only match when the upper
+/// halfword of the source is zero. The CPU flags are not changed.
+#[proc_macro]
+pub fn byte_reverse16(token_stream: TokenStream) -> TokenStream {
+  byte_reverse_impl::byte_reverse16_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Gives registers alias names for the length of a block of lines.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// with_reg_aliases! {
+///   "count" = "r4", "src" = "r1";
+///   "mov count, #0",
+///   "ldr r0, [src]",
+/// }
+/// # ;
+/// # assert_eq!(s, "count .req r4\nsrc .req r1\nmov count, #0\nldr r0, [src]\n.unreq src\n.unreq count\n");
+/// ```
+///
+/// ## Input
+/// * One or more `"alias" = "reg"` pairs, then a `;`. Each alias must be an
+///   identifier that isn't already a register name, and can only be given
+///   once.
+/// * The lines of the body, as expressions that `concat!` accepts, so other
+///   bracer macros can be used as lines.
+///
+/// ## Output
+/// A `concat!` expression of a `.req` line for each alias, the body lines, and
+/// then an `.unreq` line for each alias, in reverse order. Undoing the aliases
+/// at the end keeps them from clashing with later code in the same file.
+#[proc_macro]
+pub fn with_reg_aliases(token_stream: TokenStream) -> TokenStream {
+  with_reg_aliases_impl::with_reg_aliases_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Places the input sequence in a section you pick, then goes back to the
+/// previous section.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// with_section!(".iwram", "add r0, r0, r0", "bx lr")
+/// # ;
+/// # assert_eq!(s, ".pushsection .iwram,\"ax\",%progbits\nadd r0, r0, r0\nbx lr\n.popsection\n");
+/// ```
+///
+/// ## Input
+/// * A string literal that's a valid section name, the same as with
+///   [`put_fn_in_section!`].
+/// * Optionally, `flags = "..."` and `section_type = "..."`, which accept the
+///   same values as the flags and type of [`put_fn_in_section!`]. The defaults
+///   are `"ax"` and `%progbits`.
+/// * Then the input sequence, just like with [`t32_with_a32_scope!`].
+///
+/// ## Output
+/// A single `concat!` expression, with newlines inserted for each input
+/// expression, and with a `.pushsection` directive at the start and a
+/// `.popsection` directive at the end.
+#[proc_macro]
+pub fn with_section(token_stream: TokenStream) -> TokenStream {
+  with_section_impl::with_section_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Places a Rust function in a section you pick, optionally also picking the
+/// instruction set it's compiled as.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// #[in_section(".text.hot_loop")]
+/// fn hot_loop() -> u32 {
+///   5
+/// }
+/// # assert_eq!(hot_loop(), 5);
+/// ```
+///
+/// ## Input
+/// * A string literal that's a valid section name, the same as with
+///   [`put_fn_in_section!`].
+/// * Optionally, `a32` or `t32` to also set the instruction set of the
+///   function. Otherwise the instruction set is left alone.
+///
+/// ## Output
+/// The function, with a `#[link_section = "..."]` attribute, and with an
+/// `#[instruction_set(arm::a32)]` or `#[instruction_set(arm::t32)]` attribute
+/// when an instruction set is given. Since `instruction_set` is only allowed
+/// on ARM targets, giving an instruction set only compiles for ARM targets.
+///
+/// It's a compile error to use this on anything other than a function.
+/// ```compile_fail
+/// # use bracer::*;
+/// #[in_section(".iwram")]
+/// static X: u32 = 5;
+/// ```
+/// ```compile_fail
+/// # use bracer::*;
+/// #[in_section(".iwram text")]
+/// fn f() {}
+/// ```
+/// ```compile_fail
+/// # use bracer::*;
+/// #[in_section(".iwram", arm)]
+/// fn f() {}
+/// ```
+#[proc_macro_attribute]
+pub fn in_section(attr: TokenStream, item: TokenStream) -> TokenStream {
+  in_section_impl::in_section_impl(attr, item.clone()).unwrap_or_else(|e| {
+    // keep the item around so that uses of it don't also become errors.
+    let mut out = e.to_compile_error();
+    out.extend([TokenTree::Punct(Punct::new(';', Spacing::Alone))]);
+    out.extend(item);
+    out
+  })
+}
+
+/// Places `.code 32` at the start and `.code 16` at the end of the input
+/// sequence.
+///
+/// ## Input
+/// The input sequence should be zero or more expressions (comma separated) that
+/// could each normally be used within an `asm!` block.
+///
+/// ## Output
+/// A single `concat!` expression, with newlines inserted for each input
+/// expression, and with the `.code` directives at the start and end.
+///
+/// Before the `.code 32` there's also a `.balignw 4, 0x46C0`, since the `t32`
+/// code before the scope might end at an address that's only 2-byte aligned,
+/// and `a32` code must be 4-byte aligned. When needed, this pads with a `t32`
+/// `mov r8, r8` (the ARMv4T `nop`), since otherwise LLVM pads with zeroes,
+/// which is a `movs` that changes the flags. If you know the code is already
+/// aligned, put `align = false,` before the input sequence to leave the
+/// alignment out.
+///
+/// It's a compile error for the input to switch the instruction set itself,
+/// either with a directive such as `.code 16` or `.thumb`, or by nesting
+/// another `t32_with_a32_scope!`. Other bracer macros can be used as input.
+///
+/// ## Linting
+/// The input sequence can be preceded by `lint = a32,` to check that none of
+/// the string literal lines use a Thumb-only instruction (`cbz`, `cbnz`, `it`
+/// blocks, `tbb`, `tbh`). Also giving an architecture, such as `arch = v4,`,
+/// additionally checks for instructions too new for that architecture (`movw`,
+/// `movt`, `udiv`, `sdiv`). Any problem is a compile error naming the line.
+///
+/// ## Safety
+/// You **must* use this within `t32` code only. You **must not** use this
+/// within an `a32` encoded assembly block. It will leave the assembler in a bad
+/// state after the assembly string, which is UB.
+#[proc_macro]
+pub fn t32_with_a32_scope(token_stream: TokenStream) -> TokenStream {
+  t32_with_a32_scope_impl::t32_with_a32_scope_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Switches execution to `a32` for the input sequence, then back to `t32`.
+///
+/// Unlike [`t32_with_a32_scope!`], which only changes how the assembler
+/// encodes the lines, this also switches the CPU's execution state, so the
+/// `a32` lines are actually run as `a32` code.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// t32_execute_a32!(scratch = "r3", "mrs r0, CPSR")
+/// # ;
+/// # assert_eq!(s, ".balignw 4, 0x46C0\nbx pc\nnop\n.code 32\nmrs r0, CPSR\nadd r3, pc, #1\nbx r3\n.code 16\n");
+/// ```
+///
+/// ## Input
+/// * `scratch = "rN",` first, a register to hold the return address when
+///   switching back to `t32`. It can be `lr`, but not `sp` or `pc`.
+/// * Then optionally `lint = a32,` and `arch = ...,`, just like with
+///   [`t32_with_a32_scope!`].
+/// * Then the input sequence, just like with [`t32_with_a32_scope!`], which
+///   also can't switch the instruction set itself.
+///
+/// ## Output
+/// A single `concat!` expression. It aligns to 4 bytes, uses `bx pc` (with a
+/// `nop` after it as padding) to switch to `a32`, has each line of input, and
+/// then uses `add`/`bx` with the scratch register to switch back to `t32`.
+///
+/// ## Safety
+/// You **must* use this within `t32` code only, and the scratch register is
+/// clobbered.
+#[proc_macro]
+pub fn t32_execute_a32(token_stream: TokenStream) -> TokenStream {
+  t32_execute_a32_impl::t32_execute_a32_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Generates the asm string to set the CPU control bits.
+///
+/// Input must be of the form:
+/// ```text
+/// {mode_name}, irq_masked = {bool}, fiq_masked = {bool}
+/// ```
+///
+/// The two settings can be given in either order, and a setting that's left
+/// out defaults to `true` (masked). Giving a setting twice is an error.
+///
+/// Valid mode names are the long name or short name of a CPU mode:
+/// * User / usr
+/// * FIQ / fiq
+/// * IRQ / irq
+/// * Supervisor / svc
+/// * Abort / abt
+/// * Undefined / und
+/// * System / sys
+///
+/// ## Assembly Safety
+/// This instruction can only be used in `a32` code.
+#[proc_macro]
+pub fn a32_set_cpu_control(token_stream: TokenStream) -> TokenStream {
+  a32_set_cpu_control_impl::a32_set_cpu_control_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Generates the asm string to change only some of the CPU control bits.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// a32_change_cpu_control!("r3", mode = FIQ)
+/// # ;
+/// # assert_eq!(s, "mrs r3, CPSR\nbic r3, r3, #0xE\norr r3, r3, #0x11\nmsr CPSR_c, r3");
+/// ```
+///
+/// ## Input
+/// * The scratch register, as a string literal or a register name.
+/// * Then at least one of `mode = {mode_name}`, `irq_masked = {bool}`, and
+///   `fiq_masked = {bool}`, in any order. The mode names are the same as with
+///   [`a32_set_cpu_control!`].
+///
+/// ## Output
+/// A string literal of lines that read the CPSR into the scratch register,
+/// change the requested bits, and write the control bits back. Any setting
+/// that's left out keeps whatever value it has at runtime.
+///
+/// ## Assembly Safety
+/// This can only be used in `a32` code, and the scratch register is clobbered.
+#[proc_macro]
+pub fn a32_change_cpu_control(token_stream: TokenStream) -> TokenStream {
+  a32_change_cpu_control_impl::a32_change_cpu_control_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Generates the asm string to set the IRQ and FIQ masks, leaving the CPU mode
+/// alone.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// a32_set_interrupt_masks!("r2", irq_masked = true, fiq_masked = keep)
+/// # ;
+/// # assert_eq!(s, "mrs r2, CPSR\norr r2, r2, #0x80\nmsr CPSR_c, r2");
+/// ```
+///
+/// ## Input
+/// * The scratch register, as a string literal or a register name.
+/// * Then `irq_masked = {setting}` and `fiq_masked = {setting}`, in either
+///   order, where each setting is `true`, `false`, or `keep`.
+///
+/// ## Output
+/// A string literal of lines that read the CPSR into the scratch register,
+/// change bit 7 (I) and bit 6 (F) as requested, and write the control bits
+/// back. A mask set to `keep` keeps whatever value it has at runtime.
+///
+/// Since the current mode isn't known, this always reads and modifies the
+/// CPSR, even when both masks are `true` or `false`. The mode bits are written
+/// back exactly as they were read, so this never changes the mode.
+///
+/// ## Assembly Safety
+/// This can only be used in `a32` code, and the scratch register is clobbered.
+#[proc_macro]
+pub fn a32_set_interrupt_masks(token_stream: TokenStream) -> TokenStream {
+  a32_set_interrupt_masks_impl::a32_set_interrupt_masks_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Wraps some lines in a critical section, with IRQ and FIQ masked while they
+/// run.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// with_masked_interrupts!("r12", temp = "r3", {
+///   "ldr r0, [r1]",
+///   "add r0, r0, #1",
+///   "str r0, [r1]",
+/// })
+/// # ;
+/// # assert_eq!(s, "mrs r12, CPSR\norr r3, r12, #0xC0\nmsr CPSR_c, r3\nldr r0, [r1]\nadd r0, r0, #1\nstr r0, [r1]\nmsr CPSR_c, r12\n");
+/// ```
+///
+/// ## Input
+/// * The register that saves the CPSR, as a string literal or a register name.
+/// * `temp = "rN"`, a second register that the masked CPSR is built in. The
+///   saved CPSR has to be kept exactly as it was read, so it can't be changed
+///   in place.
+/// * Optionally `irq_only` or `fiq_only`, to mask just that one source.
+/// * The body, as a braced group of string literals, one line each.
+///
+/// ## Output
+/// A `concat!` expression that saves the CPSR, masks the interrupts, runs the
+/// body, and then writes the saved control bits back. Since the masks are
+/// restored to whatever they were before, rather than cleared, critical
+/// sections can be nested.
+///
+/// ## Assembly Safety
+/// This can only be used in `a32` code. **The body must not change the save
+/// register**, or the wrong CPU mode and masks are restored at the end. The
+/// temp register is clobbered before the body runs, so the body is free to use
+/// it.
+#[proc_macro]
+pub fn with_masked_interrupts(token_stream: TokenStream) -> TokenStream {
+  with_masked_interrupts_impl::with_masked_interrupts_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Switches to another CPU mode for some lines, then back to exactly the
+/// mode and masks from before.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// a32_with_mode!(IRQ, scratch = "r3", {
+///   "mov sp, r0",
+///   "msr SPSR_cxsf, r1",
+/// })
+/// # ;
+/// # assert_eq!(s, "mrs r3, CPSR\nmsr CPSR_c, #0b11010010\nmov sp, r0\nmsr SPSR_cxsf, r1\nmsr CPSR_c, r3\n");
+/// ```
+///
+/// ## Input
+/// * The mode to switch to, using the same mode names as with
+///   [`a32_set_cpu_control!`], other than User mode (which can't switch back).
+/// * `scratch = "rN"`: the register that saves the CPSR. This must be one of
+///   `r0` through `r7`, since `r8` and up are banked in FIQ mode.
+/// * The body, as a braced group of string literals, one line each.
+///
+/// ## Output
+/// A `concat!` expression that saves the CPSR, switches to the mode with IRQ
+/// and FIQ masked, runs the body, and then writes the saved control bits back.
+///
+/// ## Assembly Safety
+/// This can only be used in `a32` code, in a privileged mode. **The body must
+/// not change the scratch register**, or the wrong CPU mode and masks are
+/// restored at the end.
+#[proc_macro]
+pub fn a32_with_mode(token_stream: TokenStream) -> TokenStream {
+  a32_with_mode_impl::a32_with_mode_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Generates the instruction that returns from an exception handler.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// exception_return!(Irq)
+/// # ;
+/// # assert_eq!(s, "subs pc, lr, #4");
+/// ```
+///
+/// ## Input
+/// One exception kind, which is one of:
+///
+/// | Kind | Output |
+/// |:-|:-|
+/// | `Irq` | `subs pc, lr, #4` |
+/// | `Fiq` | `subs pc, lr, #4` |
+/// | `Swi` / `Svc` | `movs pc, lr` |
+/// | `Undefined` | `movs pc, lr` |
+/// | `PrefetchAbort` | `subs pc, lr, #4` |
+/// | `DataAbort` | `subs pc, lr, #8` |
+///
+/// ## Output
+/// A string literal of the return instruction, which adjusts `lr` back to the
+/// instruction that should run next. A prefetch abort returns to retry the
+/// instruction that aborted, and a data abort returns to retry the
+/// instruction that made the access.
+///
+/// ## Assembly Safety
+/// These are the `s` forms that also copy the SPSR into the CPSR, so this can
+/// only be used in `a32` code **in the mode of that exception**, with `lr`
+/// holding the value the exception put there. In User or System mode there's
+/// no SPSR and the result is unpredictable.
+#[proc_macro]
+pub fn exception_return(token_stream: TokenStream) -> TokenStream {
+  exception_return_impl::exception_return_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Generates the asm string to make a software interrupt call, placing the
+/// call number where the given state expects it.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let a =
+/// swi_call!(0x04, a32)
+/// # ;
+/// # assert_eq!(a, "swi #0x040000");
+/// # let t =
+/// swi_call!(0x04, t32)
+/// # ;
+/// # assert_eq!(t, "swi #0x04");
+/// ```
+///
+/// ## Input
+/// * The call number, as an integer literal from 0 to 0xFF. It can be hex,
+///   decimal, or binary.
+/// * `a32` or `t32`: the state the instruction is assembled in. This is
+///   required, since the number goes in a different place in each.
+///
+/// ## Output
+/// A string literal of one `swi` instruction. In `t32` the comment field is
+/// only 8 bits, so the number is placed as is. In `a32` the comment field is
+/// 24 bits, and the number is placed in the top 8 of them (`number << 16`),
+/// which is how the GBA BIOS (and others) expect a32 calls to be made.
+#[proc_macro]
+pub fn swi_call(token_stream: TokenStream) -> TokenStream {
+  swi_call_impl::swi_call_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Generates the asm string to read a CP15 register into a core register.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// cp15_read!("r0", Control)
+/// # ;
+/// # assert_eq!(s, "mrc p15, 0, r0, c1, c0, 0");
+/// # let s =
+/// cp15_read!("r0", c9, c1, 1)
+/// # ;
+/// # assert_eq!(s, "mrc p15, 0, r0, c9, c1, 1");
+/// ```
+///
+/// ## Input
+/// * The core register, as a string literal or a register name. This can't be
+///   `pc`.
+/// * Then either the name of a CP15 register, or its `CRn, CRm, opcode2` (such
+///   as `c1, c0, 0`) for any register that isn't named.
+///
+/// The named registers are:
+///
+/// | Name | `CRn, CRm, opcode2` | Access |
+/// |:-|:-|:-|
+/// | `MainId` / `Midr` / `Id` | `c0, c0, 0` | read |
+/// | `CacheType` | `c0, c0, 1` | read |
+/// | `TcmSize` | `c0, c0, 2` | read |
+/// | `Control` / `ControlRegister` | `c1, c0, 0` | read/write |
+/// | `TranslationTableBase` / `Ttb` | `c2, c0, 0` | read/write |
+/// | `DomainAccess` | `c3, c0, 0` | read/write |
+/// | `DataFaultStatus` / `Fsr` | `c5, c0, 0` | read/write |
+/// | `InstructionFaultStatus` / `Ifsr` | `c5, c0, 1` | read/write |
+/// | `FaultAddress` / `Far` | `c6, c0, 0` | read/write |
+/// | `WaitForInterrupt` | `c7, c0, 4` | write |
+/// | `InvalidateICache` | `c7, c5, 0` | write |
+/// | `InvalidateICacheLine` | `c7, c5, 1` | write |
+/// | `InvalidateDCache` | `c7, c6, 0` | write |
+/// | `InvalidateDCacheLine` | `c7, c6, 1` | write |
+/// | `InvalidateCaches` | `c7, c7, 0` | write |
+/// | `CleanDCacheLine` | `c7, c10, 1` | write |
+/// | `DrainWriteBuffer` | `c7, c10, 4` | write |
+/// | `CleanInvalidateDCacheLine` | `c7, c14, 1` | write |
+/// | `DtcmRegion` | `c9, c1, 0` | read/write |
+/// | `ItcmRegion` | `c9, c1, 1` | read/write |
+///
+/// It's an error to read a register that's only written, or to write one
+/// that's only read.
+///
+/// ## Output
+/// A string literal of one `mrc` instruction. `opcode1` is always 0.
+///
+/// ## Assembly Safety
+/// This can only be used in `a32` code, in a privileged mode, on a CPU with
+/// CP15 (such as an ARM9). The cache and TCM registers are as on the
+/// ARM946E-S, other cores can differ.
+#[proc_macro]
+pub fn cp15_read(token_stream: TokenStream) -> TokenStream {
+  cp15_impl::cp15_read_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Generates the asm string to write a core register into a CP15 register.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// cp15_write!("r0", DrainWriteBuffer)
+/// # ;
+/// # assert_eq!(s, "mcr p15, 0, r0, c7, c10, 4");
+/// ```
+///
+/// ## Input
+/// The same as with [`cp15_read!`], using the same named registers.
+///
+/// ## Output
+/// A string literal of one `mcr` instruction. `opcode1` is always 0.
+///
+/// ## Assembly Safety
+/// The same as with [`cp15_read!`]. Writing `Control` can turn the MMU (or
+/// protection unit) and caches on or off, so take care that the code doing so
+/// is still mapped afterwards.
+#[proc_macro]
+pub fn cp15_write(token_stream: TokenStream) -> TokenStream {
+  cp15_impl::cp15_write_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Generates the asm string of an atomic swap between a register and memory.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// swp_swap!("r0", "r1", ["r2"])
+/// # ;
+/// # assert_eq!(s, "swp r0, r1, [r2]");
+/// # let s =
+/// swp_swap!(byte "r0", "r1", ["r2"])
+/// # ;
+/// # assert_eq!(s, "swpb r0, r1, [r2]");
+/// ```
+///
+/// ## Input
+/// * Optionally `byte`, to swap one byte instead of one word.
+/// * The destination register, which gets the old value from memory.
+/// * The source register, whose value is stored to memory. This can be the
+///   same as the destination.
+/// * The address register, in brackets.
+///
+/// Each register is a string literal or a register name, and can't be `sp` or
+/// `pc`. The address register can't be the same as either of the others,
+/// since that's unpredictable.
+///
+/// ## Output
+/// A string literal of one `swp` (or `swpb`) instruction, which loads from the
+/// address and stores to it with no other access able to happen in between.
+///
+/// ## Assembly Safety
+/// This can only be used in `a32` code. `swp` is deprecated from ARMv6 on, in
+/// favor of `ldrex`/`strex`.
+#[proc_macro]
+pub fn swp_swap(token_stream: TokenStream) -> TokenStream {
+  swp_swap_impl::swp_swap_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Generates the asm string of a breakpoint, only in debug builds.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// bkpt_debug!(0x07)
+/// # ;
+/// # assert_eq!(s, if cfg!(debug_assertions) { "bkpt #0x07" } else { "" });
+/// # let s =
+/// bkpt_debug!(0x07, t32, debug_only = false)
+/// # ;
+/// # assert_eq!(s, "bkpt #0x07");
+/// ```
+///
+/// ## Input
+/// * The breakpoint number, as an integer literal. This can be up to 0xFFFF in
+///   `a32`, or up to 0xFF in `t32`.
+/// * `a32` or `t32` (optional, default `a32`): the state the instruction is
+///   assembled in, which sets the range of the number.
+/// * `debug_only = bool` (optional, default `true`): if the breakpoint is left
+///   out of release builds.
+///
+/// ## Output
+/// A string literal of one `bkpt` instruction, or an empty string literal if
+/// it's left out.
+///
+/// A proc-macro can't check the `cfg` of the crate that uses it, so this
+/// checks `debug_assertions` of its own build instead. Cargo builds
+/// proc-macros with the same `debug-assertions` as the profile being built,
+/// so these match unless the profile's `build-override` sets it differently.
+/// Use `debug_only = false` to always have the breakpoint.
+///
+/// ## Assembly Safety
+/// `bkpt` needs ARMv5T or later. Without a debugger attached, it's a prefetch
+/// abort.
+#[proc_macro]
+pub fn bkpt_debug(token_stream: TokenStream) -> TokenStream {
+  bkpt_debug_impl::bkpt_debug_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Generates the directive to place the literal pool here.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// emit_literal_pool!()
+/// # ;
+/// # assert_eq!(s, ".ltorg\n");
+/// ```
+///
+/// ## Input
+/// None.
+///
+/// ## Output
+/// A string literal of the `.ltorg` directive, which places all of the
+/// constants from `ldr rX, =value` lines so far.
+///
+/// ## Assembly Safety
+/// The pool is data, so it must be placed where it won't be run, such as after
+/// an unconditional branch or a return.
+#[proc_macro]
+pub fn emit_literal_pool(token_stream: TokenStream) -> TokenStream {
+  literal_pool_impl::emit_literal_pool_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Joins some lines and places the literal pool after them, checking that the
+/// pool isn't too far away.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// with_pool!("ldr r0, =0x04000208", "str r1, [r0]", "bx lr")
+/// # ;
+/// # assert_eq!(s, "ldr r0, =0x04000208\nstr r1, [r0]\nbx lr\n.ltorg\n");
+/// ```
+///
+/// ## Input
+/// * The lines, as expressions that `concat!` accepts. A string literal can
+///   hold more than one line.
+/// * `max_lines = N` (optional, default 1000): the most lines there can be
+///   before the pool.
+///
+/// An `a32` `ldr` can reach about 1024 instructions ahead, which the default
+/// stays a little under. A `t32` `ldr` can only reach about 500 instructions
+/// ahead, so use `max_lines` to lower the limit for `t32` code.
+///
+/// ## Output
+/// A `concat!` expression of the lines, then `.ltorg`. It's a compile error if
+/// there are more than `max_lines` lines. Only the lines of code in string
+/// literals are counted exactly, any other expression counts as one line.
+///
+/// ## Assembly Safety
+/// The pool is data, so the last line must be one that never falls through,
+/// such as a return.
+#[proc_macro]
+pub fn with_pool(token_stream: TokenStream) -> TokenStream {
+  literal_pool_impl::with_pool_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Emits code that will perform the test and skip past some lines if the test
+/// does not pass.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// when!(("r0" != "#0")[1]{
+///   "add r1, r2, r3",
+///   "add r0, r1, r4",
+/// })
+/// # ;
+/// ```
+///
+/// * The test to perform must be in one grouping. The left operand must be a
+///   register (or `asm!` substitution), and the right operand can be a register
+///   or an immediate with a `#`. Numeric immediates must be encodable in `a32`,
+///   either as is or negated (which the assembler does with `cmn`). Negative
+///   immediates are written as a `cmn` of the positive value, so they can only
+///   be used with equality and signed tests.
+/// * The right operand can also be a shifted register, such as `"r1, lsl #2"`.
+///   The shift must be `lsl #0` to `#31`, `lsr` or `asr` `#1` to `#32`, `ror`
+///   `#1` to `#31`, or `rrx` with no amount.
+/// * The number literal for the numeric label placed at the end of the block
+///   must be another grouping. This grouping can be left out, and then a
+///   unique label name is generated instead (eg: `.L_bracer_local_label_3`).
+///   Generated labels can't clash with another `when!`, but they're named
+///   labels, which `asm!` rejects with the deny-by-default `named_asm_labels`
+///   lint. **Only leave out the label in `global_asm!`**, or in an `asm!`
+///   with `#[allow(named_asm_labels)]` that the compiler can't duplicate (such
+///   as by inlining the function it's in at more than one place).
+/// * A numeric label can't also be defined by the body's string literal lines,
+///   since the branches meant to skip the body would stop there instead. Lines
+///   from other macros in the body aren't checked, so don't nest a `when!` with
+///   the same label number.
+/// * The lines to execute when the test passes must be in a separate grouping.
+/// * The macro *does not* care what grouping markers you use, `()`, `[]`, and
+///   `{}` are all fine.
+///
+/// ## Else
+/// The body can be followed by `else` and a grouping of lines to execute when
+/// the test does not pass. Then a second label grouping is also needed after
+/// the first, for the end of the `else` lines:
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// when!(("r0" == "#0")[1][2]{
+///   "mov r1, #5",
+/// } else {
+///   "mov r1, #9",
+/// })
+/// # ;
+/// # assert_eq!(s, "cmp r0, #0\nbne 1f\nmov r1, #5\nb 2f\n1:\nmov r1, #9\n2:\n");
+/// ```
+/// The first label is placed at the start of the `else` lines, and the body
+/// ends with a `b` to the second label, which is placed after the `else` lines.
+/// When the label groupings are left out, both labels are generated.
+///
+/// ## Compound Tests
+/// The test can be several comparisons joined with `&&` or with `||` (but not
+/// a mix of both, nest the `when!`s for that):
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// when!(("r0" == "#0" && "r1" != "#0")[1]{
+///   "mov r2, #1",
+/// })
+/// # ;
+/// # assert_eq!(s, "cmp r0, #0\nbne 1f\ncmp r1, #0\nbeq 1f\nmov r2, #1\n1:\n");
+/// ```
+/// With `&&`, each comparison that fails branches past the body. With `||`,
+/// each comparison but the last one that passes branches to a label at the
+/// start of the body, and the last one branches past the body if it fails:
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// when!(("r0" == "#0" || "r1" == "#0")[1]{
+///   "mov r2, #1",
+/// })
+/// # ;
+/// # assert_eq!(s, "cmp r0, #0\nbeq 2f\ncmp r1, #0\nbne 1f\n2:\nmov r2, #1\n1:\n");
+/// ```
+/// The label at the start of the body is the next number up from the numeric
+/// label (`2:` for `[1]`), so that number shouldn't be used by branches that
+/// cross the `when!`. When the label grouping is left out, this label is
+/// generated too.
+///
+/// ## Linting
+/// The test can be preceded by `lint = t16,` to check that the string literal
+/// lines of the body can be encoded as Thumb-1, the same as with
+/// [`assert_t16_ok!`]. Similarly, `lint = a32,` checks for Thumb-only
+/// instructions, the same as with [`t32_with_a32_scope!`].
+#[proc_macro]
+pub fn when(token_stream: TokenStream) -> TokenStream {
+  when_impl::when_impl(token_stream).unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Like [`when!`], but the lines are skipped when the test *does* pass.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// unless!(("r0" == "#0")[1]{
+///   "udiv r2, r1, r0",
+/// })
+/// # ;
+/// # assert_eq!(s, "cmp r0, #0\nbeq 1f\nudiv r2, r1, r0\n1:\n");
+/// ```
+///
+/// Everything else works the same as with `when!`, and `unless!` of a test
+/// expands to the same lines as `when!` of the opposite test. A compound test
+/// is negated as a whole, so `unless!(("r0" == "#0" && "r1" == "#0"))` runs
+/// the lines if *either* register isn't zero.
+#[proc_macro]
+pub fn unless(token_stream: TokenStream) -> TokenStream {
+  when_impl::unless_impl(token_stream).unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Emits an if / else-if / else ladder: the lines of the first test that
+/// passes are run, or the `else` lines if none of them pass.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// cond_chain!(
+///   ("r0" == "#0") { "mov r1, #10" }
+///   ("r0" == "#1") { "mov r1, #20" }
+///   ("r0" < u "#8") { "mov r1, #30" }
+///   else { "mov r1, #0" }
+/// )
+/// # ;
+/// ```
+///
+/// * Each arm is a test grouping and then a grouping of lines. The tests work
+///   the same as with [`when!`], including compound tests.
+/// * The `else` and its lines can be left out, and then nothing runs when none
+///   of the tests pass.
+/// * The labels are all generated (eg: `.L_bracer_local_label_3`), so the same
+///   caveats apply as with a `when!` that has no label grouping: **only use
+///   `cond_chain!` in `global_asm!`**, or in an `asm!` with
+///   `#[allow(named_asm_labels)]` that the compiler can't duplicate.
+#[proc_macro]
+pub fn cond_chain(token_stream: TokenStream) -> TokenStream {
+  when_impl::cond_chain_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Emits a loop that runs some lines for as long as a test passes, checking
+/// the test before each pass.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// while_loop!(("r0" != "#0")[1]{
+///   "subs r0, r0, #1",
+///   "bl do_thing",
+/// })
+/// # ;
+/// # assert_eq!(s, "1:\ncmp r0, #0\nbeq 1f\nsubs r0, r0, #1\nbl do_thing\nb 1b\n1:\n");
+/// ```
+///
+/// * The test works the same as with [`when!`], including compound tests.
+///   Each pass does a `cmp` (or `cmn`), which sets the flags but doesn't change
+///   any registers.
+/// * The numeric label is placed at both the top and the end of the loop, so
+///   that `b 1b` at the end of the body goes back to the test and the failing
+///   test goes to `1f`. The label grouping can be left out, and then two unique
+///   labels are generated instead. Those are named labels, so as with
+///   [`when!`] the label-less form is only for `global_asm!`, or for an `asm!`
+///   with `#[allow(named_asm_labels)]` that the compiler can't duplicate.
+/// * The test can be preceded by `lint = t16,` or `lint = a32,` to check the
+///   lines, the same as with `when!`.
+#[proc_macro]
+pub fn while_loop(token_stream: TokenStream) -> TokenStream {
+  loop_impl::while_loop_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Emits a loop that runs some lines at least once, and then again for as
+/// long as a test passes, checking the test after each pass.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// do_while!([1]{
+///   "ldr r3, [r1], #4",
+///   "str r3, [r2], #4",
+///   "sub r0, r0, #1",
+/// } ("r0" != "#0"))
+/// # ;
+/// # assert_eq!(s, "1:\nldr r3, [r1], #4\nstr r3, [r2], #4\nsub r0, r0, #1\ncmp r0, #0\nbne 1b\n");
+/// ```
+///
+/// * The numeric label grouping comes first, then the lines, then the test.
+///   The label is placed at the top of the loop, and the test branches back to
+///   it when it passes. The label grouping can be left out, and then a unique
+///   label is generated instead. That's a named label, so the same as with
+///   [`while_loop!`], leave out the label only in `global_asm!`, or in an
+///   `asm!` with `#[allow(named_asm_labels)]` that the compiler can't
+///   duplicate.
+/// * The test works the same as with [`when!`], including compound tests.
+/// * The test can instead be just a condition code, the same as with
+///   [`when_flags!`], and then there's no `cmp` at all. This is for when the
+///   lines end with an instruction that sets the flags:
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// do_while!([1]{
+///   "ldr r3, [r1], #4",
+///   "str r3, [r2], #4",
+///   "subs r0, r0, #1",
+/// } ("ne"))
+/// # ;
+/// # assert_eq!(s, "1:\nldr r3, [r1], #4\nstr r3, [r2], #4\nsubs r0, r0, #1\nbne 1b\n");
+/// ```
+/// * Everything can be preceded by `lint = t16,` or `lint = a32,` to check the
+///   lines, the same as with `when!`.
+#[proc_macro]
+pub fn do_while(token_stream: TokenStream) -> TokenStream {
+  loop_impl::do_while_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Emits a loop that runs some lines the number of times in a counter
+/// register, counting it down to zero.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// counted_loop!("r4", [7] {
+///   "ldr r0, [r1], #4",
+///   "str r0, [r2], #4",
+/// })
+/// # ;
+/// # assert_eq!(s, "7:\nldr r0, [r1], #4\nstr r0, [r2], #4\nsubs r4, r4, #1\nbne 7b\n");
+/// ```
+///
+/// * The counter register comes first, and can't be `sp` or `pc`. It must be
+///   set to the number of passes before the loop, and it's zero after. A count
+///   of zero would run the lines 2^32 times, so check for that first if it can
+///   happen.
+/// * The numeric label grouping comes next, and is placed at the top of the
+///   loop. It can be left out, and then a unique label is generated instead,
+///   but that's a named label: without the label grouping the loop is only
+///   for `global_asm!`, or for an `asm!` with `#[allow(named_asm_labels)]`
+///   that the compiler can't duplicate.
+/// * Then the lines of the body, which **must not** change the counter. The
+///   flags are set by the `subs` right before the `bne` that uses them, so the
+///   body can change them freely.
+/// * `unroll = N` repeats the lines `N` times in each pass, and subtracts `N`
+///   from the counter each pass. Then the count **must** be a multiple of `N`,
+///   or the counter will pass zero without stopping.
+#[proc_macro]
+pub fn counted_loop(token_stream: TokenStream) -> TokenStream {
+  loop_impl::counted_loop_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Like [`when!`], but the test is a `tst` of some bits of a register.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// when_bit!(("r0" & "#0x4000" != 0)[1]{
+///   "mov r1, #1",
+/// })
+/// # ;
+/// # assert_eq!(s, "tst r0, #0x4000\nbeq 1f\nmov r1, #1\n1:\n");
+/// ```
+///
+/// * The test is a register, `&`, a mask, and then either `!= 0` (the body
+///   runs if any of the mask bits are set) or `== 0` (the body runs if all of
+///   the mask bits are clear).
+/// * The mask can be a register (shifted or not, as with the right operand of
+///   [`when!`]) or an immediate with a `#`. Numeric immediates must be
+///   encodable in `a32`, and unlike with `when!` they can't be negated.
+/// * Everything after the test (labels, body, `else`, and `lint`) works the
+///   same as with [`when!`].
+#[proc_macro]
+pub fn when_bit(token_stream: TokenStream) -> TokenStream {
+  when_impl::when_bit_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Like [`when!`], but the test is a condition of the flags as they already
+/// are, so there's no `cmp`.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// when_flags!(("eq")[1]{
+///   "mov r1, #1",
+/// })
+/// # ;
+/// # assert_eq!(s, "bne 1f\nmov r1, #1\n1:\n");
+/// ```
+///
+/// * The test is one condition code as a str literal: `eq`, `ne`, `cs`/`hs`,
+///   `cc`/`lo`, `mi`, `pl`, `vs`, `vc`, `hi`, `ls`, `ge`, `lt`, `gt`, or `le`.
+///   The body is skipped with a branch on the opposite condition.
+/// * This is for when the flags were set by an earlier instruction, such as a
+///   `subs` or `adds`, and another `cmp` would waste time or clobber a flag
+///   that's still needed.
+/// * Everything after the test (labels, body, `else`, and `lint`) works the
+///   same as with [`when!`].
+#[proc_macro]
+pub fn when_flags(token_stream: TokenStream) -> TokenStream {
+  when_impl::when_flags_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Like [`when!`], but each line is made conditional instead of branching past
+/// them. This is an `a32` only technique.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// when_cond!(("r0" == "#0"){
+///   "add r1, r2, r3",
+///   "mov r4, #1",
+/// })
+/// # ;
+/// # assert_eq!(s, "cmp r0, #0\naddeq r1, r2, r3\nmoveq r4, #1\n");
+/// ```
+///
+/// * The test is one comparison, the same as with `when!` (but no `&&` or
+///   `||`). There's no label grouping, since there's no branch.
+/// * The condition code is put at the end of each line's mnemonic, so the lines
+///   must be string literals of instructions that don't already have one.
+/// * Lines that set the flags (such as `adds`, or a `cmp`) would change the
+///   condition of the lines after them, so they're an error.
+/// * For more than 4 lines a branch is better, so that's an error as well.
+#[proc_macro]
+pub fn when_cond(token_stream: TokenStream) -> TokenStream {
+  when_impl::when_cond_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Adds a constant byte offset to a pointer register.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// adjust_ptr!("r0", 0x1234)
+/// # ;
+/// # assert_eq!(s, "add r0, r0, #4608\nadd r0, r0, #52");
+/// ```
+///
+/// ## Input
+/// * A string literal for the register to adjust.
+/// * An integer literal for the offset. Negative offsets use `sub` instead of
+///   `add`.
+/// * Optionally `scratch = "rN"`, a register that can be clobbered.
+///
+/// ## Output
+/// The shortest of the following forms that works for the offset:
+/// * One `add`, when the offset is an encodable immediate.
+/// * Two `add`s, when the offset splits into an encodable high part and an
+///   encodable remainder.
+/// * An `ldr =` of the offset into the scratch register followed by a register
+///   `add`. If the offset needs this form and no scratch register was given,
+///   it's a compile error.
+#[proc_macro]
+pub fn adjust_ptr(token_stream: TokenStream) -> TokenStream {
+  adjust_ptr_impl::adjust_ptr_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Multiplies a register by a constant.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// mul_const!(dst = "r0", src = "r1", by = 10, scratch = "r2")
+/// # ;
+/// # assert_eq!(s, "add r0, r1, r1, lsl #2\nmov r0, r0, lsl #1");
+/// ```
+///
+/// ## Input
+/// Named arguments, in any order:
+/// * `dst`: string literal of the destination register.
+/// * `src`: string literal of the source register.
+/// * `by`: integer literal of the constant to multiply by.
+/// * `scratch` (optional): string literal of a register that can be clobbered.
+/// * `max_ops` (optional, default 4): the longest shift-and-add sequence to
+///   allow before falling back to `mul`.
+///
+/// ## Output
+/// Emits a sequence of `add` and `rsb` using shifted operands, with a final
+/// `lsl` for any even part of the constant. When that would take more than
+/// `max_ops` instructions, instead emits a `mov` (or `ldr =`) of the constant
+/// into `scratch` followed by a `mul`. It's a compile error if a scratch
+/// register is needed and none was given.
+#[proc_macro]
+pub fn mul_const(token_stream: TokenStream) -> TokenStream {
+  mul_const_impl::mul_const_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Divides an unsigned register by a constant.
+///
+/// ARMv4 has no divide instruction, so this multiplies by a "magic" reciprocal
+/// with `umull` and shifts the high half of the product.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// div_const_u32!(dst = "r0", src = "r1", by = 10, scratch = ["r2", "r3"])
+/// # ;
+/// # assert_eq!(s, "ldr r3, =0xCCCCCCCD\numull r2, r3, r1, r3\nmov r0, r3, lsr #3");
+/// ```
+///
+/// ## Input
+/// Named arguments, in any order:
+/// * `dst`: string literal of the destination register.
+/// * `src`: string literal of the source register.
+/// * `by`: integer literal of the divisor. Zero is rejected.
+/// * `scratch`: two string literals in brackets, registers that can be
+///   clobbered. These must differ from each other and from `src`. Not needed
+///   when `by` is a power of two.
+///
+/// ## Output
+/// * When `by` is a power of two, a single `mov` with `lsr`.
+/// * Otherwise, an `ldr =` of the magic number, a `umull`, possibly a `sub`
+///   and `add` fixup, and a final `mov` with `lsr`.
+#[proc_macro]
+pub fn div_const_u32(token_stream: TokenStream) -> TokenStream {
+  div_const_u32_impl::div_const_u32_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Checks that the lines given can all be encoded as Thumb-1.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// assert_t16_ok!({
+///   "add r0, r1, r2",
+///   "lsl r0, r0, #2",
+/// })
+/// # ;
+/// ```
+///
+/// ## Input
+/// A single grouping of zero or more expressions (comma separated) that could
+/// each normally be used within an `asm!` block.
+///
+/// ## Output
+/// A single `concat!` expression, with newlines inserted for each input
+/// expression.
+///
+/// If a string literal line is something that the Thumb-1 encodings of ARMv4T
+/// can't do, such as using a high register with most instructions, an
+/// immediate that's too wide, or a shifted operand, then it's a compile error
+/// naming the line and the problem.
+///
+/// The check is conservative: unknown mnemonics, non-literal expressions, and
+/// `asm!` substitutions are not checked, so passing this check doesn't mean
+/// that the code is definitely correct.
+#[proc_macro]
+pub fn assert_t16_ok(token_stream: TokenStream) -> TokenStream {
+  assert_t16_ok_impl::assert_t16_ok_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Saves the current task's registers into its task control block (TCB).
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// task_save!(tcb = "r0", from_mode = svc)
+/// # ;
+/// # assert_eq!(s, "stmia r0, {{r4-r11, sp, lr}}");
+/// ```
+///
+/// ## Input
+/// * `tcb = "rN"`: the register holding the address of the TCB. This can't be
+///   one of the registers that's saved (`r4`-`r11`, `sp`, `lr`), or `pc`.
+/// * `from_mode = mode`: the CPU mode that the switch happens from. With `svc`
+///   or `sys` the current `sp` and `lr` are saved. With `irq` or `fiq` the task
+///   is assumed to be running in User or System mode, so the user bank `sp` and
+///   `lr` are saved instead.
+///
+/// ## Output
+/// A single `stmia` of the frame, using the layout given by
+/// [`task_frame_offsets!`]. The user bank form is followed by a `nop`, because
+/// the instruction after a user bank transfer must not use a banked register.
+///
+/// The register list braces are doubled, so the output is ready to use as an
+/// `asm!` template string.
+#[proc_macro]
+pub fn task_save(token_stream: TokenStream) -> TokenStream {
+  task_frame_impl::task_save_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Loads a task's registers from its task control block (TCB).
+///
+/// This is the reverse of [`task_save!`], and it takes `to_mode` instead of
+/// `from_mode`, but otherwise works the same.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// task_restore!(tcb = "r1", to_mode = irq)
+/// # ;
+/// # assert_eq!(s, "ldmia r1, {{r4-r11, sp, lr}}^\nnop");
+/// ```
+#[proc_macro]
+pub fn task_restore(token_stream: TokenStream) -> TokenStream {
+  task_frame_impl::task_restore_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Emits `.set` directives giving the layout of the task frame used by
+/// [`task_save!`] and [`task_restore!`].
+///
+/// The frame is 40 bytes: `r4` through `r11` at offsets 0 through 28, then `sp`
+/// at 32, then `lr` at 36. This matches a Rust type such as:
+/// ```rust
+/// #[repr(C)]
+/// pub struct TaskFrame {
+///   pub r4_to_r11: [u32; 8],
+///   pub sp: u32,
+///   pub lr: u32,
+/// }
+/// ```
+///
+/// ## Input
+/// None.
+///
+/// ## Output
+/// One `.set` line per register (`TASK_FRAME_R4`, ..., `TASK_FRAME_SP`,
+/// `TASK_FRAME_LR`), then `.set TASK_FRAME_SIZE, 40`.
+#[proc_macro]
+pub fn task_frame_offsets(token_stream: TokenStream) -> TokenStream {
+  task_frame_impl::task_frame_offsets_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Saves SPSR and `lr` at the start of a re-entrant SVC handler, then unmasks
+/// IRQ.
+///
+/// A nested IRQ handler that itself does an SVC would overwrite SPSR and `lr`
+/// of Supervisor mode, so they must be saved before interrupts are enabled.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// svc_reentry_save!(scratch = "r12")
+/// # ;
+/// # assert_eq!(s, "mrs r12, SPSR\npush {{r12, lr}}\nmsr CPSR_c, #0b00010011");
+/// ```
+///
+/// ## Input
+/// * `scratch = "rN"`: a register to pass SPSR through, which is pushed along
+///   with `lr`. This must be one of `r0` through `r12`.
+/// * `fiq_masked = bool` (optional, default `false`): if FIQ should be masked
+///   while the handler runs.
+///
+/// ## Output
+/// An `mrs` of SPSR into the scratch register, a `push` of the scratch register
+/// and `lr`, then an `msr` to stay in Supervisor mode with IRQ unmasked.
+///
+/// Use [`svc_reentry_restore!`] with the same arguments at the end of the
+/// handler. Both macros are made from the same list of steps, so they always
+/// match.
+///
+/// ## Assembly Safety
+/// This can only be used in `a32` code, in Supervisor mode.
+#[proc_macro]
+pub fn svc_reentry_save(token_stream: TokenStream) -> TokenStream {
+  svc_reentry_impl::svc_reentry_save_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Undoes [`svc_reentry_save!`] at the end of a re-entrant SVC handler.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// svc_reentry_restore!(scratch = "r12")
+/// # ;
+/// # assert_eq!(s, "msr CPSR_c, #0b10010011\npop {{r12, lr}}\nmsr SPSR_cxsf, r12");
+/// ```
+///
+/// ## Input
+/// The same arguments as given to [`svc_reentry_save!`].
+///
+/// ## Output
+/// The reverse of the save: an `msr` to mask IRQ, a `pop` of the scratch
+/// register and `lr`, then an `msr` of the scratch register into SPSR.
+#[proc_macro]
+pub fn svc_reentry_restore(token_stream: TokenStream) -> TokenStream {
+  svc_reentry_impl::svc_reentry_restore_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Saves registers at the start of an IRQ handler, and optionally moves to
+/// System mode for the body.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// irq_handler_prologue!(save = "r0-r3, r12, lr", spsr = "r12", sys_mode = true)
+/// # ;
+/// # assert_eq!(s, "push {{r0-r3, r12, lr}}\nmrs r12, SPSR\npush {{r12}}\nmsr CPSR_c, #0b10011111\npush {{lr}}");
+/// ```
+///
+/// ## Input
+/// All of these are optional.
+/// * `save = "list"` (default `"r0-r3, r12, lr"`): the registers to push,
+///   written as in a `push`. Ranges such as `r4-r7` are allowed, but `sp` and
+///   `pc` are not.
+/// * `spsr = "rN"`: a register to pass SPSR through, which is then pushed on
+///   its own. This must be one of `r0` through `r12`, and must also be in the
+///   `save` list.
+/// * `sys_mode = bool` (default `false`): if the body should run in System
+///   mode, with its larger stack.
+/// * `fiq_masked = bool` (default `false`): if FIQ should be masked while the
+///   body runs in System mode. This is only used with `sys_mode`.
+///
+/// ## Output
+/// A `push` of the `save` list, then with `spsr` an `mrs` of SPSR and a `push`
+/// of it, then with `sys_mode` an `msr` to System mode (with IRQ still
+/// masked) and a `push` of System mode's `lr`.
+///
+/// Use [`irq_handler_epilogue!`] with the same arguments at the end of the
+/// handler. Both macros are made from the same list of steps, so they always
+/// match.
+///
+/// ## Assembly Safety
+/// This can only be used in `a32` code, in IRQ mode. With `sys_mode`, the
+/// `msr` sets the FIQ mask to `fiq_masked` instead of keeping what it was.
+#[proc_macro]
+pub fn irq_handler_prologue(token_stream: TokenStream) -> TokenStream {
+  irq_handler_impl::irq_handler_prologue_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Undoes [`irq_handler_prologue!`] at the end of an IRQ handler.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// irq_handler_epilogue!(save = "r0-r3, r12, lr", spsr = "r12", sys_mode = true)
+/// # ;
+/// # assert_eq!(s, "pop {{lr}}\nmsr CPSR_c, #0b10010010\npop {{r12}}\nmsr SPSR_cxsf, r12\npop {{r0-r3, r12, lr}}");
+/// ```
+///
+/// ## Input
+/// The same arguments as given to [`irq_handler_prologue!`].
+///
+/// ## Output
+/// The reverse of the prologue: with `sys_mode` a `pop` of System mode's `lr`
+/// and an `msr` back to IRQ mode, then with `spsr` a `pop` of it and an `msr`
+/// back into SPSR, then a `pop` of the `save` list.
+///
+/// This doesn't return from the handler, follow it with
+/// [`exception_return!(Irq)`](exception_return!).
+#[proc_macro]
+pub fn irq_handler_epilogue(token_stream: TokenStream) -> TokenStream {
+  irq_handler_impl::irq_handler_epilogue_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Saves SPSR and `lr` in an IRQ handler, then moves to System mode with IRQ
+/// unmasked, so that the rest of the handler can itself be interrupted.
+///
+/// A nested IRQ overwrites SPSR and `lr` of IRQ mode, so they must be saved
+/// before interrupts are enabled.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// // the whole handler, around a call to a Rust function
+/// let handler = concat!(
+///   irq_handler_prologue!(), "\n",
+///   // (acknowledge the interrupt here)
+///   nested_irq_enter!(scratch = "r12"), "\n",
+///   "bl rust_irq_handler\n",
+///   nested_irq_exit!(scratch = "r12"), "\n",
+///   irq_handler_epilogue!(), "\n",
+///   exception_return!(Irq),
+/// );
+/// # assert_eq!(handler, "push {{r0-r3, r12, lr}}\nmrs r12, SPSR\npush {{r12, lr}}\nmsr CPSR_c, #0b00011111\npush {{r12, lr}}\nbl rust_irq_handler\npop {{r12, lr}}\nmsr CPSR_c, #0b10010010\npop {{r12, lr}}\nmsr SPSR_cxsf, r12\npop {{r0-r3, r12, lr}}\nsubs pc, lr, #4");
+/// ```
+///
+/// ## Input
+/// * `scratch = "rN"`: a register to pass SPSR through, which is pushed along
+///   with `lr`. This must be one of `r0` through `r12`.
+/// * `fiq_masked = bool` (optional, default `false`): if FIQ should be masked
+///   while the handler runs.
+///
+/// ## Output
+/// An `mrs` of SPSR into the scratch register and a `push` of it and IRQ
+/// mode's `lr`, then an `msr` to System mode with IRQ unmasked, then a `push`
+/// of the scratch register and System mode's `lr`.
+///
+/// Use [`nested_irq_exit!`] with the same arguments at the end of the nested
+/// part. Both macros are made from the same list of steps, so they always
+/// match.
+///
+/// ## Assembly Safety
+/// This can only be used in `a32` code, in IRQ mode. The scratch register is
+/// clobbered, so its value from the interrupted code must already be saved, as
+/// [`irq_handler_prologue!`] does in the example. The interrupt must be
+/// acknowledged before this, or it will fire again as soon as IRQ is unmasked.
+#[proc_macro]
+pub fn nested_irq_enter(token_stream: TokenStream) -> TokenStream {
+  nested_irq_impl::nested_irq_enter_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Undoes [`nested_irq_enter!`], going back to IRQ mode with IRQ masked.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// nested_irq_exit!(scratch = "r12")
+/// # ;
+/// # assert_eq!(s, "pop {{r12, lr}}\nmsr CPSR_c, #0b10010010\npop {{r12, lr}}\nmsr SPSR_cxsf, r12");
+/// ```
+///
+/// ## Input
+/// The same arguments as given to [`nested_irq_enter!`].
+///
+/// ## Output
+/// The reverse of the enter: a `pop` of the scratch register and System mode's
+/// `lr`, an `msr` back to IRQ mode with IRQ masked, a `pop` of the scratch
+/// register and IRQ mode's `lr`, then an `msr` of the scratch register into
+/// SPSR.
+#[proc_macro]
+pub fn nested_irq_exit(token_stream: TokenStream) -> TokenStream {
+  nested_irq_impl::nested_irq_exit_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Generates the asm string to set the stack pointer of each CPU mode, as is
+/// done at startup.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// set_mode_stacks!(irq = "=0x03007FA0", svc = "=0x03007FE0", sys = "=0x03007F00")
+/// # ;
+/// # assert_eq!(s, "msr CPSR_c, #0b11010010\nldr sp, =0x03007FA0\nmsr CPSR_c, #0b11010011\nldr sp, =0x03007FE0\nmsr CPSR_c, #0b11011111\nldr sp, =0x03007F00");
+/// ```
+///
+/// ## Input
+/// * One or more `mode = "value"`, using the same mode names as with
+///   [`a32_set_cpu_control!`], other than User mode (set its stack with `sys`
+///   instead, since the two share `sp`). Each value is either `"=value"`, to
+///   load `sp` from a literal pool, or one of `"r0"` through `"r7"`, to copy
+///   `sp` from that register.
+/// * `end_in = mode_name` (optional): the mode to be in at the end. Otherwise
+///   this ends in the last mode listed.
+///
+/// ## Output
+/// For each mode in the order given, an `msr` to switch to that mode with IRQ
+/// and FIQ masked, and then an `ldr` or `mov` to set `sp`. If `end_in` is a
+/// different mode than the last one listed, one more `msr` to switch to it.
+///
+/// ## Assembly Safety
+/// This can only be used in `a32` code, in a privileged mode. A register
+/// value must not be one of the registers that's banked in some modes, so
+/// `asm!` substitutions must be limited to `r0`-`r7` some other way. IRQ and
+/// FIQ are masked at the end, even in the `end_in` mode.
+#[proc_macro]
+pub fn set_mode_stacks(token_stream: TokenStream) -> TokenStream {
+  set_mode_stacks_impl::set_mode_stacks_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Generates the asm string of the exception vector table.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// vector_table!(reset = "_start", swi = "swi_handler", irq = "irq_handler", default = "hang")
+/// # ;
+/// # assert_eq!(s, "ldr pc, =_start\nldr pc, =hang\nldr pc, =swi_handler\nldr pc, =hang\nldr pc, =hang\nldr pc, =hang\nldr pc, =irq_handler\nldr pc, =hang");
+/// ```
+///
+/// ## Input
+/// Each of these is optional, and they can be in any order.
+/// * `vector = "symbol"` for any of the vectors: `reset`, `undefined` (or
+///   `undef`), `swi` (or `svc`), `prefetch_abort`, `data_abort`, `reserved`,
+///   `irq`, and `fiq`.
+/// * `default = "symbol"`: the target of every vector that isn't given.
+///   Otherwise those vectors loop forever with `b .`.
+/// * `style = ldr` or `style = b` (default `ldr`): if each vector is an `ldr`
+///   of `pc` from a literal pool, which can reach any address, or a `b`, which
+///   can only reach +/-32MB.
+///
+/// ## Output
+/// A string literal of exactly 8 lines, one instruction per vector, in the
+/// order the CPU expects them.
+///
+/// ## Assembly Safety
+/// This can only be used in `a32` code, and must be placed where the CPU looks
+/// for the vector table (usually address 0). With `style = ldr` the literal
+/// pool is placed after the table by the assembler, so make sure there's a
+/// `.ltorg` (or the end of the section) within 4KB of it.
+#[proc_macro]
+pub fn vector_table(token_stream: TokenStream) -> TokenStream {
+  vector_table_impl::vector_table_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Branches to the word, halfword, or byte version of a copy loop depending on
+/// the alignment of two pointers.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// align_dispatch!(
+///   ptr = "r0",
+///   other = "r1",
+///   scratch = "r2",
+///   labels = { word = 10, half = 11, byte = 12 }
+/// )
+/// # ;
+/// ```
+///
+/// ## Input
+/// Named arguments, in any order:
+/// * `ptr` and `other`: string literals of the two pointer registers.
+/// * `scratch`: string literal of a register that can be clobbered. It must
+///   differ from both pointers.
+/// * `labels`: a grouping of numeric labels, `word = N`, `half = N`, and
+///   optionally `byte = N`.
+///
+/// ## Output
+/// The low bits of both pointers are combined with `orr` into `scratch`, and
+/// then:
+/// * If both pointers are 4-aligned, branch forward to the `word` label.
+/// * Otherwise, if both are 2-aligned, branch forward to the `half` label.
+/// * Otherwise, branch forward to the `byte` label, or fall through if there's
+///   no `byte` label.
+#[proc_macro]
+pub fn align_dispatch(token_stream: TokenStream) -> TokenStream {
+  align_dispatch_impl::align_dispatch_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Adds two `i32` values, saturating instead of wrapping on overflow.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// sat_add_s32!(dst = "r0", a = "r1", b = "r2", scratch = "r3")
+/// # ;
+/// # assert_eq!(s, "adds r0, r1, r2\nmovvs r3, #0x80000000\neorvs r0, r3, r0, asr #31");
+/// ```
+///
+/// ## Input
+/// Named arguments, in any order:
+/// * `dst`, `a`, `b`: string literals of the registers for `dst = a + b`.
+/// * `scratch`: string literal of a register that can be clobbered. It must
+///   differ from the other three registers. It's not needed if `arch` is
+///   `v5te` or later.
+/// * `arch` (optional, default `v4`): the target architecture.
+///
+/// ## Output
+/// Before ARMv5TE, an `adds` followed by a fixup that's only run on overflow.
+/// The fixup puts `0x7FFFFFFF` or `0x80000000` in `dst`, depending on which way
+/// the result overflowed. This clobbers the flags.
+///
+/// On ARMv5TE or later, a single `qadd` (which doesn't clobber the flags, but
+/// does set the Q flag on saturation).
+///
+/// ## Assembly Safety
+/// Before ARMv5TE this uses conditional execution, so it can only be used in
+/// `a32` code.
+#[proc_macro]
+pub fn sat_add_s32(token_stream: TokenStream) -> TokenStream {
+  sat_arith_impl::sat_add_s32_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Subtracts two `i32` values, saturating instead of wrapping on overflow.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// sat_sub_s32!(dst = "r0", a = "r1", b = "r2", arch = v5te)
+/// # ;
+/// # assert_eq!(s, "qsub r0, r1, r2");
+/// ```
+///
+/// ## Input
+/// The same as [`sat_add_s32!`], for `dst = a - b`.
+///
+/// ## Output
+/// The same as [`sat_add_s32!`], but using `subs` or `qsub`.
+///
+/// ## Assembly Safety
+/// Before ARMv5TE this uses conditional execution, so it can only be used in
+/// `a32` code.
+#[proc_macro]
+pub fn sat_sub_s32(token_stream: TokenStream) -> TokenStream {
+  sat_arith_impl::sat_sub_s32_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Swaps the values of two registers.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// swap_regs!("r0", "r1")
+/// # ;
+/// # assert_eq!(s, "eor r0, r0, r1\neor r1, r0, r1\neor r0, r0, r1");
+/// # let s =
+/// swap_regs!("r0", "r1", scratch = "r2")
+/// # ;
+/// # assert_eq!(s, "mov r2, r0\nmov r0, r1\nmov r1, r2");
+/// ```
+///
+/// ## Input
+/// * Two string literals of the registers to swap. They must be different
+///   registers, and neither can be `pc`.
+/// * Optionally, `scratch = "rN"`, a register that can be clobbered. It must
+///   differ from both registers being swapped, and can't be `pc`.
+///
+/// ## Output
+/// Without a scratch register, three `eor` instructions. If both registers were
+/// the same this would zero the register instead, which is why aliasing
+/// registers are a compile error.
+///
+/// With a scratch register, three `mov` instructions.
+#[proc_macro]
+pub fn swap_regs(token_stream: TokenStream) -> TokenStream {
+  swap_regs_impl::swap_regs_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Sets a register to the smaller of two registers.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// reg_min!("r0" = "r1", "r2", signed)
+/// # ;
+/// # assert_eq!(s, "cmp r1, r2\nmovle r0, r1\nmovgt r0, r2");
+/// ```
+///
+/// * The first argument is `"dst" = "a"`, then `"b"`, then `signed` or
+///   `unsigned` for how the registers are compared. The registers can be
+///   string literals or register names, but not `pc`.
+/// * This is a `cmp` and then a conditional `mov` for each source. When `dst`
+///   is also one of the sources, only the other source needs a `mov`.
+/// * The flags are left as the `cmp` set them.
+#[proc_macro]
+pub fn reg_min(token_stream: TokenStream) -> TokenStream {
+  reg_select_impl::reg_min_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Sets a register to the larger of two registers.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// reg_max!("r0" = "r0", "r2", unsigned)
+/// # ;
+/// # assert_eq!(s, "cmp r0, r2\nmovlo r0, r2");
+/// ```
+///
+/// This works the same as [`reg_min!`].
+#[proc_macro]
+pub fn reg_max(token_stream: TokenStream) -> TokenStream {
+  reg_select_impl::reg_max_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Sets a register to the absolute value of a register.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// reg_abs!("r0" = "r1")
+/// # ;
+/// # assert_eq!(s, "movs r0, r1\nrsbmi r0, r0, #0");
+/// ```
+///
+/// * The input is `"dst" = "src"`, or just one register to change it in place
+///   (then it's a `cmp` and `rsblt`). The registers can be string literals or
+///   register names, but not `pc`.
+/// * This sets the flags. Adding `branchless, scratch = "rN"` instead uses an
+///   `asr`, `eor`, and `sub`, which leaves the flags alone but needs a scratch
+///   register that differs from the others.
+/// * The absolute value of `i32::MIN` doesn't fit in an `i32`, so `i32::MIN`
+///   stays as `i32::MIN` (which is the right value if it's read as a `u32`).
+#[proc_macro]
+pub fn reg_abs(token_stream: TokenStream) -> TokenStream {
+  reg_abs_impl::reg_abs_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Extracts an 8-bit or 16-bit lane of a register into the low bits of another
+/// register.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// extract_lane!(dst = "r0", src = "r1", lane = 2, width = 8, signed = false)
+/// # ;
+/// # assert_eq!(s, "mov r0, r1, lsl #8\nmov r0, r0, lsr #24");
+/// ```
+///
+/// ## Input
+/// Named arguments, in any order:
+/// * `dst`, `src`: string literals of the registers. They can be the same.
+/// * `width`: the lane width in bits, `8` or `16`.
+/// * `lane`: which lane, counting from the low bits. The lane must be within
+///   the register, so it's at most `3` for bytes and `1` for halfwords.
+/// * `signed` (optional, default `false`): if the lane should be sign extended
+///   instead of zero extended.
+/// * `arch` (optional, default `v4`): the target architecture.
+///
+/// ## Output
+/// Before ARMv6, a shift left that puts the lane at the top of the register
+/// followed by a shift right (`lsr` or `asr`) that brings it to the bottom. If
+/// the lane is already at the top this is just the one shift right.
+///
+/// On ARMv6 or later, one `uxtb`, `uxth`, `sxtb`, or `sxth`, with a rotation if
+/// the lane isn't the lowest one.
+#[proc_macro]
+pub fn extract_lane(token_stream: TokenStream) -> TokenStream {
+  lane_impl::extract_lane_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Inserts the low 8 or 16 bits of a register into a lane of another register,
+/// leaving the rest of that register as is.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// insert_lane!(dst = "r0", src = "r1", scratch = "r2", lane = 1, width = 16)
+/// # ;
+/// # assert_eq!(s, "bic r0, r0, #0xFF0000\nbic r0, r0, #0xFF000000\nmov r2, r1, lsl #16\norr r0, r0, r2");
+/// ```
+///
+/// ## Input
+/// Named arguments, in any order:
+/// * `dst`: string literal of the register to insert into.
+/// * `src`: string literal of the register holding the value to insert. Any
+///   bits above the lane width are ignored.
+/// * `scratch`: string literal of a register that can be clobbered.
+/// * `width` and `lane`: as with [`extract_lane!`].
+///
+/// All three registers must be different.
+///
+/// ## Output
+/// One `bic` per byte of the lane to clear it, then a `mov` and `orr` to shift
+/// the low bits of `src` into place.
+#[proc_macro]
+pub fn insert_lane(token_stream: TokenStream) -> TokenStream {
+  lane_impl::insert_lane_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Advances a pseudo-random number generator's state by one step.
+///
+/// This is for things like memory test patterns, where you want a sequence
+/// that's cheap, repeatable, and looks random enough. It's not suitable for
+/// anything security related.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// prng_step!(state = "r0", style = xorshift32)
+/// # ;
+/// # assert_eq!(s, "eor r0, r0, r0, lsl #13\neor r0, r0, r0, lsr #17\neor r0, r0, r0, lsl #5");
+/// ```
+///
+/// ## Input
+/// Named arguments, in any order:
+/// * `state`: string literal of the register holding the state.
+/// * `style`: which generator to use, `xorshift32` or `lcg`.
+/// * `scratch`: string literal of a register that can be clobbered. It must
+///   differ from `state`. This is only needed for the `lcg` style.
+///
+/// ## Output
+/// * `xorshift32`: Marsaglia's xorshift with shifts of 13, 17, and 5, as three
+///   `eor` instructions with a shifted operand. The state must never be zero,
+///   or it will stay zero.
+/// * `lcg`: `state = state * 1664525 + 1013904223` (the *Numerical Recipes*
+///   constants), with both constants loaded into `scratch` by `ldr =`. The low
+///   bits of an LCG are much less random than the high bits.
+#[proc_macro]
+pub fn prng_step(token_stream: TokenStream) -> TokenStream {
+  prng_step_impl::prng_step_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Places a CRC-32 of some lines of assembly after those lines, so that a
+/// build tool can check which version of the code is in the final binary.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// with_integrity_word!(label = "blit_crc", {
+///   "ldmia r1!, {{r2-r5}}",
+///   "stmia r0!, {{r2-r5}}",
+/// })
+/// # ;
+/// # assert!(s.ends_with("b blit_crc_end\nblit_crc:\n.word 0xD719E632\nblit_crc_end:\n"));
+/// ```
+///
+/// ## Input
+/// * `label = "name"`: the label to place on the CRC word. It can use ascii
+///   alphanumerics, `_`, `.`, and `$`, and can't start with a digit.
+/// * A grouping of lines. Every line must be a literal, or another bracer
+///   macro, so that the text is known when the macro expands.
+///
+/// ## Output
+/// One string literal of the lines, each ending with a newline, followed by:
+/// ```text
+/// b <label>_end
+/// <label>:
+/// .word <crc>
+/// <label>_end:
+/// ```
+/// The CRC is the common CRC-32 (the one used by zip and png) of the text of
+/// the lines, each ending with a newline, but not of the lines after them. Use
+/// [`integrity_value!`] to get the same value in rust code.
+#[proc_macro]
+pub fn with_integrity_word(token_stream: TokenStream) -> TokenStream {
+  integrity_impl::with_integrity_word_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// The CRC-32 that [`with_integrity_word!`] would place after the same lines.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// let crc: u32 = integrity_value!({
+///   "ldmia r1!, {{r2-r5}}",
+///   "stmia r0!, {{r2-r5}}",
+/// });
+/// # assert_eq!(crc, 0xD719E632);
+/// ```
+///
+/// ## Input
+/// A grouping of lines, the same as given to [`with_integrity_word!`].
+///
+/// ## Output
+/// A `u32` literal.
+#[proc_macro]
+pub fn integrity_value(token_stream: TokenStream) -> TokenStream {
+  integrity_impl::integrity_value_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Opens a region that's closed by a later [`region_end!`], which can be in a
+/// different `asm!` string.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// region_begin!(kind = section, name = "blit", args = (".iwram.text"))
+/// # ;
+/// # assert_eq!(s, ".pushsection .iwram.text,\"ax\",%progbits");
+/// // ... any other lines, including ones from other macros
+/// # let s =
+/// region_end!(kind = section, name = "blit")
+/// # ;
+/// # assert_eq!(s, ".popsection");
+/// # assert_regions_closed!();
+/// ```
+///
+/// ## Input
+/// Named arguments, in any order:
+/// * `kind`: the kind of region.
+///   * `section`: the code is placed in another section. This needs
+///     `args = ("section_name")`, with the same rules for the name as
+///     [`put_fn_in_section!`].
+///   * `a32`: the code is `a32` code within `t32` code, the same as with
+///     [`t32_with_a32_scope!`]. This takes no `args`.
+/// * `name`: a string literal naming the region, which is given again to
+///   [`region_end!`]. Only one region of a given name can be open at once.
+/// * `args` (only for some kinds): a grouping of any arguments of the kind.
+///
+/// ## Output
+/// The opening directive of the region, either `.pushsection` or `.code 32`.
+///
+/// ## Caveats
+/// The open regions are tracked in the proc-macro process while a crate is
+/// being expanded, so the begin and end must be in the same crate, and the
+/// begin must come first in the source. Tools that expand macros one at a time
+/// and out of order (such as some IDEs) can report false errors.
+#[proc_macro]
+pub fn region_begin(token_stream: TokenStream) -> TokenStream {
+  region_impl::region_begin_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Closes a region opened with [`region_begin!`].
+///
+/// ## Input
+/// * `kind`: the same kind that the region was opened as. A different kind is a
+///   compile error.
+/// * `name`: the same name that the region was opened with.
+///
+/// ## Output
+/// The closing directive of the region, either `.popsection` or `.code 16`.
+#[proc_macro]
+pub fn region_end(token_stream: TokenStream) -> TokenStream {
+  region_impl::region_end_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Checks that every region opened with [`region_begin!`] was closed by
+/// [`region_end!`].
+///
+/// Put this at the end of a module (outside of any function) to be sure that
+/// there's no region left open.
+///
+/// ## Input
+/// None.
+///
+/// ## Output
+/// Nothing, or a compile error for each region that's still open. Either way,
+/// all regions are considered closed afterwards, so each one is only reported
+/// once.
+#[proc_macro]
+pub fn assert_regions_closed(token_stream: TokenStream) -> TokenStream {
+  region_impl::assert_regions_closed_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Sets bits in a memory-mapped register, with a load, an `orr`, and a store.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// mmio_set_bits!(addr_reg = "r0", mask = 0x0008, scratch = "r1", width = 16)
+/// # ;
+/// # assert_eq!(s, "ldrh r1, [r0]\norr r1, r1, #0x8\nstrh r1, [r0]");
+/// ```
+///
+/// ## Input
+/// Named arguments, in any order:
+/// * `addr_reg`: string literal of the register holding the address.
+/// * `mask`: the bits to change, as an integer literal. It can't be zero, and
+///   must fit in the width.
+/// * `scratch`: string literal of a register that can be clobbered. It must
+///   differ from `addr_reg`.
+/// * `width`: the size of the memory-mapped register in bits, `8`, `16`, or
+///   `32`. This picks between `ldrb`/`strb`, `ldrh`/`strh`, and `ldr`/`str`.
+/// * `mask_scratch` (optional): string literal of another register that can be
+///   clobbered. This is only used (and only needed) if the mask can't be an
+///   `a32` immediate, in which case the mask is loaded into it with `ldr =`.
+///
+/// ## Output
+/// The load, the bitwise op, and the store, with the mask loaded first if it
+/// needs to be.
+///
+/// ## Assembly Safety
+/// This is not atomic. If an interrupt handler could change the same register
+/// in between the load and the store, that change would be lost.
+#[proc_macro]
+pub fn mmio_set_bits(token_stream: TokenStream) -> TokenStream {
+  mmio_bits_impl::mmio_set_bits_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Clears bits in a memory-mapped register, with a load, a `bic`, and a store.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// mmio_clear_bits!(addr_reg = "r0", mask = 0x80, scratch = "r1", width = 8)
+/// # ;
+/// # assert_eq!(s, "ldrb r1, [r0]\nbic r1, r1, #0x80\nstrb r1, [r0]");
+/// ```
+///
+/// The input, output, and safety rules are the same as [`mmio_set_bits!`].
+#[proc_macro]
+pub fn mmio_clear_bits(token_stream: TokenStream) -> TokenStream {
+  mmio_bits_impl::mmio_clear_bits_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Toggles bits in a memory-mapped register, with a load, an `eor`, and a
+/// store.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// mmio_toggle_bits!(addr_reg = "r0", mask = 0x3, scratch = "r1", width = 32)
+/// # ;
+/// # assert_eq!(s, "ldr r1, [r0]\neor r1, r1, #0x3\nstr r1, [r0]");
+/// ```
+///
+/// The input, output, and safety rules are the same as [`mmio_set_bits!`].
+#[proc_macro]
+pub fn mmio_toggle_bits(token_stream: TokenStream) -> TokenStream {
+  mmio_bits_impl::mmio_toggle_bits_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Checks that a value can be encoded as an `a32` data-processing immediate.
+///
+/// This lets you put a check next to a hand-written line that uses an
+/// immediate, so that a bad value is a compile error with a clear message.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s = concat!(
+/// check_a32_imm!(0x3F0),
+/// "orr r0, r0, #0x3F0",
+/// # );
+/// # assert_eq!(s, "orr r0, r0, #0x3F0");
+/// ```
+///
+/// ## Input
+/// * An integer literal, which can be negative.
+/// * Optionally, `mvn` or `cmn`. Then the value is also fine if its complement
+///   (for `mvn`) or its negation (for `cmn`) is encodable, which is what the
+///   assembler will use in place of `mov` or `cmp` if it has to.
+///
+/// ## Output
+/// An empty string literal if the value is encodable, so the macro can be
+/// placed among other lines. Otherwise a compile error naming the value.
+#[proc_macro]
+pub fn check_a32_imm(token_stream: TokenStream) -> TokenStream {
+  check_a32_imm_impl::check_a32_imm_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Generates the asm string to put a constant in a register, picking the
+/// shortest way to do it.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// load_const!("r0", 0x04000208)
+/// # ;
+/// # assert_eq!(s, "mov r0, #0x208\norr r0, r0, #0x4000000");
+/// ```
+///
+/// ## Input
+/// * The register, as a string literal or a register name. This can't be `pc`.
+/// * The value, as an integer literal that fits in 32 bits (signed or
+///   unsigned).
+/// * `strategy = inline` or `strategy = pool` (optional): to force loading the
+///   value with instructions, or from a literal pool.
+///
+/// ## Output
+/// A string literal of the first of these that works:
+/// * A `mov` of the value, if it's an `a32` immediate (see
+///   [`check_a32_imm!`]).
+/// * An `mvn` of the complement, if that's an `a32` immediate.
+/// * A `mov` and an `orr`, if the value is two `a32` immediates put together.
+/// * An `ldr rX, =value`, which loads from a literal pool.
+///
+/// With `strategy = pool` this is always the `ldr`, and with `strategy =
+/// inline` it's a compile error if the value needs the `ldr`.
+///
+/// ## Assembly Safety
+/// This can only be used in `a32` code. The `ldr` needs a literal pool in
+/// range, see [`with_pool!`].
+#[proc_macro]
+pub fn load_const(token_stream: TokenStream) -> TokenStream {
+  load_const_impl::load_const_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Runs a polling body in a loop until a condition is met, giving up after a
+/// number of tries.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// poll_with_timeout!(
+///   count_reg = "r3",
+///   limit = 10000,
+///   [4],
+///   on_timeout = "timeout_handler",
+///   {
+///     "ldr r1, [r0]",
+///     "tst r1, #1",
+///   },
+///   until = ne
+/// )
+/// # ;
+/// # assert_eq!(s, "ldr r3, =0x2710\n4:\nldr r1, [r0]\ntst r1, #1\nbne 4f\nsubs r3, r3, #1\nbne 4b\nb timeout_handler\n4:\n");
+/// ```
+///
+/// ## Input
+/// Named arguments, in any order:
+/// * `count_reg`: string literal of the register to count the tries in. It
+///   can't be `sp` or `pc`, and the body must not change it.
+/// * `limit`: the number of tries, which can't be zero.
+/// * `until`: the condition code (eg: `ne`) that means the body succeeded.
+/// * `on_timeout` (optional): string literal of a symbol to branch to if all
+///   the tries fail. Without it, a timeout falls through just like a success.
+///
+/// And two groups, in this order:
+/// * A numeric label, as with [`when!`]. It's placed at both the top and the
+///   end of the loop.
+/// * The lines of the body, which should set the flags for `until` as the last
+///   thing they do.
+///
+/// ## Output
+/// The count register is set to the limit (with `mov` if possible, `ldr =`
+/// otherwise), then each time through the loop:
+/// * The body runs, and if the `until` condition holds the loop exits.
+/// * Otherwise the count goes down by one, and the loop runs again if it's not
+///   zero.
+///
+/// If the count reaches zero there's a branch to `on_timeout`, if given.
+#[proc_macro]
+pub fn poll_with_timeout(token_stream: TokenStream) -> TokenStream {
+  poll_with_timeout_impl::poll_with_timeout_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Checks that a block of lines switches between `a32` and `t32` consistently,
+/// and ends in the expected instruction set.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// asm_block!(start = t32, end = t32, {
+///   "movs r0, #1",
+///   t32_with_a32_scope!{
+///     "mrs r1, CPSR",
+///   },
+///   "adds r0, r0, r1",
+/// })
+/// # ;
+/// # assert_eq!(s, "movs r0, #1\n.balignw 4, 0x46C0\n.code 32\nmrs r1, CPSR\n.code 16\n\nadds r0, r0, r1\n");
+/// ```
+///
+/// ## Input
+/// * `start = a32` or `start = t32`: the instruction set at the start of the
+///   block.
+/// * `end = a32` or `end = t32`: the instruction set that the block must end
+///   in.
+/// * A grouping of the lines of the block. Each line is a string literal or a
+///   macro, the same as with [`t32_with_a32_scope!`].
+///
+/// ## Output
+/// The lines, joined just like [`t32_with_a32_scope!`] does (but without adding
+/// any `.code` lines).
+///
+/// It's a compile error if the block doesn't end in the `end` instruction set,
+/// or if a `.code 32`, `.code 16`, `.arm`, or `.thumb` line switches to the
+/// instruction set that the block is already in (which likely means that the
+/// block was written thinking the state was something else).
+///
+/// ## Caveats
+/// Only lines that are literals or bracer macros can be checked. Anything else
+/// (such as a `const`, or another crate's macro) is assumed not to change the
+/// instruction set.
+#[proc_macro]
+pub fn asm_block(token_stream: TokenStream) -> TokenStream {
+  asm_block_impl::asm_block_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Gives names to immediate values used in some lines of assembly.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// asm_consts!({ MODE_MASK = 0x1F, IRQ_BIT = 0x80 }, {
+///   "and r1, r0, #MODE_MASK",
+///   "orr r0, r0, #IRQ_BIT",
+/// })
+/// # ;
+/// # assert_eq!(s, "and r1, r0, #0x1F\norr r0, r0, #0x80\n");
+/// ```
+///
+/// ## Input
+/// * Optionally, `deny_unused = true,` at the start, which makes any declared
+///   name that isn't used a compile error.
+/// * A grouping of declarations, each `NAME = value` with an integer value.
+/// * A grouping of lines, the same as with [`t32_with_a32_scope!`].
+///
+/// ## Output
+/// The lines, joined just like [`t32_with_a32_scope!`] does, with each `#NAME`
+/// in the string literal lines replaced by `#` and the value in hex.
+///
+/// Every `#NAME` (a `#` followed by an identifier) is taken to be one of the
+/// declared names, and it's a compile error if it isn't. When a value is put
+/// into a data-processing instruction (`mov`, `cmp`, `tst`, and so on), the
+/// value must be encodable as an immediate of that instruction, or of the
+/// instruction that the assembler would swap in (eg: `mvn` for `mov`). Lines
+/// made by other macros are passed through as is.
+#[proc_macro]
+pub fn asm_consts(token_stream: TokenStream) -> TokenStream {
+  asm_consts_impl::asm_consts_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Generates the asm string to define assembler constants with `.equ`.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// # let s =
+/// def_asm_const!("REG_DISPCNT", 0x04000000)
+/// # ;
+/// # assert_eq!(s, ".equ REG_DISPCNT, 0x4000000\n");
+/// # let s =
+/// def_asm_const! {
+///   "REG_DISPCNT" = 0x04000000,
+///   "REG_IME" = 0x04000208,
+/// }
+/// # ;
+/// # assert_eq!(s, ".equ REG_DISPCNT, 0x4000000\n.equ REG_IME, 0x4000208\n");
+/// ```
+///
+/// ## Input
+/// Either one name and value, as `"NAME", value`, or any number of
+/// `"NAME" = value` definitions.
+///
+/// Each name is a string literal of ascii letters, digits, `_`, `.`, and `$`,
+/// not starting with a digit. Each value is an integer literal of any base
+/// that fits in 32 bits (signed or unsigned).
+///
+/// ## Output
+/// A string literal of one `.equ` line per definition, each with the value in
+/// hex. The definitions last until the end of the assembly they're placed in,
+/// so one `global_asm!` block (or one `asm!` block).
+///
+/// To use a constant within a single `asm!` block only, [`asm_consts!`]
+/// substitutes the values directly instead.
+#[proc_macro]
+pub fn def_asm_const(token_stream: TokenStream) -> TokenStream {
+  def_asm_const_impl::def_asm_const_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Gives the CPSR control byte that [`a32_set_cpu_control!`] would set.
+///
+/// **Usage Example:**
+/// ```rust
+/// # use bracer::*;
+/// const SVC_IRQ_MASKED: u32 =
+///   cpu_control_bits!(Supervisor, irq_masked = true, fiq_masked = false);
+/// assert_eq!(SVC_IRQ_MASKED, 0b1001_0011);
+/// ```
+///
+/// ## Input
+/// The same as with [`a32_set_cpu_control!`].
+///
+/// ## Output
+/// A `u32` integer literal of the `I F T MMMMM` bits, which can be compared
+/// against the low byte of a value read with `mrs`.
+#[proc_macro]
+pub fn cpu_control_bits(token_stream: TokenStream) -> TokenStream {
+  a32_set_cpu_control_impl::cpu_control_bits_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// Expands to a single string literal of the fully folded input.
+///
+/// This is for tests, particularly in downstream crates, that want to check
+/// the final assembly text of bracer macros without needing to run on ARM.
+///
+/// ## Input
+/// Any expression made of only string, char, and integer literals, `concat!`,
+/// and bracer macro invocations (which can also be named by path, eg:
+/// `bracer::when!`). Several comma separated expressions are joined just like
+/// with `concat!`.
+///
+/// ## Output
+/// One string literal of the folded text. If any element of the input isn't a
+/// literal (eg: a `const` or a non-bracer macro), then instead you get a
+/// `compile_error!` pointing at that element.
+///
+/// ## Stability
+/// Though hidden from the docs, this macro is a stable part of the crate's API.
+/// The folded output of any bracer invocation is always identical to what that
+/// invocation's `concat!` output would produce.
+#[doc(hidden)]
+#[proc_macro]
+pub fn __bracer_expand_to_str(token_stream: TokenStream) -> TokenStream {
+  __bracer_expand_to_str_impl(token_stream)
+    .unwrap_or_else(|e| e.to_compile_error())
+}
+
+fn __bracer_expand_to_str_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let folded = fold_to_str(token_stream)?;
+  Ok(TokenStream::from(TokenTree::Literal(Literal::string(&folded))))
+}
+
+/// Gets the error message of a failed bracer macro invocation, as a string
+/// literal. If there's more than one message they're each on their own line.
+///
+/// This lets tests check the messages of invocations that are supposed to fail.
+/// If the inner invocation *doesn't* fail then this is itself a compile error.
+#[doc(hidden)]
+#[proc_macro]
+pub fn __bracer_error_of(token_stream: TokenStream) -> TokenStream {
+  let mut stream_iter = token_stream.into_iter();
+  let invocation =
+    match (stream_iter.next(), stream_iter.next(), stream_iter.next()) {
+      (
+        Some(TokenTree::Ident(name)),
+        Some(TokenTree::Punct(bang)),
+        Some(TokenTree::Group(args)),
+      ) if bang == '!' && stream_iter.next().is_none() => {
+        Some((name.to_string(), args.stream()))
+      }
+      _ => None,
+    };
+  let Some((name, args)) = invocation else {
+    return Error::call_site("expected one macro invocation")
+      .to_compile_error();
+  };
+  match dispatch_impl(&name) {
+    Some(impl_fn) => match impl_fn(args) {
+      Ok(_) => {
+        Error::call_site(format!("`{name}!` did not fail")).to_compile_error()
+      }
+      Err(e) => {
+        TokenStream::from(TokenTree::Literal(Literal::string(&e.message())))
+      }
+    },
+    None => Error::call_site(format!("`{name}` is not a bracer macro"))
+      .to_compile_error(),
+  }
+}
+
+/// Gets the source text under the span of the first string literal that a
+/// bracer macro invocation outputs, as a string literal.
+///
+/// This lets tests check that output carries the span of the input it came
+/// from, so that an assembler error about the output points at the user's
+/// code. Output with the call site span gives the whole inner invocation.
+#[doc(hidden)]
+#[proc_macro]
+pub fn __bracer_span_of(token_stream: TokenStream) -> TokenStream {
+  fn first_literal(stream: TokenStream) -> Option<Literal> {
+    stream.into_iter().find_map(|tree| match tree {
+      TokenTree::Literal(l) => Some(l),
+      TokenTree::Group(g) => first_literal(g.stream()),
+      _ => None,
+    })
+  }
+  let mut stream_iter = token_stream.into_iter();
+  let invocation =
+    match (stream_iter.next(), stream_iter.next(), stream_iter.next()) {
+      (
+        Some(TokenTree::Ident(name)),
+        Some(TokenTree::Punct(bang)),
+        Some(TokenTree::Group(args)),
+      ) if bang == '!' && stream_iter.next().is_none() => {
+        Some((name.to_string(), args.stream()))
+      }
+      _ => None,
+    };
+  let Some((name, args)) = invocation else {
+    return Error::call_site("expected one macro invocation")
+      .to_compile_error();
+  };
+  let Some(impl_fn) = dispatch_impl(&name) else {
+    return Error::call_site(format!("`{name}` is not a bracer macro"))
+      .to_compile_error();
+  };
+  match impl_fn(args).map(first_literal) {
+    Ok(Some(literal)) => {
+      let text = literal.span().source_text().unwrap_or_default();
+      TokenStream::from(TokenTree::Literal(Literal::string(&text)))
+    }
+    Ok(None) => Error::call_site(format!("`{name}!` output no literal"))
+      .to_compile_error(),
+    Err(e) => e.to_compile_error(),
+  }
+}
+
+/// Gets the impl function of a bracer macro, by name.
+fn dispatch_impl(name: &str) -> Option<ImplFn> {
+  Some(match name {
+    "a32_read_spsr_to" => a32_read_spsr_to_impl::a32_read_spsr_to_impl,
+    "a32_write_spsr_from" => a32_write_spsr_from_impl::a32_write_spsr_from_impl,
+    "a32_fake_blx" => a32_fake_blx_impl::a32_fake_blx_impl,
+    "put_fn_in_section" => put_fn_in_section_impl::put_fn_in_section_impl,
+    "t32_with_a32_scope" => t32_with_a32_scope_impl::t32_with_a32_scope_impl,
+    "a32_set_cpu_control" => a32_set_cpu_control_impl::a32_set_cpu_control_impl,
+    "when" => when_impl::when_impl,
+    "adjust_ptr" => adjust_ptr_impl::adjust_ptr_impl,
+    "mul_const" => mul_const_impl::mul_const_impl,
+    "div_const_u32" => div_const_u32_impl::div_const_u32_impl,
+    "assert_t16_ok" => assert_t16_ok_impl::assert_t16_ok_impl,
+    "align_dispatch" => align_dispatch_impl::align_dispatch_impl,
+    "svc_reentry_save" => svc_reentry_impl::svc_reentry_save_impl,
+    "svc_reentry_restore" => svc_reentry_impl::svc_reentry_restore_impl,
+    "task_save" => task_frame_impl::task_save_impl,
+    "task_restore" => task_frame_impl::task_restore_impl,
+    "task_frame_offsets" => task_frame_impl::task_frame_offsets_impl,
+    "sat_add_s32" => sat_arith_impl::sat_add_s32_impl,
+    "sat_sub_s32" => sat_arith_impl::sat_sub_s32_impl,
+    "swap_regs" => swap_regs_impl::swap_regs_impl,
+    "extract_lane" => lane_impl::extract_lane_impl,
+    "insert_lane" => lane_impl::insert_lane_impl,
+    "prng_step" => prng_step_impl::prng_step_impl,
+    "with_integrity_word" => integrity_impl::with_integrity_word_impl,
+    "integrity_value" => integrity_impl::integrity_value_impl,
+    "region_begin" => region_impl::region_begin_impl,
+    "region_end" => region_impl::region_end_impl,
+    "assert_regions_closed" => region_impl::assert_regions_closed_impl,
+    "mmio_set_bits" => mmio_bits_impl::mmio_set_bits_impl,
+    "mmio_clear_bits" => mmio_bits_impl::mmio_clear_bits_impl,
+    "mmio_toggle_bits" => mmio_bits_impl::mmio_toggle_bits_impl,
+    "check_a32_imm" => check_a32_imm_impl::check_a32_imm_impl,
+    "poll_with_timeout" => poll_with_timeout_impl::poll_with_timeout_impl,
+    "asm_block" => asm_block_impl::asm_block_impl,
+    "asm_consts" => asm_consts_impl::asm_consts_impl,
+    "cpu_control_bits" => a32_set_cpu_control_impl::cpu_control_bits_impl,
+    "a32_change_cpu_control" => {
+      a32_change_cpu_control_impl::a32_change_cpu_control_impl
+    }
+    "a32_set_interrupt_masks" => {
+      a32_set_interrupt_masks_impl::a32_set_interrupt_masks_impl
+    }
+    "t32_execute_a32" => t32_execute_a32_impl::t32_execute_a32_impl,
+    "with_section" => with_section_impl::with_section_impl,
+    "t32_fake_blx" => a32_fake_blx_impl::t32_fake_blx_impl,
+    "when_bit" => when_impl::when_bit_impl,
+    "when_flags" => when_impl::when_flags_impl,
+    "unless" => when_impl::unless_impl,
+    "while_loop" => loop_impl::while_loop_impl,
+    "do_while" => loop_impl::do_while_impl,
+    "counted_loop" => loop_impl::counted_loop_impl,
+    "cond_chain" => when_impl::cond_chain_impl,
+    "when_cond" => when_impl::when_cond_impl,
+    "reg_min" => reg_select_impl::reg_min_impl,
+    "reg_max" => reg_select_impl::reg_max_impl,
+    "reg_abs" => reg_abs_impl::reg_abs_impl,
+    "a32_read_cpsr_to" => a32_read_spsr_to_impl::a32_read_cpsr_to_impl,
+    "a32_write_cpsr_from" => a32_write_spsr_from_impl::a32_write_cpsr_from_impl,
+    "a32_get_cpu_mode" => a32_read_spsr_to_impl::a32_get_cpu_mode_impl,
+    "with_masked_interrupts" => {
+      with_masked_interrupts_impl::with_masked_interrupts_impl
+    }
+    "exception_return" => exception_return_impl::exception_return_impl,
+    "irq_handler_prologue" => irq_handler_impl::irq_handler_prologue_impl,
+    "irq_handler_epilogue" => irq_handler_impl::irq_handler_epilogue_impl,
+    "set_mode_stacks" => set_mode_stacks_impl::set_mode_stacks_impl,
+    "vector_table" => vector_table_impl::vector_table_impl,
+    "swi_call" => swi_call_impl::swi_call_impl,
+    "nested_irq_enter" => nested_irq_impl::nested_irq_enter_impl,
+    "nested_irq_exit" => nested_irq_impl::nested_irq_exit_impl,
+    "cp15_read" => cp15_impl::cp15_read_impl,
+    "cp15_write" => cp15_impl::cp15_write_impl,
+    "swp_swap" => swp_swap_impl::swp_swap_impl,
+    "a32_with_mode" => a32_with_mode_impl::a32_with_mode_impl,
+    "bkpt_debug" => bkpt_debug_impl::bkpt_debug_impl,
+    "emit_literal_pool" => literal_pool_impl::emit_literal_pool_impl,
+    "with_pool" => literal_pool_impl::with_pool_impl,
+    "load_const" => load_const_impl::load_const_impl,
+    "def_asm_const" => def_asm_const_impl::def_asm_const_impl,
+    "a32_fn_header" => fn_header_impl::a32_fn_header_impl,
+    "t32_fn_header" => fn_header_impl::t32_fn_header_impl,
+    "fn_footer" => fn_footer_impl::fn_footer_impl,
+    "asm_fn" => fn_footer_impl::asm_fn_impl,
+    "balign" => balign_impl::balign_impl,
+    "reserve_zeroed" => reserve_zeroed_impl::reserve_zeroed_impl,
+    "incbin" => incbin_impl::incbin_impl,
+    "emit_words" => emit_data_impl::emit_words_impl,
+    "emit_halfwords" => emit_data_impl::emit_halfwords_impl,
+    "emit_bytes" => emit_data_impl::emit_bytes_impl,
+    "asciz" => asciz_impl::asciz_impl,
+    "weak_symbol" => weak_symbol_impl::weak_symbol_impl,
+    "symbol_visibility" => weak_symbol_impl::symbol_visibility_impl,
+    "with_reg_aliases" => with_reg_aliases_impl::with_reg_aliases_impl,
+    "set_symbol" => set_symbol_impl::set_symbol_impl,
+    "gas_macro" => gas_macro_impl::gas_macro_impl,
+    "irp_each" => irp_each_impl::irp_each_impl,
+    "cfi_fn" => cfi_fn_impl::cfi_fn_impl,
+    "assert_code_size_le" => assert_code_size_impl::assert_code_size_le_impl,
+    "size_marker" => assert_code_size_impl::size_marker_impl,
+    "arch_directive" => arch_directive_impl::arch_directive_impl,
+    "jump_table" => jump_table_impl::jump_table_impl,
+    "clz_emulate" => clz_emulate_impl::clz_emulate_impl,
+    "byte_reverse" => byte_reverse_impl::byte_reverse_impl,
+    "byte_reverse16" => byte_reverse_impl::byte_reverse16_impl,
+    "__bracer_expand_to_str" => __bracer_expand_to_str_impl,
+    _ => return None,
+  })
 }
@@ -0,0 +1,424 @@
+//! Checks that lines of assembly can be encoded in a particular instruction
+//! set.
+//!
+//! The checks are conservative: only instructions that are known to the tables
+//! here get checked, and anything that can't be understood (unknown mnemonics,
+//! `asm!` substitutions, symbolic immediates) is allowed through.
+
+use super::*;
+
+/// An instruction set to lint assembly lines against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lint {
+  /// Thumb-1, the 16-bit only `t32` encodings of ARMv4T.
+  T16,
+  /// The `a32` encodings.
+  A32,
+}
+impl Lint {
+  /// Parses the value given to a `lint = ...` option.
+  pub fn from_option(value: &TokenTree) -> Result<Self, Error> {
+    match value.to_string().as_str() {
+      "t16" => Ok(Self::T16),
+      "a32" => Ok(Self::A32),
+      other => Err(Error::new(
+        value.span(),
+        format!("unknown lint `{other}`, expected `t16` or `a32`"),
+      )),
+    }
+  }
+}
+
+/// Lints every string literal line in a body of `asm!` expressions.
+///
+/// Any non-literal expressions (such as other macros) are skipped. When an
+/// `arch` is given, instructions that are too new for it are also errors.
+pub fn lint_body(
+  body: &TokenStream, lint: Lint, arch: Option<Arch>,
+) -> Result<(), Error> {
+  for tree in body.clone() {
+    let Some(content) = get_str_literal_content(&tree) else { continue };
+    for line in content.split(['\n', ';']) {
+      let (result, isa_name) = match lint {
+        Lint::T16 => (lint_t16_line(line), "Thumb-1"),
+        Lint::A32 => (lint_a32_line(line, arch), "A32"),
+      };
+      if let Err(problem) = result {
+        return Err(Error::new(
+          tree.span(),
+          format!("`{}` is not {isa_name}: {problem}", line.trim()),
+        ));
+      }
+    }
+  }
+  Ok(())
+}
+
+/// One operand of an instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Operand<'s> {
+  /// A core register, by number.
+  Reg(u8),
+  /// An immediate with a known value.
+  Imm(i64),
+  /// A shift applied to the previous operand.
+  Shift(&'s str),
+  /// A memory address in brackets, along with if there's a writeback `!`.
+  Mem(Vec<Operand<'s>>, bool),
+  /// A register list in braces.
+  RegList(Vec<Operand<'s>>),
+  /// Anything else (substitutions, labels, symbolic values).
+  Other(&'s str),
+}
+use Operand::*;
+
+/// Parses an immediate (without the `#`).
+pub fn parse_imm(text: &str) -> Option<i64> {
+  let (negative, digits) = match text.strip_prefix('-') {
+    Some(digits) => (true, digits.trim()),
+    None => (false, text),
+  };
+  let value = i64::try_from(parse_int_literal(digits)?).ok()?;
+  Some(if negative { -value } else { value })
+}
+
+/// Splits text on the commas that aren't inside `[]` or `{}`.
+fn split_operands(text: &str) -> Vec<&str> {
+  let mut out = Vec::new();
+  let mut depth = 0_i32;
+  let mut start = 0;
+  for (i, ch) in text.char_indices() {
+    match ch {
+      '[' | '{' => depth += 1,
+      ']' | '}' => depth -= 1,
+      ',' if depth == 0 => {
+        out.push(text[start..i].trim());
+        start = i + 1;
+      }
+      _ => (),
+    }
+  }
+  let last = text[start..].trim();
+  if !last.is_empty() {
+    out.push(last);
+  }
+  out
+}
+
+fn parse_operand(text: &str, reg_list: bool) -> Operand<'_> {
+  const SHIFTS: &[&str] = &["lsl", "lsr", "asr", "ror", "rrx"];
+  if let Some(imm) = text.strip_prefix('#') {
+    parse_imm(imm.trim()).map_or(Other(text), Imm)
+  } else if let Some(inner) = text.strip_prefix('[') {
+    let (inner, writeback) = match inner.strip_suffix('!') {
+      Some(inner) => (inner.trim_end(), true),
+      None => (inner, false),
+    };
+    let inner = inner.strip_suffix(']').unwrap_or(inner);
+    Mem(
+      split_operands(inner)
+        .into_iter()
+        .map(|t| parse_operand(t, false))
+        .collect(),
+      writeback,
+    )
+  } else if reg_list && text.starts_with('{') {
+    let inner = text.trim_start_matches('{').trim_end_matches('}');
+    RegList(
+      split_operands(inner)
+        .into_iter()
+        .map(|t| parse_operand(t, false))
+        .collect(),
+    )
+  } else if SHIFTS.iter().any(|s| text.to_ascii_lowercase().starts_with(s)) {
+    Shift(text)
+  } else if let Some(r) = parse_reg(text) {
+    Reg(r)
+  } else {
+    Other(text)
+  }
+}
+
+/// A parsed line of assembly.
+struct Line<'s> {
+  /// The mnemonic with any condition code and `s` suffix removed.
+  base: &'static str,
+  /// If there was a condition code on the mnemonic.
+  cond: bool,
+  operands: Vec<Operand<'s>>,
+}
+
+/// The condition codes.
+pub const CONDS: &[&str] = &[
+  "eq", "ne", "cs", "hs", "cc", "lo", "mi", "pl", "vs", "vc", "hi", "ls", "ge",
+  "lt", "gt", "le", "al",
+];
+
+/// Data processing mnemonics, which can take an `s` suffix.
+const DATA_PROCESSING: &[&str] = &[
+  "and", "eor", "sub", "rsb", "add", "adc", "sbc", "rsc", "orr", "mov", "bic",
+  "mvn", "mul", "mla", "lsl", "lsr", "asr", "ror", "neg", "umull", "umlal",
+  "smull", "smlal",
+];
+
+/// All the mnemonics the lints know about.
+const KNOWN: &[&str] = &[
+  "and", "eor", "sub", "rsb", "add", "adc", "sbc", "rsc", "orr", "mov", "bic",
+  "mvn", "mul", "mla", "lsl", "lsr", "asr", "ror", "neg", "umull", "umlal",
+  "smull", "smlal", "tst", "teq", "cmp", "cmn", "ldr", "str", "ldrb", "strb",
+  "ldrh", "strh", "ldrsb", "ldrsh", "ldm", "ldmia", "stm", "stmia", "push",
+  "pop", "b", "bl", "bx", "blx", "swi", "svc", "mrs", "msr", "mrc", "mcr",
+  "cdp", "ldc", "stc", "swp", "swpb", "nop",
+];
+
+/// Splits a mnemonic into a known base and if there's a condition code.
+///
+/// Any `s` suffix on a data processing mnemonic is dropped.
+pub fn split_mnemonic(mnemonic: &str) -> Option<(&'static str, bool)> {
+  let mnemonic = mnemonic.to_ascii_lowercase();
+  let mnemonic = mnemonic.strip_suffix(".n").unwrap_or(&mnemonic);
+  if let Some(base) = KNOWN.iter().find(|k| **k == mnemonic) {
+    return Some((base, false));
+  }
+  for base in KNOWN {
+    let Some(rest) = mnemonic.strip_prefix(base) else { continue };
+    let dp = DATA_PROCESSING.contains(base);
+    if dp && rest == "s" {
+      return Some((base, false));
+    }
+    let cond = rest.strip_prefix('s').filter(|_| dp).unwrap_or(rest);
+    let cond = cond.strip_suffix('s').filter(|_| dp).unwrap_or(cond);
+    if CONDS.contains(&cond) {
+      return Some((base, true));
+    }
+  }
+  None
+}
+
+/// Removes any comment and any labels from a line of assembly.
+pub fn strip_line(line: &str) -> &str {
+  let line = line.split("//").next().unwrap_or("");
+  let line = line.split('@').next().unwrap_or("").trim();
+  match line.rfind(':') {
+    Some(i) if !line[..i].contains(char::is_whitespace) => line[i + 1..].trim(),
+    _ => line,
+  }
+}
+
+/// Parses a line of assembly, if it's a known instruction.
+fn parse_line(line: &str) -> Option<Line<'_>> {
+  let line = strip_line(line);
+  if line.is_empty() || line.starts_with('.') {
+    return None;
+  }
+  let (mnemonic, rest) =
+    line.split_once(char::is_whitespace).unwrap_or((line, ""));
+  let (base, cond) = split_mnemonic(mnemonic)?;
+  let reg_list =
+    matches!(base, "push" | "pop" | "ldm" | "ldmia" | "stm" | "stmia");
+  let operands = split_operands(rest)
+    .into_iter()
+    .map(|t| parse_operand(t, reg_list))
+    .collect();
+  Some(Line { base, cond, operands })
+}
+
+fn is_high(operand: &Operand) -> Option<u8> {
+  match operand {
+    Reg(r) if *r > 7 => Some(*r),
+    _ => None,
+  }
+}
+
+fn reg_name(r: u8) -> String {
+  match r {
+    13 => "sp".to_string(),
+    14 => "lr".to_string(),
+    15 => "pc".to_string(),
+    _ => format!("r{r}"),
+  }
+}
+
+/// Checks that every register is a low register.
+fn all_low(operands: &[Operand]) -> Result<(), String> {
+  for operand in operands {
+    match operand {
+      Mem(inner, _) | RegList(inner) => all_low(inner)?,
+      _ => {
+        if let Some(r) = is_high(operand) {
+          return Err(format!("high register `{}`", reg_name(r)));
+        }
+      }
+    }
+  }
+  Ok(())
+}
+
+fn imm_fits(imm: i64, max: i64, multiple_of: i64) -> Result<(), String> {
+  if (0..=max).contains(&imm) && imm % multiple_of == 0 {
+    Ok(())
+  } else {
+    Err(format!("immediate `#{imm}` too wide"))
+  }
+}
+
+/// Checks one line of assembly against the Thumb-1 encodings.
+pub fn lint_t16_line(line: &str) -> Result<(), String> {
+  let Some(Line { base, cond, operands }) = parse_line(line) else {
+    return Ok(());
+  };
+  if cond && base != "b" {
+    return Err(format!("conditional `{base}` is A32-only"));
+  }
+  if operands.iter().any(|o| matches!(o, Shift(_)))
+    && !matches!(base, "lsl" | "lsr" | "asr" | "ror")
+  {
+    return Err("shifted operand".to_string());
+  }
+  const SP: u8 = 13;
+  const PC: u8 = 15;
+  match (base, operands.as_slice()) {
+    (
+      "mrs" | "msr" | "mrc" | "mcr" | "cdp" | "ldc" | "stc" | "swp" | "swpb"
+      | "rsc" | "teq" | "mla" | "umull" | "umlal" | "smull" | "smlal",
+      _,
+    ) => Err(format!("`{base}` is A32-only")),
+
+    // negation is the only `rsb` form.
+    ("rsb", [d, n, Imm(0)]) => all_low(&[d.clone(), n.clone()]),
+    ("rsb", _) => Err("`rsb` is A32-only, except `rsb rd, rn, #0`".to_string()),
+
+    ("mov", [d, Imm(imm)]) => {
+      all_low(core::slice::from_ref(d))?;
+      imm_fits(*imm, 255, 1)
+    }
+    ("mov", [_, _]) => Ok(()),
+    ("cmp", [n, Imm(imm)]) => {
+      all_low(core::slice::from_ref(n))?;
+      imm_fits(*imm, 255, 1)
+    }
+    ("cmp", [_, _]) => Ok(()),
+
+    ("add", [Reg(SP), Imm(imm)])
+    | ("add" | "sub", [Reg(SP), Reg(SP), Imm(imm)]) => imm_fits(*imm, 508, 4),
+    ("add", [d, Reg(SP | PC), Imm(imm)]) => {
+      all_low(core::slice::from_ref(d))?;
+      imm_fits(*imm, 1020, 4)
+    }
+    ("add" | "sub", [d, Imm(imm)]) => {
+      all_low(core::slice::from_ref(d))?;
+      imm_fits(*imm, 255, 1)
+    }
+    ("add" | "sub", [d, n, Imm(imm)]) => {
+      all_low(&[d.clone(), n.clone()])?;
+      imm_fits(*imm, if d == n { 255 } else { 7 }, 1)
+    }
+    // the high register `add` only has two operands.
+    ("add", [_, _]) => Ok(()),
+    ("add", [d, n, m]) if d == n && !matches!(m, Imm(_)) => Ok(()),
+    ("add" | "sub", [_, _, _]) | ("sub", [_, _]) => all_low(&operands),
+
+    ("lsl" | "lsr" | "asr", [d, m, Imm(imm)]) => {
+      all_low(&[d.clone(), m.clone()])?;
+      if base == "lsl" {
+        imm_fits(*imm, 31, 1)
+      } else if *imm == 0 {
+        Err(format!("immediate `#{imm}` too wide"))
+      } else {
+        imm_fits(*imm, 32, 1)
+      }
+    }
+    (
+      "and" | "eor" | "adc" | "sbc" | "ror" | "tst" | "neg" | "cmn" | "orr"
+      | "mul" | "bic" | "mvn" | "lsl" | "lsr" | "asr",
+      _,
+    ) => {
+      if let Some(Imm(imm)) = operands.iter().find(|o| matches!(o, Imm(_))) {
+        return Err(format!("immediate `#{imm}` can't be used with `{base}`"));
+      }
+      if let [d, n, _] = operands.as_slice() {
+        if d != n && base != "mul" {
+          return Err(format!(
+            "three-operand `{base}` needs the destination as the first source"
+          ));
+        }
+      }
+      all_low(&operands)
+    }
+
+    (
+      "ldr" | "str" | "ldrb" | "strb" | "ldrh" | "strh" | "ldrsb" | "ldrsh",
+      [d, rest @ ..],
+    ) => {
+      all_low(core::slice::from_ref(d))?;
+      match rest {
+        [Mem(_, true)] | [Mem(..), _, ..] => {
+          Err("writeback addressing".to_string())
+        }
+        [Mem(address, false)] => match address.as_slice() {
+          [Reg(SP | PC), Imm(imm)] if matches!(base, "ldr" | "str") => {
+            imm_fits(*imm, 1020, 4)
+          }
+          [n, Imm(imm)] => {
+            all_low(core::slice::from_ref(n))?;
+            match base {
+              "ldr" | "str" => imm_fits(*imm, 124, 4),
+              "ldrb" | "strb" => imm_fits(*imm, 31, 1),
+              "ldrh" | "strh" => imm_fits(*imm, 62, 2),
+              _ => Err(format!("`{base}` has no immediate offset form")),
+            }
+          }
+          [Reg(PC | SP)] if matches!(base, "ldr" | "str") => Ok(()),
+          _ => all_low(address),
+        },
+        _ => Ok(()),
+      }
+    }
+
+    ("push" | "pop", [RegList(regs)]) => {
+      let extra = if base == "push" { 14 } else { 15 };
+      for reg in regs {
+        match reg {
+          Reg(r) if *r > 7 && *r != extra => {
+            return Err(format!("high register `{}`", reg_name(*r)))
+          }
+          _ => (),
+        }
+      }
+      Ok(())
+    }
+    ("ldm" | "ldmia" | "stm" | "stmia", _) => all_low(&operands),
+
+    ("swi" | "svc", [Imm(imm)]) => imm_fits(*imm, 255, 1),
+
+    _ => Ok(()),
+  }
+}
+
+/// Checks one line of assembly against the `a32` encodings.
+///
+/// This only looks for the mnemonics that are Thumb-only, or (when an `arch` is
+/// given) too new for the architecture.
+pub fn lint_a32_line(line: &str, arch: Option<Arch>) -> Result<(), String> {
+  let line = strip_line(line);
+  let mnemonic = line.split(char::is_whitespace).next().unwrap_or("");
+  let mnemonic = mnemonic.to_ascii_lowercase();
+  let mnemonic = mnemonic.split('.').next().unwrap_or("");
+  let is_it = mnemonic.strip_prefix("it").is_some_and(|rest| {
+    rest.len() <= 3 && rest.chars().all(|c| matches!(c, 't' | 'e'))
+  });
+  if is_it || matches!(mnemonic, "cbz" | "cbnz" | "tbb" | "tbh") {
+    return Err(format!("`{mnemonic}` is Thumb-only"));
+  }
+  let needs = match mnemonic {
+    "movw" | "movt" => Some((Arch::V6T2, "ARMv6T2")),
+    "udiv" | "sdiv" => Some((Arch::V7, "ARMv7")),
+    _ => None,
+  };
+  match (needs, arch) {
+    (Some((needed, name)), Some(arch)) if arch < needed => {
+      Err(format!("`{mnemonic}` needs {name} or later"))
+    }
+    _ => Ok(()),
+  }
+}
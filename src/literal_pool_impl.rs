@@ -0,0 +1,75 @@
+use super::*;
+
+/// The default limit of lines before the pool, a bit under the 1024
+/// instructions that an `a32` `ldr` at the very start could reach past.
+const DEFAULT_MAX_LINES: u32 = 1000;
+
+pub fn emit_literal_pool_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  if let Some(tree) = token_stream.into_iter().next() {
+    return Err(Error::new(tree.span(), "this macro takes no input"));
+  }
+  Ok(TokenStream::from(str_literal_at(".ltorg\n", out_span)))
+}
+
+pub fn with_pool_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  let mut max_lines = DEFAULT_MAX_LINES;
+  let mut body: Vec<Vec<TokenTree>> = Vec::new();
+  for arg in split_args(token_stream) {
+    match get_named_arg(&arg) {
+      Some((name, value)) if name == "max_lines" => {
+        max_lines = get_int_arg(&name, value)?
+      }
+      Some((name, _)) => {
+        return Err(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{name}`"),
+        ))
+      }
+      None => body.push(arg),
+    }
+  }
+  if body.is_empty() {
+    return Err(Error::call_site("missing the body lines"));
+  }
+
+  // Each string literal counts as the lines of code in it, and anything else
+  // (such as another macro) counts as one line.
+  let line_count: usize = body
+    .iter()
+    .map(|arg| match arg.as_slice() {
+      [tree] => get_str_literal_content(tree).map_or(1, |text| {
+        text.lines().filter(|line| !strip_line(line).is_empty()).count()
+      }),
+      _ => 1,
+    })
+    .sum();
+  if line_count > max_lines as usize {
+    return Err(Error::call_site(format!(
+      "the body is {line_count} lines, more than `max_lines` ({max_lines}), \
+       so an `ldr` near the start might not reach the literal pool at the \
+       end, split it up with `emit_literal_pool!()` or raise `max_lines`"
+    )));
+  }
+
+  let mut body_stream: Vec<TokenTree> = Vec::new();
+  for arg in body {
+    body_stream.extend(arg);
+    body_stream.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+  }
+  let mut out_buffer: Vec<TokenTree> = Vec::new();
+  extend_concat_as_lines(&mut out_buffer, body_stream);
+  out_buffer.push(TokenTree::Literal(Literal::string(".ltorg\n")));
+
+  let concat_expr = vec![
+    TokenTree::Ident(Ident::new("concat", Span::call_site())),
+    TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+    TokenTree::Group(Group::new(
+      Delimiter::Parenthesis,
+      TokenStream::from_iter(out_buffer),
+    )),
+  ];
+  Ok(TokenStream::from_iter(concat_expr))
+}
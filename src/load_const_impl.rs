@@ -0,0 +1,70 @@
+use super::*;
+
+pub fn load_const_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let args = split_args(token_stream);
+  let (reg_arg, value_arg, rest) = match args.as_slice() {
+    [reg_arg, value_arg, rest @ ..] => (reg_arg, value_arg, rest),
+    _ => return Err(Error::call_site(NOT_ENOUGH_INPUT)),
+  };
+  let reg = select_reg(reg_arg)?;
+  let span = args_span(value_arg);
+  let value = get_int(value_arg)
+    .ok_or_else(|| Error::new(span, "the value must be an integer literal"))?;
+  let text: String = value_arg.iter().map(TokenTree::to_string).collect();
+  let bits = u32::try_from(value)
+    .or_else(|_| i32::try_from(value).map(|v| v as u32))
+    .map_err(|_| {
+      Error::new(span, format!("`{text}` doesn't fit in 32 bits"))
+    })?;
+
+  // `None` picks the best, `Some(true)` is inline, `Some(false)` is the pool.
+  let mut inline: Option<bool> = None;
+  for arg in rest {
+    let (name, value) = get_named_arg(arg).ok_or_else(|| {
+      Error::new(args_span(arg), "arguments must be of the form `name = value`")
+    })?;
+    if name != "strategy" {
+      return Err(Error::new(
+        args_span(arg),
+        format!("unknown argument `{name}`"),
+      ));
+    }
+    inline = match value {
+      [TokenTree::Ident(i)] if i.to_string() == "inline" => Some(true),
+      [TokenTree::Ident(i)] if i.to_string() == "pool" => Some(false),
+      _ => {
+        return Err(Error::new(
+          args_span(value),
+          "`strategy` must be `inline` or `pool`",
+        ))
+      }
+    };
+  }
+
+  let lines = if inline == Some(false) {
+    vec![format!("ldr {reg}, ={bits:#X}")]
+  } else if a32_imm_encodable(bits) {
+    vec![format!("mov {reg}, #{bits:#X}")]
+  } else if a32_imm_encodable(!bits) {
+    vec![format!("mvn {reg}, #{:#X}", !bits)]
+  } else if let Some((low, high)) = a32_imm_split(bits) {
+    vec![
+      format!("mov {reg}, #{low:#X}"),
+      format!("orr {reg}, {reg}, #{high:#X}"),
+    ]
+  } else if inline == Some(true) {
+    return Err(Error::new(
+      span,
+      format!(
+        "`{text}` ({bits:#X}) can't be loaded inline, it isn't an `a32` \
+         immediate, the complement of one, or two of them `orr`ed together"
+      ),
+    ));
+  } else {
+    vec![format!("ldr {reg}, ={bits:#X}")]
+  };
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
@@ -0,0 +1,259 @@
+use super::*;
+
+pub fn while_loop_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let (options, token_stream) = take_options(token_stream, &["lint"]);
+  let mut lint = None;
+  for (_name, value) in &options {
+    lint = Some(Lint::from_option(value)?);
+  }
+
+  let mut groups: Vec<Group> = Vec::new();
+  for tree in token_stream {
+    match tree {
+      TokenTree::Group(g) => groups.push(g),
+      other => {
+        return Err(Error::new(other.span(), "expected only groups"));
+      }
+    }
+  }
+  let (test_group, label_group, body_group) = match groups.as_slice() {
+    // that's a label, the body is missing.
+    [_, label] if parse_label(label).is_ok() => {
+      return Err(Error::call_site("too few tokens"))
+    }
+    [test, body] => (test, None, body),
+    [test, label, body] => (test, Some(label), body),
+    [_, _, _, extra, ..] => {
+      return Err(Error::new(extra.span(), "too many tokens"))
+    }
+    _ => return Err(Error::call_site("too few tokens")),
+  };
+  if let Some(lint) = lint {
+    lint_body(&body_group.stream(), lint, None)?;
+  }
+
+  // A numeric label is placed at both the top and the end, so `Nb` goes back
+  // to the top and `Nf` goes past the end. Generated labels have to differ.
+  let (top, exit) = match label_group {
+    Some(label_group) => {
+      let label = parse_label(label_group)?.to_string();
+      check_label_not_defined(
+        &label,
+        label_group.span(),
+        &body_group.stream(),
+      )?;
+      ((format!("{label}b"), label.clone()), (format!("{label}f"), label))
+    }
+    None => {
+      let top = next_local_label();
+      let exit = next_local_label();
+      ((top.clone(), top), (exit.clone(), exit))
+    }
+  };
+
+  let test = cmp_test_with(test_group, &exit.0, false)?;
+  let mut out_buffer: Vec<TokenTree> = Vec::new();
+  out_buffer
+    .push(str_literal_at(&format!("{}:\n{test}", top.1), test_group.span()));
+  out_buffer.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+  extend_concat_as_lines(&mut out_buffer, body_group.stream());
+  out_buffer.push(TokenTree::Literal(Literal::string(&format!(
+    "b {}\n{}:\n",
+    top.0, exit.1
+  ))));
+
+  let concat_expr = vec![
+    TokenTree::Ident(Ident::new("concat", Span::call_site())),
+    TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+    TokenTree::Group(Group::new(
+      Delimiter::Parenthesis,
+      TokenStream::from_iter(out_buffer),
+    )),
+  ];
+  Ok(TokenStream::from_iter(concat_expr))
+}
+
+pub fn do_while_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  let (options, token_stream) = take_options(token_stream, &["lint"]);
+  let mut lint = None;
+  for (_name, value) in &options {
+    lint = Some(Lint::from_option(value)?);
+  }
+
+  let mut groups: Vec<Group> = Vec::new();
+  for tree in token_stream {
+    match tree {
+      TokenTree::Group(g) => groups.push(g),
+      other => {
+        return Err(Error::new(other.span(), "expected only groups"));
+      }
+    }
+  }
+  let (label_group, body_group, test_group) = match groups.as_slice() {
+    // that's a label, the body is missing.
+    [label, _] if parse_label(label).is_ok() => {
+      return Err(Error::call_site("too few tokens"))
+    }
+    [body, test] => (None, body, test),
+    [label, body, test] => (Some(label), body, test),
+    [_, _, _, extra, ..] => {
+      return Err(Error::new(extra.span(), "too many tokens"))
+    }
+    _ => return Err(Error::call_site("too few tokens")),
+  };
+  if let Some(lint) = lint {
+    lint_body(&body_group.stream(), lint, None)?;
+  }
+
+  let (top_target, top_def) = match label_group {
+    Some(label_group) => {
+      let label = parse_label(label_group)?.to_string();
+      check_label_not_defined(
+        &label,
+        label_group.span(),
+        &body_group.stream(),
+      )?;
+      (format!("{label}b"), label)
+    }
+    None => {
+      let top = next_local_label();
+      (top.clone(), top)
+    }
+  };
+
+  // This branches back when the test *does* pass. A lone condition code is
+  // a test of the flags left by the body, with no `cmp`.
+  let test = if test_group.stream().into_iter().count() == 1 {
+    flags_test_with(test_group, &top_target, true)?
+  } else {
+    cmp_test_with(test_group, &top_target, true)?
+  };
+  let mut out_buffer: Vec<TokenTree> = Vec::new();
+  out_buffer
+    .push(TokenTree::Literal(Literal::string(&format!("{top_def}:\n"))));
+  out_buffer.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+  extend_concat_as_lines(&mut out_buffer, body_group.stream());
+  out_buffer.push(str_literal_at(&test, test_group.span()));
+
+  let concat_expr = vec![
+    TokenTree::Ident(Ident::new("concat", Span::call_site())),
+    TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+    TokenTree::Group(Group::new(
+      Delimiter::Parenthesis,
+      TokenStream::from_iter(out_buffer),
+    )),
+  ];
+  Ok(TokenStream::from_iter(concat_expr))
+}
+
+pub fn counted_loop_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let mut args = split_args(token_stream).into_iter();
+  let counter_arg =
+    args.next().ok_or_else(|| Error::call_site(NOT_ENOUGH_INPUT))?;
+  let counter = match counter_arg.as_slice() {
+    [tree] => get_reg_text(tree),
+    _ => None,
+  }
+  .ok_or_else(|| {
+    Error::new(
+      args_span(&counter_arg),
+      "the first argument must be the counter register, as a string literal \
+       or a register name",
+    )
+  })?;
+  check_reg(&counter, args_span(&counter_arg))?;
+  if matches!(parse_reg(&counter), Some(13 | 15)) {
+    return Err(Error::new(
+      args_span(&counter_arg),
+      format!("can't use `{counter}` as the counter register"),
+    ));
+  }
+
+  let mut unroll: u32 = 1;
+  let mut groups: Option<Vec<TokenTree>> = None;
+  for arg in args {
+    if let Some((name, value)) = get_named_arg(&arg) {
+      match name.as_str() {
+        "unroll" => {
+          unroll = get_int_arg(&name, value)?;
+          if unroll == 0 || !a32_imm_encodable(unroll) {
+            return Err(Error::new(
+              args_span(value),
+              format!(
+                "`unroll` must be at least 1 and encodable as a `subs` \
+                 immediate, got {unroll}"
+              ),
+            ));
+          }
+        }
+        other => {
+          return Err(Error::new(
+            args_span(&arg),
+            format!("unknown argument `{other}`"),
+          ))
+        }
+      }
+    } else if groups.is_none() {
+      groups = Some(arg);
+    } else {
+      return Err(Error::new(args_span(&arg), "too many tokens"));
+    }
+  }
+  let groups = groups.ok_or_else(|| Error::call_site("missing the body"))?;
+  let (label_group, body_group) = match groups.as_slice() {
+    // that's a label, the body is missing.
+    [TokenTree::Group(label)] if parse_label(label).is_ok() => {
+      return Err(Error::call_site("missing the body"))
+    }
+    [TokenTree::Group(body)] => (None, body),
+    [TokenTree::Group(label), TokenTree::Group(body)] => (Some(label), body),
+    _ => {
+      return Err(Error::new(
+        args_span(&groups),
+        "expected an optional label group and then a body group, eg: \
+         `[1] { ... }`",
+      ))
+    }
+  };
+
+  let (top_target, top_def) = match label_group {
+    Some(label_group) => {
+      let label = parse_label(label_group)?.to_string();
+      check_label_not_defined(
+        &label,
+        label_group.span(),
+        &body_group.stream(),
+      )?;
+      (format!("{label}b"), label)
+    }
+    None => {
+      let top = next_local_label();
+      (top.clone(), top)
+    }
+  };
+
+  let mut out_buffer: Vec<TokenTree> = Vec::new();
+  out_buffer
+    .push(TokenTree::Literal(Literal::string(&format!("{top_def}:\n"))));
+  out_buffer.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+  for _ in 0..unroll {
+    extend_concat_as_lines(&mut out_buffer, body_group.stream());
+  }
+  out_buffer.push(TokenTree::Literal(Literal::string(&format!(
+    "subs {counter}, {counter}, #{unroll}\nbne {top_target}\n"
+  ))));
+
+  let concat_expr = vec![
+    TokenTree::Ident(Ident::new("concat", Span::call_site())),
+    TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+    TokenTree::Group(Group::new(
+      Delimiter::Parenthesis,
+      TokenStream::from_iter(out_buffer),
+    )),
+  ];
+  Ok(TokenStream::from_iter(concat_expr))
+}
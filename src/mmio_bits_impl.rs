@@ -0,0 +1,110 @@
+use super::*;
+
+pub fn mmio_set_bits_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  mmio_rmw(token_stream, "orr")
+}
+
+pub fn mmio_clear_bits_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  mmio_rmw(token_stream, "bic")
+}
+
+pub fn mmio_toggle_bits_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  mmio_rmw(token_stream, "eor")
+}
+
+/// A load, a bitwise op with the mask, then a store.
+fn mmio_rmw(token_stream: TokenStream, op: &str) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut addr: Option<String> = None;
+  let mut mask: Option<(u32, Span)> = None;
+  let mut scratch: Option<(String, Span)> = None;
+  let mut mask_scratch: Option<(String, Span)> = None;
+  let mut width: Option<(u32, Span)> = None;
+  for arg in split_args(token_stream) {
+    let (name, value) = get_named_arg(&arg).ok_or_else(|| {
+      Error::new(
+        args_span(&arg),
+        "arguments must be of the form `name = value`",
+      )
+    })?;
+    match name.as_str() {
+      "addr_reg" => addr = Some(get_reg_arg(&name, value)?),
+      "mask" => mask = Some((get_int_arg(&name, value)?, args_span(value))),
+      "scratch" => {
+        scratch = Some((get_reg_arg(&name, value)?, args_span(value)))
+      }
+      "mask_scratch" => {
+        mask_scratch = Some((get_reg_arg(&name, value)?, args_span(value)))
+      }
+      "width" => width = Some((get_int_arg(&name, value)?, args_span(value))),
+      other => {
+        return Err(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        ))
+      }
+    }
+  }
+  let addr =
+    addr.ok_or_else(|| Error::call_site("missing `addr_reg = \"rN\"`"))?;
+  let (mask, mask_span) =
+    mask.ok_or_else(|| Error::call_site("missing `mask = N`"))?;
+  let (scratch, scratch_span) =
+    scratch.ok_or_else(|| Error::call_site("missing `scratch = \"rN\"`"))?;
+  let (width, width_span) =
+    width.ok_or_else(|| Error::call_site("missing `width = N`"))?;
+  let (load, store) = match width {
+    8 => ("ldrb", "strb"),
+    16 => ("ldrh", "strh"),
+    32 => ("ldr", "str"),
+    _ => return Err(Error::new(width_span, "`width` must be 8, 16, or 32")),
+  };
+  if mask == 0 {
+    return Err(Error::new(mask_span, "the mask can't be zero"));
+  }
+  if width < 32 && mask >> width != 0 {
+    return Err(Error::new(
+      mask_span,
+      format!("the mask {mask:#X} doesn't fit in {width} bits"),
+    ));
+  }
+  if regs_alias(&scratch, &addr) {
+    return Err(Error::new(
+      scratch_span,
+      "`scratch` must differ from `addr_reg`",
+    ));
+  }
+
+  let mut lines = Vec::new();
+  let mask_operand = if a32_imm_encodable(mask) {
+    format!("#{mask:#X}")
+  } else {
+    let (mask_scratch, mask_scratch_span) = mask_scratch.ok_or_else(|| {
+      Error::new(
+        mask_span,
+        format!(
+          "the mask {mask:#X} can't be an immediate, so it needs a \
+           `mask_scratch = \"rN\"` register"
+        ),
+      )
+    })?;
+    if regs_alias(&mask_scratch, &addr) || regs_alias(&mask_scratch, &scratch) {
+      return Err(Error::new(
+        mask_scratch_span,
+        "`mask_scratch` must differ from `addr_reg` and `scratch`",
+      ));
+    }
+    lines.push(format!("ldr {mask_scratch}, ={mask:#X}"));
+    mask_scratch
+  };
+  lines.push(format!("{load} {scratch}, [{addr}]"));
+  lines.push(format!("{op} {scratch}, {scratch}, {mask_operand}"));
+  lines.push(format!("{store} {scratch}, [{addr}]"));
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
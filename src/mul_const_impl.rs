@@ -0,0 +1,120 @@
+use super::*;
+
+pub fn mul_const_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut dst: Option<String> = None;
+  let mut src: Option<String> = None;
+  let mut by: Option<u32> = None;
+  let mut scratch: Option<(String, Span)> = None;
+  let mut max_ops: usize = 4;
+  for arg in split_args(token_stream) {
+    let (name, value) = get_named_arg(&arg).ok_or_else(|| {
+      Error::new(
+        args_span(&arg),
+        "arguments must be of the form `name = value`",
+      )
+    })?;
+    match name.as_str() {
+      "dst" => dst = Some(get_reg_arg(&name, value)?),
+      "src" => src = Some(get_reg_arg(&name, value)?),
+      "scratch" => {
+        scratch = Some((get_reg_arg(&name, value)?, args_span(value)))
+      }
+      "by" => by = Some(get_int_arg(&name, value)?),
+      "max_ops" => max_ops = get_int_arg(&name, value)?,
+      other => {
+        return Err(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        ))
+      }
+    }
+  }
+  let dst = dst.ok_or_else(|| Error::call_site("missing `dst = \"rN\"`"))?;
+  let src = src.ok_or_else(|| Error::call_site("missing `src = \"rN\"`"))?;
+  let by = by.ok_or_else(|| Error::call_site("missing `by = N`"))?;
+  if let Some((scratch, span)) = &scratch {
+    if regs_alias(scratch, &dst) || regs_alias(scratch, &src) {
+      return Err(Error::new(
+        *span,
+        "`scratch` must differ from `dst` and `src`",
+      ));
+    }
+  }
+  let scratch = scratch.map(|(scratch, _)| scratch);
+
+  let lines = match shift_add_sequence(&dst, &src, by, scratch.as_deref())
+    .filter(|lines| lines.len() <= max_ops)
+  {
+    Some(lines) => lines,
+    None => {
+      let scratch = scratch.as_deref().ok_or_else(|| {
+        Error::call_site(format!(
+          "multiplying by {by} needs a `scratch = \"rN\"` register"
+        ))
+      })?;
+      // ARMv4 `mul` must not have the destination as the first operand.
+      vec![load_const_line(scratch, by), format!("mul {dst}, {scratch}, {src}")]
+    }
+  };
+
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
+
+/// Builds `dst = src * by` out of shifted `add` and `rsb` instructions.
+///
+/// The odd part of `by` is written in canonical signed digit form and then
+/// evaluated from the top digit down (Horner style), with any even part as a
+/// final shift. Gives `None` if `dst` and `src` are the same register and the
+/// sequence would need a scratch copy that isn't available.
+fn shift_add_sequence(
+  dst: &str, src: &str, by: u32, scratch: Option<&str>,
+) -> Option<Vec<String>> {
+  if by == 0 {
+    return Some(vec![format!("mov {dst}, #0")]);
+  }
+  let shift = by.trailing_zeros();
+  let odd = by >> shift;
+  if odd == 1 {
+    return Some(vec![if shift == 0 {
+      format!("mov {dst}, {src}")
+    } else {
+      format!("mov {dst}, {src}, lsl #{shift}")
+    }]);
+  }
+
+  // signed digits, highest position first.
+  let mut digits: Vec<(u32, bool)> = Vec::new();
+  let mut n = i64::from(odd);
+  let mut position = 0;
+  while n != 0 {
+    if n & 1 != 0 {
+      let negative = n & 0b11 == 0b11;
+      digits.push((position, negative));
+      n += if negative { 1 } else { -1 };
+    }
+    n >>= 1;
+    position += 1;
+  }
+  digits.reverse();
+
+  let mut lines = Vec::new();
+  let mut src = src;
+  if regs_alias(dst, src) && digits.len() > 2 {
+    let scratch = scratch?;
+    lines.push(format!("mov {scratch}, {src}"));
+    src = scratch;
+  }
+  let mut acc = src;
+  for pair in digits.windows(2) {
+    let (high, _) = pair[0];
+    let (low, negative) = pair[1];
+    let op = if negative { "rsb" } else { "add" };
+    lines.push(format!("{op} {dst}, {src}, {acc}, lsl #{}", high - low));
+    acc = dst;
+  }
+  if shift > 0 {
+    lines.push(format!("mov {dst}, {dst}, lsl #{shift}"));
+  }
+  Some(lines)
+}
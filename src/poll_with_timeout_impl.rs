@@ -0,0 +1,117 @@
+use super::*;
+
+pub fn poll_with_timeout_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let mut count: Option<(String, Span)> = None;
+  let mut limit: Option<(u32, Span)> = None;
+  let mut on_timeout: Option<String> = None;
+  let mut until: Option<String> = None;
+  let mut groups: Vec<(Group, Span)> = Vec::new();
+  for arg in split_args(token_stream) {
+    let Some((name, value)) = get_named_arg(&arg) else {
+      match arg.as_slice() {
+        [TokenTree::Group(g)] => groups.push((g.clone(), g.span())),
+        _ => {
+          return Err(Error::new(
+            args_span(&arg),
+            "arguments must be `name = value`, the label group, or the body \
+             group",
+          ))
+        }
+      }
+      continue;
+    };
+    match name.as_str() {
+      "count_reg" => {
+        count = Some((get_reg_arg(&name, value)?, args_span(value)))
+      }
+      "limit" => limit = Some((get_int_arg(&name, value)?, args_span(value))),
+      "on_timeout" => {
+        let symbol = get_str_arg(&name, value)?;
+        if !is_symbol_name(&symbol) {
+          return Err(Error::new(
+            args_span(value),
+            format!("`{symbol}` is not a valid symbol name"),
+          ));
+        }
+        on_timeout = Some(symbol);
+      }
+      "until" => {
+        until = Some(
+          match value {
+            [TokenTree::Ident(i)] => Some(i.to_string()),
+            _ => None,
+          }
+          .filter(|cond| cond != "al" && CONDS.contains(&cond.as_str()))
+          .ok_or_else(|| {
+            Error::new(
+              args_span(value),
+              "`until` must be a condition code, eg: `ne`",
+            )
+          })?,
+        )
+      }
+      other => {
+        return Err(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        ))
+      }
+    }
+  }
+  let (count, count_span) =
+    count.ok_or_else(|| Error::call_site("missing `count_reg = \"rN\"`"))?;
+  let (limit, limit_span) =
+    limit.ok_or_else(|| Error::call_site("missing `limit = N`"))?;
+  let until =
+    until.ok_or_else(|| Error::call_site("missing `until = cond`"))?;
+  let [(label_group, label_span), (body_group, _)] = <[_; 2]>::try_from(groups)
+    .map_err(|_| {
+      Error::call_site(
+        "expected a group for the label and a group for the body",
+      )
+    })?;
+  if matches!(parse_reg(&count), Some(13 | 15)) {
+    return Err(Error::new(
+      count_span,
+      format!("`count_reg` can't be `{count}`"),
+    ));
+  }
+  if limit == 0 {
+    return Err(Error::new(limit_span, "`limit` can't be zero"));
+  }
+  let label_trees: Vec<TokenTree> = label_group.stream().into_iter().collect();
+  let label: u32 = get_int(&label_trees)
+    .and_then(|label| u32::try_from(label).ok())
+    .ok_or_else(|| {
+      Error::new(label_span, "the label must be one u32 literal")
+    })?;
+
+  // The same numeric label is placed at the top and the end of the loop, so
+  // `b {label}b` goes back to the top and `b {label}f` goes past the end.
+  let mut out_buffer: Vec<TokenTree> = Vec::new();
+  out_buffer.push(TokenTree::Literal(Literal::string(&format!(
+    "{}\n{label}:\n",
+    load_const_line(&count, limit)
+  ))));
+  out_buffer.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+  extend_concat_as_lines(&mut out_buffer, body_group.stream());
+  let mut tail =
+    format!("b{until} {label}f\nsubs {count}, {count}, #1\nbne {label}b\n");
+  if let Some(on_timeout) = on_timeout {
+    tail.push_str(&format!("b {on_timeout}\n"));
+  }
+  tail.push_str(&format!("{label}:\n"));
+  out_buffer.push(TokenTree::Literal(Literal::string(&tail)));
+
+  let concat_expr = vec![
+    TokenTree::Ident(Ident::new("concat", Span::call_site())),
+    TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+    TokenTree::Group(Group::new(
+      Delimiter::Parenthesis,
+      TokenStream::from_iter(out_buffer),
+    )),
+  ];
+  Ok(TokenStream::from_iter(concat_expr))
+}
@@ -0,0 +1,91 @@
+use super::*;
+
+/// The multiplier of the `lcg` style, from *Numerical Recipes*.
+const LCG_MUL: u32 = 1664525;
+/// The increment of the `lcg` style, from *Numerical Recipes*.
+const LCG_ADD: u32 = 1013904223;
+
+pub fn prng_step_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut state: Option<(String, Span)> = None;
+  let mut style: Option<String> = None;
+  let mut scratch: Option<(String, Span)> = None;
+  for arg in split_args(token_stream) {
+    let (name, value) = get_named_arg(&arg).ok_or_else(|| {
+      Error::new(
+        args_span(&arg),
+        "arguments must be of the form `name = value`",
+      )
+    })?;
+    match name.as_str() {
+      "state" => state = Some((get_reg_arg(&name, value)?, args_span(value))),
+      "scratch" => {
+        scratch = Some((get_reg_arg(&name, value)?, args_span(value)))
+      }
+      "style" => {
+        style = Some(
+          match value {
+            [TokenTree::Ident(i)] => Some(i.to_string()),
+            _ => None,
+          }
+          .filter(|s| s == "xorshift32" || s == "lcg")
+          .ok_or_else(|| {
+            Error::new(
+              args_span(value),
+              "`style` must be `xorshift32` or `lcg`",
+            )
+          })?,
+        )
+      }
+      other => {
+        return Err(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        ))
+      }
+    }
+  }
+  let (state, state_span) =
+    state.ok_or_else(|| Error::call_site("missing `state = \"rN\"`"))?;
+  let style = style.ok_or_else(|| {
+    Error::call_site("missing `style = xorshift32` or `style = lcg`")
+  })?;
+  if parse_reg(&state) == Some(15) {
+    return Err(Error::new(state_span, "`state` can't be the program counter"));
+  }
+  if let Some((scratch, scratch_span)) = &scratch {
+    if parse_reg(scratch) == Some(15) {
+      return Err(Error::new(
+        *scratch_span,
+        "`scratch` can't be the program counter",
+      ));
+    }
+    if regs_alias(scratch, &state) {
+      return Err(Error::new(
+        *scratch_span,
+        "`scratch` must differ from `state`",
+      ));
+    }
+  }
+
+  let lines = if style == "xorshift32" {
+    vec![
+      format!("eor {state}, {state}, {state}, lsl #13"),
+      format!("eor {state}, {state}, {state}, lsr #17"),
+      format!("eor {state}, {state}, {state}, lsl #5"),
+    ]
+  } else {
+    let (scratch, _) = scratch.ok_or_else(|| {
+      Error::call_site("the `lcg` style needs a `scratch = \"rN\"`")
+    })?;
+    // `mul` can't have the same register for the destination and the first
+    // operand before ARMv6, so the multiplier goes first.
+    vec![
+      format!("ldr {scratch}, ={LCG_MUL:#X}"),
+      format!("mul {state}, {scratch}, {state}"),
+      format!("ldr {scratch}, ={LCG_ADD:#X}"),
+      format!("add {state}, {state}, {scratch}"),
+    ]
+  };
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
@@ -1,9 +1,103 @@
 use super::*;
 
-pub fn put_fn_in_section_impl(token_stream: TokenStream) -> TokenStream {
-  let section_name = one_str_literal_or_panic(token_stream);
+const STR_ARGS_ONLY: &str = "Provide string literals only: the section name, \
+                             then optionally the flags and the type.";
 
-  TokenStream::from(TokenTree::Literal(Literal::string(&format!(
-    r#".section {section_name},"ax",%progbits"#
-  ))))
+pub fn put_fn_in_section_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let mut strs: Vec<(String, Span)> = Vec::new();
+  for arg in split_args(token_stream) {
+    let text = match arg.as_slice() {
+      [tree] => get_str_literal_content(tree),
+      _ => None,
+    }
+    .ok_or_else(|| Error::new(args_span(&arg), STR_ARGS_ONLY))?;
+    strs.push((text, args_span(&arg)));
+  }
+  let (flags, section_type) = match strs.as_slice() {
+    [] => return Err(Error::call_site(NOT_ENOUGH_INPUT)),
+    [_] => ("ax", "%progbits"),
+    [_, (flags, span)] => (check_section_flags(flags, *span)?, "%progbits"),
+    [_, (flags, flags_span), (section_type, type_span)] => (
+      check_section_flags(flags, *flags_span)?,
+      check_section_type(section_type, *type_span)?,
+    ),
+    [.., (_, extra)] => {
+      return Err(Error::new(
+        *extra,
+        "too many arguments, expected the section name, flags, and type",
+      ))
+    }
+  };
+  let (section_name, span) = &strs[0];
+  if let Some(problem) = section_name_problem(section_name) {
+    return Err(Error::new(
+      *span,
+      format!("`{section_name}` is not a valid section name: {problem}"),
+    ));
+  }
+
+  Ok(TokenStream::from(str_literal_at(
+    &format!(r#".section {section_name},"{flags}",{section_type}"#),
+    *span,
+  )))
+}
+
+/// Checks that the flags are each a known flag, given at most once.
+pub fn check_section_flags(flags: &str, span: Span) -> Result<&str, Error> {
+  // `G` and `?` (groups) are left out since they need more arguments.
+  const KNOWN: &str = "awxMST";
+  for (i, c) in flags.char_indices() {
+    if !KNOWN.contains(c) {
+      return Err(Error::new(
+        span,
+        format!(
+          "{c:?} isn't a known section flag, expected some of `a`, `w`, `x`, \
+           `M`, `S`, `T`"
+        ),
+      ));
+    }
+    if flags[..i].contains(c) {
+      return Err(Error::new(span, format!("the `{c}` flag is given twice")));
+    }
+  }
+  Ok(flags)
+}
+
+/// Checks that the type is one of the known section types.
+pub fn check_section_type(
+  section_type: &str, span: Span,
+) -> Result<&str, Error> {
+  match section_type {
+    "%progbits" | "%nobits" | "%note" => Ok(section_type),
+    other => Err(Error::new(
+      span,
+      format!(
+        "`{other}` isn't a known section type, expected `%progbits`, \
+         `%nobits`, or `%note`"
+      ),
+    )),
+  }
+}
+
+/// Checks that a section name can be spliced into a `.section` directive as is.
+///
+/// Anything outside of alphanumerics and `._-$` could change the meaning of the
+/// directive (a `,` or `"` starts the flags), so it's rejected.
+pub fn section_name_problem(name: &str) -> Option<String> {
+  let Some(first) = name.chars().next() else {
+    return Some("it can't be empty".to_string());
+  };
+  if let Some(bad) = name.chars().find(|&c| {
+    !(c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '$'))
+  }) {
+    return Some(format!(
+      "{bad:?} isn't allowed, only ascii alphanumerics and `.`, `_`, `-`, `$`"
+    ));
+  }
+  if first.is_ascii_digit() {
+    return Some("it can't start with a digit".to_string());
+  }
+  None
 }
@@ -0,0 +1,74 @@
+use super::*;
+
+pub fn reg_abs_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut args = split_args(token_stream).into_iter();
+  let first = args.next().ok_or_else(|| Error::call_site(NOT_ENOUGH_INPUT))?;
+  let (dst, src) = match first.as_slice() {
+    [_] => {
+      let reg = select_reg(&first)?;
+      (reg.clone(), reg)
+    }
+    _ => get_reg_assign(&first)?,
+  };
+
+  let mut branchless = false;
+  let mut scratch: Option<(String, Span)> = None;
+  for arg in args {
+    match arg.as_slice() {
+      [TokenTree::Ident(i)] if i.to_string() == "branchless" => {
+        branchless = true
+      }
+      _ => match get_named_arg(&arg) {
+        Some((name, value)) if name == "scratch" => {
+          scratch = Some((get_reg_arg(&name, value)?, args_span(value)))
+        }
+        Some((other, _)) => {
+          return Err(Error::new(
+            args_span(&arg),
+            format!("unknown argument `{other}`"),
+          ))
+        }
+        None => {
+          return Err(Error::new(
+            args_span(&arg),
+            "expected `branchless` or `scratch = \"rN\"`",
+          ))
+        }
+      },
+    }
+  }
+
+  let lines = match (branchless, scratch) {
+    (false, None) if regs_alias(&dst, &src) => {
+      vec![format!("cmp {dst}, #0"), format!("rsblt {dst}, {dst}, #0")]
+    }
+    (false, None) => {
+      vec![format!("movs {dst}, {src}"), format!("rsbmi {dst}, {dst}, #0")]
+    }
+    (false, Some((_, span))) => {
+      return Err(Error::new(span, "`scratch` is only used with `branchless`"))
+    }
+    (true, None) => {
+      return Err(Error::call_site(
+        "`branchless` needs a `scratch = \"rN\"` register",
+      ))
+    }
+    (true, Some((scratch, span))) => {
+      if regs_alias(&scratch, &dst) || regs_alias(&scratch, &src) {
+        return Err(Error::new(
+          span,
+          "`scratch` must differ from the source and destination",
+        ));
+      }
+      // The mask is all 1s for a negative value and all 0s otherwise, and
+      // `(x ^ mask) - mask` negates `x` only when the mask is all 1s.
+      vec![
+        format!("mov {scratch}, {src}, asr #31"),
+        format!("eor {dst}, {src}, {scratch}"),
+        format!("sub {dst}, {dst}, {scratch}"),
+      ]
+    }
+  };
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
@@ -0,0 +1,104 @@
+use super::*;
+
+pub fn reg_min_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  reg_select(token_stream, true)
+}
+
+pub fn reg_max_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  reg_select(token_stream, false)
+}
+
+/// Selects the min (or max) of two registers with a `cmp` and conditional
+/// `mov`s.
+fn reg_select(
+  token_stream: TokenStream, min: bool,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let args = split_args(token_stream);
+  let (dst_arg, b_arg, signed_arg) =
+    match args.as_slice() {
+      [dst_arg, b_arg, signed_arg] => (dst_arg, b_arg, signed_arg),
+      [_, _] => {
+        return Err(Error::call_site(
+          "missing `signed` or `unsigned` as the last argument",
+        ))
+      }
+      _ => return Err(Error::call_site(
+        "expected `\"dst\" = \"a\"`, then `\"b\"`, then `signed` or `unsigned`",
+      )),
+    };
+  let (dst, a) = get_reg_assign(dst_arg)?;
+  let b = select_reg(b_arg)?;
+  let signed = match signed_arg.as_slice() {
+    [TokenTree::Ident(i)] if i.to_string() == "signed" => true,
+    [TokenTree::Ident(i)] if i.to_string() == "unsigned" => false,
+    _ => {
+      return Err(Error::new(
+        args_span(signed_arg),
+        "expected `signed` or `unsigned` as the last argument",
+      ))
+    }
+  };
+  if regs_alias(&a, &b) {
+    return Err(Error::new(
+      args_span(b_arg),
+      format!("`{a}` and `{b}` are the same register"),
+    ));
+  }
+
+  // The conditions for `a` being the pick and for `b` being the pick.
+  let (pick_a, pick_b) = match (min, signed) {
+    (true, true) => ("le", "gt"),
+    (true, false) => ("ls", "hi"),
+    (false, true) => ("ge", "lt"),
+    (false, false) => ("hs", "lo"),
+  };
+  // When `dst` is already one of them, only the other one needs a `mov`.
+  let mut lines = vec![format!("cmp {a}, {b}")];
+  if regs_alias(&dst, &a) {
+    lines.push(format!("mov{pick_b} {dst}, {b}"));
+  } else if regs_alias(&dst, &b) {
+    lines.push(format!("mov{pick_a} {dst}, {a}"));
+  } else {
+    lines.push(format!("mov{pick_a} {dst}, {a}"));
+    lines.push(format!("mov{pick_b} {dst}, {b}"));
+  }
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
+
+/// Gets the registers of a `"dst" = "src"` argument.
+pub fn get_reg_assign(arg: &[TokenTree]) -> Result<(String, String), Error> {
+  match arg {
+    [dst, TokenTree::Punct(p), src]
+      if *p == '=' && p.spacing() == Spacing::Alone =>
+    {
+      let dst = select_reg(core::slice::from_ref(dst))?;
+      let src = select_reg(core::slice::from_ref(src))?;
+      Ok((dst, src))
+    }
+    _ => Err(Error::new(
+      args_span(arg),
+      "the first argument must be of the form `\"dst\" = \"src\"`",
+    )),
+  }
+}
+
+/// Gets one register argument, which can't be `pc`.
+pub fn select_reg(arg: &[TokenTree]) -> Result<String, Error> {
+  let span = args_span(arg);
+  let reg = match arg {
+    [tree] => get_reg_text(tree),
+    _ => None,
+  }
+  .ok_or_else(|| {
+    Error::new(span, "registers must be string literals or register names")
+  })?;
+  check_reg(&reg, span)?;
+  if parse_reg(&reg) == Some(15) {
+    return Err(Error::new(
+      span,
+      format!("`{reg}` can't be used, it's the program counter"),
+    ));
+  }
+  Ok(reg)
+}
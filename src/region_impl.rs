@@ -0,0 +1,178 @@
+use super::*;
+use std::sync::Mutex;
+
+/// The regions that are open: `(name, kind)`, in the order they were opened.
+///
+/// Spans can't be kept across invocations, so only the text is stored.
+static OPEN_REGIONS: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+fn open_regions() -> std::sync::MutexGuard<'static, Vec<(String, String)>> {
+  // a panic while holding the lock can't leave the list in a bad state.
+  OPEN_REGIONS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn region_begin_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let args = RegionArgs::parse(token_stream, true)?;
+  let open = match args.kind.as_str() {
+    "section" => {
+      let section_name = match args.args.as_slice() {
+        [arg] => get_str_arg("args", arg).ok(),
+        _ => None,
+      }
+      .ok_or_else(|| {
+        Error::new(
+          args.args_span,
+          "a `section` region needs `args = (\"section_name\")`",
+        )
+      })?;
+      if let Some(problem) = section_name_problem(&section_name) {
+        return Err(Error::new(
+          args.args_span,
+          format!("`{section_name}` is not a valid section name: {problem}"),
+        ));
+      }
+      format!(r#".pushsection {section_name},"ax",%progbits"#)
+    }
+    _ => {
+      if !args.args.is_empty() {
+        return Err(Error::new(
+          args.args_span,
+          "an `a32` region doesn't take any `args`",
+        ));
+      }
+      String::from(".code 32")
+    }
+  };
+
+  let mut regions = open_regions();
+  if let Some((_, kind)) = regions.iter().find(|(name, _)| *name == args.name) {
+    return Err(Error::new(
+      args.name_span,
+      format!("the region named `{}` is already open as `{kind}`", args.name),
+    ));
+  }
+  regions.push((args.name, args.kind));
+  Ok(TokenStream::from(str_literal_at(&open, out_span)))
+}
+
+pub fn region_end_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let args = RegionArgs::parse(token_stream, false)?;
+  let mut regions = open_regions();
+  let index = regions
+    .iter()
+    .position(|(name, _)| *name == args.name)
+    .ok_or_else(|| {
+      Error::new(
+        args.name_span,
+        format!("there's no open region named `{}`", args.name),
+      )
+    })?;
+  let kind = &regions[index].1;
+  if *kind != args.kind {
+    return Err(Error::new(
+      args.kind_span,
+      format!(
+        "the region named `{}` was opened as `{kind}`, not `{}`",
+        args.name, args.kind
+      ),
+    ));
+  }
+  regions.remove(index);
+  let close = match args.kind.as_str() {
+    "section" => ".popsection",
+    _ => ".code 16",
+  };
+  Ok(TokenStream::from(str_literal_at(close, out_span)))
+}
+
+pub fn assert_regions_closed_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  if let Some(extra) = token_stream.into_iter().next() {
+    return Err(Error::new(extra.span(), "no input is expected"));
+  }
+  // Clear the list even on an error, so each region is only reported once.
+  let regions = core::mem::take(&mut *open_regions());
+  let mut errors = Errors::default();
+  for (name, kind) in regions {
+    errors.push(Error::call_site(format!(
+      "the `{kind}` region named `{name}` was never closed"
+    )));
+  }
+  errors.finish()?;
+  Ok(TokenStream::new())
+}
+
+/// The arguments of the begin and end macros.
+struct RegionArgs {
+  kind: String,
+  kind_span: Span,
+  name: String,
+  name_span: Span,
+  args: Vec<Vec<TokenTree>>,
+  args_span: Span,
+}
+impl RegionArgs {
+  fn parse(token_stream: TokenStream, allow_args: bool) -> Result<Self, Error> {
+    let mut kind: Option<(String, Span)> = None;
+    let mut name: Option<(String, Span)> = None;
+    let mut args = Vec::new();
+    let mut group_span = Span::call_site();
+    for arg in split_args(token_stream) {
+      let (arg_name, value) = get_named_arg(&arg).ok_or_else(|| {
+        Error::new(
+          args_span(&arg),
+          "arguments must be of the form `name = value`",
+        )
+      })?;
+      match arg_name.as_str() {
+        "kind" => {
+          kind = Some(
+            match value {
+              [TokenTree::Ident(i)] => Some((i.to_string(), i.span())),
+              _ => None,
+            }
+            .filter(|(k, _)| k == "section" || k == "a32")
+            .ok_or_else(|| {
+              Error::new(args_span(value), "`kind` must be `section` or `a32`")
+            })?,
+          )
+        }
+        "name" => {
+          name = Some((get_str_arg(&arg_name, value)?, args_span(value)))
+        }
+        "args" if allow_args => {
+          group_span = args_span(value);
+          let group = match value {
+            [tree] => get_group(tree.clone()),
+            _ => None,
+          }
+          .ok_or_else(|| {
+            Error::new(
+              group_span,
+              "`args` must be a grouping, eg: `(\"name\")`",
+            )
+          })?;
+          args = split_args(group.stream());
+        }
+        other => {
+          return Err(Error::new(
+            args_span(&arg),
+            format!("unknown argument `{other}`"),
+          ))
+        }
+      }
+    }
+    let (kind, kind_span) =
+      kind.ok_or_else(|| Error::call_site("missing `kind = ...`"))?;
+    let (name, name_span) =
+      name.ok_or_else(|| Error::call_site("missing `name = \"...\"`"))?;
+    Ok(Self { kind, kind_span, name, name_span, args, args_span: group_span })
+  }
+}
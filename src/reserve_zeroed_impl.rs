@@ -0,0 +1,91 @@
+use super::*;
+
+pub fn reserve_zeroed_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut args = split_args(token_stream).into_iter();
+  let name_arg =
+    args.next().ok_or_else(|| Error::call_site(NOT_ENOUGH_INPUT))?;
+  let name = match name_arg.as_slice() {
+    [tree] => get_str_literal_content(tree),
+    _ => None,
+  }
+  .ok_or_else(|| {
+    Error::new(
+      args_span(&name_arg),
+      "the first argument must be the symbol name, as a string literal",
+    )
+  })?;
+  if !is_symbol_name(&name) {
+    return Err(Error::new(
+      args_span(&name_arg),
+      format!("`{name}` is not a valid symbol name"),
+    ));
+  }
+  let size_arg =
+    args.next().ok_or_else(|| Error::call_site("missing the size"))?;
+  let size = get_int(&size_arg)
+    .and_then(|size| u32::try_from(size).ok())
+    .filter(|size| *size > 0)
+    .ok_or_else(|| {
+      Error::new(
+        args_span(&size_arg),
+        "the size must be a positive integer literal that fits in 32 bits",
+      )
+    })?;
+
+  let mut section = ".bss".to_string();
+  let mut flags = "aw".to_string();
+  let mut align: u32 = 4;
+  for arg in args {
+    let (arg_name, value) = get_named_arg(&arg).ok_or_else(|| {
+      Error::new(
+        args_span(&arg),
+        "arguments must be of the form `name = value`",
+      )
+    })?;
+    match arg_name.as_str() {
+      "section" => {
+        section = get_str_arg(&arg_name, value)?;
+        if let Some(problem) = section_name_problem(&section) {
+          return Err(Error::new(
+            args_span(value),
+            format!("`{section}` is not a valid section name: {problem}"),
+          ));
+        }
+      }
+      "flags" => {
+        flags = get_str_arg(&arg_name, value)?;
+        check_section_flags(&flags, args_span(value))?;
+      }
+      "align" => {
+        align = match get_int(value) {
+          Some(align) => check_align(align, args_span(value))?,
+          None => {
+            return Err(Error::new(
+              args_span(value),
+              "`align` must be an integer literal",
+            ))
+          }
+        }
+      }
+      other => {
+        return Err(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        ))
+      }
+    }
+  }
+
+  let lines = [
+    format!(r#".section {section},"{flags}",%nobits"#),
+    format!(".balign {align}"),
+    format!(".global {name}"),
+    format!("{name}:"),
+    format!(".space {size:#X}"),
+    format!(".size {name}, . - {name}"),
+  ];
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
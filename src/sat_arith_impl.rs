@@ -0,0 +1,90 @@
+use super::*;
+
+pub fn sat_add_s32_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  sat_arith(token_stream, "adds", "qadd")
+}
+
+pub fn sat_sub_s32_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  sat_arith(token_stream, "subs", "qsub")
+}
+
+/// Saturating add or sub, using `flags_op` on ARMv4 and `q_op` when the DSP
+/// extensions are available.
+fn sat_arith(
+  token_stream: TokenStream, flags_op: &str, q_op: &str,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut dst: Option<String> = None;
+  let mut a: Option<String> = None;
+  let mut b: Option<String> = None;
+  let mut scratch: Option<(String, Span)> = None;
+  let mut arch = Arch::V4;
+  for arg in split_args(token_stream) {
+    let (name, value) = get_named_arg(&arg).ok_or_else(|| {
+      Error::new(
+        args_span(&arg),
+        "arguments must be of the form `name = value`",
+      )
+    })?;
+    match name.as_str() {
+      "dst" => dst = Some(get_reg_arg(&name, value)?),
+      "a" => a = Some(get_reg_arg(&name, value)?),
+      "b" => b = Some(get_reg_arg(&name, value)?),
+      "scratch" => {
+        scratch = Some((get_reg_arg(&name, value)?, args_span(value)))
+      }
+      "arch" => {
+        arch = match value {
+          [tree] => Arch::from_option(tree)?,
+          _ => {
+            return Err(Error::new(
+              args_span(value),
+              "`arch` must be one arch name, eg: `v5te`",
+            ))
+          }
+        }
+      }
+      other => {
+        return Err(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        ))
+      }
+    }
+  }
+  let dst = dst.ok_or_else(|| Error::call_site("missing `dst = \"rN\"`"))?;
+  let a = a.ok_or_else(|| Error::call_site("missing `a = \"rN\"`"))?;
+  let b = b.ok_or_else(|| Error::call_site("missing `b = \"rN\"`"))?;
+
+  if arch >= Arch::V5TE {
+    return Ok(TokenStream::from(str_literal_at(
+      &format!("{q_op} {dst}, {a}, {b}"),
+      out_span,
+    )));
+  }
+
+  let (scratch, scratch_span) =
+    scratch.ok_or_else(|| Error::call_site("missing `scratch = \"rN\"`"))?;
+  if regs_alias(&scratch, &dst)
+    || regs_alias(&scratch, &a)
+    || regs_alias(&scratch, &b)
+  {
+    return Err(Error::new(
+      scratch_span,
+      "`scratch` must differ from `dst`, `a`, and `b`",
+    ));
+  }
+  // On overflow the result has the wrong sign, so `asr #31` of it is all 1s
+  // when we need the max and all 0s when we need the min. Flipping the top bit
+  // of that gives 0x7FFFFFFF or 0x80000000, and 0x80000000 is encodable.
+  let lines = [
+    format!("{flags_op} {dst}, {a}, {b}"),
+    format!("movvs {scratch}, #0x80000000"),
+    format!("eorvs {dst}, {scratch}, {dst}, asr #31"),
+  ];
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
@@ -0,0 +1,115 @@
+use super::*;
+
+pub fn set_mode_stacks_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut errors = Errors::default();
+  // Each mode's bits and the line that sets its `sp`, in the order given.
+  let mut stacks: Vec<(u8, String)> = Vec::new();
+  let mut end_in: Option<u8> = None;
+  // A mode that was given but had an error shouldn't also count as missing.
+  let mut any_mode = false;
+  for arg in split_args(token_stream) {
+    let Some((name, value)) = get_named_arg(&arg) else {
+      errors.push(Error::new(
+        args_span(&arg),
+        "arguments must be of the form `name = value`",
+      ));
+      continue;
+    };
+    if name == "end_in" {
+      if end_in.is_some() {
+        errors.push(Error::new(args_span(&arg), "`end_in` is given twice"));
+      }
+      match value {
+        [tree] if cpu_mode_bits(&tree.to_string()).is_some() => {
+          end_in = cpu_mode_bits(&tree.to_string())
+        }
+        _ => errors.push(Error::new(
+          args_span(value),
+          format!("`end_in` must be one of: {}", cpu_mode_names()),
+        )),
+      }
+      continue;
+    }
+    any_mode = true;
+    let Some(bits) = cpu_mode_bits(&name) else {
+      errors.push(Error::new(
+        args_span(&arg),
+        format!("unknown mode `{name}`, expected one of: {}", cpu_mode_names()),
+      ));
+      continue;
+    };
+    if bits == cpu_mode_bits("usr").unwrap() {
+      errors.push(Error::new(
+        args_span(&arg),
+        "User mode can't switch to other modes, set its stack with `sys`, \
+         which shares `sp` with User mode",
+      ));
+      continue;
+    }
+    if stacks.iter().any(|(b, _)| *b == bits) {
+      errors.push(Error::new(
+        args_span(&arg),
+        format!("the stack for `{name}` is given twice"),
+      ));
+      continue;
+    }
+    if let Some(line) = errors.check(stack_value(&name, value)) {
+      stacks.push((bits, line));
+    }
+  }
+  if !any_mode {
+    errors.push(Error::call_site(
+      "no stacks to set, give at least one `mode = value`, eg: \
+       `irq = \"=0x03007FA0\"`",
+    ));
+  }
+  errors.finish()?;
+
+  let mut lines: Vec<String> = Vec::new();
+  for (bits, line) in &stacks {
+    lines.push(set_cpu_control_asm(*bits, true, true));
+    lines.push(line.clone());
+  }
+  let last = stacks.last().map(|(bits, _)| *bits).unwrap();
+  match end_in {
+    Some(end) if end != last => {
+      lines.push(set_cpu_control_asm(end, true, true))
+    }
+    _ => (),
+  }
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
+
+/// The line that sets `sp` to a stack's value, which is either `=value` to
+/// load with a literal pool, or a register to copy.
+fn stack_value(name: &str, value: &[TokenTree]) -> Result<String, Error> {
+  let text = get_str_arg(name, value)?;
+  if let Some(constant) = text.strip_prefix('=') {
+    if constant.trim().is_empty() {
+      return Err(Error::new(
+        args_span(value),
+        format!("`{name}` is missing the value after the `=`"),
+      ));
+    }
+    return Ok(format!("ldr sp, ={}", constant.trim()));
+  }
+  match classify_reg(&text) {
+    // r8 and up are banked in FIQ mode, and r13 and up in every mode, so they
+    // can change out from under us as the modes switch.
+    Some(RegArg::Core(n)) if n > 7 => Err(Error::new(
+      args_span(value),
+      format!("`{text}` is banked in some modes, use one of `r0`-`r7`"),
+    )),
+    Some(_) => Ok(format!("mov sp, {text}")),
+    None => Err(Error::new(
+      args_span(value),
+      format!(
+        "`{name}` must be `=value` to load from a literal pool, or a \
+         register, eg: `\"=0x03007FA0\"` or `\"r0\"`"
+      ),
+    )),
+  }
+}
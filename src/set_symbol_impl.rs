@@ -0,0 +1,64 @@
+use super::*;
+
+pub fn set_symbol_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut args = split_args(token_stream).into_iter();
+  let alias_arg =
+    args.next().ok_or_else(|| Error::call_site(NOT_ENOUGH_INPUT))?;
+  let target_arg =
+    args.next().ok_or_else(|| Error::call_site(NOT_ENOUGH_INPUT))?;
+  let mut errors = Errors::default();
+  let alias = errors.check(symbol_name_arg(&alias_arg));
+  let target = errors.check(symbol_name_arg(&target_arg));
+  errors.finish()?;
+  let (alias, target) = (alias.unwrap(), target.unwrap());
+  if alias == target {
+    return Err(Error::new(
+      args_span(&target_arg),
+      format!("`{alias}` can't be set as an alias of itself"),
+    ));
+  }
+
+  let mut global = true;
+  let mut function = false;
+  for arg in args {
+    let (arg_name, value) = get_named_arg(&arg).ok_or_else(|| {
+      Error::new(
+        args_span(&arg),
+        "arguments must be of the form `name = value`",
+      )
+    })?;
+    let flag = match arg_name.as_str() {
+      "global" => &mut global,
+      "function" => &mut function,
+      other => {
+        return Err(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        ))
+      }
+    };
+    *flag = match value {
+      [tree] => get_bool(tree),
+      _ => None,
+    }
+    .ok_or_else(|| {
+      Error::new(
+        args_span(value),
+        format!("`{arg_name}` must be set as `true` or `false`"),
+      )
+    })?;
+  }
+
+  let mut out = String::new();
+  if global {
+    out.push_str(&format!(".global {alias}\n"));
+  }
+  if function {
+    out.push_str(&format!(".type {alias}, %function\n"));
+  }
+  out.push_str(&format!(".set {alias}, {target}\n"));
+  Ok(TokenStream::from(str_literal_at(&out, out_span)))
+}
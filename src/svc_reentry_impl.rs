@@ -0,0 +1,83 @@
+use super::*;
+
+pub fn svc_reentry_save_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let steps = reentry_steps(token_stream)?;
+  let lines: Vec<&str> = steps.iter().map(|(save, _)| save.as_str()).collect();
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
+
+pub fn svc_reentry_restore_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let steps = reentry_steps(token_stream)?;
+  let lines: Vec<&str> =
+    steps.iter().rev().map(|(_, restore)| restore.as_str()).collect();
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
+
+/// Parses the arguments, then gives each step of the save paired with the step
+/// of the restore that undoes it.
+///
+/// The save does the steps in order, and the restore does them in reverse.
+fn reentry_steps(
+  token_stream: TokenStream,
+) -> Result<Vec<(String, String)>, Error> {
+  let mut scratch: Option<(String, Span)> = None;
+  let mut fiq_masked = false;
+  for arg in split_args(token_stream) {
+    let (name, value) = get_named_arg(&arg).ok_or_else(|| {
+      Error::new(
+        args_span(&arg),
+        "arguments must be of the form `name = value`",
+      )
+    })?;
+    match name.as_str() {
+      "scratch" => {
+        scratch = Some((get_reg_arg(&name, value)?, args_span(value)))
+      }
+      "fiq_masked" => {
+        fiq_masked = match value {
+          [tree] => get_bool(tree),
+          _ => None,
+        }
+        .ok_or_else(|| {
+          Error::new(
+            args_span(value),
+            "`fiq_masked` must be set as `true` or `false`",
+          )
+        })?
+      }
+      other => {
+        return Err(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        ))
+      }
+    }
+  }
+  let (scratch, scratch_span) =
+    scratch.ok_or_else(|| Error::call_site("missing `scratch = \"rN\"`"))?;
+  if matches!(parse_reg(&scratch), Some(13..=15)) {
+    return Err(Error::new(
+      scratch_span,
+      format!("`scratch` can't be `{scratch}`, it must be one of `r0`-`r12`"),
+    ));
+  }
+
+  let svc = cpu_mode_bits("svc").unwrap();
+  Ok(vec![
+    (format!("mrs {scratch}, SPSR"), format!("msr SPSR_cxsf, {scratch}")),
+    (
+      format!("push {{{{{scratch}, lr}}}}"),
+      format!("pop {{{{{scratch}, lr}}}}"),
+    ),
+    (
+      set_cpu_control_asm(svc, false, fiq_masked),
+      set_cpu_control_asm(svc, true, fiq_masked),
+    ),
+  ])
+}
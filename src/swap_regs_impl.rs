@@ -0,0 +1,76 @@
+use super::*;
+
+pub fn swap_regs_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let args = split_args(token_stream);
+  let (a, b, scratch) = match args.as_slice() {
+    [a, b] => (a, b, None),
+    [a, b, scratch] => (a, b, Some(scratch)),
+    _ => {
+      return Err(Error::call_site(
+        "expected two registers, and optionally a scratch",
+      ))
+    }
+  };
+  let get_reg = |arg: &[TokenTree]| {
+    let span = args_span(arg);
+    let reg = match arg {
+      [tree] => get_str_literal_content(tree),
+      _ => None,
+    }
+    .ok_or_else(|| Error::new(span, "the registers must be string literals"))?;
+    check_reg(&reg, span)?;
+    if parse_reg(&reg) == Some(15) {
+      return Err(Error::new(
+        span,
+        format!("`{reg}` can't be swapped, it's the program counter"),
+      ));
+    }
+    Ok((reg, span))
+  };
+  let (a, _) = get_reg(a)?;
+  let (b, b_span) = get_reg(b)?;
+  if regs_alias(&a, &b) {
+    return Err(Error::new(
+      b_span,
+      format!("`{a}` and `{b}` are the same register"),
+    ));
+  }
+
+  let lines = match scratch {
+    None => [
+      format!("eor {a}, {a}, {b}"),
+      format!("eor {b}, {a}, {b}"),
+      format!("eor {a}, {a}, {b}"),
+    ],
+    Some(scratch) => {
+      let (name, value) = get_named_arg(scratch)
+        .filter(|(name, _)| name == "scratch")
+        .ok_or_else(|| {
+          Error::new(
+            args_span(scratch),
+            "the third argument must be `scratch = \"rN\"`",
+          )
+        })?;
+      let s = get_reg_arg(&name, value)?;
+      if parse_reg(&s) == Some(15) {
+        return Err(Error::new(
+          args_span(value),
+          "`scratch` can't be the program counter",
+        ));
+      }
+      if regs_alias(&s, &a) || regs_alias(&s, &b) {
+        return Err(Error::new(
+          args_span(value),
+          "`scratch` must differ from the registers being swapped",
+        ));
+      }
+      [
+        format!("mov {s}, {a}"),
+        format!("mov {a}, {b}"),
+        format!("mov {b}, {s}"),
+      ]
+    }
+  };
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
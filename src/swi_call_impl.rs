@@ -0,0 +1,50 @@
+use super::*;
+
+pub fn swi_call_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut args = split_args(token_stream).into_iter();
+  let number_arg =
+    args.next().ok_or_else(|| Error::call_site(NOT_ENOUGH_INPUT))?;
+  let number = get_int(&number_arg).ok_or_else(|| {
+    Error::new(
+      args_span(&number_arg),
+      "the first argument must be the call number, as an integer literal",
+    )
+  })?;
+  let state = match args.next().as_deref() {
+    Some([TokenTree::Ident(i)]) if i.to_string() == "a32" => IsaState::A32,
+    Some([TokenTree::Ident(i)]) if i.to_string() == "t32" => IsaState::T32,
+    Some(other) => {
+      return Err(Error::new(
+        args_span(other),
+        "the state must be `a32` or `t32`",
+      ))
+    }
+    None => {
+      return Err(Error::call_site(
+        "pick a state with `a32` or `t32`, the call number is placed \
+         differently in each",
+      ))
+    }
+  };
+  if let Some(extra) = args.next() {
+    return Err(Error::new(args_span(&extra), "too many arguments"));
+  }
+  // The t32 comment field is 8 bits. The a32 one is 24 bits, but the call
+  // number goes in the top 8 of them so that either state can read it the
+  // same way (with `lsr #16` or not).
+  if !(0..=0xFF).contains(&number) {
+    return Err(Error::new(
+      args_span(&number_arg),
+      format!(
+        "the call number must be 0 to 0xFF, which is all that fits in the \
+         t32 comment field, and the a32 one after `lsl #16`, got {number}"
+      ),
+    ));
+  }
+  let line = match state {
+    IsaState::A32 => format!("swi #{:#08X}", number << 16),
+    IsaState::T32 => format!("swi #{number:#04X}"),
+  };
+  Ok(TokenStream::from(str_literal_at(&line, out_span)))
+}
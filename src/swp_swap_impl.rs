@@ -0,0 +1,74 @@
+use super::*;
+
+pub fn swp_swap_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let args = split_args(token_stream);
+  let [dst_arg, src_arg, addr_arg] = args.as_slice() else {
+    return Err(Error::call_site(
+      "expected `\"dst\", \"src\", [\"addr\"]`, optionally with `byte` first",
+    ));
+  };
+  let (byte, dst_arg) = match dst_arg.as_slice() {
+    [TokenTree::Ident(i), rest @ ..] if i.to_string() == "byte" => (true, rest),
+    other => (false, other),
+  };
+  let dst = swp_reg("destination", dst_arg)?;
+  let src = swp_reg("source", src_arg)?;
+  let addr = match addr_arg.as_slice() {
+    [TokenTree::Group(g)] if g.delimiter() == Delimiter::Bracket => {
+      let inner: Vec<TokenTree> = g.stream().into_iter().collect();
+      swp_reg("address", &inner)?
+    }
+    _ => {
+      return Err(Error::new(
+        args_span(addr_arg),
+        "the address register must be in brackets, eg: `[\"r2\"]`",
+      ))
+    }
+  };
+  // The load and store of a `swp` are done through the address register
+  // after the destination is written and the source is read, so any overlap
+  // with it is unpredictable.
+  for (which, reg) in [("destination", &dst), ("source", &src)] {
+    if regs_alias(reg, &addr) {
+      return Err(Error::new(
+        args_span(addr_arg),
+        format!(
+          "the address register `{addr}` can't also be the {which} \
+           register, the result of that is unpredictable"
+        ),
+      ));
+    }
+  }
+
+  let op = if byte { "swpb" } else { "swp" };
+  Ok(TokenStream::from(str_literal_at(
+    &format!("{op} {dst}, {src}, [{addr}]"),
+    out_span,
+  )))
+}
+
+/// Gets one of the registers of a `swp`, which can't be `sp` or `pc`.
+fn swp_reg(which: &str, arg: &[TokenTree]) -> Result<String, Error> {
+  let span = args_span(arg);
+  let reg = match arg {
+    [tree] => get_reg_text(tree),
+    _ => None,
+  }
+  .ok_or_else(|| {
+    Error::new(
+      span,
+      format!(
+        "the {which} register must be a string literal or a register name"
+      ),
+    )
+  })?;
+  check_reg(&reg, span)?;
+  if matches!(parse_reg(&reg), Some(13 | 15)) {
+    return Err(Error::new(
+      span,
+      format!("can't use `{reg}` as the {which} register of a `swp`"),
+    ));
+  }
+  Ok(reg)
+}
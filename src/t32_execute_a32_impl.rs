@@ -0,0 +1,68 @@
+use super::*;
+
+pub fn t32_execute_a32_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let (options, token_stream) =
+    take_options(token_stream, &["scratch", "lint", "arch"]);
+  let mut scratch = None;
+  let mut lint = None;
+  let mut arch = None;
+  for (name, value) in &options {
+    match name.to_string().as_str() {
+      "scratch" => {
+        let text = get_reg_text(value).ok_or_else(|| {
+          Error::new(
+            value.span(),
+            "`scratch` must be a string literal or a register name",
+          )
+        })?;
+        check_reg(&text, value.span())?;
+        if matches!(parse_reg(&text), Some(13 | 15)) {
+          return Err(Error::new(
+            value.span(),
+            format!(
+              "`scratch` can't be `{text}`, it must be one of `r0`-`r12` or \
+               `lr`"
+            ),
+          ));
+        }
+        scratch = Some(text);
+      }
+      "lint" => lint = Some(Lint::from_option(value)?),
+      _ => arch = Some(Arch::from_option(value)?),
+    }
+  }
+  let scratch =
+    scratch.ok_or_else(|| Error::call_site("missing `scratch = \"rN\",`"))?;
+  if let Some(lint) = lint {
+    lint_body(&token_stream, lint, arch)?;
+  }
+  check_no_state_switch(&token_stream, "t32_execute_a32")?;
+
+  // In t32, reading `pc` gives the address of the instruction plus 4. Once the
+  // `bx pc` is 4-byte aligned, that's the a32 code just after the `nop`, and
+  // the low bit is clear so `bx` switches to a32.
+  let enter = ".balignw 4, 0x46C0\nbx pc\nnop\n.code 32\n";
+  // In a32, reading `pc` gives the address of the instruction plus 8, which is
+  // just after the `bx`. Setting the low bit makes `bx` switch back to t32.
+  let exit = format!("add {scratch}, pc, #1\nbx {scratch}\n.code 16\n");
+
+  let mut out_buffer: Vec<TokenTree> = Vec::new();
+  out_buffer.push(TokenTree::Literal(Literal::string(enter)));
+  out_buffer.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+  extend_concat_as_lines(&mut out_buffer, token_stream);
+  // the above fn always leaves a trailing comma, no need for a secondary check.
+  out_buffer.push(TokenTree::Literal(Literal::string(&exit)));
+
+  let concat_expr = vec![
+    TokenTree::Ident(Ident::new("concat", Span::call_site())),
+    TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+    TokenTree::Group(Group::new(
+      Delimiter::Parenthesis,
+      TokenStream::from_iter(out_buffer),
+    )),
+  ];
+
+  Ok(TokenStream::from_iter(concat_expr))
+}
@@ -1,7 +1,38 @@
 use super::*;
 
-pub fn t32_with_a32_scope_impl(token_stream: TokenStream) -> TokenStream {
+pub fn t32_with_a32_scope_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let (options, token_stream) =
+    take_options(token_stream, &["lint", "arch", "align"]);
+  let mut lint = None;
+  let mut arch = None;
+  let mut align = true;
+  for (name, value) in &options {
+    match name.to_string().as_str() {
+      "lint" => lint = Some(Lint::from_option(value)?),
+      "align" => {
+        align = get_bool(value).ok_or_else(|| {
+          Error::new(value.span(), "`align` must be set as `true` or `false`")
+        })?
+      }
+      _ => arch = Some(Arch::from_option(value)?),
+    }
+  }
+  if let Some(lint) = lint {
+    lint_body(&token_stream, lint, arch)?;
+  }
+  check_no_state_switch(&token_stream, "t32_with_a32_scope")?;
+
   let mut out_buffer: Vec<TokenTree> = Vec::new();
+  if align {
+    // The t32 code before the scope might only be 2-byte aligned, but a32 code
+    // must be 4-byte aligned. This pads with a t32 `mov r8, r8` when needed,
+    // since zero padding would be a `movs` that changes the flags.
+    out_buffer
+      .push(TokenTree::Literal(Literal::string(".balignw 4, 0x46C0\n")));
+    out_buffer.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+  }
   out_buffer.push(TokenTree::Literal(Literal::string(".code 32\n")));
   out_buffer.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
 
@@ -18,5 +49,52 @@ pub fn t32_with_a32_scope_impl(token_stream: TokenStream) -> TokenStream {
     )),
   ];
 
-  TokenStream::from_iter(concat_expr)
+  Ok(TokenStream::from_iter(concat_expr))
+}
+
+/// Checks that none of the body switches the instruction set, since the scope
+/// macro is what switches to a32 and back.
+///
+/// Anything that can't be folded to text is skipped, so this is best-effort.
+pub fn check_no_state_switch(
+  body: &TokenStream, macro_name: &str,
+) -> Result<(), Error> {
+  for element in split_args(body.clone()) {
+    let nested = element.windows(2).find_map(|pair| match pair {
+      [TokenTree::Ident(i), TokenTree::Punct(p)]
+        if *p == '!'
+          && ["t32_with_a32_scope", "t32_execute_a32"]
+            .contains(&i.to_string().as_str()) =>
+      {
+        Some(i.to_string())
+      }
+      _ => None,
+    });
+    if let Some(inner) = nested {
+      return Err(Error::new(
+        args_span(&element),
+        format!(
+          "`{inner}!` can't be nested inside `{macro_name}!`, the inner \
+           `.code 16` would put the rest of the outer scope back in t32"
+        ),
+      ));
+    }
+    let Ok(text) = fold_to_str(TokenStream::from_iter(element.iter().cloned()))
+    else {
+      continue;
+    };
+    for line in text.split(['\n', ';']) {
+      if IsaState::of_directive(line).is_some() {
+        return Err(Error::new(
+          args_span(&element),
+          format!(
+            "`{}` can't be used inside `{macro_name}!`, which already \
+             switches to a32 and back",
+            line.trim()
+          ),
+        ));
+      }
+    }
+  }
+  Ok(())
 }
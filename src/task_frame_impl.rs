@@ -0,0 +1,115 @@
+use super::*;
+
+/// The registers of a task frame, in the order they're stored, along with the
+/// name of each `.set` constant.
+///
+/// Each register is 4 bytes, so the offset of each entry is 4 times its index.
+const TASK_FRAME: &[(&str, &str)] = &[
+  ("r4", "TASK_FRAME_R4"),
+  ("r5", "TASK_FRAME_R5"),
+  ("r6", "TASK_FRAME_R6"),
+  ("r7", "TASK_FRAME_R7"),
+  ("r8", "TASK_FRAME_R8"),
+  ("r9", "TASK_FRAME_R9"),
+  ("r10", "TASK_FRAME_R10"),
+  ("r11", "TASK_FRAME_R11"),
+  ("sp", "TASK_FRAME_SP"),
+  ("lr", "TASK_FRAME_LR"),
+];
+
+pub fn task_save_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  task_transfer(token_stream, "stmia", "from_mode")
+}
+
+pub fn task_restore_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  task_transfer(token_stream, "ldmia", "to_mode")
+}
+
+pub fn task_frame_offsets_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  if let Some(extra) = token_stream.into_iter().next() {
+    return Err(Error::new(extra.span(), "too many tokens"));
+  }
+  let mut lines: Vec<String> = TASK_FRAME
+    .iter()
+    .enumerate()
+    .map(|(i, (_, name))| format!(".set {name}, {}", i * 4))
+    .collect();
+  lines.push(format!(".set TASK_FRAME_SIZE, {}", TASK_FRAME.len() * 4));
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
+
+/// Parses `tcb = "rN", <mode_arg> = mode` and emits the `stm`/`ldm` of the
+/// whole frame.
+fn task_transfer(
+  token_stream: TokenStream, op: &str, mode_arg: &str,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut tcb: Option<(String, Span)> = None;
+  let mut user_bank: Option<bool> = None;
+  for arg in split_args(token_stream) {
+    let (name, value) = get_named_arg(&arg).ok_or_else(|| {
+      Error::new(
+        args_span(&arg),
+        "arguments must be of the form `name = value`",
+      )
+    })?;
+    match name.as_str() {
+      "tcb" => tcb = Some((get_reg_arg(&name, value)?, args_span(value))),
+      _ if name == mode_arg => {
+        user_bank = Some(match value {
+          [TokenTree::Ident(i)] => match i.to_string().as_str() {
+            "svc" | "sys" => false,
+            "irq" | "fiq" => true,
+            other => {
+              return Err(Error::new(
+                i.span(),
+                format!(
+                  "`{mode_arg}` must be `svc`, `sys`, `irq`, or `fiq`, got `{other}`"
+                ),
+              ))
+            }
+          },
+          _ => {
+            return Err(Error::new(
+              args_span(value),
+              format!("`{mode_arg}` must be a mode name"),
+            ))
+          }
+        })
+      }
+      other => {
+        return Err(Error::new(
+          args_span(&arg),
+          format!("unknown argument `{other}`"),
+        ))
+      }
+    }
+  }
+  let (tcb, tcb_span) =
+    tcb.ok_or_else(|| Error::call_site("missing `tcb = \"rN\"`"))?;
+  let user_bank = user_bank
+    .ok_or_else(|| Error::call_site(format!("missing `{mode_arg} = ...`")))?;
+  if let Some(r) = parse_reg(&tcb) {
+    let in_frame = TASK_FRAME.iter().any(|(reg, _)| parse_reg(reg) == Some(r));
+    if in_frame || r == 15 {
+      return Err(Error::new(
+        tcb_span,
+        format!("`tcb` can't be `{tcb}`, it's part of the task frame"),
+      ));
+    }
+  }
+
+  let asm = if user_bank {
+    // The user bank form can't use writeback, and the next instruction must not
+    // touch a banked register.
+    format!("{op} {tcb}, {{{{r4-r11, sp, lr}}}}^\nnop")
+  } else {
+    format!("{op} {tcb}, {{{{r4-r11, sp, lr}}}}")
+  };
+  Ok(TokenStream::from(str_literal_at(&asm, out_span)))
+}
@@ -2,8 +2,152 @@
 
 use super::*;
 
-const NOT_ENOUGH_INPUT: &str = "Not enough input";
+pub const NOT_ENOUGH_INPUT: &str = "Not enough input";
 const ONE_STR_ONLY: &str = "Provide one string literal only.";
+pub const ONE_REG_ONLY: &str =
+  "Provide one register only, as a string literal or a register name.";
+
+/// The signature of every macro's impl function.
+pub type ImplFn = fn(TokenStream) -> Result<TokenStream, Error>;
+
+/// A problem with the macro input, reported as a `compile_error!`.
+///
+/// Errors can be combined so that several problems are all reported at once.
+#[derive(Debug, Clone)]
+pub struct Error {
+  messages: Vec<(Span, String)>,
+}
+impl Error {
+  /// An error at the given span.
+  pub fn new(span: Span, msg: impl Into<String>) -> Self {
+    Self { messages: vec![(span, msg.into())] }
+  }
+
+  /// An error attached to the whole macro invocation.
+  pub fn call_site(msg: impl Into<String>) -> Self {
+    Self::new(Span::call_site(), msg)
+  }
+
+  /// Adds the messages of another error after this error's messages.
+  pub fn combine(&mut self, other: Error) {
+    self.messages.extend(other.messages);
+  }
+
+  /// All the messages of this error, one per line.
+  pub fn message(&self) -> String {
+    let msgs: Vec<&str> =
+      self.messages.iter().map(|(_, msg)| msg.as_str()).collect();
+    msgs.join("\n")
+  }
+
+  /// Makes the `compile_error!("...")` expression for this error.
+  ///
+  /// Every token gets the error's span so the diagnostic lands there. When
+  /// there's more than one message, each `compile_error!` goes within a single
+  /// `concat!` so that the output is still one expression.
+  pub fn to_compile_error(&self) -> TokenStream {
+    let mut errors: Vec<TokenTree> = Vec::new();
+    for (span, msg) in &self.messages {
+      if !errors.is_empty() {
+        errors.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+      }
+      let mut literal = Literal::string(msg);
+      literal.set_span(*span);
+      let mut group = Group::new(
+        Delimiter::Parenthesis,
+        TokenStream::from(TokenTree::Literal(literal)),
+      );
+      group.set_span(*span);
+      let mut bang = Punct::new('!', Spacing::Alone);
+      bang.set_span(*span);
+      errors.push(TokenTree::Ident(Ident::new("compile_error", *span)));
+      errors.push(TokenTree::Punct(bang));
+      errors.push(TokenTree::Group(group));
+    }
+    if self.messages.len() == 1 {
+      TokenStream::from_iter(errors)
+    } else {
+      TokenStream::from_iter([
+        TokenTree::Ident(Ident::new("concat", Span::call_site())),
+        TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+        TokenTree::Group(Group::new(
+          Delimiter::Parenthesis,
+          TokenStream::from_iter(errors),
+        )),
+      ])
+    }
+  }
+}
+
+/// Collects errors during parsing, so that every problem with the input can be
+/// reported at once instead of stopping at the first one.
+#[derive(Debug, Default)]
+pub struct Errors(Option<Error>);
+impl Errors {
+  /// Records an error.
+  pub fn push(&mut self, error: Error) {
+    match &mut self.0 {
+      Some(e) => e.combine(error),
+      None => self.0 = Some(error),
+    }
+  }
+
+  /// Records the error of a result, if any, and gives back the success value.
+  pub fn check<T>(&mut self, result: Result<T, Error>) -> Option<T> {
+    result.map_err(|e| self.push(e)).ok()
+  }
+
+  /// An error of everything recorded, followed by one final error.
+  ///
+  /// This is for when parsing can't continue at all.
+  pub fn finish_with(mut self, last: Error) -> Error {
+    self.push(last);
+    self.0.unwrap()
+  }
+
+  /// An error of everything recorded, if anything was.
+  pub fn finish(self) -> Result<(), Error> {
+    match self.0 {
+      Some(e) => Err(e),
+      None => Ok(()),
+    }
+  }
+}
+
+/// An architecture version, for when the available instructions matter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Arch {
+  V4,
+  V4T,
+  V5T,
+  V5TE,
+  V6,
+  V6T2,
+  V7,
+}
+impl Arch {
+  /// Parses the value given to an `arch = ...` option.
+  pub fn from_option(value: &TokenTree) -> Result<Self, Error> {
+    Ok(match value.to_string().as_str() {
+      "v4" => Self::V4,
+      "v4t" => Self::V4T,
+      "v5t" => Self::V5T,
+      "v5te" => Self::V5TE,
+      "v6" => Self::V6,
+      "v6t2" => Self::V6T2,
+      "v7" => Self::V7,
+      other => {
+        return Err(Error::new(
+          value.span(),
+          format!(
+            "unknown arch `{other}`, expected one of: v4, v4t, v5t, v5te, v6, \
+             v6t2, v7"
+          ),
+        ))
+      }
+    })
+  }
+}
 
 /// Gets out the `Group`, if any.
 pub fn get_group(tree: TokenTree) -> Option<Group> {
@@ -52,52 +196,116 @@ pub fn get_bool(tree: &TokenTree) -> Option<bool> {
 /// Gets the content inside a string literal, if it is one.
 pub fn get_str_literal_content(tree: &TokenTree) -> Option<String> {
   match tree {
-    TokenTree::Literal(l) => {
-      let mut string = format!("{l}");
-      if string.starts_with('"') && string.ends_with('"') {
-        string.pop();
-        string.remove(0);
-        Some(string)
-      } else {
-        None
-      }
-    }
+    TokenTree::Literal(l) => str_literal_content(&format!("{l}")),
     _ => None,
   }
 }
 
-pub fn one_str_literal_or_panic(token_stream: TokenStream) -> String {
+/// Gets the content inside the source text of a string literal, if it is one.
+///
+/// Both normal strings and raw strings (with any number of `#`) work. Any
+/// escapes in a normal string are undone, so the content is the actual text
+/// that the literal stands for.
+pub fn str_literal_content(text: &str) -> Option<String> {
+  if let Some(raw) = text.strip_prefix('r') {
+    let hashes = raw.len() - raw.trim_start_matches('#').len();
+    let fence = &raw[..hashes];
+    let content = raw[hashes..].strip_prefix('"')?.strip_suffix(fence)?;
+    Some(content.strip_suffix('"')?.to_string())
+  } else if text.len() >= 2 && text.starts_with('"') && text.ends_with('"') {
+    unescape(&text[1..text.len() - 1])
+  } else {
+    None
+  }
+}
+
+/// Gets the content and span of the only string literal in the input.
+pub fn one_str_literal(
+  token_stream: TokenStream,
+) -> Result<(String, Span), Error> {
   let mut stream_iter = token_stream.into_iter();
-  let literal =
-    get_str_literal_content(&stream_iter.next().expect(NOT_ENOUGH_INPUT))
-      .expect(ONE_STR_ONLY);
-  assert!(stream_iter.next().is_none(), "{ONE_STR_ONLY}");
-  literal
+  let tree =
+    stream_iter.next().ok_or_else(|| Error::call_site(NOT_ENOUGH_INPUT))?;
+  let literal = get_str_literal_content(&tree)
+    .ok_or_else(|| Error::new(tree.span(), ONE_STR_ONLY))?;
+  if let Some(extra) = stream_iter.next() {
+    return Err(Error::new(extra.span(), ONE_STR_ONLY));
+  }
+  Ok((literal, tree.span()))
+}
+
+/// Gets the text of a register argument, which can be a string literal or an
+/// ident.
+///
+/// The text isn't checked, see [`check_reg`] for that.
+pub fn get_reg_text(tree: &TokenTree) -> Option<String> {
+  match tree {
+    TokenTree::Ident(i) => Some(i.to_string()),
+    _ => get_str_literal_content(tree),
+  }
+}
+
+/// Gets the text and span of the only register argument in the input, see
+/// [`get_reg_text`].
+pub fn one_reg(token_stream: TokenStream) -> Result<(String, Span), Error> {
+  let mut stream_iter = token_stream.into_iter();
+  let tree =
+    stream_iter.next().ok_or_else(|| Error::call_site(NOT_ENOUGH_INPUT))?;
+  let text =
+    get_reg_text(&tree).ok_or_else(|| Error::new(tree.span(), ONE_REG_ONLY))?;
+  if let Some(extra) = stream_iter.next() {
+    return Err(Error::new(extra.span(), ONE_REG_ONLY));
+  }
+  Ok((text, tree.span()))
+}
+
+/// Gets the span of the first token of the input, or the call site if there's
+/// no input.
+///
+/// This is the span for output that comes from the input as a whole.
+pub fn input_span(token_stream: &TokenStream) -> Span {
+  token_stream
+    .clone()
+    .into_iter()
+    .next()
+    .map_or(Span::call_site(), |tree| tree.span())
+}
+
+/// Makes a string literal token with the given span.
+///
+/// Output should carry the span of the input it came from, so that if the
+/// assembler rejects the text the error points at the user's code.
+pub fn str_literal_at(text: &str, span: Span) -> TokenTree {
+  let mut literal = Literal::string(text);
+  literal.set_span(span);
+  TokenTree::Literal(literal)
 }
 
 #[allow(clippy::enum_variant_names)]
 pub enum EzTokenTree {
-  EzGroup(Delimiter, Vec<EzTokenTree>),
+  EzGroup(Delimiter, Vec<EzTokenTree>, Span),
   EzId(String, Span),
-  EzPu(char, Spacing),
-  EzLi(String),
+  EzPu(char, Spacing, Span),
+  EzLi(String, Span),
 }
 impl EzTokenTree {
+  pub fn span(&self) -> Span {
+    match self {
+      Self::EzGroup(_, _, s)
+      | Self::EzId(_, s)
+      | Self::EzPu(_, _, s)
+      | Self::EzLi(_, s) => *s,
+    }
+  }
   pub fn get_literal(&self) -> Option<&str> {
     match self {
-      Self::EzLi(s) => Some(s.as_str()),
+      Self::EzLi(s, _) => Some(s.as_str()),
       _ => None,
     }
   }
-  pub fn get_str_literal_content(&self) -> Option<&str> {
+  pub fn get_str_literal_content(&self) -> Option<String> {
     match self {
-      Self::EzLi(s) => {
-        if s.starts_with('"') && s.ends_with('"') {
-          Some(&s[..s.len() - 1][1..])
-        } else {
-          None
-        }
-      }
+      Self::EzLi(s, _) => str_literal_content(s),
       _ => None,
     }
   }
@@ -108,28 +316,48 @@ impl From<TokenTree> for EzTokenTree {
       TokenTree::Group(g) => EzTokenTree::EzGroup(
         g.delimiter(),
         g.stream().into_iter().map(EzTokenTree::from).collect(),
+        g.span(),
       ),
       TokenTree::Ident(i) => EzTokenTree::EzId(i.to_string(), i.span()),
-      TokenTree::Punct(p) => EzTokenTree::EzPu(p.as_char(), p.spacing()),
-      TokenTree::Literal(l) => EzTokenTree::EzLi(l.to_string()),
+      TokenTree::Punct(p) => {
+        EzTokenTree::EzPu(p.as_char(), p.spacing(), p.span())
+      }
+      TokenTree::Literal(l) => EzTokenTree::EzLi(l.to_string(), l.span()),
     }
   }
 }
-impl From<EzTokenTree> for TokenTree {
-  fn from(value: EzTokenTree) -> Self {
-    match value {
-      EzTokenTree::EzGroup(delimiter, trees) => TokenTree::Group(Group::new(
-        delimiter,
-        TokenStream::from_iter(trees.into_iter().map(TokenTree::from)),
-      )),
-      EzTokenTree::EzId(i, s) => TokenTree::Ident(Ident::new(&i, s)),
-      EzTokenTree::EzPu(ch, spacing) => {
-        TokenTree::Punct(Punct::new(ch, spacing))
+impl TryFrom<EzTokenTree> for TokenTree {
+  type Error = Error;
+  /// Fails if a literal's text isn't a valid literal, which can only happen if
+  /// the `EzLi` text was edited after it was made from a real literal.
+  fn try_from(value: EzTokenTree) -> Result<Self, Error> {
+    Ok(match value {
+      EzTokenTree::EzGroup(delimiter, trees, s) => {
+        let trees = trees
+          .into_iter()
+          .map(TokenTree::try_from)
+          .collect::<Result<TokenStream, Error>>()?;
+        let mut g = Group::new(delimiter, trees);
+        g.set_span(s);
+        TokenTree::Group(g)
       }
-      EzTokenTree::EzLi(l) => {
-        TokenTree::Literal(Literal::from_str(&l).unwrap())
+      EzTokenTree::EzId(i, s) => match i.strip_prefix("r#") {
+        Some(raw) => TokenTree::Ident(Ident::new_raw(raw, s)),
+        None => TokenTree::Ident(Ident::new(&i, s)),
+      },
+      EzTokenTree::EzPu(ch, spacing, s) => {
+        let mut p = Punct::new(ch, spacing);
+        p.set_span(s);
+        TokenTree::Punct(p)
       }
-    }
+      EzTokenTree::EzLi(l, s) => {
+        let mut lit = Literal::from_str(&l).map_err(|_| {
+          Error::new(s, format!("`{l}` is not a valid literal"))
+        })?;
+        lit.set_span(s);
+        TokenTree::Literal(lit)
+      }
+    })
   }
 }
 
@@ -179,3 +407,425 @@ pub fn extend_concat_as_lines(
     }
   }
 }
+
+/// Splits a token stream into the comma separated arguments it holds.
+///
+/// Groups are single token trees, so only the top level commas split. A
+/// trailing comma does not produce an extra empty argument.
+pub fn split_args(token_stream: TokenStream) -> Vec<Vec<TokenTree>> {
+  let mut args: Vec<Vec<TokenTree>> = Vec::new();
+  let mut current: Vec<TokenTree> = Vec::new();
+  for tree in token_stream {
+    match tree {
+      TokenTree::Punct(p) if p == ',' => {
+        args.push(core::mem::take(&mut current))
+      }
+      other => current.push(other),
+    }
+  }
+  if !current.is_empty() {
+    args.push(current);
+  }
+  args
+}
+
+/// If the argument is of the form `name = value...`, gets the name and the
+/// value trees.
+pub fn get_named_arg(arg: &[TokenTree]) -> Option<(String, &[TokenTree])> {
+  match arg {
+    [TokenTree::Ident(i), TokenTree::Punct(p), rest @ ..]
+      if *p == '=' && p.spacing() == Spacing::Alone =>
+    {
+      Some((i.to_string(), rest))
+    }
+    _ => None,
+  }
+}
+
+/// Takes any leading `name = value,` options off the front of the input.
+///
+/// Only the option names given are taken, so the rest of the input can be
+/// anything at all. Each option's value must be a single token tree.
+pub fn take_options(
+  token_stream: TokenStream, names: &[&str],
+) -> (Vec<(Ident, TokenTree)>, TokenStream) {
+  let trees: Vec<TokenTree> = token_stream.into_iter().collect();
+  let mut options = Vec::new();
+  let mut rest = trees.as_slice();
+  while let [TokenTree::Ident(name), TokenTree::Punct(eq), value, tail @ ..] =
+    rest
+  {
+    if *eq != '=' || !names.contains(&name.to_string().as_str()) {
+      break;
+    }
+    options.push((name.clone(), value.clone()));
+    rest = match tail {
+      [TokenTree::Punct(comma), tail @ ..] if *comma == ',' => tail,
+      _ => tail,
+    };
+  }
+  (options, TokenStream::from_iter(rest.iter().cloned()))
+}
+
+/// Gets the string literal content of a named argument's value.
+pub fn get_str_arg(name: &str, value: &[TokenTree]) -> Result<String, Error> {
+  match value {
+    [tree] => get_str_literal_content(tree),
+    _ => None,
+  }
+  .ok_or_else(|| {
+    Error::new(args_span(value), format!("`{name}` must be a string literal"))
+  })
+}
+
+/// Gets a named argument's value as a register name, see [`check_reg`].
+pub fn get_reg_arg(name: &str, value: &[TokenTree]) -> Result<String, Error> {
+  let reg = get_str_arg(name, value)?;
+  check_reg(&reg, args_span(value))?;
+  Ok(reg)
+}
+
+/// Gets the integer value of a named argument's value, which must fit in `T`.
+pub fn get_int_arg<T: TryFrom<i128>>(
+  name: &str, value: &[TokenTree],
+) -> Result<T, Error> {
+  get_int(value).and_then(|v| T::try_from(v).ok()).ok_or_else(|| {
+    Error::new(
+      args_span(value),
+      format!(
+        "`{name}` must be a `{}` integer literal",
+        core::any::type_name::<T>()
+      ),
+    )
+  })
+}
+
+/// Gets the span of the first of some trees, or the call site if there are
+/// none.
+pub fn args_span(trees: &[TokenTree]) -> Span {
+  trees.first().map_or(Span::call_site(), TokenTree::span)
+}
+
+/// Parses the text of a rust integer literal.
+///
+/// Handles the `0x`, `0o`, and `0b` prefixes, `_` separators, and any integer
+/// type suffix.
+pub fn parse_int_literal(text: &str) -> Option<i128> {
+  let (radix, digits) = match text.get(..2) {
+    Some("0x") | Some("0X") => (16, &text[2..]),
+    Some("0o") => (8, &text[2..]),
+    Some("0b") => (2, &text[2..]),
+    _ => (10, text),
+  };
+  let mut value: i128 = 0;
+  let mut any_digits = false;
+  for (i, ch) in digits.char_indices() {
+    if ch == '_' {
+      continue;
+    }
+    match ch.to_digit(radix) {
+      Some(d) => {
+        value = value.checked_mul(radix as i128)?.checked_add(d as i128)?;
+        any_digits = true;
+      }
+      None => {
+        const SUFFIXES: &[&str] = &[
+          "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32",
+          "i64", "i128", "isize",
+        ];
+        if SUFFIXES.contains(&&digits[i..]) {
+          break;
+        } else {
+          return None;
+        }
+      }
+    }
+  }
+  if any_digits {
+    Some(value)
+  } else {
+    None
+  }
+}
+
+/// Gets the value of an integer literal, possibly with a leading `-`.
+pub fn get_int(trees: &[TokenTree]) -> Option<i128> {
+  match trees {
+    [TokenTree::Literal(l)] => parse_int_literal(&l.to_string()),
+    [TokenTree::Punct(p), TokenTree::Literal(l)] if *p == '-' => {
+      parse_int_literal(&l.to_string()).map(|v| -v)
+    }
+    _ => None,
+  }
+}
+
+/// Parses a register name into its number.
+pub fn parse_reg(text: &str) -> Option<u8> {
+  let text = text.to_ascii_lowercase();
+  match text.as_str() {
+    "sb" => Some(9),
+    "sl" => Some(10),
+    "fp" => Some(11),
+    "ip" => Some(12),
+    "sp" => Some(13),
+    "lr" => Some(14),
+    "pc" => Some(15),
+    _ => {
+      let digits = text.strip_prefix('r')?;
+      if digits.is_empty()
+        || !digits.bytes().all(|b| b.is_ascii_digit())
+        || (digits.len() > 1 && digits.starts_with('0'))
+      {
+        return None;
+      }
+      digits.parse().ok().filter(|n| *n <= 15)
+    }
+  }
+}
+
+/// What a register argument names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegArg {
+  /// A core register, by number.
+  Core(u8),
+  /// An `asm!` substitution, such as `{temp}` or `{temp:x}`, which could be
+  /// any register.
+  Substitution,
+}
+
+/// Classifies the text of a register argument, or `None` if it's neither a
+/// core register nor an `asm!` substitution.
+pub fn classify_reg(text: &str) -> Option<RegArg> {
+  if let Some(r) = parse_reg(text) {
+    return Some(RegArg::Core(r));
+  }
+  let inner = text.strip_prefix('{')?.strip_suffix('}')?;
+  let (name, modifier) = inner.split_once(':').unwrap_or((inner, ""));
+  let is_word =
+    |s: &str| s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+  (is_word(name) && is_word(modifier)).then_some(RegArg::Substitution)
+}
+
+/// Checks that the text of a register argument is a register, giving an error
+/// at the span if it isn't.
+pub fn check_reg(text: &str, span: Span) -> Result<RegArg, Error> {
+  classify_reg(text).ok_or_else(|| {
+    Error::new(
+      span,
+      format!(
+        "`{text}` is not a register name or an `asm!` substitution like \
+         `{{temp}}`"
+      ),
+    )
+  })
+}
+
+/// Checks if two register arguments are the same register.
+///
+/// Core registers are compared by number, so `"r14"` and `"LR"` alias.
+/// Substitutions can only be compared by name, so two different names are
+/// assumed to be different registers.
+pub fn regs_alias(a: &str, b: &str) -> bool {
+  match (parse_reg(a), parse_reg(b)) {
+    (Some(a), Some(b)) => a == b,
+    _ => a == b,
+  }
+}
+
+/// Checks if a value can be an `a32` data-processing immediate.
+///
+/// That's any 8-bit value rotated right by an even amount.
+pub fn a32_imm_encodable(value: u32) -> bool {
+  (0..16).any(|half_rot| value.rotate_left(half_rot * 2) <= 0xFF)
+}
+
+/// Splits a value into two `a32` immediates that `orr` together to make it,
+/// if it can be. The smaller immediate is first.
+pub fn a32_imm_split(value: u32) -> Option<(u32, u32)> {
+  // If there's a split, one part fits in some 8-bit window, and taking all of
+  // the value's bits in that window leaves the other part with fewer bits,
+  // which are still encodable.
+  (0..16).find_map(|half_rot| {
+    let low = value & 0xFF_u32.rotate_right(half_rot * 2);
+    let high = value & !low;
+    (low != 0 && high != 0 && a32_imm_encodable(high))
+      .then_some((low.min(high), low.max(high)))
+  })
+}
+
+/// The line that puts a constant in a register: a `mov` if the value is an
+/// immediate, or a `ldr =` otherwise.
+pub fn load_const_line(reg: &str, value: u32) -> String {
+  if a32_imm_encodable(value) {
+    format!("mov {reg}, #{value}")
+  } else {
+    format!("ldr {reg}, ={value:#X}")
+  }
+}
+
+/// Gets the condition that's true exactly when the given one is false.
+pub fn invert_cond(cond: &str) -> Option<&'static str> {
+  Some(match cond {
+    "eq" => "ne",
+    "ne" => "eq",
+    "cs" => "cc",
+    "hs" => "lo",
+    "cc" => "cs",
+    "lo" => "hs",
+    "mi" => "pl",
+    "pl" => "mi",
+    "vs" => "vc",
+    "vc" => "vs",
+    "hi" => "ls",
+    "ls" => "hi",
+    "ge" => "lt",
+    "lt" => "ge",
+    "gt" => "le",
+    "le" => "gt",
+    _ => return None,
+  })
+}
+
+/// Gets a new label name, unique within the crate being compiled.
+pub fn next_local_label() -> String {
+  static NEXT_LABEL: AtomicU64 = AtomicU64::new(0);
+  let n = NEXT_LABEL.fetch_add(1, Ordering::Relaxed);
+  format!(".L_bracer_local_label_{n}")
+}
+
+/// Checks if text can be used as a symbol name as is: ascii alphanumerics,
+/// `_`, `.`, and `$`, and not starting with a digit.
+pub fn is_symbol_name(text: &str) -> bool {
+  text.starts_with(|c: char| !c.is_ascii_digit())
+    && text.chars().all(|c| c.is_ascii_alphanumeric() || "_.$".contains(c))
+}
+
+/// Gets the option closest to the text, by edit distance, for suggesting a
+/// fix when the text isn't one of the options. Ties go to the earlier option.
+pub fn closest_match<'a>(text: &str, options: &[&'a str]) -> Option<&'a str> {
+  let distance = |option: &str| {
+    let text: Vec<char> = text.chars().collect();
+    let mut row: Vec<usize> = (0..=text.len()).collect();
+    for (i, b) in option.chars().enumerate() {
+      let mut diag = row[0];
+      row[0] = i + 1;
+      for (j, a) in text.iter().enumerate() {
+        let next =
+          (diag + usize::from(*a != b)).min(row[j] + 1).min(row[j + 1] + 1);
+        diag = row[j + 1];
+        row[j + 1] = next;
+      }
+    }
+    row[text.len()]
+  };
+  options.iter().copied().min_by_key(|option| distance(option))
+}
+
+/// Undoes the escapes in the text between the quotes of a string or char
+/// literal.
+pub fn unescape(text: &str) -> Option<String> {
+  let mut out = String::with_capacity(text.len());
+  let mut chars = text.chars().peekable();
+  while let Some(ch) = chars.next() {
+    if ch != '\\' {
+      out.push(ch);
+      continue;
+    }
+    match chars.next()? {
+      'n' => out.push('\n'),
+      'r' => out.push('\r'),
+      't' => out.push('\t'),
+      '0' => out.push('\0'),
+      '\\' => out.push('\\'),
+      '\'' => out.push('\''),
+      '"' => out.push('"'),
+      'x' => {
+        let hex: String = chars.by_ref().take(2).collect();
+        let value = u8::from_str_radix(&hex, 16).ok().filter(|v| *v < 0x80)?;
+        out.push(char::from(value));
+      }
+      'u' => {
+        if chars.next()? != '{' {
+          return None;
+        }
+        let hex: String = chars.by_ref().take_while(|ch| *ch != '}').collect();
+        let value = u32::from_str_radix(&hex.replace('_', ""), 16).ok()?;
+        out.push(char::from_u32(value)?);
+      }
+      '\n' => {
+        // a line continuation skips the newline and all leading whitespace.
+        while chars.peek().is_some_and(|ch| ch.is_whitespace()) {
+          chars.next();
+        }
+      }
+      _ => return None,
+    }
+  }
+  Some(out)
+}
+
+/// Folds a fully literal expression down to the string it would produce.
+///
+/// The expression can be string, char, and integer literals, `concat!`, and
+/// any bracer macro invocation (which is expanded and then folded). Several
+/// comma separated expressions are folded and joined like `concat!` would.
+/// Anything else can't be known at expansion time, and is an error at that
+/// element.
+pub fn fold_to_str(token_stream: TokenStream) -> Result<String, Error> {
+  let mut out = String::new();
+  for arg in split_args(token_stream) {
+    // allow paths like `bracer::when!(...)` by skipping to the last segment.
+    let last_segment = arg
+      .iter()
+      .rposition(|tree| matches!(tree, TokenTree::Punct(p) if *p == ':'))
+      .map_or(0, |i| i + 1);
+    match &arg[last_segment..] {
+      [TokenTree::Literal(l)] => {
+        let text = l.to_string();
+        let folded =
+          if let Some(content) = get_str_literal_content(&arg[last_segment]) {
+            Some(content)
+          } else if text.starts_with('\'')
+            && text.ends_with('\'')
+            && text.len() > 1
+          {
+            unescape(&text[1..text.len() - 1])
+          } else {
+            parse_int_literal(&text).map(|value| value.to_string())
+          };
+        out.push_str(&folded.ok_or_else(|| {
+          Error::new(
+            l.span(),
+            format!("`{text}` is not a string, char, or integer literal"),
+          )
+        })?);
+      }
+      [TokenTree::Ident(name), TokenTree::Punct(bang), TokenTree::Group(args)]
+        if *bang == '!' =>
+      {
+        let name_str = name.to_string();
+        if name_str == "concat" {
+          out.push_str(&fold_to_str(args.stream())?);
+        } else if let Some(impl_fn) = dispatch_impl(&name_str) {
+          out.push_str(&fold_to_str(impl_fn(args.stream())?)?);
+        } else {
+          return Err(Error::new(
+            name.span(),
+            format!(
+              "`{name_str}!` is not a bracer macro, so it can't be folded"
+            ),
+          ));
+        }
+      }
+      other => {
+        let span = args_span(other);
+        let text = TokenStream::from_iter(other.iter().cloned()).to_string();
+        return Err(Error::new(
+          span,
+          format!("`{text}` is not a literal, so it can't be folded"),
+        ));
+      }
+    }
+  }
+  Ok(out)
+}
@@ -0,0 +1,93 @@
+use super::*;
+
+/// The names of each entry of the exception vector table, in the order of the
+/// table.
+const VECTOR_SLOTS: &[&[&str]] = &[
+  &["reset"],
+  &["undefined", "undef"],
+  &["swi", "svc"],
+  &["prefetch_abort"],
+  &["data_abort"],
+  &["reserved"],
+  &["irq"],
+  &["fiq"],
+];
+
+pub fn vector_table_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut errors = Errors::default();
+  let mut targets: [Option<String>; 8] = Default::default();
+  let mut default: Option<String> = None;
+  let mut use_ldr: Option<bool> = None;
+  for arg in split_args(token_stream) {
+    let Some((name, value)) = get_named_arg(&arg) else {
+      errors.push(Error::new(
+        args_span(&arg),
+        "arguments must be of the form `name = value`",
+      ));
+      continue;
+    };
+    if name == "style" {
+      if use_ldr.is_some() {
+        errors.push(Error::new(args_span(&arg), "`style` is given twice"));
+      }
+      match value {
+        [TokenTree::Ident(i)] if i.to_string() == "ldr" => use_ldr = Some(true),
+        [TokenTree::Ident(i)] if i.to_string() == "b" => use_ldr = Some(false),
+        _ => errors
+          .push(Error::new(args_span(value), "`style` must be `ldr` or `b`")),
+      }
+      continue;
+    }
+    let slot = if name == "default" {
+      &mut default
+    } else if let Some(i) =
+      VECTOR_SLOTS.iter().position(|names| names.contains(&name.as_str()))
+    {
+      &mut targets[i]
+    } else {
+      let names: Vec<String> =
+        VECTOR_SLOTS.iter().map(|names| format!("`{}`", names[0])).collect();
+      errors.push(Error::new(
+        args_span(&arg),
+        format!(
+          "unknown vector `{name}`, expected one of: {}, or `default`",
+          names.join(", ")
+        ),
+      ));
+      continue;
+    };
+    if slot.is_some() {
+      errors.push(Error::new(
+        args_span(&arg),
+        format!("the target for `{name}` is given twice"),
+      ));
+      continue;
+    }
+    let Some(symbol) = errors.check(get_str_arg(&name, value)) else {
+      continue;
+    };
+    if !is_symbol_name(&symbol) {
+      errors.push(Error::new(
+        args_span(value),
+        format!("`{symbol}` is not a valid symbol name"),
+      ));
+      continue;
+    }
+    *slot = Some(symbol);
+  }
+  errors.finish()?;
+
+  let use_ldr = use_ldr.unwrap_or(true);
+  let lines: Vec<String> = targets
+    .iter()
+    .map(|target| match target.as_ref().or(default.as_ref()) {
+      Some(target) if use_ldr => format!("ldr pc, ={target}"),
+      Some(target) => format!("b {target}"),
+      None => "b .".to_string(),
+    })
+    .collect();
+  Ok(TokenStream::from(str_literal_at(&lines.join("\n"), out_span)))
+}
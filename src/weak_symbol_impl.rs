@@ -0,0 +1,85 @@
+use super::*;
+
+/// The visibility keywords and the directive each one emits.
+const VISIBILITIES: &[(&str, &str)] = &[
+  ("hidden", ".hidden"),
+  ("protected", ".protected"),
+  ("internal", ".internal"),
+];
+
+pub fn weak_symbol_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let mut errors = Errors::default();
+  let mut out = String::new();
+  for arg in split_args(token_stream) {
+    if arg.is_empty() {
+      continue;
+    }
+    if let Some(name) = errors.check(symbol_name_arg(&arg)) {
+      out.push_str(&format!(".weak {name}\n"));
+    }
+  }
+  if out.is_empty() {
+    errors.push(Error::call_site(NOT_ENOUGH_INPUT));
+  }
+  errors.finish()?;
+  Ok(TokenStream::from(str_literal_at(&out, out_span)))
+}
+
+pub fn symbol_visibility_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let out_span = input_span(&token_stream);
+  let args = split_args(token_stream);
+  let [name_arg, vis_arg] = args.as_slice() else {
+    return Err(Error::new(
+      args_span(&args.concat()),
+      "expected a symbol name and a visibility",
+    ));
+  };
+  let name = symbol_name_arg(name_arg)?;
+  let expected = VISIBILITIES
+    .iter()
+    .map(|(keyword, _)| format!("`{keyword}`"))
+    .collect::<Vec<_>>()
+    .join(", ");
+  let vis_text = match vis_arg.as_slice() {
+    [TokenTree::Ident(i)] => i.to_string(),
+    _ => {
+      return Err(Error::new(
+        args_span(vis_arg),
+        format!("the visibility must be one of: {expected}"),
+      ))
+    }
+  };
+  let Some((_, directive)) =
+    VISIBILITIES.iter().find(|(keyword, _)| *keyword == vis_text)
+  else {
+    return Err(Error::new(
+      args_span(vis_arg),
+      format!("unknown visibility `{vis_text}`, expected one of: {expected}"),
+    ));
+  };
+  let out = format!("{directive} {name}\n");
+  Ok(TokenStream::from(str_literal_at(&out, out_span)))
+}
+
+/// Gets a symbol name given as a string literal.
+pub fn symbol_name_arg(arg: &[TokenTree]) -> Result<String, Error> {
+  let name = match arg {
+    [tree] => get_str_literal_content(tree),
+    _ => None,
+  }
+  .ok_or_else(|| {
+    Error::new(args_span(arg), "symbol names must be string literals")
+  })?;
+  if !is_symbol_name(&name) {
+    return Err(Error::new(
+      args_span(arg),
+      format!("`{name}` is not a valid symbol name"),
+    ));
+  }
+  Ok(name)
+}
@@ -1,99 +1,796 @@
 use super::*;
 
-pub fn when_impl(token_stream: TokenStream) -> TokenStream {
-  use EzTokenTree::*;
+pub fn when_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  when_with(token_stream, cmp_test)
+}
+
+pub fn unless_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  when_with(token_stream, |test_group, target| {
+    cmp_test_with(test_group, target, true)
+  })
+}
+
+pub fn when_bit_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  when_with(token_stream, tst_test)
+}
+
+pub fn when_flags_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  when_with(token_stream, flags_test)
+}
+
+pub fn cond_chain_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let trees: Vec<TokenTree> = token_stream.into_iter().collect();
+  let else_pos = trees.iter().position(
+    |tree| matches!(tree, TokenTree::Ident(e) if e.to_string() == "else"),
+  );
+  let (arm_trees, else_group) = match else_pos {
+    None => (trees.as_slice(), None),
+    Some(i) => match &trees[i + 1..] {
+      [TokenTree::Group(g)] => (&trees[..i], Some(g)),
+      [_, extra, ..] => {
+        return Err(Error::new(extra.span(), "too many tokens"))
+      }
+      _ => {
+        return Err(Error::new(
+          trees[i].span(),
+          "`else` must be followed by a group for the lines",
+        ))
+      }
+    },
+  };
+  let mut arms: Vec<(&Group, &Group)> = Vec::new();
+  for pair in arm_trees.chunks(2) {
+    match pair {
+      [TokenTree::Group(test), TokenTree::Group(body)] => {
+        arms.push((test, body))
+      }
+      [TokenTree::Group(test)] => {
+        return Err(Error::new(test.span(), "this test is missing a body"))
+      }
+      [TokenTree::Group(_), other] | [other, ..] => {
+        return Err(Error::new(
+          other.span(),
+          "expected a test group followed by a body group",
+        ))
+      }
+      [] => unreachable!(),
+    }
+  }
+  if arms.is_empty() {
+    return Err(Error::call_site("too few tokens"));
+  }
+
+  // Each failing test goes on to the next test (or the else lines), and each
+  // body ends by going to the end.
+  let end_label = next_local_label();
+  let mut out_buffer: Vec<TokenTree> = Vec::new();
+  for (i, (test_group, body_group)) in arms.iter().enumerate() {
+    let is_last = i + 1 == arms.len();
+    let next_label = if is_last && else_group.is_none() {
+      end_label.clone()
+    } else {
+      next_local_label()
+    };
+    let text = cmp_test(test_group, &next_label)?;
+    out_buffer.push(str_literal_at(&text, test_group.span()));
+    out_buffer.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+    extend_concat_as_lines(&mut out_buffer, body_group.stream());
+    if next_label != end_label {
+      out_buffer.push(TokenTree::Literal(Literal::string(&format!(
+        "b {end_label}\n{next_label}:\n"
+      ))));
+      out_buffer.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+    }
+  }
+  if let Some(else_group) = else_group {
+    extend_concat_as_lines(&mut out_buffer, else_group.stream());
+  }
+  out_buffer
+    .push(TokenTree::Literal(Literal::string(&format!("{end_label}:\n"))));
+
+  let concat_expr = vec![
+    TokenTree::Ident(Ident::new("concat", Span::call_site())),
+    TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+    TokenTree::Group(Group::new(
+      Delimiter::Parenthesis,
+      TokenStream::from_iter(out_buffer),
+    )),
+  ];
+  Ok(TokenStream::from_iter(concat_expr))
+}
+
+pub fn when_cond_impl(token_stream: TokenStream) -> Result<TokenStream, Error> {
+  /// More lines than this are better off as a branch.
+  const MAX_LINES: usize = 4;
+
+  let trees: Vec<TokenTree> = token_stream.into_iter().collect();
+  let (test_group, body_group) = match trees.as_slice() {
+    [TokenTree::Group(test), TokenTree::Group(body)] => (test, body),
+    [_, _, extra, ..] => {
+      return Err(Error::new(extra.span(), "too many tokens"))
+    }
+    _ => {
+      return Err(Error::call_site(
+        "expected a test group followed by a body group",
+      ))
+    }
+  };
+  let test_trees: Vec<EzTokenTree> =
+    test_group.stream().into_iter().map(EzTokenTree::from).collect();
+  let (terms, joiner) = split_test(&test_trees)?;
+  if joiner.is_some() {
+    return Err(Error::new(
+      test_group.span(),
+      "`when_cond!` can only use one comparison, use `when!` for `&&` or `||`",
+    ));
+  }
+  let (compare, fail_cond) = parse_test(terms[0], test_group.span())?;
+  let cond = invert_cond(fail_cond).unwrap();
+
+  let mut text = format!("{compare}\n");
+  let mut line_count = 0;
+  for tree in body_group.stream() {
+    if matches!(&tree, TokenTree::Punct(p) if *p == ',') {
+      continue;
+    }
+    let content = get_str_literal_content(&tree).ok_or_else(|| {
+      Error::new(
+        tree.span(),
+        "only string literal lines can be made conditional, use `when!` for \
+         other macros",
+      )
+    })?;
+    for line in content.split(['\n', ';']) {
+      let line = line.split("//").next().unwrap_or("");
+      let line = line.split('@').next().unwrap_or("").trim();
+      if line.is_empty() {
+        continue;
+      }
+      let line = conditional_line(line, cond)
+        .map_err(|problem| Error::new(tree.span(), problem))?;
+      writeln!(text, "{line}").unwrap();
+      line_count += 1;
+    }
+  }
+  if line_count > MAX_LINES {
+    return Err(Error::new(
+      body_group.span(),
+      format!(
+        "`when_cond!` is for up to {MAX_LINES} lines, this has {line_count}, \
+         use `when!` to branch past them instead"
+      ),
+    ));
+  }
+  Ok(TokenStream::from(str_literal_at(&text, body_group.span())))
+}
+
+/// Puts the condition code on the mnemonic of a line.
+fn conditional_line(line: &str, cond: &str) -> Result<String, String> {
+  if strip_line(line) != line {
+    return Err(format!("`{line}` has a label, which can't be conditional"));
+  }
+  if line.starts_with('.') {
+    return Err(format!("`{line}` is a directive, which can't be conditional"));
+  }
+  let (mnemonic, rest) =
+    line.split_once(char::is_whitespace).unwrap_or((line, ""));
+  let Some((base, has_cond)) = split_mnemonic(mnemonic) else {
+    return Err(format!(
+      "`{mnemonic}` isn't an instruction that `when_cond!` knows how to make \
+       conditional, use `when!` instead"
+    ));
+  };
+  if has_cond {
+    return Err(format!("`{line}` already has a condition code"));
+  }
+  // The flags have to stay as the test left them for the lines after.
+  if mnemonic.to_ascii_lowercase() != base
+    || matches!(base, "cmp" | "cmn" | "tst" | "teq")
+  {
+    return Err(format!(
+      "`{line}` sets the flags, which would change the condition of the lines \
+       after it, use `when!` instead"
+    ));
+  }
+  Ok(format!("{mnemonic}{cond} {}", rest.trim()).trim_end().to_string())
+}
+
+/// The shared part of the `when` macros. The `test` fn gets the test group and
+/// the label to branch to when the test fails, and gives the test's lines.
+fn when_with(
+  token_stream: TokenStream, test: fn(&Group, &str) -> Result<String, Error>,
+) -> Result<TokenStream, Error> {
   use Spacing::*;
 
+  let (options, token_stream) = take_options(token_stream, &["lint"]);
+  let mut lint = None;
+  for (_name, value) in &options {
+    lint = Some(Lint::from_option(value)?);
+  }
+
   let mut token_iter = token_stream.into_iter();
-  let test_group = get_group(token_iter.next().expect("too few tokens"))
-    .expect("must have a group for the test");
-  let label_group = get_group(token_iter.next().expect("too few tokens"))
-    .expect("must have a group for the label");
-  let body_group = get_group(token_iter.next().expect("too few tokens"))
-    .expect("must have a group for the body");
-  assert!(token_iter.next().is_none(), "too many tokens");
+  let test_group = token_iter
+    .next()
+    .ok_or_else(|| Error::call_site("too few tokens"))
+    .and_then(|tree| {
+      let span = tree.span();
+      get_group(tree)
+        .ok_or_else(|| Error::new(span, "must have a group for the test"))
+    })?;
+  let rest: Vec<TokenTree> = token_iter.collect();
+  let else_pos = rest.iter().position(
+    |tree| matches!(tree, TokenTree::Ident(e) if e.to_string() == "else"),
+  );
+  let (before_else, else_group) = match else_pos {
+    None => (rest.as_slice(), None),
+    Some(i) => match &rest[i + 1..] {
+      [TokenTree::Group(g)] => (&rest[..i], Some(g.clone())),
+      [_, extra, ..] => {
+        return Err(Error::new(extra.span(), "too many tokens"))
+      }
+      _ => {
+        return Err(Error::new(
+          rest[i].span(),
+          "`else` must be followed by a group for the lines",
+        ))
+      }
+    },
+  };
+  // The body is the last group, and any before it are labels.
+  let mut groups: Vec<Group> = Vec::new();
+  for tree in before_else {
+    match tree {
+      TokenTree::Group(g) => groups.push(g.clone()),
+      other => {
+        return Err(Error::new(other.span(), "must have a group for the body"))
+      }
+    }
+  }
+  let (body_group, label_groups) =
+    groups.split_last().ok_or_else(|| Error::call_site("too few tokens"))?;
+  if label_groups.is_empty()
+    && else_group.is_none()
+    && parse_label(body_group).is_ok()
+  {
+    // that's a label, the body is missing.
+    return Err(Error::call_site("too few tokens"));
+  }
+
+  if let Some(lint) = lint {
+    lint_body(&body_group.stream(), lint, None)?;
+    if let Some(else_group) = &else_group {
+      lint_body(&else_group.stream(), lint, None)?;
+    }
+  }
 
   let mut out_buffer: Vec<TokenTree> = Vec::new();
 
-  let label_trees: Vec<EzTokenTree> =
-    label_group.stream().into_iter().map(EzTokenTree::from).collect();
-  let local_label: u32 = match label_trees.as_slice() {
-    [EzLi(l)] => {
-      let f = l.to_string();
-      f.parse::<u32>().expect("literal must be a valid u32")
+  // Each label is the text to branch to it, the text to define it, and the
+  // span of its group. Omitted labels are generated.
+  let numeric = |group: &Group| -> Result<(String, String, Span), Error> {
+    let label = parse_label(group)?;
+    Ok((format!("{label}f"), label.to_string(), group.span()))
+  };
+  let generated = || {
+    let label = next_local_label();
+    (label.clone(), label, Span::call_site())
+  };
+  // The end label is only needed (and only allowed) with an `else` block.
+  let (local_label, end_label) = match (label_groups, &else_group) {
+    ([], None) => (generated(), None),
+    ([], Some(_)) => (generated(), Some(generated())),
+    ([label_group], None) => (numeric(label_group)?, None),
+    ([_], Some(_)) => {
+      return Err(Error::new(
+        else_pos.map_or(Span::call_site(), |i| rest[i].span()),
+        "an `else` block needs a second label group for the end, \
+         eg: `[1][2]`",
+      ))
+    }
+    ([_, end], None) => {
+      return Err(Error::new(
+        end.span(),
+        "a second label group is only used with an `else` block",
+      ))
+    }
+    ([label_group, end], Some(_)) => {
+      let local_label = numeric(label_group)?;
+      let end_label = numeric(end)?;
+      if end_label.1 == local_label.1 {
+        return Err(Error::new(
+          end.span(),
+          "the end label must differ from the else label",
+        ));
+      }
+      (local_label, Some(end_label))
+    }
+    ([_, _, extra, ..], _) => {
+      return Err(Error::new(extra.span(), "too many tokens"))
+    }
+  };
+
+  // A numeric label defined in the body would catch the branches meant for
+  // ours, so it can't be the same number.
+  let bodies = [Some(body_group), else_group.as_ref()];
+  for (_, def, span) in
+    [Some(&local_label), end_label.as_ref()].into_iter().flatten()
+  {
+    for body in bodies.iter().flatten() {
+      check_label_not_defined(def, *span, &body.stream())?;
+    }
+  }
+
+  let text = test(&test_group, &local_label.0)?;
+  out_buffer.push(str_literal_at(&text, test_group.span()));
+  out_buffer.push(TokenTree::Punct(Punct::new(',', Alone)));
+
+  extend_concat_as_lines(&mut out_buffer, body_group.stream());
+  // the above fn always leaves a trailing comma, no need for a secondary check.
+  match (end_label, &else_group) {
+    (Some((end_target, end_def, end_span)), Some(else_group)) => {
+      let (_, else_def, else_span) = local_label;
+      out_buffer.push(str_literal_at(
+        &format!("b {end_target}\n{else_def}:\n"),
+        else_span,
+      ));
+      out_buffer.push(TokenTree::Punct(Punct::new(',', Alone)));
+      extend_concat_as_lines(&mut out_buffer, else_group.stream());
+      out_buffer.push(str_literal_at(&format!("{end_def}:\n"), end_span));
     }
     _ => {
-      panic!("please provide only 1 literal for the label")
+      let (_, def, span) = local_label;
+      out_buffer.push(str_literal_at(&format!("{def}:\n"), span));
+    }
+  }
+
+  let concat_expr = vec![
+    TokenTree::Ident(Ident::new("concat", Span::call_site())),
+    TokenTree::Punct(Punct::new('!', Alone)),
+    TokenTree::Group(Group::new(
+      Delimiter::Parenthesis,
+      TokenStream::from_iter(out_buffer),
+    )),
+  ];
+
+  Ok(TokenStream::from_iter(concat_expr))
+}
+
+/// The test of `when!`, some `cmp`s joined with `&&` or `||`.
+fn cmp_test(test_group: &Group, target: &str) -> Result<String, Error> {
+  cmp_test_with(test_group, target, false)
+}
+
+/// The `cmp` test, or with `negate` the test of `unless!`, which skips the
+/// body when the test passes.
+pub fn cmp_test_with(
+  test_group: &Group, target: &str, negate: bool,
+) -> Result<String, Error> {
+  let test_trees: Vec<EzTokenTree> =
+    test_group.stream().into_iter().map(EzTokenTree::from).collect();
+  let (terms, mut joiner) = split_test(&test_trees)?;
+  let mut tests = Vec::new();
+  for term in terms {
+    tests.push(parse_test(term, test_group.span())?);
+  }
+  if negate {
+    // `!(a && b)` is `!a || !b`, and `!(a || b)` is `!a && !b`.
+    for (_, fail_cond) in &mut tests {
+      *fail_cond = invert_cond(fail_cond).unwrap();
+    }
+    joiner = joiner.map(|j| if j == '&' { '|' } else { '&' });
+  }
+  let mut text = String::new();
+  if joiner == Some('|') {
+    // Any passing test goes straight to the body, and only the last test
+    // failing goes to the label. With a numeric label the body gets the next
+    // number up, which keeps the output usable in `asm!`.
+    let (body_target, body_def) = match target
+      .strip_suffix(['f', 'b'])
+      .and_then(|label| label.parse::<u32>().ok())
+    {
+      Some(label) => {
+        let body = label.wrapping_add(1);
+        (format!("{body}f"), body.to_string())
+      }
+      None => {
+        let body = next_local_label();
+        (body.clone(), body)
+      }
+    };
+    let (last, firsts) = tests.split_last().unwrap();
+    for (compare, fail_cond) in firsts {
+      let pass_cond = invert_cond(fail_cond).unwrap();
+      writeln!(text, "{compare}\nb{pass_cond} {body_target}").unwrap();
+    }
+    let (compare, fail_cond) = last;
+    writeln!(text, "{compare}\nb{fail_cond} {target}").unwrap();
+    writeln!(text, "{body_def}:").unwrap();
+  } else {
+    // Any failing test skips the body.
+    for (compare, fail_cond) in &tests {
+      writeln!(text, "{compare}\nb{fail_cond} {target}").unwrap();
+    }
+  }
+  Ok(text)
+}
+
+/// The test of `when_bit!`, a `tst` of a register and a mask.
+fn tst_test(test_group: &Group, target: &str) -> Result<String, Error> {
+  use EzTokenTree::*;
+  use Spacing::*;
+  let test_trees: Vec<EzTokenTree> =
+    test_group.stream().into_iter().map(EzTokenTree::from).collect();
+  // We branch when the test *does not* pass, like with `when!`.
+  let (reg_tree, mask_tree, cond) = match test_trees.as_slice() {
+    [reg, EzPu('&', Alone, _), mask, EzPu('!', Joint, _), EzPu('=', ..), EzLi(zero, _)]
+      if zero == "0" =>
+    {
+      (reg, mask, "eq")
+    }
+    [reg, EzPu('&', Alone, _), mask, EzPu('=', Joint, _), EzPu('=', ..), EzLi(zero, _)]
+      if zero == "0" =>
+    {
+      (reg, mask, "ne")
+    }
+    _ => {
+      return Err(Error::new(
+        test_group.span(),
+        "unknown test expression, expected `\"rN\" & \"#mask\" != 0` or \
+         `== 0`",
+      ))
     }
   };
+  let reg = str_operand(reg_tree)?;
+  check_operand(&reg, reg_tree.span(), "left", None)?;
+  let mask = str_operand(mask_tree)?;
+  check_operand(&mask, mask_tree.span(), "right", Some(tst_imm_problem))?;
+  Ok(format!("tst {reg}, {mask}\nb{cond} {target}\n"))
+}
 
+/// The test of `when_flags!`, a condition of the flags as they already are.
+fn flags_test(test_group: &Group, target: &str) -> Result<String, Error> {
+  flags_test_with(test_group, target, false)
+}
+
+/// The flags test, or with `negate` a branch when the condition holds.
+pub fn flags_test_with(
+  test_group: &Group, target: &str, negate: bool,
+) -> Result<String, Error> {
   let test_trees: Vec<EzTokenTree> =
     test_group.stream().into_iter().map(EzTokenTree::from).collect();
+  let [cond_tree] = test_trees.as_slice() else {
+    return Err(Error::new(
+      test_group.span(),
+      "the test must be one condition code as a str literal, eg: `(\"eq\")`",
+    ));
+  };
+  let cond = str_operand(cond_tree)?;
+  let fail_cond = invert_cond(cond.trim()).ok_or_else(|| {
+    Error::new(
+      cond_tree.span(),
+      format!(
+        "unknown condition `{cond}`, expected one of: eq, ne, cs/hs, cc/lo, \
+         mi, pl, vs, vc, hi, ls, ge, lt, gt, le"
+      ),
+    )
+  })?;
+  let branch_cond =
+    if negate { invert_cond(fail_cond).unwrap() } else { fail_cond };
+  Ok(format!("b{branch_cond} {target}\n"))
+}
+
+/// Gets the content of a test operand, which must be a str literal.
+fn str_operand(tree: &EzTokenTree) -> Result<String, Error> {
+  tree
+    .get_str_literal_content()
+    .ok_or_else(|| Error::new(tree.span(), "test input must be a str literal"))
+}
+
+/// Splits a test at each `&&` or `||`, giving the terms and which of `&` or
+/// `|` joins them (if there's more than one term).
+fn split_test(
+  test_trees: &[EzTokenTree],
+) -> Result<(Vec<&[EzTokenTree]>, Option<char>), Error> {
+  use EzTokenTree::*;
+  let mut terms = Vec::new();
+  let mut joiner: Option<char> = None;
+  let mut start = 0;
+  let mut i = 0;
+  while i + 1 < test_trees.len() {
+    match (&test_trees[i], &test_trees[i + 1]) {
+      (EzPu(a, Spacing::Joint, span), EzPu(b, ..))
+        if a == b && (*a == '&' || *a == '|') =>
+      {
+        if joiner.is_some_and(|j| j != *a) {
+          return Err(Error::new(
+            *span,
+            "can't mix `&&` and `||` in one test, use nested `when!` instead",
+          ));
+        }
+        joiner = Some(*a);
+        terms.push(&test_trees[start..i]);
+        start = i + 2;
+        i += 2;
+      }
+      _ => i += 1,
+    }
+  }
+  terms.push(&test_trees[start..]);
+  Ok((terms, joiner))
+}
+
+/// Parses one comparison of a test, giving the `cmp` (or `cmn`) line and the
+/// condition to branch on when the comparison *fails*.
+fn parse_test(
+  test_trees: &[EzTokenTree], span: Span,
+) -> Result<(String, &'static str), Error> {
+  use EzTokenTree::*;
+  use Spacing::*;
   // We're branching when the test *does not* pass, so for example when the
   // users passes in `==` we branch using the inverted case's condition, `ne`
   #[allow(unused_variables)]
-  let cond = match test_trees.as_slice() {
+  let cond = match test_trees {
     // equality has no signed-ness
-    [EzLi(lhs), EzPu('=', Joint), EzPu('=', _), EzLi(op2)] => "ne",
-    [EzLi(lhs), EzPu('!', Joint), EzPu('=', _), EzLi(op2)] => "eq",
+    [EzLi(lhs, _), EzPu('=', Joint, _), EzPu('=', _, _), EzLi(op2, _)] => "ne",
+    [EzLi(lhs, _), EzPu('!', Joint, _), EzPu('=', _, _), EzLi(op2, _)] => "eq",
 
     // unsigned comparison
-    [EzLi(lhs), EzPu('>', Joint), EzPu('=', _), EzId(i, _), EzLi(op2)]
+    [EzLi(lhs, _), EzPu('>', Joint, _), EzPu('=', _, _), EzId(i, _), EzLi(op2, _)]
       if i == "u" =>
     {
       "lo"
     }
-    [EzLi(lhs), EzPu('<', Joint), EzPu('=', _), EzId(u, _), EzLi(op2)]
+    [EzLi(lhs, _), EzPu('<', Joint, _), EzPu('=', _, _), EzId(u, _), EzLi(op2, _)]
       if u == "u" =>
     {
       "hi"
     }
-    [EzLi(lhs), EzPu('<', Alone), EzId(u, _), EzLi(op2)] if u == "u" => "hs",
-    [EzLi(lhs), EzPu('>', Alone), EzId(u, _), EzLi(op2)] if u == "u" => "ls",
+    [EzLi(lhs, _), EzPu('<', Alone, _), EzId(u, _), EzLi(op2, _)]
+      if u == "u" =>
+    {
+      "hs"
+    }
+    [EzLi(lhs, _), EzPu('>', Alone, _), EzId(u, _), EzLi(op2, _)]
+      if u == "u" =>
+    {
+      "ls"
+    }
 
     // signed comparison
-    [EzLi(lhs), EzPu('>', Joint), EzPu('=', _), EzId(i, _), EzLi(op2)]
+    [EzLi(lhs, _), EzPu('>', Joint, _), EzPu('=', _, _), EzId(i, _), EzLi(op2, _)]
       if i == "i" =>
     {
       "lt"
     }
-    [EzLi(lhs), EzPu('<', Joint), EzPu('=', _), EzId(i, _), EzLi(op2)]
+    [EzLi(lhs, _), EzPu('<', Joint, _), EzPu('=', _, _), EzId(i, _), EzLi(op2, _)]
       if i == "i" =>
     {
       "gt"
     }
-    [EzLi(lhs), EzPu('<', Alone), EzId(i, _), EzLi(op2)] if i == "i" => "ge",
-    [EzLi(lhs), EzPu('>', Alone), EzId(i, _), EzLi(op2)] if i == "i" => "le",
-    _ => panic!("unknown test expression"),
+    [EzLi(lhs, _), EzPu('<', Alone, _), EzId(i, _), EzLi(op2, _)]
+      if i == "i" =>
+    {
+      "ge"
+    }
+    [EzLi(lhs, _), EzPu('>', Alone, _), EzId(i, _), EzLi(op2, _)]
+      if i == "i" =>
+    {
+      "le"
+    }
+    _ => return Err(Error::new(span, "unknown test expression")),
   };
-  let lhs = test_trees
-    .first()
-    .unwrap()
-    .get_str_literal_content()
-    .expect("test input must be a str literal");
-  let op2 = test_trees
-    .last()
-    .unwrap()
-    .get_str_literal_content()
-    .expect("test input must be a str literal");
-  out_buffer.push(TokenTree::Literal(Literal::string(&format!(
-    "cmp {lhs}, {op2}\nb{cond} {local_label}f\n"
-  ))));
-  out_buffer.push(TokenTree::Punct(Punct::new(',', Alone)));
+  let lhs_tree = test_trees.first().unwrap();
+  let lhs = str_operand(lhs_tree)?;
+  check_operand(&lhs, lhs_tree.span(), "left", None)?;
+  let op2_tree = test_trees.last().unwrap();
+  let op2 = str_operand(op2_tree)?;
+  check_operand(&op2, op2_tree.span(), "right", Some(cmp_imm_problem))?;
 
-  extend_concat_as_lines(&mut out_buffer, body_group.stream());
-  // the above fn always leaves a trailing comma, no need for a secondary check.
-  out_buffer
-    .push(TokenTree::Literal(Literal::string(&format!("{local_label}:\n"))));
+  // A negative immediate is spelled as a `cmn` of the positive value, which
+  // sets the flags the same way for equality and signed tests. The exception
+  // is `i32::MIN`, where `cmn` overflows differently than `cmp` would, but its
+  // bits are encodable so it's given to `cmp` directly.
+  let imm = op2.strip_prefix('#').map(str::trim);
+  if let Some(value @ ..0) = imm.and_then(parse_imm) {
+    if matches!(cond, "lo" | "hi" | "hs" | "ls") {
+      let bits = value as u32;
+      return Err(Error::new(
+        op2_tree.span(),
+        format!(
+          "an unsigned test against the negative immediate `{op2}` compares \
+           against {bits:#X}, use a signed test or write `#{bits:#X}`"
+        ),
+      ));
+    }
+    if value == i64::from(i32::MIN) {
+      return Ok((format!("cmp {lhs}, #0x80000000"), cond));
+    }
+    if u32::try_from(-value).is_ok_and(a32_imm_encodable) {
+      let positive = imm.unwrap()[1..].trim();
+      return Ok((format!("cmn {lhs}, #{positive}"), cond));
+    }
+  }
+  Ok((format!("cmp {lhs}, {op2}"), cond))
+}
 
-  let concat_expr = vec![
-    TokenTree::Ident(Ident::new("concat", Span::call_site())),
-    TokenTree::Punct(Punct::new('!', Alone)),
-    TokenTree::Group(Group::new(
-      Delimiter::Parenthesis,
-      TokenStream::from_iter(out_buffer),
+/// Checks that the str literals of a body don't define the label.
+///
+/// Lines from other macros in the body aren't known until later, so they
+/// aren't checked.
+pub fn check_label_not_defined(
+  label: &str, span: Span, body: &TokenStream,
+) -> Result<(), Error> {
+  for tree in body.clone() {
+    let Some(content) = get_str_literal_content(&tree) else { continue };
+    for line in content.split(['\n', ';']) {
+      let mut line = line.split("//").next().unwrap_or("");
+      line = line.split('@').next().unwrap_or("").trim();
+      while let Some((name, rest)) = line.split_once(':') {
+        let name = name.trim();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+          break;
+        }
+        if name == label {
+          return Err(Error::new(
+            span,
+            format!(
+              "the label `{label}` is also defined in the body, which would \
+               catch the branches meant for this one, use another number"
+            ),
+          ));
+        }
+        line = rest.trim();
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Parses the numeric label within a label group.
+pub fn parse_label(label_group: &Group) -> Result<u32, Error> {
+  use EzTokenTree::*;
+  let label_trees: Vec<EzTokenTree> =
+    label_group.stream().into_iter().map(EzTokenTree::from).collect();
+  match label_trees.as_slice() {
+    [EzLi(l, span)] => parse_int_literal(l)
+      .and_then(|label| u32::try_from(label).ok())
+      .ok_or_else(|| Error::new(*span, "literal must be a valid u32")),
+    [EzPu('-', _, span), EzLi(..)] => {
+      Err(Error::new(*span, "the label can't be negative"))
+    }
+    _ => Err(Error::new(
+      label_group.span(),
+      "please provide only 1 literal for the label",
     )),
-  ];
+  }
+}
+
+/// Checks that a test operand is a register, or if allowed an immediate. The
+/// `imm_problem` fn, if any, checks that an immediate can be encoded.
+fn check_operand(
+  text: &str, span: Span, side: &str,
+  imm_problem: Option<fn(i64) -> Option<String>>,
+) -> Result<(), Error> {
+  let problem = if text.trim().is_empty() {
+    format!("the {side} operand can't be empty")
+  } else if let Some(imm) = text.strip_prefix('#') {
+    if let Some(imm_problem) = imm_problem {
+      if imm.trim().is_empty() {
+        format!("the {side} operand `{text}` is missing the immediate value")
+      } else if let Some(problem) = parse_imm(imm.trim()).and_then(imm_problem)
+      {
+        problem
+      } else {
+        return Ok(());
+      }
+    } else {
+      format!(
+        "the {side} operand must be a register, not the immediate `{text}`"
+      )
+    }
+  } else if let (Some(_), Some((reg, shift))) =
+    (imm_problem, text.split_once(','))
+  {
+    // a shifted register, which only the right operand can be.
+    check_reg(reg.trim(), span)?;
+    match shift_problem(shift.trim(), text) {
+      Some(problem) => problem,
+      None => return Ok(()),
+    }
+  } else if text
+    .trim_start_matches('-')
+    .starts_with(|c: char| c.is_ascii_digit())
+  {
+    if imm_problem.is_some() {
+      format!("the {side} operand `{text}` needs a `#` to be an immediate")
+    } else {
+      format!("the {side} operand must be a register, not the number `{text}`")
+    }
+  } else {
+    return check_reg(text, span).map(|_| ());
+  };
+  Err(Error::new(span, problem))
+}
+
+/// Checks the shift of a shifted register operand, such as `lsl #2`.
+fn shift_problem(shift: &str, text: &str) -> Option<String> {
+  let (name, amount) = match shift.split_once(char::is_whitespace) {
+    Some((name, amount)) => (name, Some(amount.trim())),
+    None => (shift, None),
+  };
+  let (min, max) = match name.to_ascii_lowercase().as_str() {
+    "lsl" => (0, 31),
+    "lsr" | "asr" => (1, 32),
+    "ror" => (1, 31),
+    "rrx" => {
+      return amount
+        .map(|_| format!("`rrx` doesn't take an amount, got `{text}`"))
+    }
+    _ => {
+      return Some(format!(
+        "unknown shift `{name}` in `{text}`, expected `lsl`, `lsr`, `asr`, \
+         `ror`, or `rrx`"
+      ))
+    }
+  };
+  let Some(amount) = amount else {
+    return Some(format!("the shift in `{text}` is missing an amount"));
+  };
+  match amount.strip_prefix('#').and_then(|imm| parse_imm(imm.trim())) {
+    Some(n) if (min..=max).contains(&n) => None,
+    Some(n) => Some(format!(
+      "the shift amount {n} in `{text}` is out of range, `{name}` can shift by \
+       {min} to {max}"
+    )),
+    None => Some(format!(
+      "the shift amount in `{text}` must be an immediate, eg: `{name} #2`"
+    )),
+  }
+}
+
+/// Checks that a number can be the immediate of a `cmp`.
+///
+/// The assembler will turn a `cmp` into a `cmn` if only the negated value can
+/// be encoded, so either one is fine.
+fn cmp_imm_problem(value: i64) -> Option<String> {
+  let bits = match imm_bits(value) {
+    Ok(bits) => bits,
+    Err(problem) => return Some(problem),
+  };
+  if a32_imm_encodable(bits) || a32_imm_encodable(bits.wrapping_neg()) {
+    None
+  } else {
+    Some(format!(
+      "the immediate {value} ({bits:#X}) can't be encoded, it must be an 8-bit \
+       value rotated right by an even amount (or the negation of one)"
+    ))
+  }
+}
+
+/// Checks that a number can be the immediate of a `tst`, which has no negated
+/// form to fall back on.
+fn tst_imm_problem(value: i64) -> Option<String> {
+  let bits = match imm_bits(value) {
+    Ok(bits) => bits,
+    Err(problem) => return Some(problem),
+  };
+  if a32_imm_encodable(bits) {
+    None
+  } else {
+    Some(format!(
+      "the immediate {value} ({bits:#X}) can't be encoded, it must be an 8-bit \
+       value rotated right by an even amount"
+    ))
+  }
+}
 
-  TokenStream::from_iter(concat_expr)
+/// Gets the bits of an immediate that's either a `u32` or an `i32`.
+fn imm_bits(value: i64) -> Result<u32, String> {
+  u32::try_from(value)
+    .or_else(|_| i32::try_from(value).map(|v| v as u32))
+    .map_err(|_| format!("the immediate {value} doesn't fit in 32 bits"))
 }
@@ -0,0 +1,96 @@
+use super::*;
+
+pub fn with_masked_interrupts_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let mut args = split_args(token_stream).into_iter();
+  let save = scratch_arg(args.next())?;
+
+  let mut temp: Option<(String, Span)> = None;
+  let mut mask: Option<(&str, Span)> = None;
+  let mut body: Option<Group> = None;
+  for arg in args {
+    if let Some((name, value)) = get_named_arg(&arg) {
+      match name.as_str() {
+        "temp" => temp = Some((get_reg_arg(&name, value)?, args_span(value))),
+        other => {
+          return Err(Error::new(
+            args_span(&arg),
+            format!("unknown argument `{other}`"),
+          ))
+        }
+      }
+      continue;
+    }
+    match arg.as_slice() {
+      [TokenTree::Ident(i)]
+        if ["irq_only", "fiq_only"].contains(&i.to_string().as_str()) =>
+      {
+        if mask.is_some() {
+          return Err(Error::new(
+            i.span(),
+            "only one of `irq_only` or `fiq_only` can be given",
+          ));
+        }
+        let bits = if i.to_string() == "irq_only" { "#0x80" } else { "#0x40" };
+        mask = Some((bits, i.span()));
+      }
+      [TokenTree::Group(g)] if g.delimiter() == Delimiter::Brace => {
+        if body.is_some() {
+          return Err(Error::new(g.span(), "the body is given twice"));
+        }
+        body = Some(g.clone());
+      }
+      _ => {
+        return Err(Error::new(
+          args_span(&arg),
+          "expected `temp = \"rN\"`, `irq_only`, `fiq_only`, or the body, eg: \
+           `{ ... }`",
+        ))
+      }
+    }
+  }
+  let (temp, temp_span) = temp.ok_or_else(|| {
+    Error::call_site(
+      "missing `temp = \"rN\"`, a second register to build the masked CPSR \
+       in, since the saved CPSR has to be kept as it is",
+    )
+  })?;
+  if matches!(parse_reg(&temp), Some(13 | 15)) {
+    return Err(Error::new(
+      temp_span,
+      format!(
+        "can't use `{temp}` as the temp register, using `sp` or `pc` with \
+         `mrs`/`msr` is unpredictable"
+      ),
+    ));
+  }
+  if regs_alias(&temp, &save) {
+    return Err(Error::new(
+      temp_span,
+      "`temp` must differ from the register that saves the CPSR",
+    ));
+  }
+  let body = body.ok_or_else(|| Error::call_site("missing the body"))?;
+  let bits = mask.map_or("#0xC0", |(bits, _)| bits);
+
+  let mut out_buffer: Vec<TokenTree> = Vec::new();
+  out_buffer.push(TokenTree::Literal(Literal::string(&format!(
+    "mrs {save}, CPSR\norr {temp}, {save}, {bits}\nmsr CPSR_c, {temp}\n"
+  ))));
+  out_buffer.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+  extend_concat_as_lines(&mut out_buffer, body.stream());
+  out_buffer.push(TokenTree::Literal(Literal::string(&format!(
+    "msr CPSR_c, {save}\n"
+  ))));
+
+  let concat_expr = vec![
+    TokenTree::Ident(Ident::new("concat", Span::call_site())),
+    TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+    TokenTree::Group(Group::new(
+      Delimiter::Parenthesis,
+      TokenStream::from_iter(out_buffer),
+    )),
+  ];
+  Ok(TokenStream::from_iter(concat_expr))
+}
@@ -0,0 +1,127 @@
+use super::*;
+
+pub fn with_reg_aliases_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let trees: Vec<TokenTree> = token_stream.into_iter().collect();
+  let semi = trees
+    .iter()
+    .position(|tree| matches!(tree, TokenTree::Punct(p) if *p == ';'))
+    .ok_or_else(|| {
+      Error::new(
+        args_span(&trees),
+        "expected `;` between the aliases and the body lines",
+      )
+    })?;
+  let alias_trees = TokenStream::from_iter(trees[..semi].iter().cloned());
+  let body = TokenStream::from_iter(trees[semi + 1..].iter().cloned());
+
+  let mut errors = Errors::default();
+  let mut aliases: Vec<(String, String)> = Vec::new();
+  let alias_args = split_args(alias_trees);
+  if alias_args.is_empty() {
+    return Err(Error::new(
+      args_span(&trees[..=semi]),
+      "expected at least one `\"alias\" = \"reg\"` before the `;`",
+    ));
+  }
+  for arg in alias_args {
+    let Some((alias, reg)) = errors.check(alias_pair(&arg)) else {
+      continue;
+    };
+    // GAS also defines the all upper and all lower case forms of an alias, so
+    // aliases that differ only by case are the same alias.
+    if aliases.iter().any(|(a, _)| a.eq_ignore_ascii_case(&alias)) {
+      errors.push(Error::new(
+        args_span(&arg),
+        format!("the alias `{alias}` is given more than once"),
+      ));
+      continue;
+    }
+    aliases.push((alias, reg));
+  }
+  errors.finish()?;
+
+  let req_lines: String = aliases
+    .iter()
+    .map(|(alias, reg)| format!("{alias} .req {reg}\n"))
+    .collect();
+  let unreq_lines: String = aliases
+    .iter()
+    .rev()
+    .map(|(alias, _)| format!(".unreq {alias}\n"))
+    .collect();
+
+  let mut out_buffer: Vec<TokenTree> = Vec::new();
+  out_buffer.push(TokenTree::Literal(Literal::string(&req_lines)));
+  out_buffer.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+  extend_concat_as_lines(&mut out_buffer, body);
+  out_buffer.push(TokenTree::Literal(Literal::string(&unreq_lines)));
+
+  let concat_expr = vec![
+    TokenTree::Ident(Ident::new("concat", Span::call_site())),
+    TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+    TokenTree::Group(Group::new(
+      Delimiter::Parenthesis,
+      TokenStream::from_iter(out_buffer),
+    )),
+  ];
+  Ok(TokenStream::from_iter(concat_expr))
+}
+
+/// Gets the alias and register of a `"alias" = "reg"` argument.
+fn alias_pair(arg: &[TokenTree]) -> Result<(String, String), Error> {
+  let [alias_tree, TokenTree::Punct(eq), reg @ ..] = arg else {
+    return Err(Error::new(
+      args_span(arg),
+      "aliases must be of the form `\"alias\" = \"reg\"`",
+    ));
+  };
+  if *eq != '=' {
+    return Err(Error::new(
+      eq.span(),
+      "aliases must be of the form `\"alias\" = \"reg\"`",
+    ));
+  }
+  let alias = get_str_literal_content(alias_tree).ok_or_else(|| {
+    Error::new(alias_tree.span(), "the alias must be a string literal")
+  })?;
+  let is_ident = alias
+    .starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+    && alias.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+  if !is_ident {
+    return Err(Error::new(
+      alias_tree.span(),
+      format!("`{alias}` is not a valid alias name"),
+    ));
+  }
+  if parse_reg(&alias).is_some() || is_apcs_reg_name(&alias) {
+    return Err(Error::new(
+      alias_tree.span(),
+      format!("`{alias}` is already a register name"),
+    ));
+  }
+  let reg = get_reg_arg(&alias, reg)?;
+  Ok((alias, reg))
+}
+
+/// Checks for the APCS register names (`a1`-`a4` and `v1`-`v8`), which the
+/// assembler knows even though they aren't used elsewhere in bracer.
+fn is_apcs_reg_name(text: &str) -> bool {
+  let text = text.to_ascii_lowercase();
+  matches!(
+    text.as_str(),
+    "a1"
+      | "a2"
+      | "a3"
+      | "a4"
+      | "v1"
+      | "v2"
+      | "v3"
+      | "v4"
+      | "v5"
+      | "v6"
+      | "v7"
+      | "v8"
+  )
+}
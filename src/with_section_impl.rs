@@ -0,0 +1,68 @@
+use super::*;
+
+pub fn with_section_impl(
+  token_stream: TokenStream,
+) -> Result<TokenStream, Error> {
+  let mut stream_iter = token_stream.into_iter();
+  let name_tree =
+    stream_iter.next().ok_or_else(|| Error::call_site(NOT_ENOUGH_INPUT))?;
+  let section_name = get_str_literal_content(&name_tree).ok_or_else(|| {
+    Error::new(
+      name_tree.span(),
+      "the first argument must be the section name, as a string literal",
+    )
+  })?;
+  if let Some(problem) = section_name_problem(&section_name) {
+    return Err(Error::new(
+      name_tree.span(),
+      format!("`{section_name}` is not a valid section name: {problem}"),
+    ));
+  }
+  match stream_iter.next() {
+    None => (),
+    Some(TokenTree::Punct(p)) if p == ',' => (),
+    Some(other) => {
+      return Err(Error::new(
+        other.span(),
+        "must have comma after the section name",
+      ))
+    }
+  }
+
+  let (options, token_stream) =
+    take_options(stream_iter.collect(), &["flags", "section_type"]);
+  let mut flags = String::from("ax");
+  let mut section_type = String::from("%progbits");
+  for (name, value) in &options {
+    let text = get_str_literal_content(value).ok_or_else(|| {
+      Error::new(value.span(), format!("`{name}` must be a string literal"))
+    })?;
+    if name.to_string() == "flags" {
+      flags = check_section_flags(&text, value.span())?.to_string();
+    } else {
+      section_type = check_section_type(&text, value.span())?.to_string();
+    }
+  }
+
+  let mut out_buffer: Vec<TokenTree> = Vec::new();
+  out_buffer.push(str_literal_at(
+    &format!(".pushsection {section_name},\"{flags}\",{section_type}\n"),
+    name_tree.span(),
+  ));
+  out_buffer.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+
+  extend_concat_as_lines(&mut out_buffer, token_stream);
+  // the above fn always leaves a trailing comma, no need for a secondary check.
+  out_buffer.push(TokenTree::Literal(Literal::string(".popsection\n")));
+
+  let concat_expr = vec![
+    TokenTree::Ident(Ident::new("concat", Span::call_site())),
+    TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+    TokenTree::Group(Group::new(
+      Delimiter::Parenthesis,
+      TokenStream::from_iter(out_buffer),
+    )),
+  ];
+
+  Ok(TokenStream::from_iter(concat_expr))
+}
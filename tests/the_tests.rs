@@ -1,6 +1,26 @@
 use bracer::{
-  a32_fake_blx, a32_read_spsr_to, a32_set_cpu_control, a32_write_spsr_from,
-  put_fn_in_section, t32_with_a32_scope, when,
+  __bracer_error_of, __bracer_expand_to_str, __bracer_span_of,
+  a32_change_cpu_control, a32_fake_blx, a32_fn_header, a32_get_cpu_mode,
+  a32_read_cpsr_to, a32_read_spsr_to, a32_set_cpu_control,
+  a32_set_interrupt_masks, a32_with_mode, a32_write_cpsr_from,
+  a32_write_spsr_from, adjust_ptr, align_dispatch, arch_directive, asciz,
+  asm_block, asm_consts, asm_fn, assert_code_size_le, assert_regions_closed,
+  assert_t16_ok, balign, bkpt_debug, byte_reverse, byte_reverse16, cfi_fn,
+  check_a32_imm, clz_emulate, cond_chain, counted_loop, cp15_read, cp15_write,
+  cpu_control_bits, def_asm_const, div_const_u32, do_while, emit_bytes,
+  emit_halfwords, emit_literal_pool, emit_words, exception_return,
+  extract_lane, fn_footer, gas_macro, in_section, incbin, insert_lane,
+  integrity_value, irp_each, irq_handler_epilogue, irq_handler_prologue,
+  jump_table, load_const, mmio_clear_bits, mmio_set_bits, mmio_toggle_bits,
+  mul_const, nested_irq_enter, nested_irq_exit, poll_with_timeout, prng_step,
+  put_fn_in_section, reg_abs, reg_max, reg_min, region_begin, region_end,
+  reserve_zeroed, sat_add_s32, sat_sub_s32, set_mode_stacks, set_symbol,
+  size_marker, svc_reentry_restore, svc_reentry_save, swap_regs, swi_call,
+  swp_swap, symbol_visibility, t32_execute_a32, t32_fake_blx, t32_fn_header,
+  t32_with_a32_scope, task_frame_offsets, task_restore, task_save, unless,
+  vector_table, weak_symbol, when, when_bit, when_cond, when_flags, while_loop,
+  with_integrity_word, with_masked_interrupts, with_pool, with_reg_aliases,
+  with_section,
 };
 
 #[test]
@@ -8,7 +28,39 @@ fn test_a32_read_spsr_to() {
   assert_eq!(a32_read_spsr_to!("r0"), "mrs r0, SPSR");
   assert_eq!(a32_read_spsr_to!("R0"), "mrs R0, SPSR");
   assert_eq!(a32_read_spsr_to!("lr"), "mrs lr, SPSR");
+  assert_eq!(a32_read_spsr_to!("r12"), "mrs r12, SPSR");
+  // register names can also be idents
+  assert_eq!(a32_read_spsr_to!(r0), "mrs r0, SPSR");
+  assert_eq!(a32_read_spsr_to!(lr), "mrs lr, SPSR");
+  assert_eq!(
+    __bracer_error_of!(a32_read_spsr_to!(r0 r1)),
+    "Provide one register only, as a string literal or a register name."
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_read_spsr_to!(temp)),
+    "`temp` is not a register name or an `asm!` substitution like `{temp}`"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_read_spsr_to!("pc")),
+    "can't read SPSR into `pc`, using `sp` or `pc` with `mrs` is unpredictable"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_read_spsr_to!("R13")),
+    "can't read SPSR into `R13`, using `sp` or `pc` with `mrs` is unpredictable"
+  );
   assert_eq!(a32_read_spsr_to!("{temp}"), "mrs {temp}, SPSR");
+  assert_eq!(a32_read_spsr_to!(r"r0"), "mrs r0, SPSR");
+
+  // escapes are undone before the text is checked and used
+  assert_eq!(a32_read_spsr_to!("\u{72}0"), "mrs r0, SPSR");
+  assert_eq!(
+    __bracer_error_of!(a32_read_spsr_to!("\"r0\"")),
+    "`\"r0\"` is not a register name or an `asm!` substitution like `{temp}`"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_read_spsr_to!("\tr0")),
+    "`\tr0` is not a register name or an `asm!` substitution like `{temp}`"
+  );
 
   unsafe {
     core::arch::asm!(
@@ -21,12 +73,147 @@ fn test_a32_read_spsr_to() {
   }
 }
 
+#[test]
+fn test_a32_read_cpsr_to() {
+  assert_eq!(a32_read_cpsr_to!("r0"), "mrs r0, CPSR");
+  assert_eq!(a32_read_cpsr_to!("R0"), "mrs R0, CPSR");
+  assert_eq!(a32_read_cpsr_to!("lr"), "mrs lr, CPSR");
+  assert_eq!(a32_read_cpsr_to!(r12), "mrs r12, CPSR");
+  assert_eq!(a32_read_cpsr_to!("{temp}"), "mrs {temp}, CPSR");
+  assert_eq!(
+    __bracer_error_of!(a32_read_cpsr_to!(r0 r1)),
+    "Provide one register only, as a string literal or a register name."
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_read_cpsr_to!(temp)),
+    "`temp` is not a register name or an `asm!` substitution like `{temp}`"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_read_cpsr_to!("pc")),
+    "can't read CPSR into `pc`, using `sp` or `pc` with `mrs` is unpredictable"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_read_cpsr_to!("sp")),
+    "can't read CPSR into `sp`, using `sp` or `pc` with `mrs` is unpredictable"
+  );
+
+  unsafe {
+    core::arch::asm!(
+      // rustfmt stop making this one line
+      "/*",
+      a32_read_cpsr_to!("r0"),
+      "*/",
+      options(nostack)
+    )
+  }
+}
+
+#[test]
+fn test_a32_get_cpu_mode() {
+  assert_eq!(a32_get_cpu_mode!("r0"), "mrs r0, CPSR\nand r0, r0, #0x1F");
+  assert_eq!(a32_get_cpu_mode!(r12), "mrs r12, CPSR\nand r12, r12, #0x1F");
+  assert_eq!(
+    a32_get_cpu_mode!("{mode}"),
+    "mrs {mode}, CPSR\nand {mode}, {mode}, #0x1F"
+  );
+  // the mode bits line up with `cpu_control_bits!`
+  assert_eq!(cpu_control_bits!(sys) & 0x1F, 0b11111);
+  assert_eq!(
+    __bracer_error_of!(a32_get_cpu_mode!("sp")),
+    "can't read CPSR into `sp`, using `sp` or `pc` with `mrs` is unpredictable"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_get_cpu_mode!("r16")),
+    "`r16` is not a register name or an `asm!` substitution like `{temp}`"
+  );
+
+  unsafe {
+    core::arch::asm!(
+      // rustfmt stop making this one line
+      "/*",
+      a32_get_cpu_mode!("r0"),
+      "*/",
+      options(nostack)
+    )
+  }
+}
+
 #[test]
 fn test_a32_write_spsr_from() {
-  assert_eq!(a32_write_spsr_from!("r0"), "msr SPSR, r0");
-  assert_eq!(a32_write_spsr_from!("R0"), "msr SPSR, R0");
-  assert_eq!(a32_write_spsr_from!("lr"), "msr SPSR, lr");
-  assert_eq!(a32_write_spsr_from!("{temp}"), "msr SPSR, {temp}");
+  assert_eq!(a32_write_spsr_from!("r0"), "msr SPSR_cxsf, r0");
+  assert_eq!(a32_write_spsr_from!("R0"), "msr SPSR_cxsf, R0");
+  assert_eq!(a32_write_spsr_from!("lr"), "msr SPSR_cxsf, lr");
+  assert_eq!(a32_write_spsr_from!("{temp}"), "msr SPSR_cxsf, {temp}");
+  assert_eq!(a32_write_spsr_from!("r12"), "msr SPSR_cxsf, r12");
+
+  // only some fields
+  assert_eq!(a32_write_spsr_from!("r0", "cf"), "msr SPSR_cf, r0");
+  assert_eq!(a32_write_spsr_from!(r0, "cf"), "msr SPSR_cf, r0");
+  assert_eq!(a32_write_spsr_from!(R12), "msr SPSR_cxsf, R12");
+  assert_eq!(a32_write_spsr_from!("r0", "fc"), "msr SPSR_cf, r0");
+  assert_eq!(a32_write_spsr_from!("r0", "f"), "msr SPSR_f, r0");
+  assert_eq!(a32_write_spsr_from!("r0", "sfxc"), "msr SPSR_cxsf, r0");
+  assert_eq!(
+    __bracer_error_of!(a32_write_spsr_from!("r0", "cc")),
+    "the `c` field is given twice"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_write_spsr_from!("r0", "cg")),
+    "'g' is not a field, expected some of `c`, `x`, `s`, `f`"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_write_spsr_from!("r0", "")),
+    "the fields can't be empty"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_write_spsr_from!("r0", "c", "f")),
+    "expected a register, and optionally the SPSR fields to write"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_write_spsr_from!("sp")),
+    "can't write SPSR from `sp`, using `sp` or `pc` with `msr` is unpredictable"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_write_spsr_from!("r15")),
+    "can't write SPSR from `r15`, using `sp` or `pc` with `msr` is \
+     unpredictable"
+  );
+}
+
+#[test]
+fn test_a32_write_cpsr_from() {
+  assert_eq!(a32_write_cpsr_from!("r0"), "msr CPSR_cxsf, r0");
+  assert_eq!(a32_write_cpsr_from!(lr), "msr CPSR_cxsf, lr");
+  assert_eq!(a32_write_cpsr_from!("{saved}"), "msr CPSR_cxsf, {saved}");
+
+  // only some fields
+  assert_eq!(a32_write_cpsr_from!("r0", "f"), "msr CPSR_f, r0");
+  assert_eq!(a32_write_cpsr_from!("r0", "c"), "msr CPSR_c, r0");
+  assert_eq!(a32_write_cpsr_from!("r0", "fc"), "msr CPSR_cf, r0");
+  assert_eq!(
+    __bracer_error_of!(a32_write_cpsr_from!("r0", "flags")),
+    "'l' is not a field, expected some of `c`, `x`, `s`, `f`"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_write_cpsr_from!("r0", "ff")),
+    "the `f` field is given twice"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_write_cpsr_from!("r0", "")),
+    "the fields can't be empty"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_write_cpsr_from!()),
+    "expected a register, and optionally the CPSR fields to write"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_write_cpsr_from!("pc")),
+    "can't write CPSR from `pc`, using `sp` or `pc` with `msr` is unpredictable"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_write_cpsr_from!("x0")),
+    "`x0` is not a register name or an `asm!` substitution like `{temp}`"
+  );
 }
 
 #[test]
@@ -34,6 +221,85 @@ fn test_a32_fake_blx() {
   let expected = concat!("add lr, pc, #0\n", "bx r12",);
   let actual = a32_fake_blx!("r12");
   assert_eq!(expected, actual);
+  assert_eq!(a32_fake_blx!("R0"), "add lr, pc, #0\nbx R0");
+  assert_eq!(a32_fake_blx!(r0), "add lr, pc, #0\nbx r0");
+
+  // newer architectures have a real `blx`
+  assert_eq!(a32_fake_blx!("r2", arch = v4), "add lr, pc, #0\nbx r2");
+  assert_eq!(a32_fake_blx!("r2", arch = v4t), "add lr, pc, #0\nbx r2");
+  assert_eq!(a32_fake_blx!("r2", arch = v5t), "blx r2");
+  assert_eq!(a32_fake_blx!(r2, arch = v5te), "blx r2");
+  assert_eq!(a32_fake_blx!("{target}", arch = v7), "blx {target}");
+  assert_eq!(a32_fake_blx!(lr, arch = v5te), "blx lr");
+  assert_eq!(
+    __bracer_error_of!(a32_fake_blx!("r2", arch = v5)),
+    "unknown arch `v5`, expected one of: v4, v4t, v5t, v5te, v6, v6t2, v7"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_fake_blx!("r2", target = v5te)),
+    "unknown argument `target`"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_fake_blx!("pc", arch = v5te)),
+    "`pc` can't be the target, it's the program counter, not a code address"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_fake_blx!(r0,)),
+    "Provide one register only, as a string literal or a register name."
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_fake_blx!(lr)),
+    "`lr` can't be the target, `lr` is overwritten with the return address \
+     before the `bx`"
+  );
+  assert_eq!(a32_fake_blx!("ip"), "add lr, pc, #0\nbx ip");
+  assert_eq!(a32_fake_blx!("{target}"), "add lr, pc, #0\nbx {target}");
+
+  assert_eq!(
+    __bracer_error_of!(a32_fake_blx!("lr")),
+    "`lr` can't be the target, `lr` is overwritten with the return address \
+     before the `bx`"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_fake_blx!("R14")),
+    "`R14` can't be the target, `lr` is overwritten with the return address \
+     before the `bx`"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_fake_blx!("SP")),
+    "`SP` can't be the target, it's the stack pointer, not a code address"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_fake_blx!("r15")),
+    "`r15` can't be the target, it's the program counter, not a code address"
+  );
+}
+
+#[test]
+fn test_t32_fake_blx() {
+  let expected = "bl .+8\nbl .+6\nbx r3";
+  assert_eq!(expected, t32_fake_blx!("r3"));
+  assert_eq!(expected, t32_fake_blx!(r3));
+  assert_eq!(t32_fake_blx!("r12"), "bl .+8\nbl .+6\nbx r12");
+  assert_eq!(t32_fake_blx!("{target}"), "bl .+8\nbl .+6\nbx {target}");
+
+  assert_eq!(
+    __bracer_error_of!(t32_fake_blx!("lr")),
+    "`lr` can't be the target, `lr` is overwritten with the return address \
+     before the `bx`"
+  );
+  assert_eq!(
+    __bracer_error_of!(t32_fake_blx!(sp)),
+    "`sp` can't be the target, it's the stack pointer, not a code address"
+  );
+  assert_eq!(
+    __bracer_error_of!(t32_fake_blx!("pc")),
+    "`pc` can't be the target, it's the program counter, not a code address"
+  );
+  assert_eq!(
+    __bracer_error_of!(t32_fake_blx!("r3", "r4")),
+    "Provide one register only, as a string literal or a register name."
+  );
 }
 
 #[test]
@@ -41,6 +307,165 @@ fn test_put_fn_in_section() {
   let expected = ".section .text._start,\"ax\",%progbits";
   let actual = put_fn_in_section!(".text._start");
   assert_eq!(expected, actual);
+
+  // raw strings work too
+  let expected = ".section .text.iwram,\"ax\",%progbits";
+  assert_eq!(expected, put_fn_in_section!(r".text.iwram"));
+  assert_eq!(expected, put_fn_in_section!(r#".text.iwram"#));
+  assert_eq!(expected, put_fn_in_section!(r##".text.iwram"##));
+
+  // other allowed characters, including a leading `.` or `_`
+  assert_eq!(
+    put_fn_in_section!(".text.my_fn-2$x"),
+    ".section .text.my_fn-2$x,\"ax\",%progbits"
+  );
+  assert_eq!(put_fn_in_section!("_iwram"), ".section _iwram,\"ax\",%progbits");
+
+  assert_eq!(
+    __bracer_error_of!(put_fn_in_section!("foo\",\"aw")),
+    "`foo\",\"aw` is not a valid section name: '\"' isn't allowed, only ascii \
+     alphanumerics and `.`, `_`, `-`, `$`"
+  );
+  assert_eq!(
+    __bracer_error_of!(put_fn_in_section!(".text foo")),
+    "`.text foo` is not a valid section name: ' ' isn't allowed, only ascii \
+     alphanumerics and `.`, `_`, `-`, `$`"
+  );
+  assert_eq!(
+    __bracer_error_of!(put_fn_in_section!("")),
+    "`` is not a valid section name: it can't be empty"
+  );
+  assert_eq!(
+    __bracer_error_of!(put_fn_in_section!("1text")),
+    "`1text` is not a valid section name: it can't start with a digit"
+  );
+
+  // flags and a type can be given too
+  assert_eq!(
+    put_fn_in_section!(".iwram.buffer", "aw"),
+    ".section .iwram.buffer,\"aw\",%progbits"
+  );
+  assert_eq!(
+    put_fn_in_section!(".persist", "aw", "%nobits"),
+    ".section .persist,\"aw\",%nobits"
+  );
+  assert_eq!(
+    put_fn_in_section!(".rodata.str", "aMS", "%progbits",),
+    ".section .rodata.str,\"aMS\",%progbits"
+  );
+  assert_eq!(
+    put_fn_in_section!(".note.thing", "", "%note"),
+    ".section .note.thing,\"\",%note"
+  );
+  assert_eq!(
+    __bracer_error_of!(put_fn_in_section!(".data", "aq")),
+    "'q' isn't a known section flag, expected some of `a`, `w`, `x`, `M`, \
+     `S`, `T`"
+  );
+  assert_eq!(
+    __bracer_error_of!(put_fn_in_section!(".data", "awa")),
+    "the `a` flag is given twice"
+  );
+  assert_eq!(
+    __bracer_error_of!(put_fn_in_section!(".bss", "aw", "@nobits")),
+    "`@nobits` isn't a known section type, expected `%progbits`, `%nobits`, \
+     or `%note`"
+  );
+  assert_eq!(
+    __bracer_error_of!(put_fn_in_section!(".bss", "aw", "%nobits", "x")),
+    "too many arguments, expected the section name, flags, and type"
+  );
+  assert_eq!(
+    __bracer_error_of!(put_fn_in_section!(".bss", aw)),
+    "Provide string literals only: the section name, then optionally the \
+     flags and the type."
+  );
+}
+
+#[test]
+fn test_with_section() {
+  let expected = concat!(
+    ".pushsection .iwram,\"ax\",%progbits\n",
+    "add r0, r0, r0\n",
+    "mrs r0, SPSR\n",
+    ".popsection\n",
+  );
+  let actual = __bracer_expand_to_str!(with_section!(
+    ".iwram",
+    "add r0, r0, r0",
+    a32_read_spsr_to!("r0"),
+  ));
+  assert_eq!(expected, actual);
+
+  // an empty body still pushes and pops
+  assert_eq!(
+    with_section!(".iwram"),
+    ".pushsection .iwram,\"ax\",%progbits\n.popsection\n"
+  );
+  assert_eq!(
+    with_section!(".iwram",),
+    ".pushsection .iwram,\"ax\",%progbits\n.popsection\n"
+  );
+
+  // flags and a type can be given too
+  assert_eq!(
+    with_section!(
+      ".persist",
+      flags = "aw",
+      section_type = "%nobits",
+      ".space 4"
+    ),
+    ".pushsection .persist,\"aw\",%nobits\n.space 4\n.popsection\n"
+  );
+  assert_eq!(
+    with_section!(".iwram.data", flags = "aw", ".word 0"),
+    ".pushsection .iwram.data,\"aw\",%progbits\n.word 0\n.popsection\n"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(with_section!("1iwram", "nop")),
+    "`1iwram` is not a valid section name: it can't start with a digit"
+  );
+  assert_eq!(
+    __bracer_error_of!(with_section!(".iwram", flags = "ay", "nop")),
+    "'y' isn't a known section flag, expected some of `a`, `w`, `x`, `M`, \
+     `S`, `T`"
+  );
+  assert_eq!(
+    __bracer_error_of!(with_section!(".bss", section_type = "%bits", "nop")),
+    "`%bits` isn't a known section type, expected `%progbits`, `%nobits`, or \
+     `%note`"
+  );
+  assert_eq!(
+    __bracer_error_of!(with_section!(iwram, "nop")),
+    "the first argument must be the section name, as a string literal"
+  );
+}
+
+#[in_section(".text.bracer_in_section")]
+fn in_section_plain() -> u32 {
+  1
+}
+
+#[in_section(r".text.bracer_in_section")]
+/// doc comments and other attributes are fine.
+#[inline(never)]
+pub(crate) const unsafe extern "C" fn in_section_qualified() -> u32 {
+  2
+}
+
+#[cfg(target_arch = "arm")]
+#[in_section(".text.bracer_in_section", a32)]
+fn in_section_a32() -> u32 {
+  3
+}
+
+#[test]
+fn test_in_section() {
+  assert_eq!(in_section_plain(), 1);
+  assert_eq!(unsafe { in_section_qualified() }, 2);
+  #[cfg(target_arch = "arm")]
+  assert_eq!(in_section_a32(), 3);
 }
 
 #[test]
@@ -69,6 +494,328 @@ fn test_a32_set_cpu_control() {
   let actual =
     a32_set_cpu_control!(Supervisor, irq_masked = true, fiq_masked = false);
   assert_eq!(expected, actual);
+
+  assert_eq!(
+    a32_set_cpu_control!(Abort, irq_masked = false, fiq_masked = false),
+    "msr CPSR_c, #0b00010111"
+  );
+  assert_eq!(
+    a32_set_cpu_control!(Abort, irq_masked = true, fiq_masked = false),
+    "msr CPSR_c, #0b10010111"
+  );
+  assert_eq!(
+    a32_set_cpu_control!(abt, irq_masked = false, fiq_masked = true),
+    "msr CPSR_c, #0b01010111"
+  );
+  assert_eq!(
+    a32_set_cpu_control!(abt, irq_masked = true, fiq_masked = true),
+    "msr CPSR_c, #0b11010111"
+  );
+
+  assert_eq!(
+    a32_set_cpu_control!(Undefined, irq_masked = false, fiq_masked = false),
+    "msr CPSR_c, #0b00011011"
+  );
+  assert_eq!(
+    a32_set_cpu_control!(Undefined, irq_masked = true, fiq_masked = false),
+    "msr CPSR_c, #0b10011011"
+  );
+  assert_eq!(
+    a32_set_cpu_control!(und, irq_masked = false, fiq_masked = true),
+    "msr CPSR_c, #0b01011011"
+  );
+  assert_eq!(
+    a32_set_cpu_control!(und, irq_masked = true, fiq_masked = true),
+    "msr CPSR_c, #0b11011011"
+  );
+}
+
+#[test]
+fn test_a32_set_cpu_control_errors() {
+  // every problem is reported, not just the first one.
+  assert_eq!(
+    __bracer_error_of!(a32_set_cpu_control!(
+      Hyp,
+      irq_masked = true,
+      fiq_masked = maybe
+    )),
+    concat!(
+      "First argument must be a valid cpu mode name, got `Hyp`, expected one ",
+      "of: `User`/`usr`, `FIQ`/`fiq`, `IRQ`/`irq`, `Supervisor`/`svc`, ",
+      "`Abort`/`abt`, `Undefined`/`und`, `System`/`sys`\n",
+      "`fiq_masked` must be set as `true` or `false`",
+    )
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_set_cpu_control!(
+      System irq_masked = yes,
+      fiq_masked = false
+    )),
+    concat!(
+      "must have comma after the first arg\n",
+      "`irq_masked` must be set as `true` or `false`",
+    )
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_set_cpu_control!(
+      System,
+      irq_mask = true,
+      fiq_masked = false,
+      extra
+    )),
+    concat!(
+      "unknown argument `irq_mask`\n",
+      "arguments must be of the form `name = value`"
+    )
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_set_cpu_control!(svc, irq_masked true, fiq_masked)),
+    concat!(
+      "arguments must be of the form `name = value`\n",
+      "arguments must be of the form `name = value`"
+    )
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_set_cpu_control!(
+      svc,
+      fiq_masked = true,
+      irq_masked = false,
+      fiq_masked = false
+    )),
+    "`fiq_masked` is given twice"
+  );
+}
+
+#[test]
+fn test_a32_set_cpu_control_named_args() {
+  // the settings can be in either order.
+  assert_eq!(
+    a32_set_cpu_control!(System, fiq_masked = true, irq_masked = false),
+    a32_set_cpu_control!(System, irq_masked = false, fiq_masked = true),
+  );
+  // an omitted setting defaults to masked.
+  assert_eq!(a32_set_cpu_control!(IRQ), "msr CPSR_c, #0b11010010");
+  assert_eq!(
+    a32_set_cpu_control!(IRQ),
+    a32_set_cpu_control!(IRQ, irq_masked = true, fiq_masked = true),
+  );
+  assert_eq!(
+    a32_set_cpu_control!(svc, fiq_masked = false),
+    "msr CPSR_c, #0b10010011"
+  );
+  assert_eq!(
+    a32_set_cpu_control!(sys, irq_masked = false,),
+    "msr CPSR_c, #0b01011111"
+  );
+}
+
+#[test]
+fn test_a32_change_cpu_control() {
+  // a mode clears the mode bits it doesn't have, then sets the ones it does.
+  assert_eq!(
+    a32_change_cpu_control!("r3", mode = FIQ),
+    "mrs r3, CPSR\nbic r3, r3, #0xE\norr r3, r3, #0x11\nmsr CPSR_c, r3"
+  );
+  assert_eq!(
+    a32_change_cpu_control!(r3, mode = svc),
+    "mrs r3, CPSR\nbic r3, r3, #0xC\norr r3, r3, #0x13\nmsr CPSR_c, r3"
+  );
+  // System mode is all of the mode bits, so there's nothing to clear.
+  assert_eq!(
+    a32_change_cpu_control!("r3", mode = System),
+    "mrs r3, CPSR\norr r3, r3, #0x1F\nmsr CPSR_c, r3"
+  );
+  assert_eq!(
+    a32_change_cpu_control!("{tmp}", irq_masked = true),
+    "mrs {tmp}, CPSR\norr {tmp}, {tmp}, #0x80\nmsr CPSR_c, {tmp}"
+  );
+  assert_eq!(
+    a32_change_cpu_control!("{tmp}", irq_masked = false),
+    "mrs {tmp}, CPSR\nbic {tmp}, {tmp}, #0x80\nmsr CPSR_c, {tmp}"
+  );
+  assert_eq!(
+    a32_change_cpu_control!("r0", fiq_masked = true),
+    "mrs r0, CPSR\norr r0, r0, #0x40\nmsr CPSR_c, r0"
+  );
+  assert_eq!(
+    a32_change_cpu_control!("r0", fiq_masked = false),
+    "mrs r0, CPSR\nbic r0, r0, #0x40\nmsr CPSR_c, r0"
+  );
+  assert_eq!(
+    a32_change_cpu_control!("r0", fiq_masked = false, irq_masked = true),
+    "mrs r0, CPSR\nbic r0, r0, #0x40\norr r0, r0, #0x80\nmsr CPSR_c, r0"
+  );
+  assert_eq!(
+    a32_change_cpu_control!("r0", irq_masked = false, fiq_masked = false),
+    "mrs r0, CPSR\nbic r0, r0, #0xC0\nmsr CPSR_c, r0"
+  );
+  assert_eq!(
+    a32_change_cpu_control!(
+      "r0",
+      mode = User,
+      irq_masked = false,
+      fiq_masked = true
+    ),
+    "mrs r0, CPSR\nbic r0, r0, #0x8F\norr r0, r0, #0x50\nmsr CPSR_c, r0"
+  );
+  assert_eq!(
+    a32_change_cpu_control!(
+      "r0",
+      irq_masked = true,
+      fiq_masked = true,
+      mode = und
+    ),
+    "mrs r0, CPSR\nbic r0, r0, #0x4\norr r0, r0, #0xDB\nmsr CPSR_c, r0"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(a32_change_cpu_control!("r3")),
+    "nothing to change, give at least one of `mode`, `irq_masked`, or \
+     `fiq_masked`"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_change_cpu_control!("sp", mode = FIQ)),
+    "can't use `sp` as the scratch register, using `sp` or `pc` with \
+     `mrs`/`msr` is unpredictable"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_change_cpu_control!(
+      "r3",
+      mode = Hyp,
+      mode = FIQ,
+      irq = true
+    )),
+    concat!(
+      "`mode` must be one of: `User`/`usr`, `FIQ`/`fiq`, `IRQ`/`irq`, ",
+      "`Supervisor`/`svc`, `Abort`/`abt`, `Undefined`/`und`, `System`/`sys`\n",
+      "`mode` is given twice\n",
+      "unknown argument `irq`"
+    )
+  );
+}
+
+#[test]
+fn test_a32_set_interrupt_masks() {
+  assert_eq!(
+    a32_set_interrupt_masks!("r2", irq_masked = true, fiq_masked = false),
+    "mrs r2, CPSR\nbic r2, r2, #0x40\norr r2, r2, #0x80\nmsr CPSR_c, r2"
+  );
+  assert_eq!(
+    a32_set_interrupt_masks!(r2, fiq_masked = true, irq_masked = false),
+    "mrs r2, CPSR\nbic r2, r2, #0x80\norr r2, r2, #0x40\nmsr CPSR_c, r2"
+  );
+  assert_eq!(
+    a32_set_interrupt_masks!("r2", irq_masked = true, fiq_masked = true),
+    "mrs r2, CPSR\norr r2, r2, #0xC0\nmsr CPSR_c, r2"
+  );
+  assert_eq!(
+    a32_set_interrupt_masks!("r2", irq_masked = false, fiq_masked = false),
+    "mrs r2, CPSR\nbic r2, r2, #0xC0\nmsr CPSR_c, r2"
+  );
+  assert_eq!(
+    a32_set_interrupt_masks!("{tmp}", irq_masked = keep, fiq_masked = true),
+    "mrs {tmp}, CPSR\norr {tmp}, {tmp}, #0x40\nmsr CPSR_c, {tmp}"
+  );
+  assert_eq!(
+    a32_set_interrupt_masks!("r2", irq_masked = false, fiq_masked = keep),
+    "mrs r2, CPSR\nbic r2, r2, #0x80\nmsr CPSR_c, r2"
+  );
+
+  // the mode bits (0x1F) are never part of a `bic` or `orr` mask.
+  for (expected_clear, expected_set, actual) in [
+    (
+      0x40,
+      0x80,
+      a32_set_interrupt_masks!("r2", irq_masked = true, fiq_masked = false),
+    ),
+    (
+      0xC0,
+      0,
+      a32_set_interrupt_masks!("r2", irq_masked = false, fiq_masked = false),
+    ),
+    (
+      0,
+      0xC0,
+      a32_set_interrupt_masks!("r2", irq_masked = true, fiq_masked = true),
+    ),
+  ] {
+    let mut clear = 0;
+    let mut set = 0;
+    for line in actual.lines() {
+      if let Some(mask) = line.strip_prefix("bic r2, r2, #0x") {
+        clear |= u32::from_str_radix(mask, 16).unwrap();
+      } else if let Some(mask) = line.strip_prefix("orr r2, r2, #0x") {
+        set |= u32::from_str_radix(mask, 16).unwrap();
+      }
+    }
+    assert_eq!((clear, set), (expected_clear, expected_set));
+    assert_eq!((clear | set) & 0x1F, 0);
+  }
+
+  assert_eq!(
+    __bracer_error_of!(a32_set_interrupt_masks!(
+      "r2",
+      irq_masked = keep,
+      fiq_masked = keep
+    )),
+    "both masks are `keep`, so there's nothing to change"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_set_interrupt_masks!(
+      "r2",
+      irq_masked = maybe,
+      mode = svc
+    )),
+    concat!(
+      "`irq_masked` must be set as `true`, `false`, or `keep`\n",
+      "unknown argument `mode`\n",
+      "missing `fiq_masked = true`, `false`, or `keep`"
+    )
+  );
+}
+
+#[test]
+fn test_cpu_control_bits() {
+  assert_eq!(
+    cpu_control_bits!(Supervisor, irq_masked = true, fiq_masked = false),
+    0b1001_0011
+  );
+  assert_eq!(cpu_control_bits!(IRQ), 0b1101_0010);
+  const ABT: u32 = cpu_control_bits!(abt, fiq_masked = false);
+  assert_eq!(ABT, 0b1001_0111);
+
+  // the two macros always agree.
+  macro_rules! check_agree {
+    ($($mode:ident),*) => {$(
+      for (expected, actual) in [
+        (
+          cpu_control_bits!($mode, irq_masked = false, fiq_masked = false),
+          a32_set_cpu_control!($mode, irq_masked = false, fiq_masked = false),
+        ),
+        (
+          cpu_control_bits!($mode, irq_masked = true, fiq_masked = false),
+          a32_set_cpu_control!($mode, irq_masked = true, fiq_masked = false),
+        ),
+        (
+          cpu_control_bits!($mode, irq_masked = false, fiq_masked = true),
+          a32_set_cpu_control!($mode, irq_masked = false, fiq_masked = true),
+        ),
+        (
+          cpu_control_bits!($mode, irq_masked = true, fiq_masked = true),
+          a32_set_cpu_control!($mode, irq_masked = true, fiq_masked = true),
+        ),
+      ] {
+        assert_eq!(format!("msr CPSR_c, #{expected:#010b}"), actual);
+      }
+    )*};
+  }
+  check_agree!(User, FIQ, IRQ, Supervisor, Abort, Undefined, System);
+  check_agree!(usr, fiq, irq, svc, abt, und, sys);
+
+  assert_eq!(
+    __bracer_error_of!(cpu_control_bits!(svc, irq_masked = maybe)),
+    "`irq_masked` must be set as `true` or `false`"
+  );
 }
 
 #[test]
@@ -88,8 +835,85 @@ fn test_t32_with_a32_scope() {
     )
   }
 
+  // a32 code after a single 16-bit t32 instruction has to be realigned first.
+  unsafe {
+    core::arch::asm!(
+      "/*",
+      "movs r0, #1",
+      t32_with_a32_scope!("add r0, r0, r0"),
+      "adds r0, r0, r0",
+      "*/",
+      options(nostack)
+    )
+  }
+  let expected =
+    "movs r0, #1\n.balignw 4, 0x46C0\n.code 32\nadd r0, r0, r0\n.code 16\nadds r0, r0, r0";
+  let actual = concat!(
+    "movs r0, #1\n",
+    t32_with_a32_scope!("add r0, r0, r0"),
+    "adds r0, r0, r0"
+  );
+  assert_eq!(expected, actual);
+
+  // the alignment can be left out when it's known to not be needed
+  let expected = ".code 32\nadd r0, r0, r0\n.code 16\n";
+  let actual = t32_with_a32_scope!(align = false, "add r0, r0, r0");
+  assert_eq!(expected, actual);
+  let expected = ".code 32\nmovw r0, #1234\n.code 16\n";
+  let actual = t32_with_a32_scope!(align = false, lint = a32, "movw r0, #1234");
+  assert_eq!(expected, actual);
+  assert_eq!(
+    __bracer_error_of!(t32_with_a32_scope!(align = no, "add r0, r0, r0")),
+    "`align` must be set as `true` or `false`"
+  );
+
+  // the scope can't be nested, and the input can't switch state itself
+  assert_eq!(
+    __bracer_error_of!(t32_with_a32_scope!(t32_with_a32_scope!("nop"))),
+    "`t32_with_a32_scope!` can't be nested inside `t32_with_a32_scope!`, the \
+     inner `.code 16` would put the rest of the outer scope back in t32"
+  );
+  assert_eq!(
+    __bracer_error_of!(t32_with_a32_scope!(
+      "mov r0, #1",
+      bracer::t32_with_a32_scope!("nop"),
+    )),
+    "`t32_with_a32_scope!` can't be nested inside `t32_with_a32_scope!`, the \
+     inner `.code 16` would put the rest of the outer scope back in t32"
+  );
+  assert_eq!(
+    __bracer_error_of!(t32_with_a32_scope!("mov r0, #1", ".code 16", "nop")),
+    "`.code 16` can't be used inside `t32_with_a32_scope!`, which already \
+     switches to a32 and back"
+  );
+  assert_eq!(
+    __bracer_error_of!(t32_with_a32_scope!("mov r0, #1; .thumb")),
+    "`.thumb` can't be used inside `t32_with_a32_scope!`, which already \
+     switches to a32 and back"
+  );
+  assert_eq!(
+    __bracer_error_of!(t32_with_a32_scope!(asm_block!(
+      start = t32,
+      end = a32,
+      { ".arm" }
+    ))),
+    "`.arm` can't be used inside `t32_with_a32_scope!`, which already \
+     switches to a32 and back"
+  );
+
+  // test that nested macros fold down to the expected text
+  let expected =
+    ".balignw 4, 0x46C0\n.code 32\nadd r0, r0, r0\nmrs r0, SPSR\n.code 16\n";
+  let actual = __bracer_expand_to_str!(t32_with_a32_scope!(
+    // rustfmt stop making this one line
+    "add r0, r0, r0",
+    a32_read_spsr_to!("r0"),
+  ));
+  assert_eq!(expected, actual);
+
   // test that 'multi-line' input works (where there's a comma on the end)
-  let expected = ".code 32\nmov r0, #0\nadd r0, r0, r0\n.code 16\n";
+  let expected =
+    ".balignw 4, 0x46C0\n.code 32\nmov r0, #0\nadd r0, r0, r0\n.code 16\n";
   let actual = t32_with_a32_scope!(
     // rustfmt stop making this one line
     "mov r0, #0",
@@ -98,36 +922,514 @@ fn test_t32_with_a32_scope() {
   assert_eq!(expected, actual);
 
   // test that 'one line' of input works (with no comma on the end)
-  let expected = ".code 32\nadd r0, r0, r0\n.code 16\n";
+  let expected = ".balignw 4, 0x46C0\n.code 32\nadd r0, r0, r0\n.code 16\n";
   let actual = t32_with_a32_scope!("add r0, r0, r0");
   assert_eq!(expected, actual);
 
   // test that the macro works on an empty input sequence.
-  let expected = ".code 32\n.code 16\n";
+  let expected = ".balignw 4, 0x46C0\n.code 32\n.code 16\n";
   let actual = t32_with_a32_scope!();
   assert_eq!(expected, actual);
+
+  // raw string lines work, including when linting
+  let expected = ".balignw 4, 0x46C0\n.code 32\nadd r0, r0, r0\n.code 16\n";
+  let actual = t32_with_a32_scope!(r"add r0, r0, r0");
+  assert_eq!(expected, actual);
+  assert_eq!(
+    __bracer_error_of!(t32_with_a32_scope!(lint = a32, r#"cbz r0, 1f"#)),
+    "`cbz r0, 1f` is not A32: `cbz` is Thumb-only"
+  );
+
+  // linting doesn't change the output
+  let expected =
+    ".balignw 4, 0x46C0\n.code 32\nmovs r0, r1, lsl #2\nmrs r0, CPSR\n.code 16\n";
+  let actual = t32_with_a32_scope!(
+    lint = a32,
+    arch = v4,
+    "movs r0, r1, lsl #2",
+    "mrs r0, CPSR",
+  );
+  assert_eq!(expected, actual);
+
+  // Thumb-only instructions are caught
+  assert_eq!(
+    __bracer_error_of!(t32_with_a32_scope!(lint = a32, "cbz r0, 1f", "1:")),
+    "`cbz r0, 1f` is not A32: `cbz` is Thumb-only"
+  );
+  assert_eq!(
+    __bracer_error_of!(t32_with_a32_scope!(lint = a32, "ite eq")),
+    "`ite eq` is not A32: `ite` is Thumb-only"
+  );
+
+  // newer instructions are only caught when an `arch` is given
+  let expected = ".balignw 4, 0x46C0\n.code 32\nmovw r0, #1234\n.code 16\n";
+  let actual = t32_with_a32_scope!(lint = a32, "movw r0, #1234");
+  assert_eq!(expected, actual);
+  assert_eq!(
+    __bracer_error_of!(t32_with_a32_scope!(
+      lint = a32,
+      arch = v4,
+      "movw r0, #1234"
+    )),
+    "`movw r0, #1234` is not A32: `movw` needs ARMv6T2 or later"
+  );
 }
 
 #[test]
-fn test_when() {
+fn test_t32_execute_a32() {
+  // test that the output works within an `asm!` invocation.
+  unsafe {
+    core::arch::asm!(
+      "/*",
+      "movs r0, #1",
+      t32_execute_a32!(
+        scratch = "r3",
+        "add r0, r0, r0",
+        a32_read_spsr_to!("r1"),
+      ),
+      "*/",
+      options(nostack)
+    )
+  }
+
   let expected = concat!(
-    "cmp r0, #0\n",
-    "beq 1f\n",
-    "add r1, r2, r3\n",
-    "add r0, r1, r4\n",
-    "1:\n"
+    ".balignw 4, 0x46C0\n",
+    "bx pc\n",
+    "nop\n",
+    ".code 32\n",
+    "add r0, r0, r0\n",
+    "mrs r1, SPSR\n",
+    "add r3, pc, #1\n",
+    "bx r3\n",
+    ".code 16\n",
   );
-  let actual = when!(("r0" != "#0")[1]{
-    "add r1, r2, r3",
-    "add r0, r1, r4",
-  });
+  let actual = __bracer_expand_to_str!(t32_execute_a32!(
+    scratch = "r3",
+    "add r0, r0, r0",
+    a32_read_spsr_to!("r1"),
+  ));
   assert_eq!(expected, actual);
 
-  // signed-ness doesn't matter
-  let _actual = when!(("r0" == "#0")[1]{
-    "add r1, r2, r3",
-    "add r0, r1, r4",
-  });
+  let expected = concat!(
+    ".balignw 4, 0x46C0\n",
+    "bx pc\n",
+    "nop\n",
+    ".code 32\n",
+    "movs r0, r1, lsl #2\n",
+    "add lr, pc, #1\n",
+    "bx lr\n",
+    ".code 16\n",
+  );
+  let actual = t32_execute_a32!(
+    scratch = lr,
+    lint = a32,
+    arch = v4,
+    "movs r0, r1, lsl #2"
+  );
+  assert_eq!(expected, actual);
+
+  assert_eq!(
+    __bracer_error_of!(t32_execute_a32!("add r0, r0, r0")),
+    "missing `scratch = \"rN\",`"
+  );
+  assert_eq!(
+    __bracer_error_of!(t32_execute_a32!(scratch = "pc", "add r0, r0, r0")),
+    "`scratch` can't be `pc`, it must be one of `r0`-`r12` or `lr`"
+  );
+  assert_eq!(
+    __bracer_error_of!(t32_execute_a32!(
+      scratch = "r3",
+      lint = a32,
+      "cbz r0, 1f"
+    )),
+    "`cbz r0, 1f` is not A32: `cbz` is Thumb-only"
+  );
+  assert_eq!(
+    __bracer_error_of!(t32_execute_a32!(
+      scratch = "r3",
+      t32_with_a32_scope!("nop")
+    )),
+    "`t32_with_a32_scope!` can't be nested inside `t32_execute_a32!`, the \
+     inner `.code 16` would put the rest of the outer scope back in t32"
+  );
+  assert_eq!(
+    __bracer_error_of!(t32_with_a32_scope!(t32_execute_a32!(
+      scratch = "r3",
+      "nop"
+    ))),
+    "`t32_execute_a32!` can't be nested inside `t32_with_a32_scope!`, the \
+     inner `.code 16` would put the rest of the outer scope back in t32"
+  );
+}
+
+// without a label the output is meant for `global_asm!`
+core::arch::global_asm!("/*", when!(("r0" != "#0"){ "nop" }), "*/");
+
+#[test]
+fn test_when() {
+  let expected = concat!(
+    "cmp r0, #0\n",
+    "beq 1f\n",
+    "add r1, r2, r3\n",
+    "add r0, r1, r4\n",
+    "1:\n"
+  );
+  let actual = when!(("r0" != "#0")[1]{
+    "add r1, r2, r3",
+    "add r0, r1, r4",
+  });
+  assert_eq!(expected, actual);
+
+  // raw strings work as the test operands
+  let actual = when!((r"r0" != r#"#0"#)[1]{
+    "add r1, r2, r3",
+    "add r0, r1, r4",
+  });
+  assert_eq!(expected, actual);
+
+  // an else block goes between the two labels
+  let expected = concat!(
+    "cmp r0, #0\n",
+    "bne 1f\n",
+    "mov r1, #5\n",
+    "b 2f\n",
+    "1:\n",
+    "mov r1, #9\n",
+    "2:\n"
+  );
+  let actual = when!(("r0" == "#0")[1][2]{
+    "mov r1, #5",
+  } else {
+    "mov r1, #9",
+  });
+  assert_eq!(expected, actual);
+  let expected = concat!(
+    "cmp r0, r1\n",
+    "bge 3f\n",
+    "mrs r2, SPSR\n",
+    "b 4f\n",
+    "3:\n",
+    "mov r2, #0\n",
+    "mrs r3, SPSR\n",
+    "4:\n"
+  );
+  let actual = __bracer_expand_to_str!(when!(("r0" < i "r1")[3][4]{
+    a32_read_spsr_to!(r2),
+  } else {
+    "mov r2, #0",
+    a32_read_spsr_to!(r3),
+  }));
+  assert_eq!(expected, actual);
+  assert_eq!(
+    when!(("r0" == "#0")[1][2]{} else {}),
+    "cmp r0, #0\nbne 1f\nb 2f\n1:\n2:\n"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" == "#0")[1]{ "nop" } else { "nop" })),
+    "an `else` block needs a second label group for the end, eg: `[1][2]`"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" == "#0")[1][2]{ "nop" })),
+    "a second label group is only used with an `else` block"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" == "#0")[1][1]{ "nop" } else { "nop" })),
+    "the end label must differ from the else label"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(lint = t16, ("r0" == "#0")[1][2]{
+      "nop",
+    } else {
+      "mrs r0, CPSR",
+    })),
+    "`mrs r0, CPSR` is not Thumb-1: `mrs` is A32-only"
+  );
+
+  // the labels can be left out, and then unique ones are generated
+  let actual = __bracer_expand_to_str!(concat!(
+    when!(("r0" != "#0"){ "add r1, r2, r3", }),
+    when!(("r0" != "#0"){ "add r1, r2, r3", }),
+    when!(("r0" == "#0"){ "mov r1, #5" } else { "mov r1, #9" }),
+  ));
+  let lines: Vec<&str> = actual.lines().collect();
+  let label_of = |line: &str, prefix: &str| {
+    let label = line.strip_prefix(prefix).unwrap();
+    assert!(label.starts_with(".L_bracer_local_label_"), "{label}");
+    label.to_string()
+  };
+  let first = label_of(lines[1], "beq ");
+  assert_eq!(lines[0], "cmp r0, #0");
+  assert_eq!(lines[2], "add r1, r2, r3");
+  assert_eq!(lines[3], format!("{first}:"));
+  let second = label_of(lines[5], "beq ");
+  assert_eq!(lines[7], format!("{second}:"));
+  let else_label = label_of(lines[9], "bne ");
+  assert_eq!(lines[10], "mov r1, #5");
+  let end_label = label_of(lines[11], "b ");
+  assert_eq!(lines[12], format!("{else_label}:"));
+  assert_eq!(lines[13], "mov r1, #9");
+  assert_eq!(lines[14], format!("{end_label}:"));
+  let mut labels = vec![first, second, else_label, end_label];
+  labels.sort();
+  labels.dedup();
+  assert_eq!(labels.len(), 4);
+  // generated labels are named, so `asm!` must allow them
+  #[allow(named_asm_labels)]
+  unsafe {
+    core::arch::asm!(
+      "/*",
+      when!(("r0" != "#0"){ "nop" }),
+      "*/",
+      options(nostack)
+    )
+  }
+
+  // compound tests, `&&` skips the body on any failure
+  let expected = concat!(
+    "cmp r0, #0\n",
+    "bne 1f\n",
+    "cmp r1, #0\n",
+    "beq 1f\n",
+    "mov r2, #1\n",
+    "1:\n"
+  );
+  let actual = when!(("r0" == "#0" && "r1" != "#0")[1]{ "mov r2, #1" });
+  assert_eq!(expected, actual);
+  let expected = concat!(
+    "cmp r0, #0\n",
+    "bne 1f\n",
+    "cmp r1, r2\n",
+    "bhs 1f\n",
+    "cmp r3, #7\n",
+    "ble 1f\n",
+    "mov r2, #1\n",
+    "b 2f\n",
+    "1:\n",
+    "mov r2, #0\n",
+    "2:\n"
+  );
+  let actual = when!(("r0" == "#0" && "r1" < u "r2" && "r3" > i "#7")[1][2]{
+    "mov r2, #1",
+  } else {
+    "mov r2, #0",
+  });
+  assert_eq!(expected, actual);
+
+  // and `||` goes to the body on any pass
+  // to the next label number up, so that it still works in `asm!`
+  assert_eq!(
+    when!(("r0" == "#0" || "r1" != "#0")[1]{ "mov r2, #1" }),
+    "cmp r0, #0\nbeq 2f\ncmp r1, #0\nbeq 1f\n2:\nmov r2, #1\n1:\n"
+  );
+  let expected = concat!(
+    "cmp r0, r1\n",
+    "bhs 8f\n",
+    "cmp r2, r3\n",
+    "ble 8f\n",
+    "cmp r4, #1\n",
+    "beq 7f\n",
+    "8:\n",
+    "mov r2, #1\n",
+    "7:\n",
+  );
+  let actual = when!(("r0" >= u "r1" || "r2" <= i "r3" || "r4" != "#1")[7]{
+    "mov r2, #1",
+  });
+  assert_eq!(expected, actual);
+  // with an `else` the end label is free to be the same number
+  let expected = concat!(
+    "cmp r0, #0\n",
+    "beq 2f\n",
+    "cmp r1, #0\n",
+    "bne 1f\n",
+    "2:\n",
+    "mov r2, #1\n",
+    "b 2f\n",
+    "1:\n",
+    "mov r2, #0\n",
+    "2:\n",
+  );
+  let actual = when!(("r0" == "#0" || "r1" == "#0")[1][2]{
+    "mov r2, #1",
+  } else {
+    "mov r2, #0",
+  });
+  assert_eq!(expected, actual);
+  assert_eq!(
+    when!(("r0" == "#0" || "r1" == "#0")[4294967295]{}),
+    "cmp r0, #0\nbeq 0f\ncmp r1, #0\nbne 4294967295f\n0:\n4294967295:\n"
+  );
+  unsafe {
+    core::arch::asm!(
+      "/*",
+      when!(("r0" == "#0" || "r1" == "#0")[2]{ "nop" }),
+      "*/",
+      options(nostack)
+    )
+  }
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" == "#0" && "r1" == "#0" || "r2" == "#0"){})),
+    "can't mix `&&` and `||` in one test, use nested `when!` instead"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" == "#0" &&){})),
+    "unknown test expression"
+  );
+
+  // the right operand can be a shifted register
+  let expected = "cmp r0, r1, lsl #2\nbhs 1f\nmov r2, #1\n1:\n";
+  let actual = when!(("r0" <u "r1, lsl #2")[1]{ "mov r2, #1" });
+  assert_eq!(expected, actual);
+  assert_eq!(
+    when!(("r0" == "r1, lsr #32")[1]{}),
+    "cmp r0, r1, lsr #32\nbne 1f\n1:\n"
+  );
+  assert_eq!(
+    when!(("r0" == "r1,ASR #1")[1]{}),
+    "cmp r0, r1,ASR #1\nbne 1f\n1:\n"
+  );
+  assert_eq!(
+    when!(("r0" == "r1, ror #31")[1]{}),
+    "cmp r0, r1, ror #31\nbne 1f\n1:\n"
+  );
+  assert_eq!(when!(("r0" == "r1, rrx")[1]{}), "cmp r0, r1, rrx\nbne 1f\n1:\n");
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" == "r1, lsx #2")[1]{})),
+    "unknown shift `lsx` in `r1, lsx #2`, expected `lsl`, `lsr`, `asr`, `ror`, \
+     or `rrx`"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" == "r1, lsl #32")[1]{})),
+    "the shift amount 32 in `r1, lsl #32` is out of range, `lsl` can shift by 0 \
+     to 31"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" == "r1, lsr #0")[1]{})),
+    "the shift amount 0 in `r1, lsr #0` is out of range, `lsr` can shift by 1 \
+     to 32"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" == "r1, ror #32")[1]{})),
+    "the shift amount 32 in `r1, ror #32` is out of range, `ror` can shift by 1 \
+     to 31"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" == "r1, rrx #1")[1]{})),
+    "`rrx` doesn't take an amount, got `r1, rrx #1`"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" == "r1, lsl")[1]{})),
+    "the shift in `r1, lsl` is missing an amount"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" == "r1, lsl r2")[1]{})),
+    "the shift amount in `r1, lsl r2` must be an immediate, eg: `lsl #2`"
+  );
+  // but only the right one
+  assert_eq!(
+    __bracer_error_of!(when!(("r0, lsl #1" == "r1")[1]{})),
+    "`r0, lsl #1` is not a register name or an `asm!` substitution like `{temp}`"
+  );
+
+  // negative immediates use `cmn`
+  assert_eq!(when!(("r0" == "#-1")[1]{}), "cmn r0, #1\nbne 1f\n1:\n");
+  assert_eq!(when!(("r0" >= i "#-0x100")[1]{}), "cmn r0, #0x100\nblt 1f\n1:\n");
+  // except `i32::MIN`, where `cmn` would set V differently than `cmp`
+  assert_eq!(
+    when!(("r0" >= i "#-0x80000000")[1]{}),
+    "cmp r0, #0x80000000\nblt 1f\n1:\n"
+  );
+  assert_eq!(
+    when!(("r0" < i "#-2147483648")[1]{}),
+    "cmp r0, #0x80000000\nbge 1f\n1:\n"
+  );
+  assert_eq!(
+    when!(("r0" != "#-1" && "r1" < i "#- 5")[1]{}),
+    "cmn r0, #1\nbeq 1f\ncmn r1, #5\nbge 1f\n1:\n"
+  );
+  // unless only the `cmp` form can be encoded
+  assert_eq!(
+    when!(("r0" == "#-268435441")[1]{}),
+    "cmp r0, #-268435441\nbne 1f\n1:\n"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" == "#-0x101")[1]{})),
+    "the immediate -257 (0xFFFFFEFF) can't be encoded, it must be an 8-bit \
+     value rotated right by an even amount (or the negation of one)"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" < u "#-1")[1]{})),
+    "an unsigned test against the negative immediate `#-1` compares against \
+     0xFFFFFFFF, use a signed test or write `#0xFFFFFFFF`"
+  );
+  assert_eq!(
+    when!(("r0" < u "#0xFFFFFFFF")[1]{}),
+    "cmp r0, #0xFFFFFFFF\nbhs 1f\n1:\n"
+  );
+
+  // the label can't also be defined in the body
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" != "#0")[1]{ "subs r1, r1, #1", "1:" })),
+    "the label `1` is also defined in the body, which would catch the \
+     branches meant for this one, use another number"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" != "#0")[1]{ "nop\n1: subs r1, r1, #1" })),
+    "the label `1` is also defined in the body, which would catch the \
+     branches meant for this one, use another number"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" != "#0")[1][2]{ "nop" } else {
+      "2: nop; bne 2b",
+    })),
+    "the label `2` is also defined in the body, which would catch the \
+     branches meant for this one, use another number"
+  );
+  // but other numbers, uses of the label, and comments are fine
+  assert_eq!(
+    when!(("r0" != "#0")[1]{ "3: subs r1, r1, #1", "bne 3b", "b 1f // 1:" }),
+    "cmp r0, #0\nbeq 1f\n3: subs r1, r1, #1\nbne 3b\nb 1f // 1:\n1:\n"
+  );
+  // the lines of a nested macro aren't known yet, so they aren't checked
+  assert_eq!(
+    __bracer_expand_to_str!(when!(("r0" != "#0")[1]{
+      when!(("r1" != "#0")[1]{}),
+    })),
+    "cmp r0, #0\nbeq 1f\ncmp r1, #0\nbeq 1f\n1:\n\n1:\n"
+  );
+
+  // the label can have any integer literal form
+  let expected = "cmp r0, #0\nbeq 16f\n16:\n";
+  assert_eq!(expected, when!(("r0" != "#0")[16u32]{}));
+  assert_eq!(expected, when!(("r0" != "#0")[1_6_u8]{}));
+  assert_eq!(expected, when!(("r0" != "#0")[0x10]{}));
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" != "#0")[-1]{})),
+    "the label can't be negative"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" != "#0")[1.5]{})),
+    "literal must be a valid u32"
+  );
+
+  // literals with quotes and non-ascii text pass through
+  let expected = "cmp r0, #'\"'\nbeq 1f\nmov r0, #'\u{e9}'\n1:\n";
+  let actual = when!(("r0" != "#'\"'")[1]{ "mov r0, #'\u{e9}'" });
+  assert_eq!(expected, actual);
+
+  // escapes in the test operands are undone
+  let expected = "cmp r0, #'0'\nbeq 1f\n1:\n";
+  let actual = when!(("r0" != "#\'0\'")[1]{});
+  assert_eq!(expected, actual);
+  let expected = "cmp r0, #\t0\nbeq 1f\n1:\n";
+  let actual = when!(("\x72\u{30}" != "#\t0")[1]{});
+  assert_eq!(expected, actual);
+
+  // signed-ness doesn't matter
+  let _actual = when!(("r0" == "#0")[1]{
+    "add r1, r2, r3",
+    "add r0, r1, r4",
+  });
   let _actual = when!(("r0" != "#0")[1]{
     "add r1, r2, r3",
     "add r0, r1, r4",
@@ -169,3 +1471,3290 @@ fn test_when() {
     "add r0, r1, r4",
   });
 }
+
+#[test]
+fn test_unless() {
+  let expected = "cmp r0, #0\nbeq 1f\nmov r1, #1\n1:\n";
+  assert_eq!(expected, unless!(("r0" == "#0")[1]{ "mov r1, #1" }));
+
+  // every comparison is the same as `when!` of the opposite comparison
+  assert_eq!(unless!(("r0" == "r1")[1]{}), when!(("r0" != "r1")[1]{}));
+  assert_eq!(unless!(("r0" != "r1")[1]{}), when!(("r0" == "r1")[1]{}));
+  assert_eq!(unless!(("r0" >= u "r1")[1]{}), when!(("r0" < u "r1")[1]{}));
+  assert_eq!(unless!(("r0" <= u "r1")[1]{}), when!(("r0" > u "r1")[1]{}));
+  assert_eq!(unless!(("r0" < u "r1")[1]{}), when!(("r0" >= u "r1")[1]{}));
+  assert_eq!(unless!(("r0" > u "r1")[1]{}), when!(("r0" <= u "r1")[1]{}));
+  assert_eq!(unless!(("r0" >= i "r1")[1]{}), when!(("r0" < i "r1")[1]{}));
+  assert_eq!(unless!(("r0" <= i "r1")[1]{}), when!(("r0" > i "r1")[1]{}));
+  assert_eq!(unless!(("r0" < i "r1")[1]{}), when!(("r0" >= i "r1")[1]{}));
+  assert_eq!(unless!(("r0" > i "r1")[1]{}), when!(("r0" <= i "r1")[1]{}));
+
+  // the labels, else blocks, and operand forms all work the same
+  assert_eq!(
+    unless!(("r0" == "#-1")[1][2]{ "mov r1, #1" } else { "mov r1, #0" }),
+    when!(("r0" != "#-1")[1][2]{ "mov r1, #1" } else { "mov r1, #0" })
+  );
+  assert_eq!(
+    unless!(("r0" < u "r1, lsl #2")[7]{}),
+    "cmp r0, r1, lsl #2\nblo 7f\n7:\n"
+  );
+
+  // a compound test is negated as a whole
+  assert_eq!(
+    unless!(("r0" == "#0" || "r1" == "#0")[1]{}),
+    when!(("r0" != "#0" && "r1" != "#0")[1]{})
+  );
+  assert_eq!(
+    unless!(("r0" == "#0" && "r1" < i "#5")[1]{ "mov r2, #1" }),
+    "cmp r0, #0\nbne 2f\ncmp r1, #5\nblt 1f\n2:\nmov r2, #1\n1:\n"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(unless!(("r0" == "#0x102")[1]{})),
+    "the immediate 258 (0x102) can't be encoded, it must be an 8-bit value \
+     rotated right by an even amount (or the negation of one)"
+  );
+}
+
+// without a label the loop is meant for `global_asm!`
+core::arch::global_asm!(
+  "/*",
+  while_loop!(("r0" != "#0"){ "sub r0, r0, #1" }),
+  "*/"
+);
+
+#[test]
+fn test_while_loop() {
+  let expected = concat!(
+    "1:\n",
+    "cmp r0, #0\n",
+    "beq 1f\n",
+    "subs r0, r0, #1\n",
+    "bl do_thing\n",
+    "b 1b\n",
+    "1:\n"
+  );
+  let actual = while_loop!(("r0" != "#0")[1]{
+    "subs r0, r0, #1",
+    "bl do_thing",
+  });
+  assert_eq!(expected, actual);
+
+  let expected = concat!(
+    "5:\n",
+    "cmp r1, r2\n",
+    "bhs 5f\n",
+    "ldrb r3, [r1], #1\n",
+    "b 5b\n",
+    "5:\n"
+  );
+  let actual = while_loop!(("r1" < u "r2")[5]{ "ldrb r3, [r1], #1" });
+  assert_eq!(expected, actual);
+  assert_eq!(
+    while_loop!(("r0" > i "#-1")[2]{ "sub r0, r0, #2" }),
+    "2:\ncmn r0, #1\nble 2f\nsub r0, r0, #2\nb 2b\n2:\n"
+  );
+  assert_eq!(
+    while_loop!(("r0" != "#0" && "r1" != "#0")[1]{}),
+    "1:\ncmp r0, #0\nbeq 1f\ncmp r1, #0\nbeq 1f\nb 1b\n1:\n"
+  );
+
+  // without a label, the top and end get different generated labels
+  let actual = while_loop!(("r0" == "r1"){ "add r0, r0, #1" });
+  let lines: Vec<&str> = actual.lines().collect();
+  let top = lines[0].strip_suffix(':').unwrap();
+  let end = lines[2].strip_prefix("bne ").unwrap();
+  assert!(top.starts_with(".L_bracer_local_label_"), "{top}");
+  assert!(end.starts_with(".L_bracer_local_label_"), "{end}");
+  assert_ne!(top, end);
+  assert_eq!(lines[1], "cmp r0, r1");
+  assert_eq!(
+    lines[3..],
+    ["add r0, r0, #1", &format!("b {top}"), &format!("{end}:")]
+  );
+
+  assert_eq!(
+    __bracer_error_of!(while_loop!(("r0" == "#0x102")[1]{})),
+    "the immediate 258 (0x102) can't be encoded, it must be an 8-bit value \
+     rotated right by an even amount (or the negation of one)"
+  );
+  assert_eq!(
+    __bracer_error_of!(while_loop!(("r0" != "#0")[1]{ "1: nop" })),
+    "the label `1` is also defined in the body, which would catch the \
+     branches meant for this one, use another number"
+  );
+  assert_eq!(
+    __bracer_error_of!(while_loop!(lint = t16, ("r0" != "#0")[1]{
+      "mrs r1, CPSR",
+    })),
+    "`mrs r1, CPSR` is not Thumb-1: `mrs` is A32-only"
+  );
+  assert_eq!(
+    __bracer_error_of!(while_loop!(("r0" != "#0")[1])),
+    "too few tokens"
+  );
+  assert_eq!(
+    __bracer_error_of!(while_loop!(("r0" != "#0")[1]{}{})),
+    "too many tokens"
+  );
+  assert_eq!(
+    __bracer_error_of!(while_loop!(("r0" != "#0") 1 {})),
+    "expected only groups"
+  );
+}
+
+// without a label the loop is meant for `global_asm!`
+core::arch::global_asm!("/*", do_while!({ "subs r0, r0, #1" }("ne")), "*/");
+
+#[test]
+fn test_do_while() {
+  let expected = concat!(
+    "1:\n",
+    "ldr r3, [r1], #4\n",
+    "str r3, [r2], #4\n",
+    "sub r0, r0, #1\n",
+    "cmp r0, #0\n",
+    "bne 1b\n"
+  );
+  let actual = do_while!([1]{
+    "ldr r3, [r1], #4",
+    "str r3, [r2], #4",
+    "sub r0, r0, #1",
+  } ("r0" != "#0"));
+  assert_eq!(expected, actual);
+  assert_eq!(
+    do_while!([3]{ "add r0, r0, #1" } ("r0" < u "r1")),
+    "3:\nadd r0, r0, #1\ncmp r0, r1\nblo 3b\n"
+  );
+  assert_eq!(
+    do_while!([3]{ "add r0, r0, #1" } ("r0" <= i "#-1")),
+    "3:\nadd r0, r0, #1\ncmn r0, #1\nble 3b\n"
+  );
+
+  // a condition code alone tests the flags the body left
+  let expected = concat!(
+    "1:\n",
+    "ldr r3, [r1], #4\n",
+    "str r3, [r2], #4\n",
+    "subs r0, r0, #1\n",
+    "bne 1b\n"
+  );
+  let actual = do_while!([1]{
+    "ldr r3, [r1], #4",
+    "str r3, [r2], #4",
+    "subs r0, r0, #1",
+  } ("ne"));
+  assert_eq!(expected, actual);
+  assert_eq!(
+    do_while!([2]{ "adds r0, r0, r1" } ("cc")),
+    "2:\nadds r0, r0, r1\nbcc 2b\n"
+  );
+  assert_eq!(
+    __bracer_error_of!(do_while!([2]{} ("al"))),
+    "unknown condition `al`, expected one of: eq, ne, cs/hs, cc/lo, mi, pl, \
+     vs, vc, hi, ls, ge, lt, gt, le"
+  );
+
+  // with `&&` only the last test branches back
+  assert_eq!(
+    do_while!([1]{ "nop" } ("r0" != "#0" && "r1" != "#0")),
+    "1:\nnop\ncmp r0, #0\nbeq 2f\ncmp r1, #0\nbne 1b\n2:\n"
+  );
+
+  // without a label one is generated
+  let actual = do_while!({ "nop" }("r0" == "#0"));
+  let lines: Vec<&str> = actual.lines().collect();
+  let top = lines[0].strip_suffix(':').unwrap();
+  assert!(top.starts_with(".L_bracer_local_label_"), "{top}");
+  assert_eq!(lines[1..], ["nop", "cmp r0, #0", &format!("beq {top}")]);
+
+  assert_eq!(
+    __bracer_error_of!(do_while!([1]("r0" != "#0"))),
+    "too few tokens"
+  );
+  assert_eq!(
+    __bracer_error_of!(do_while!([1]{ "1:" } ("r0" != "#0"))),
+    "the label `1` is also defined in the body, which would catch the \
+     branches meant for this one, use another number"
+  );
+  assert_eq!(
+    __bracer_error_of!(do_while!(lint = t16, [1]{ "mrs r1, CPSR" } ("ne"))),
+    "`mrs r1, CPSR` is not Thumb-1: `mrs` is A32-only"
+  );
+}
+
+// without a label the loop is meant for `global_asm!`
+core::arch::global_asm!(
+  "/*",
+  counted_loop!("r4", { "str r0, [r1], #4" }),
+  "*/"
+);
+
+#[test]
+fn test_counted_loop() {
+  let expected = concat!(
+    "7:\n",
+    "ldr r0, [r1], #4\n",
+    "str r0, [r2], #4\n",
+    "subs r4, r4, #1\n",
+    "bne 7b\n"
+  );
+  let actual = counted_loop!("r4", [7] {
+    "ldr r0, [r1], #4",
+    "str r0, [r2], #4",
+  });
+  assert_eq!(expected, actual);
+  assert_eq!(
+    counted_loop!(r4, [7] { "nop" }),
+    "7:\nnop\nsubs r4, r4, #1\nbne 7b\n"
+  );
+  assert_eq!(
+    counted_loop!("{count}", [1] {}),
+    "1:\nsubs {count}, {count}, #1\nbne 1b\n"
+  );
+
+  // unrolling repeats the body and takes that much off the count
+  let expected = concat!(
+    "2:\n",
+    "ldr r0, [r1], #4\n",
+    "str r0, [r2], #4\n",
+    "ldr r0, [r1], #4\n",
+    "str r0, [r2], #4\n",
+    "ldr r0, [r1], #4\n",
+    "str r0, [r2], #4\n",
+    "subs r3, r3, #3\n",
+    "bne 2b\n"
+  );
+  let actual = counted_loop!("r3", [2] {
+    "ldr r0, [r1], #4",
+    "str r0, [r2], #4",
+  }, unroll = 3);
+  assert_eq!(expected, actual);
+
+  // without a label one is generated
+  let actual = counted_loop!("r3", { "nop" });
+  let lines: Vec<&str> = actual.lines().collect();
+  let top = lines[0].strip_suffix(':').unwrap();
+  assert!(top.starts_with(".L_bracer_local_label_"), "{top}");
+  assert_eq!(lines[1..], ["nop", "subs r3, r3, #1", &format!("bne {top}")]);
+
+  assert_eq!(
+    __bracer_error_of!(counted_loop!("sp", [1] {})),
+    "can't use `sp` as the counter register"
+  );
+  assert_eq!(
+    __bracer_error_of!(counted_loop!("r15", [1] {})),
+    "can't use `r15` as the counter register"
+  );
+  assert_eq!(
+    __bracer_error_of!(counted_loop!("x4", [1] {})),
+    "`x4` is not a register name or an `asm!` substitution like `{temp}`"
+  );
+  assert_eq!(
+    __bracer_error_of!(counted_loop!("r4", [1] {}, unroll = 0)),
+    "`unroll` must be at least 1 and encodable as a `subs` immediate, got 0"
+  );
+  assert_eq!(
+    __bracer_error_of!(counted_loop!("r4", [1] {}, unroll = 257)),
+    "`unroll` must be at least 1 and encodable as a `subs` immediate, got 257"
+  );
+  assert_eq!(
+    __bracer_error_of!(counted_loop!("r4", [1] {}, times = 2)),
+    "unknown argument `times`"
+  );
+  assert_eq!(
+    __bracer_error_of!(counted_loop!("r4", [1] { "1: nop" })),
+    "the label `1` is also defined in the body, which would catch the \
+     branches meant for this one, use another number"
+  );
+  assert_eq!(
+    __bracer_error_of!(counted_loop!("r4", [1] "nop")),
+    "expected an optional label group and then a body group, eg: `[1] { ... }`"
+  );
+  assert_eq!(__bracer_error_of!(counted_loop!("r4", [1])), "missing the body");
+  assert_eq!(__bracer_error_of!(counted_loop!("r4")), "missing the body");
+}
+
+// the labels are all named, so the output is meant for `global_asm!`
+core::arch::global_asm!(
+  "/*",
+  cond_chain!(
+    ("r0" == "#0") { "mov r1, #10" }
+    ("r0" == "#1" || "r0" == "#2") { "mov r1, #20" }
+    else { "mov r1, #0" }
+  ),
+  "*/"
+);
+
+#[test]
+fn test_cond_chain() {
+  // picks out each generated label after the given prefix
+  let label_of = |line: &str, prefix: &str| {
+    let label = line.strip_prefix(prefix).unwrap();
+    assert!(label.starts_with(".L_bracer_local_label_"), "{label}");
+    label.to_string()
+  };
+
+  let actual = cond_chain!(
+    ("r0" == "#0") { "mov r1, #10" }
+    ("r0" == "#1") { "mov r1, #20" }
+    ("r0" < u "#8") { "mov r1, #30" }
+    else { "mov r1, #0" }
+  );
+  let lines: Vec<&str> = actual.lines().collect();
+  assert_eq!(lines.len(), 17, "{actual}");
+  assert_eq!(lines[0], "cmp r0, #0");
+  let second = label_of(lines[1], "bne ");
+  assert_eq!(lines[2], "mov r1, #10");
+  let end = label_of(lines[3], "b ");
+  assert_eq!(lines[4], format!("{second}:"));
+  assert_eq!(lines[5], "cmp r0, #1");
+  let third = label_of(lines[6], "bne ");
+  assert_eq!(lines[7..9], ["mov r1, #20", &format!("b {end}")]);
+  assert_eq!(lines[9], format!("{third}:"));
+  assert_eq!(lines[10], "cmp r0, #8");
+  let other = label_of(lines[11], "bhs ");
+  assert_eq!(lines[12..14], ["mov r1, #30", &format!("b {end}")]);
+  assert_eq!(
+    lines[14..],
+    [&format!("{other}:"), "mov r1, #0", &format!("{end}:")]
+  );
+  let mut labels = vec![second, third, other, end];
+  labels.sort();
+  labels.dedup();
+  assert_eq!(labels.len(), 4);
+
+  // without an else, the last test goes straight to the end
+  let actual = cond_chain!(
+    ("r0" == "#0") { "mov r1, #10" }
+    ("r0" == "#1") { "mov r1, #20" }
+    ("r0" < u "#8" && "r2" != "#0") { "mov r1, #30" }
+  );
+  let lines: Vec<&str> = actual.lines().collect();
+  assert_eq!(lines.len(), 16, "{actual}");
+  let second = label_of(lines[1], "bne ");
+  let end = label_of(lines[3], "b ");
+  assert_eq!(lines[4], format!("{second}:"));
+  let third = label_of(lines[6], "bne ");
+  assert_eq!(lines[8], format!("b {end}"));
+  assert_eq!(lines[9], format!("{third}:"));
+  assert_eq!(lines[10..12], ["cmp r0, #8", &format!("bhs {end}")]);
+  assert_eq!(lines[12..14], ["cmp r2, #0", &format!("beq {end}")]);
+  assert_eq!(lines[14..], ["mov r1, #30", &format!("{end}:")]);
+  let actual = cond_chain!(("r0" == "#0") { "mov r1, #10" });
+  let lines: Vec<&str> = actual.lines().collect();
+  let end = label_of(lines[1], "bne ");
+  assert_eq!(lines[2..], ["mov r1, #10", &format!("{end}:")]);
+
+  assert_eq!(
+    __bracer_error_of!(cond_chain!(("r0" == "#0") { "nop" } ("r1" == "#0"))),
+    "this test is missing a body"
+  );
+  assert_eq!(
+    __bracer_error_of!(cond_chain!(("r0" == "#0") "nop")),
+    "expected a test group followed by a body group"
+  );
+  assert_eq!(
+    __bracer_error_of!(cond_chain!(("r0" == "#0") { "nop" } else)),
+    "`else` must be followed by a group for the lines"
+  );
+  assert_eq!(__bracer_error_of!(cond_chain!(else { "nop" })), "too few tokens");
+  assert_eq!(
+    __bracer_error_of!(cond_chain!(("r0" == "#0x102") { "nop" })),
+    "the immediate 258 (0x102) can't be encoded, it must be an 8-bit value \
+     rotated right by an even amount (or the negation of one)"
+  );
+}
+
+#[test]
+fn test_when_cond() {
+  let expected = "cmp r0, #0\naddeq r1, r2, r3\nmoveq r4, #1\n";
+  let actual = when_cond!(("r0" == "#0"){
+    "add r1, r2, r3",
+    "mov r4, #1",
+  });
+  assert_eq!(expected, actual);
+  assert_eq!(
+    when_cond!(("r0" < u "r1"){ "ldrb r2, [r0]", "bx lr // done" }),
+    "cmp r0, r1\nldrblo r2, [r0]\nbxlo lr\n"
+  );
+  assert_eq!(
+    when_cond!(("r0" >= i "#-1"){ "push {r4, lr}; bl func" }),
+    "cmn r0, #1\npushge {r4, lr}\nblge func\n"
+  );
+  assert_eq!(when_cond!(("r0" != "r1"){ "nop" }), "cmp r0, r1\nnopne\n");
+
+  assert_eq!(
+    __bracer_error_of!(when_cond!(("r0" == "#0"){ "adds r1, r2, r3" })),
+    "`adds r1, r2, r3` sets the flags, which would change the condition of the \
+     lines after it, use `when!` instead"
+  );
+  assert_eq!(
+    __bracer_error_of!(when_cond!(("r0" == "#0"){ "cmp r1, #2" })),
+    "`cmp r1, #2` sets the flags, which would change the condition of the \
+     lines after it, use `when!` instead"
+  );
+  assert_eq!(
+    __bracer_error_of!(when_cond!(("r0" == "#0"){ "movne r1, #2" })),
+    "`movne r1, #2` already has a condition code"
+  );
+  assert_eq!(
+    __bracer_error_of!(when_cond!(("r0" == "#0"){ "1: mov r1, #2" })),
+    "`1: mov r1, #2` has a label, which can't be conditional"
+  );
+  assert_eq!(
+    __bracer_error_of!(when_cond!(("r0" == "#0"){ ".word 0" })),
+    "`.word 0` is a directive, which can't be conditional"
+  );
+  assert_eq!(
+    __bracer_error_of!(when_cond!(("r0" == "#0"){ "frob r1" })),
+    "`frob` isn't an instruction that `when_cond!` knows how to make \
+     conditional, use `when!` instead"
+  );
+  assert_eq!(
+    __bracer_error_of!(when_cond!(("r0" == "#0"){ a32_read_spsr_to!(r1) })),
+    "only string literal lines can be made conditional, use `when!` for other \
+     macros"
+  );
+  assert_eq!(
+    __bracer_error_of!(when_cond!(("r0" == "#0"){
+      "mov r1, #1", "mov r2, #1", "mov r3, #1", "mov r4, #1", "mov r5, #1",
+    })),
+    "`when_cond!` is for up to 4 lines, this has 5, use `when!` to branch past \
+     them instead"
+  );
+  assert_eq!(
+    __bracer_error_of!(when_cond!(("r0" == "#0" && "r1" == "#0"){ "nop" })),
+    "`when_cond!` can only use one comparison, use `when!` for `&&` or `||`"
+  );
+  assert_eq!(
+    __bracer_error_of!(when_cond!(("r0" == "#0")[1]{ "nop" })),
+    "too many tokens"
+  );
+}
+
+#[test]
+fn test_when_bit() {
+  // `!= 0` skips the body when the bits are all clear
+  let expected =
+    concat!("tst r0, #0x4000\n", "beq 1f\n", "mov r1, #1\n", "1:\n");
+  let actual = when_bit!(("r0" & "#0x4000" != 0)[1]{ "mov r1, #1" });
+  assert_eq!(expected, actual);
+
+  // `== 0` skips the body when any bit is set
+  let expected = concat!(
+    "tst r0, r2\n",
+    "bne 1f\n",
+    "mov r1, #1\n",
+    "b 2f\n",
+    "1:\n",
+    "mov r1, #0\n",
+    "2:\n"
+  );
+  let actual = when_bit!(("r0" & "r2" == 0)[1][2]{
+    "mov r1, #1",
+  } else {
+    "mov r1, #0",
+  });
+  assert_eq!(expected, actual);
+
+  // placeholders work for either side
+  assert_eq!(
+    when_bit!(("{x}" & "{mask}" != 0)[3]{}),
+    "tst {x}, {mask}\nbeq 3f\n3:\n"
+  );
+
+  // the label can be generated
+  let actual = when_bit!(("r0" & "#1" == 0){});
+  let label = actual.strip_prefix("tst r0, #1\nbne ").unwrap();
+  assert!(label.starts_with(".L_bracer_local_label_"), "{label}");
+
+  assert_eq!(
+    __bracer_error_of!(when_bit!(("r0" & "#-1" != 0)[1]{})),
+    "the immediate -1 (0xFFFFFFFF) can't be encoded, it must be an 8-bit value \
+     rotated right by an even amount"
+  );
+  assert_eq!(
+    __bracer_error_of!(when_bit!(("#1" & "r0" != 0)[1]{})),
+    "the left operand must be a register, not the immediate `#1`"
+  );
+  assert_eq!(
+    __bracer_error_of!(when_bit!(("r0" & "#4" != 1)[1]{})),
+    "unknown test expression, expected `\"rN\" & \"#mask\" != 0` or `== 0`"
+  );
+  assert_eq!(
+    __bracer_error_of!(when_bit!(("r0" & "#4" > 0)[1]{})),
+    "unknown test expression, expected `\"rN\" & \"#mask\" != 0` or `== 0`"
+  );
+  assert_eq!(
+    __bracer_error_of!(when_bit!((r0 & "#4" != 0)[1]{})),
+    "test input must be a str literal"
+  );
+}
+
+#[test]
+fn test_when_flags() {
+  let expected = concat!("bne 1f\n", "mov r1, #1\n", "1:\n");
+  let actual = when_flags!(("eq")[1]{ "mov r1, #1" });
+  assert_eq!(expected, actual);
+
+  // every condition branches past the body on its inverse
+  assert_eq!(when_flags!(("eq")[1]{}), "bne 1f\n1:\n");
+  assert_eq!(when_flags!(("ne")[1]{}), "beq 1f\n1:\n");
+  assert_eq!(when_flags!(("cs")[1]{}), "bcc 1f\n1:\n");
+  assert_eq!(when_flags!(("hs")[1]{}), "blo 1f\n1:\n");
+  assert_eq!(when_flags!(("cc")[1]{}), "bcs 1f\n1:\n");
+  assert_eq!(when_flags!(("lo")[1]{}), "bhs 1f\n1:\n");
+  assert_eq!(when_flags!(("mi")[1]{}), "bpl 1f\n1:\n");
+  assert_eq!(when_flags!(("pl")[1]{}), "bmi 1f\n1:\n");
+  assert_eq!(when_flags!(("vs")[1]{}), "bvc 1f\n1:\n");
+  assert_eq!(when_flags!(("vc")[1]{}), "bvs 1f\n1:\n");
+  assert_eq!(when_flags!(("hi")[1]{}), "bls 1f\n1:\n");
+  assert_eq!(when_flags!(("ls")[1]{}), "bhi 1f\n1:\n");
+  assert_eq!(when_flags!(("ge")[1]{}), "blt 1f\n1:\n");
+  assert_eq!(when_flags!(("lt")[1]{}), "bge 1f\n1:\n");
+  assert_eq!(when_flags!(("gt")[1]{}), "ble 1f\n1:\n");
+  assert_eq!(when_flags!(("le")[1]{}), "bgt 1f\n1:\n");
+
+  // with an else block
+  let expected = concat!(
+    "bcc 1f\n",
+    "mov r1, #1\n",
+    "b 2f\n",
+    "1:\n",
+    "mov r1, #0\n",
+    "2:\n"
+  );
+  let actual = when_flags!(("cs")[1][2]{
+    "mov r1, #1",
+  } else {
+    "mov r1, #0",
+  });
+  assert_eq!(expected, actual);
+
+  assert_eq!(
+    __bracer_error_of!(when_flags!(("al")[1]{})),
+    "unknown condition `al`, expected one of: eq, ne, cs/hs, cc/lo, mi, pl, \
+     vs, vc, hi, ls, ge, lt, gt, le"
+  );
+  assert_eq!(
+    __bracer_error_of!(when_flags!(("EQ")[1]{})),
+    "unknown condition `EQ`, expected one of: eq, ne, cs/hs, cc/lo, mi, pl, \
+     vs, vc, hi, ls, ge, lt, gt, le"
+  );
+  assert_eq!(
+    __bracer_error_of!(when_flags!(("eq" "ne")[1]{})),
+    "the test must be one condition code as a str literal, eg: `(\"eq\")`"
+  );
+  assert_eq!(
+    __bracer_error_of!(when_flags!((eq)[1]{})),
+    "test input must be a str literal"
+  );
+}
+
+#[test]
+fn test_adjust_ptr() {
+  // one instruction
+  assert_eq!(adjust_ptr!("r0", 16), "add r0, r0, #16");
+  assert_eq!(adjust_ptr!("r0", 0xFF00), "add r0, r0, #65280");
+
+  // negative offsets
+  assert_eq!(adjust_ptr!("r0", -8), "sub r0, r0, #8");
+
+  // two instructions
+  let expected = "add r0, r0, #4608\nadd r0, r0, #52";
+  assert_eq!(adjust_ptr!("r0", 0x1234), expected);
+  let expected = "sub r3, r3, #4608\nsub r3, r3, #52";
+  assert_eq!(adjust_ptr!("r3", -0x1234), expected);
+
+  // scratch register
+  let expected = "ldr r1, =0x12345678\nadd r0, r0, r1";
+  assert_eq!(adjust_ptr!("r0", 0x1234_5678, scratch = "r1"), expected);
+  let expected = "ldr {tmp}, =0x12345678\nsub r0, r0, {tmp}";
+  assert_eq!(adjust_ptr!("r0", -0x1234_5678, scratch = "{tmp}"), expected);
+
+  // the scratch can't be the pointer, by any spelling
+  assert_eq!(
+    __bracer_error_of!(adjust_ptr!("r0", 0x1234_5678, scratch = "R0")),
+    "`scratch` must not be the pointer register"
+  );
+  assert_eq!(
+    __bracer_error_of!(adjust_ptr!("ip", 0x1234_5678, scratch = "r12")),
+    "`scratch` must not be the pointer register"
+  );
+}
+
+#[test]
+fn test_mul_const() {
+  let expected = "add r0, r1, r1, lsl #2";
+  assert_eq!(mul_const!(dst = "r0", src = "r1", by = 5), expected);
+
+  let expected = "add r0, r1, r1, lsl #2\nmov r0, r0, lsl #1";
+  assert_eq!(
+    mul_const!(dst = "r0", src = "r1", by = 10, scratch = "r2"),
+    expected
+  );
+
+  let expected = "rsb r0, r1, r1, lsl #3";
+  assert_eq!(mul_const!(dst = "r0", src = "r1", by = 7), expected);
+
+  // trivial cases
+  assert_eq!(mul_const!(dst = "r0", src = "r1", by = 0), "mov r0, #0");
+  assert_eq!(mul_const!(dst = "r0", src = "r1", by = 1), "mov r0, r1");
+  assert_eq!(mul_const!(dst = "r0", src = "r1", by = 8), "mov r0, r1, lsl #3");
+
+  // a large prime goes over `max_ops`
+  let expected = "ldr r2, =0xF4243\nmul r0, r2, r1";
+  assert_eq!(
+    mul_const!(dst = "r0", src = "r1", by = 1_000_003, scratch = "r2"),
+    expected
+  );
+  let expected = "mov r2, #11\nmul r0, r2, r1";
+  assert_eq!(
+    mul_const!(dst = "r0", src = "r1", by = 11, scratch = "r2", max_ops = 1),
+    expected
+  );
+
+  // in-place multiply copies the source first when it's needed more than once
+  let expected = "mov r2, r0\nadd r0, r2, r2, lsl #2\nadd r0, r2, r0, lsl #2";
+  assert_eq!(
+    mul_const!(dst = "r0", src = "r0", by = 21, scratch = "r2"),
+    expected
+  );
+  let expected = "mov r2, R0\nadd r0, r2, r2, lsl #2\nadd r0, r2, r0, lsl #2";
+  assert_eq!(
+    mul_const!(dst = "r0", src = "R0", by = 21, scratch = "r2"),
+    expected
+  );
+  let expected =
+    "mov r2, ip\nadd r12, r2, r2, lsl #2\nadd r12, r2, r12, lsl #2";
+  assert_eq!(
+    mul_const!(dst = "r12", src = "ip", by = 21, scratch = "r2"),
+    expected
+  );
+
+  // registers are compared by number, not by spelling
+  assert_eq!(
+    __bracer_error_of!(mul_const!(
+      dst = "r0",
+      src = "r1",
+      by = 11,
+      scratch = "R1",
+      max_ops = 1
+    )),
+    "`scratch` must differ from `dst` and `src`"
+  );
+}
+
+#[test]
+fn test_div_const_u32() {
+  let expected = concat!(
+    "ldr r3, =0xCCCCCCCD\n",
+    "umull r2, r3, r1, r3\n",
+    "mov r0, r3, lsr #3",
+  );
+  let actual =
+    div_const_u32!(dst = "r0", src = "r1", by = 10, scratch = ["r2", "r3"]);
+  assert_eq!(expected, actual);
+
+  let expected = concat!(
+    "ldr r3, =0x24924925\n",
+    "umull r2, r3, r1, r3\n",
+    "sub r2, r1, r3\n",
+    "add r3, r3, r2, lsr #1\n",
+    "mov r0, r3, lsr #2",
+  );
+  let actual =
+    div_const_u32!(dst = "r0", src = "r1", by = 7, scratch = ["r2", "r3"]);
+  assert_eq!(expected, actual);
+
+  // powers of two don't need scratch registers
+  assert_eq!(
+    div_const_u32!(dst = "r0", src = "r1", by = 16),
+    "mov r0, r1, lsr #4"
+  );
+  assert_eq!(div_const_u32!(dst = "r0", src = "r1", by = 1), "mov r0, r1");
+
+  // registers are compared by number, so `ip` is `r12`
+  assert_eq!(
+    __bracer_error_of!(div_const_u32!(
+      dst = "r0",
+      src = "r12",
+      by = 7,
+      scratch = ["r2", "ip"]
+    )),
+    "`src` and both `scratch` registers must all be different"
+  );
+  assert_eq!(
+    __bracer_error_of!(div_const_u32!(
+      dst = "r0",
+      src = "r1",
+      by = 7,
+      scratch = ["r2", "R2"]
+    )),
+    "`src` and both `scratch` registers must all be different"
+  );
+}
+
+#[test]
+fn test_error_messages() {
+  assert_eq!(__bracer_error_of!(a32_read_spsr_to!()), "Not enough input");
+  assert_eq!(
+    __bracer_error_of!(a32_write_spsr_from!(0)),
+    "the register must be a string literal or a register name"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_fake_blx!("r0", "r1")),
+    "Provide one register only, as a string literal or a register name."
+  );
+  assert_eq!(
+    __bracer_error_of!(put_fn_in_section!(12)),
+    "Provide string literals only: the section name, then optionally the \
+     flags and the type."
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_set_cpu_control!(
+      Hyp,
+      irq_masked = true,
+      fiq_masked = true
+    )),
+    concat!(
+      "First argument must be a valid cpu mode name, got `Hyp`, expected one ",
+      "of: `User`/`usr`, `FIQ`/`fiq`, `IRQ`/`irq`, `Supervisor`/`svc`, ",
+      "`Abort`/`abt`, `Undefined`/`und`, `System`/`sys`"
+    )
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_set_cpu_control!(
+      System,
+      irq_masked = yes,
+      fiq_masked = true
+    )),
+    "`irq_masked` must be set as `true` or `false`"
+  );
+  assert_eq!(__bracer_error_of!(a32_set_cpu_control!()), "too few tokens");
+  assert_eq!(
+    __bracer_error_of!(when!((r0 != "#0")[1]{ "add r1, r2, r3" })),
+    "unknown test expression"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" != 0)[1]{ "add r1, r2, r3" })),
+    "test input must be a str literal"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" != "#0")[one]{ "add r1, r2, r3" })),
+    "please provide only 1 literal for the label"
+  );
+  assert_eq!(__bracer_error_of!(when!(("r0" != "#0")[1])), "too few tokens");
+  assert_eq!(
+    __bracer_error_of!(adjust_ptr!("r0", 0x1234_5678)),
+    "the offset 305419896 needs a `scratch = \"rN\"` register"
+  );
+  assert_eq!(
+    __bracer_error_of!(mul_const!(dst = "r0", src = "r1", by = -1)),
+    "`by` must be a `u32` integer literal"
+  );
+  assert_eq!(
+    __bracer_error_of!(div_const_u32!(dst = "r0", src = "r1", by = 0)),
+    "can't divide by zero"
+  );
+}
+
+#[test]
+fn test_bracer_expand_to_str() {
+  // plain literals and concat
+  assert_eq!(__bracer_expand_to_str!("mov r0, #0"), "mov r0, #0");
+  assert_eq!(__bracer_expand_to_str!(concat!("a", 'b', 3)), "ab3");
+  assert_eq!(__bracer_expand_to_str!("a", "b"), "ab");
+
+  // bracer invocations, including by path
+  let expected = concat!("cmp r0, #0\n", "beq 1f\n", "mrs r1, SPSR\n", "1:\n");
+  let actual = __bracer_expand_to_str!(bracer::when!(("r0" != "#0")[1]{
+    bracer::a32_read_spsr_to!("r1"),
+  }));
+  assert_eq!(expected, actual);
+
+  // dynamic elements are an error
+  assert_eq!(
+    __bracer_error_of!(__bracer_expand_to_str!(concat!("a", SOME_CONST))),
+    "`SOME_CONST` is not a literal, so it can't be folded"
+  );
+  assert_eq!(
+    __bracer_error_of!(__bracer_expand_to_str!(stringify!(r0))),
+    "`stringify!` is not a bracer macro, so it can't be folded"
+  );
+  assert_eq!(
+    __bracer_error_of!(__bracer_expand_to_str!(when!(("r0" != 0)[1]{}))),
+    "test input must be a str literal"
+  );
+}
+
+#[test]
+fn test_assert_t16_ok() {
+  let expected = "adds r0, r1, r2\nlsls r0, r0, #2\nmov r8, r0\n1:\n";
+  let actual = assert_t16_ok!({
+    "adds r0, r1, r2",
+    "lsls r0, r0, #2",
+    "mov r8, r0",
+    "1:",
+  });
+  assert_eq!(expected, actual);
+
+  // high register
+  assert_eq!(
+    __bracer_error_of!(assert_t16_ok!({ "add r8, r1, r2" })),
+    "`add r8, r1, r2` is not Thumb-1: high register `r8`"
+  );
+  // wide immediate
+  assert_eq!(
+    __bracer_error_of!(assert_t16_ok!({ "movs r0, #256" })),
+    "`movs r0, #256` is not Thumb-1: immediate `#256` too wide"
+  );
+  assert_eq!(
+    __bracer_error_of!(assert_t16_ok!({ "adds r0, r1, #8" })),
+    "`adds r0, r1, #8` is not Thumb-1: immediate `#8` too wide"
+  );
+  // shifted operand
+  assert_eq!(
+    __bracer_error_of!(assert_t16_ok!({ "add r0, r1, r2, lsl #2" })),
+    "`add r0, r1, r2, lsl #2` is not Thumb-1: shifted operand"
+  );
+  // A32-only
+  assert_eq!(
+    __bracer_error_of!(assert_t16_ok!({ "mrs r0, CPSR" })),
+    "`mrs r0, CPSR` is not Thumb-1: `mrs` is A32-only"
+  );
+
+  // the same lint is available in `when!`
+  let expected = "cmp r0, #0\nbeq 1f\nadds r1, r2, r3\n1:\n";
+  let actual = when!(lint = t16, ("r0" != "#0")[1]{ "adds r1, r2, r3" });
+  assert_eq!(expected, actual);
+  assert_eq!(
+    __bracer_error_of!(
+      when!(lint = t16, ("r0" != "#0")[1]{ "ldr r0, [r1, #128]" })
+    ),
+    "`ldr r0, [r1, #128]` is not Thumb-1: immediate `#128` too wide"
+  );
+}
+
+#[test]
+fn test_task_frame() {
+  assert_eq!(
+    task_save!(tcb = "r0", from_mode = svc),
+    "stmia r0, {{r4-r11, sp, lr}}"
+  );
+  assert_eq!(
+    task_save!(tcb = "r0", from_mode = irq),
+    "stmia r0, {{r4-r11, sp, lr}}^\nnop"
+  );
+  assert_eq!(
+    task_restore!(tcb = "r1", to_mode = svc),
+    "ldmia r1, {{r4-r11, sp, lr}}"
+  );
+  assert_eq!(
+    task_restore!(tcb = "r1", to_mode = irq),
+    "ldmia r1, {{r4-r11, sp, lr}}^\nnop"
+  );
+
+  let expected = concat!(
+    ".set TASK_FRAME_R4, 0\n",
+    ".set TASK_FRAME_R5, 4\n",
+    ".set TASK_FRAME_R6, 8\n",
+    ".set TASK_FRAME_R7, 12\n",
+    ".set TASK_FRAME_R8, 16\n",
+    ".set TASK_FRAME_R9, 20\n",
+    ".set TASK_FRAME_R10, 24\n",
+    ".set TASK_FRAME_R11, 28\n",
+    ".set TASK_FRAME_SP, 32\n",
+    ".set TASK_FRAME_LR, 36\n",
+    ".set TASK_FRAME_SIZE, 40",
+  );
+  assert_eq!(task_frame_offsets!(), expected);
+
+  // the tcb can't be part of the frame
+  assert_eq!(
+    __bracer_error_of!(task_save!(tcb = "r4", from_mode = svc)),
+    "`tcb` can't be `r4`, it's part of the task frame"
+  );
+  assert_eq!(
+    __bracer_error_of!(task_restore!(tcb = "sp", to_mode = svc)),
+    "`tcb` can't be `sp`, it's part of the task frame"
+  );
+}
+
+#[test]
+fn test_svc_reentry() {
+  let expected = concat!(
+    "mrs r12, SPSR\n",
+    "push {{r12, lr}}\n",
+    "msr CPSR_c, #0b00010011",
+  );
+  assert_eq!(svc_reentry_save!(scratch = "r12"), expected);
+
+  let expected = concat!(
+    "msr CPSR_c, #0b10010011\n",
+    "pop {{r12, lr}}\n",
+    "msr SPSR_cxsf, r12",
+  );
+  assert_eq!(svc_reentry_restore!(scratch = "r12"), expected);
+
+  // the restore is the save's steps undone in reverse
+  let save = svc_reentry_save!(scratch = "r3", fiq_masked = true);
+  let restore = svc_reentry_restore!(scratch = "r3", fiq_masked = true);
+  let save_lines: Vec<&str> = save.lines().collect();
+  let restore_lines: Vec<&str> = restore.lines().rev().collect();
+  assert_eq!(save_lines.len(), restore_lines.len());
+  assert_eq!(save_lines[0], "mrs r3, SPSR");
+  assert_eq!(restore_lines[0], "msr SPSR_cxsf, r3");
+  assert_eq!(save_lines[1], "push {{r3, lr}}");
+  assert_eq!(restore_lines[1], "pop {{r3, lr}}");
+  assert_eq!(save_lines[2], "msr CPSR_c, #0b01010011");
+  assert_eq!(restore_lines[2], "msr CPSR_c, #0b11010011");
+
+  assert_eq!(
+    __bracer_error_of!(svc_reentry_save!(scratch = "lr")),
+    "`scratch` can't be `lr`, it must be one of `r0`-`r12`"
+  );
+}
+
+#[test]
+fn test_align_dispatch() {
+  let expected = concat!(
+    "orr r2, r0, r1\n",
+    "tst r2, #3\n",
+    "beq 10f\n",
+    "tst r2, #1\n",
+    "beq 11f\n",
+    "b 12f",
+  );
+  let actual = align_dispatch!(
+    ptr = "r0",
+    other = "r1",
+    scratch = "r2",
+    labels = { word = 10, half = 11, byte = 12 }
+  );
+  assert_eq!(expected, actual);
+
+  // without a byte label the byte case falls through
+  let expected = concat!(
+    "orr r12, r0, r1\n",
+    "tst r12, #3\n",
+    "beq 1f\n",
+    "tst r12, #1\n",
+    "beq 2f",
+  );
+  let actual = align_dispatch!(
+    ptr = "r0",
+    other = "r1",
+    scratch = "r12",
+    labels = { word = 1, half = 2 }
+  );
+  assert_eq!(expected, actual);
+
+  assert_eq!(
+    __bracer_error_of!(align_dispatch!(
+      ptr = "r0",
+      other = "r1",
+      scratch = "r1",
+      labels = { word = 1, half = 2 }
+    )),
+    "`scratch` must differ from `ptr` and `other`"
+  );
+  assert_eq!(
+    __bracer_error_of!(align_dispatch!(
+      ptr = "r0",
+      other = "r1",
+      scratch = "R0",
+      labels = { word = 1, half = 2 }
+    )),
+    "`scratch` must differ from `ptr` and `other`"
+  );
+}
+
+#[test]
+fn test_sat_arith_s32() {
+  let expected = concat!(
+    "adds r0, r1, r2\n",
+    "movvs r3, #0x80000000\n",
+    "eorvs r0, r3, r0, asr #31",
+  );
+  let actual = sat_add_s32!(dst = "r0", a = "r1", b = "r2", scratch = "r3");
+  assert_eq!(expected, actual);
+
+  // dst can be one of the inputs
+  let expected = concat!(
+    "subs r4, r4, r5\n",
+    "movvs r12, #0x80000000\n",
+    "eorvs r4, r12, r4, asr #31",
+  );
+  let actual = sat_sub_s32!(dst = "r4", a = "r4", b = "r5", scratch = "r12");
+  assert_eq!(expected, actual);
+
+  // arch = v4t is still the long form
+  let actual =
+    sat_add_s32!(arch = v4t, dst = "r0", a = "r1", b = "r2", scratch = "r3");
+  assert!(actual.starts_with("adds r0, r1, r2\n"));
+
+  // v5te and later collapse to one instruction, and need no scratch
+  assert_eq!(
+    sat_add_s32!(dst = "r0", a = "r1", b = "r2", arch = v5te),
+    "qadd r0, r1, r2"
+  );
+  assert_eq!(
+    sat_sub_s32!(dst = "r0", a = "r1", b = "r2", scratch = "r3", arch = v7),
+    "qsub r0, r1, r2"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(sat_add_s32!(dst = "r0", a = "r1", b = "r2")),
+    "missing `scratch = \"rN\"`"
+  );
+  assert_eq!(
+    __bracer_error_of!(sat_sub_s32!(
+      dst = "r0",
+      a = "r1",
+      b = "r2",
+      scratch = "r0"
+    )),
+    "`scratch` must differ from `dst`, `a`, and `b`"
+  );
+  assert_eq!(
+    __bracer_error_of!(sat_add_s32!(
+      dst = "r0",
+      a = "r1",
+      b = "r2",
+      scratch = "R0"
+    )),
+    "`scratch` must differ from `dst`, `a`, and `b`"
+  );
+  assert_eq!(
+    __bracer_error_of!(sat_add_s32!(
+      dst = "r0",
+      a = "r1",
+      b = "r2",
+      scratch = "r3",
+      arch = v9
+    )),
+    "unknown arch `v9`, expected one of: v4, v4t, v5t, v5te, v6, v6t2, v7"
+  );
+}
+
+#[test]
+fn test_register_names() {
+  // names are case-insensitive, and the aliases work too
+  assert_eq!(a32_write_spsr_from!("IP"), "msr SPSR_cxsf, IP");
+  assert_eq!(a32_write_spsr_from!("fp"), "msr SPSR_cxsf, fp");
+  assert_eq!(a32_write_spsr_from!("r11"), "msr SPSR_cxsf, r11");
+  // substitutions pass through untouched
+  assert_eq!(a32_write_spsr_from!("{temp:x}"), "msr SPSR_cxsf, {temp:x}");
+  assert_eq!(a32_write_spsr_from!("{0}"), "msr SPSR_cxsf, {0}");
+
+  assert_eq!(
+    __bracer_error_of!(a32_read_spsr_to!("banana")),
+    "`banana` is not a register name or an `asm!` substitution like `{temp}`"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_fake_blx!("q7")),
+    "`q7` is not a register name or an `asm!` substitution like `{temp}`"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_write_spsr_from!("r16")),
+    "`r16` is not a register name or an `asm!` substitution like `{temp}`"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_write_spsr_from!("r01")),
+    "`r01` is not a register name or an `asm!` substitution like `{temp}`"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_write_spsr_from!("{temp")),
+    "`{temp` is not a register name or an `asm!` substitution like `{temp}`"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("x0" != "#0")[1]{})),
+    "`x0` is not a register name or an `asm!` substitution like `{temp}`"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" != "0")[1]{})),
+    "the right operand `0` needs a `#` to be an immediate"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("#5" != "r0")[1]{})),
+    "the left operand must be a register, not the immediate `#5`"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("5" != "r0")[1]{})),
+    "the left operand must be a register, not the number `5`"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("" != "r0")[1]{})),
+    "the left operand can't be empty"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" != " ")[1]{})),
+    "the right operand can't be empty"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" != "#")[1]{})),
+    "the right operand `#` is missing the immediate value"
+  );
+  // immediates must be encodable
+  assert_eq!(
+    when!(("r0" == "#0xFF000000")[1]{}),
+    "cmp r0, #0xFF000000\nbne 1f\n1:\n"
+  );
+  assert_eq!(when!(("r0" == "#0x3FC")[1]{}), "cmp r0, #0x3FC\nbne 1f\n1:\n");
+  assert_eq!(when!(("r0" == "#0b1100")[1]{}), "cmp r0, #0b1100\nbne 1f\n1:\n");
+  // negative values are fine when they'd work with `cmn`
+  assert_eq!(when!(("r0" == "#-1")[1]{}), "cmn r0, #1\nbne 1f\n1:\n");
+  assert_eq!(
+    when!(("r0" == "#0xFFFFFF00")[1]{}),
+    "cmp r0, #0xFFFFFF00\nbne 1f\n1:\n"
+  );
+  // anything else is left to the assembler
+  assert_eq!(
+    when!(("r0" == "#SOME_SYM")[1]{}),
+    "cmp r0, #SOME_SYM\nbne 1f\n1:\n"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" == "#0x102")[1]{})),
+    "the immediate 258 (0x102) can't be encoded, it must be an 8-bit value \
+     rotated right by an even amount (or the negation of one)"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" == "#0x12345")[1]{})),
+    "the immediate 74565 (0x12345) can't be encoded, it must be an 8-bit value \
+     rotated right by an even amount (or the negation of one)"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" == "#-257")[1]{})),
+    "the immediate -257 (0xFFFFFEFF) can't be encoded, it must be an 8-bit \
+     value rotated right by an even amount (or the negation of one)"
+  );
+  assert_eq!(
+    __bracer_error_of!(when!(("r0" == "#0x1_0000_0000")[1]{})),
+    "the immediate 4294967296 doesn't fit in 32 bits"
+  );
+  // registers and substitutions are fine on either side
+  assert_eq!(when!(("{a}" <u "r1")[1]{}), "cmp {a}, r1\nbhs 1f\n1:\n");
+  assert_eq!(
+    __bracer_error_of!(mul_const!(dst = "r0", src = "w1", by = 10)),
+    "`w1` is not a register name or an `asm!` substitution like `{temp}`"
+  );
+}
+
+#[test]
+fn test_swap_regs() {
+  let expected =
+    concat!("eor r0, r0, r1\n", "eor r1, r0, r1\n", "eor r0, r0, r1",);
+  assert_eq!(expected, swap_regs!("r0", "r1"));
+
+  let expected = concat!("mov r12, r4\n", "mov r4, lr\n", "mov lr, r12");
+  assert_eq!(expected, swap_regs!("r4", "lr", scratch = "r12"));
+
+  // substitutions with different names are taken to be different registers
+  assert_eq!(
+    swap_regs!("{a}", "{b}"),
+    "eor {a}, {a}, {b}\neor {b}, {a}, {b}\neor {a}, {a}, {b}"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(swap_regs!("r0", "r0")),
+    "`r0` and `r0` are the same register"
+  );
+  assert_eq!(
+    __bracer_error_of!(swap_regs!("r14", "LR")),
+    "`r14` and `LR` are the same register"
+  );
+  assert_eq!(
+    __bracer_error_of!(swap_regs!("{x}", "{x}")),
+    "`{x}` and `{x}` are the same register"
+  );
+  assert_eq!(
+    __bracer_error_of!(swap_regs!("r0", "pc")),
+    "`pc` can't be swapped, it's the program counter"
+  );
+  assert_eq!(
+    __bracer_error_of!(swap_regs!("r15", "r0")),
+    "`r15` can't be swapped, it's the program counter"
+  );
+  assert_eq!(
+    __bracer_error_of!(swap_regs!("r0", "r1", scratch = "r1")),
+    "`scratch` must differ from the registers being swapped"
+  );
+  assert_eq!(
+    __bracer_error_of!(swap_regs!("r0", "r1", scratch = "pc")),
+    "`scratch` can't be the program counter"
+  );
+  assert_eq!(
+    __bracer_error_of!(swap_regs!("r0")),
+    "expected two registers, and optionally a scratch"
+  );
+}
+
+#[test]
+fn test_reg_min_max() {
+  // all four kinds with three different registers
+  assert_eq!(
+    reg_min!("r0" = "r1", "r2", signed),
+    "cmp r1, r2\nmovle r0, r1\nmovgt r0, r2"
+  );
+  assert_eq!(
+    reg_min!("r0" = "r1", "r2", unsigned),
+    "cmp r1, r2\nmovls r0, r1\nmovhi r0, r2"
+  );
+  assert_eq!(
+    reg_max!("r0" = "r1", "r2", signed),
+    "cmp r1, r2\nmovge r0, r1\nmovlt r0, r2"
+  );
+  assert_eq!(
+    reg_max!("r0" = "r1", "r2", unsigned),
+    "cmp r1, r2\nmovhs r0, r1\nmovlo r0, r2"
+  );
+
+  // when `dst` is a source, only the other source is moved
+  assert_eq!(reg_min!("r0" = "r0", "r2", signed), "cmp r0, r2\nmovgt r0, r2");
+  assert_eq!(reg_min!("r2" = "r0", "r2", signed), "cmp r0, r2\nmovle r2, r0");
+  assert_eq!(reg_min!(r0 = r0, r2, unsigned), "cmp r0, r2\nmovhi r0, r2");
+  assert_eq!(reg_min!("r2" = "r0", "r2", unsigned), "cmp r0, r2\nmovls r2, r0");
+  assert_eq!(reg_max!("fp" = "r11", "r2", signed), "cmp r11, r2\nmovlt fp, r2");
+  assert_eq!(reg_max!("r2" = "r0", "r2", signed), "cmp r0, r2\nmovge r2, r0");
+  assert_eq!(reg_max!("r0" = "r0", "r2", unsigned), "cmp r0, r2\nmovlo r0, r2");
+  assert_eq!(reg_max!("r2" = "r0", "r2", unsigned), "cmp r0, r2\nmovhs r2, r0");
+
+  // placeholders work, but only alias if they're the same text
+  assert_eq!(
+    reg_max!("{out}" = "{x}", "{y}", unsigned),
+    "cmp {x}, {y}\nmovhs {out}, {x}\nmovlo {out}, {y}"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(reg_min!("r0" = "r1", "r2")),
+    "missing `signed` or `unsigned` as the last argument"
+  );
+  assert_eq!(
+    __bracer_error_of!(reg_min!("r0" = "r1", "r2", i32)),
+    "expected `signed` or `unsigned` as the last argument"
+  );
+  assert_eq!(
+    __bracer_error_of!(reg_min!("r0", "r1", "r2", signed)),
+    "expected `\"dst\" = \"a\"`, then `\"b\"`, then `signed` or `unsigned`"
+  );
+  assert_eq!(
+    __bracer_error_of!(reg_min!("r0" "r1", "r2", signed)),
+    "the first argument must be of the form `\"dst\" = \"src\"`"
+  );
+  assert_eq!(
+    __bracer_error_of!(reg_min!("r0" = "r1", "r1", signed)),
+    "`r1` and `r1` are the same register"
+  );
+  assert_eq!(
+    __bracer_error_of!(reg_max!("pc" = "r1", "r2", signed)),
+    "`pc` can't be used, it's the program counter"
+  );
+  assert_eq!(
+    __bracer_error_of!(reg_max!("r0" = "r1", "x2", signed)),
+    "`x2` is not a register name or an `asm!` substitution like `{temp}`"
+  );
+}
+
+#[test]
+fn test_reg_abs() {
+  assert_eq!(reg_abs!("r0" = "r1"), "movs r0, r1\nrsbmi r0, r0, #0");
+  assert_eq!(reg_abs!(r0 = r1), "movs r0, r1\nrsbmi r0, r0, #0");
+  assert_eq!(reg_abs!("{x}" = "{y}"), "movs {x}, {y}\nrsbmi {x}, {x}, #0");
+
+  // in place
+  assert_eq!(reg_abs!("r0"), "cmp r0, #0\nrsblt r0, r0, #0");
+  assert_eq!(reg_abs!(r3 = r3), "cmp r3, #0\nrsblt r3, r3, #0");
+
+  // without touching the flags
+  assert_eq!(
+    reg_abs!("r0" = "r1", branchless, scratch = "r2"),
+    "mov r2, r1, asr #31\neor r0, r1, r2\nsub r0, r0, r2"
+  );
+  assert_eq!(
+    reg_abs!("r0", branchless, scratch = "r12"),
+    "mov r12, r0, asr #31\neor r0, r0, r12\nsub r0, r0, r12"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(reg_abs!("r0" = "r1", branchless)),
+    "`branchless` needs a `scratch = \"rN\"` register"
+  );
+  assert_eq!(
+    __bracer_error_of!(reg_abs!("r0" = "r1", scratch = "r2")),
+    "`scratch` is only used with `branchless`"
+  );
+  assert_eq!(
+    __bracer_error_of!(reg_abs!("r0" = "r1", branchless, scratch = "r1")),
+    "`scratch` must differ from the source and destination"
+  );
+  assert_eq!(
+    __bracer_error_of!(reg_abs!("r0", branchless, scratch = "r0")),
+    "`scratch` must differ from the source and destination"
+  );
+  assert_eq!(
+    __bracer_error_of!(reg_abs!("r0", fast)),
+    "expected `branchless` or `scratch = \"rN\"`"
+  );
+  assert_eq!(
+    __bracer_error_of!(reg_abs!("r0", mode = 1)),
+    "unknown argument `mode`"
+  );
+  assert_eq!(
+    __bracer_error_of!(reg_abs!("r15")),
+    "`r15` can't be used, it's the program counter"
+  );
+  assert_eq!(
+    __bracer_error_of!(reg_abs!("r0" = "q1")),
+    "`q1` is not a register name or an `asm!` substitution like `{temp}`"
+  );
+}
+
+#[test]
+fn test_lanes() {
+  assert_eq!(
+    extract_lane!(dst = "r0", src = "r1", lane = 1, width = 8, signed = true),
+    "mov r0, r1, lsl #16\nmov r0, r0, asr #24"
+  );
+  assert_eq!(
+    extract_lane!(dst = "r0", src = "r0", lane = 0, width = 16),
+    "mov r0, r0, lsl #16\nmov r0, r0, lsr #16"
+  );
+  // the top lane only needs the one shift
+  assert_eq!(
+    extract_lane!(dst = "r0", src = "r1", lane = 3, width = 8),
+    "mov r0, r1, lsr #24"
+  );
+
+  // v6 and later have the extend instructions
+  assert_eq!(
+    extract_lane!(
+      dst = "r0",
+      src = "r1",
+      lane = 2,
+      width = 8,
+      signed = true,
+      arch = v6
+    ),
+    "sxtb r0, r1, ror #16"
+  );
+  assert_eq!(
+    extract_lane!(dst = "r0", src = "r1", lane = 0, width = 16, arch = v7),
+    "uxth r0, r1"
+  );
+
+  let expected = concat!(
+    "bic r0, r0, #0xFF0000\n",
+    "bic r0, r0, #0xFF000000\n",
+    "mov r2, r1, lsl #16\n",
+    "orr r0, r0, r2",
+  );
+  assert_eq!(
+    expected,
+    insert_lane!(dst = "r0", src = "r1", scratch = "r2", lane = 1, width = 16)
+  );
+  let expected = concat!(
+    "bic r0, r0, #0xFF00\n",
+    "mov r2, r1, lsl #24\n",
+    "orr r0, r0, r2, lsr #16",
+  );
+  assert_eq!(
+    expected,
+    insert_lane!(dst = "r0", src = "r1", scratch = "r2", lane = 1, width = 8)
+  );
+
+  assert_eq!(
+    __bracer_error_of!(extract_lane!(
+      dst = "r0",
+      src = "r1",
+      lane = 2,
+      width = 16
+    )),
+    "lane 2 of width 16 is past the end of the register, `lane` must be less \
+     than 2"
+  );
+  assert_eq!(
+    __bracer_error_of!(extract_lane!(
+      dst = "r0",
+      src = "r1",
+      lane = 0,
+      width = 4
+    )),
+    "`width` must be 8 or 16"
+  );
+  assert_eq!(
+    __bracer_error_of!(insert_lane!(
+      dst = "r0",
+      src = "r1",
+      scratch = "r0",
+      lane = 0,
+      width = 8
+    )),
+    "`scratch` must differ from `dst` and `src`"
+  );
+  assert_eq!(
+    __bracer_error_of!(insert_lane!(
+      dst = "r0",
+      src = "r1",
+      lane = 0,
+      width = 8,
+      signed = true
+    )),
+    "unknown argument `signed`"
+  );
+  assert_eq!(
+    __bracer_error_of!(insert_lane!(
+      dst = "r0",
+      src = "r1",
+      lane = 0,
+      width = 8
+    )),
+    "missing `scratch = \"rN\"`"
+  );
+}
+
+#[test]
+fn test_prng_step() {
+  let expected = concat!(
+    "eor r0, r0, r0, lsl #13\n",
+    "eor r0, r0, r0, lsr #17\n",
+    "eor r0, r0, r0, lsl #5",
+  );
+  assert_eq!(expected, prng_step!(state = "r0", style = xorshift32));
+  // a scratch is allowed, but not used
+  assert_eq!(
+    expected,
+    prng_step!(state = "r0", style = xorshift32, scratch = "r1")
+  );
+
+  let expected = concat!(
+    "ldr r1, =0x19660D\n",
+    "mul r0, r1, r0\n",
+    "ldr r1, =0x3C6EF35F\n",
+    "add r0, r0, r1",
+  );
+  assert_eq!(expected, prng_step!(state = "r0", style = lcg, scratch = "r1"));
+
+  assert_eq!(
+    __bracer_error_of!(prng_step!(state = "r0", style = lcg)),
+    "the `lcg` style needs a `scratch = \"rN\"`"
+  );
+  assert_eq!(
+    __bracer_error_of!(prng_step!(state = "r0", style = lcg, scratch = "r0")),
+    "`scratch` must differ from `state`"
+  );
+  assert_eq!(
+    __bracer_error_of!(prng_step!(state = "r0", style = mt19937)),
+    "`style` must be `xorshift32` or `lcg`"
+  );
+  assert_eq!(
+    __bracer_error_of!(prng_step!(state = "pc", style = xorshift32)),
+    "`state` can't be the program counter"
+  );
+}
+
+#[test]
+fn test_integrity_word() {
+  // the CRC is the usual CRC-32 of the text, with each line ending in `\n`
+  assert_eq!(integrity_value!({}), 0);
+  assert_eq!(integrity_value!({ "123456789" }), 0xE0117757);
+
+  let expected = concat!(
+    "add r0, r0, #1\n",
+    "sub r1, r1, #1\n",
+    "b check_end\n",
+    "check:\n",
+    ".word 0x3C77ACE9\n",
+    "check_end:\n",
+  );
+  let actual = with_integrity_word!(label = "check", {
+    "add r0, r0, #1",
+    "sub r1, r1, #1",
+  });
+  assert_eq!(expected, actual);
+  assert_eq!(
+    integrity_value!({ "add r0, r0, #1", "sub r1, r1, #1" }),
+    0x3C77ACE9
+  );
+
+  // changing any line changes the value
+  assert_ne!(
+    integrity_value!({ "add r0, r0, #1", "sub r1, r1, #1" }),
+    integrity_value!({ "add r0, r0, #1", "sub r1, r1, #2" })
+  );
+
+  // other bracer macros can be used as lines
+  assert_eq!(
+    integrity_value!({ a32_read_spsr_to!("r0") }),
+    integrity_value!({ "mrs r0, SPSR" })
+  );
+
+  assert_eq!(
+    __bracer_error_of!(with_integrity_word!(label = "check", {
+      "add r0, r0, #1",
+      SOME_CONST,
+    })),
+    "`SOME_CONST` is not a literal, so it can't be folded"
+  );
+  assert_eq!(
+    __bracer_error_of!(with_integrity_word!(label = "1check", {})),
+    "`1check` is not a valid label name"
+  );
+  assert_eq!(
+    __bracer_error_of!(integrity_value!("nop")),
+    "the lines must be in a single grouping"
+  );
+}
+
+#[test]
+fn test_regions() {
+  assert_eq!(
+    region_begin!(kind = section, name = "blit", args = (".iwram.text")),
+    ".pushsection .iwram.text,\"ax\",%progbits"
+  );
+  assert_eq!(region_begin!(kind = a32, name = "mode"), ".code 32");
+  // regions can be closed in any order
+  assert_eq!(region_end!(kind = section, name = "blit"), ".popsection");
+  assert_eq!(region_end!(kind = a32, name = "mode"), ".code 16");
+
+  // a mismatched kind is an error, and leaves the region open
+  assert_eq!(region_begin!(kind = a32, name = "oops"), ".code 32");
+  assert_eq!(
+    __bracer_error_of!(region_end!(kind = section, name = "oops")),
+    "the region named `oops` was opened as `a32`, not `section`"
+  );
+  assert_eq!(
+    __bracer_error_of!(region_begin!(kind = a32, name = "oops")),
+    "the region named `oops` is already open as `a32`"
+  );
+  assert_eq!(
+    __bracer_error_of!(assert_regions_closed!()),
+    "the `a32` region named `oops` was never closed"
+  );
+  // the assertion reports each region once
+  assert_regions_closed!();
+
+  assert_eq!(
+    __bracer_error_of!(region_end!(kind = a32, name = "never")),
+    "there's no open region named `never`"
+  );
+  assert_eq!(
+    __bracer_error_of!(region_begin!(kind = section, name = "s")),
+    "a `section` region needs `args = (\"section_name\")`"
+  );
+  assert_eq!(
+    __bracer_error_of!(region_begin!(kind = vfp, name = "s")),
+    "`kind` must be `section` or `a32`"
+  );
+}
+
+assert_regions_closed!();
+
+#[test]
+fn test_mmio_bits() {
+  assert_eq!(
+    mmio_set_bits!(addr_reg = "r0", mask = 0x0008, scratch = "r1", width = 16),
+    "ldrh r1, [r0]\norr r1, r1, #0x8\nstrh r1, [r0]"
+  );
+  assert_eq!(
+    mmio_set_bits!(addr_reg = "r2", mask = 0x300, scratch = "r3", width = 32),
+    "ldr r3, [r2]\norr r3, r3, #0x300\nstr r3, [r2]"
+  );
+  assert_eq!(
+    mmio_clear_bits!(addr_reg = "r0", mask = 0b101, scratch = "r1", width = 8),
+    "ldrb r1, [r0]\nbic r1, r1, #0x5\nstrb r1, [r0]"
+  );
+  assert_eq!(
+    mmio_clear_bits!(
+      addr_reg = "r0",
+      mask = 0xF000,
+      scratch = "r1",
+      width = 16
+    ),
+    "ldrh r1, [r0]\nbic r1, r1, #0xF000\nstrh r1, [r0]"
+  );
+  assert_eq!(
+    mmio_toggle_bits!(addr_reg = "r0", mask = 1, scratch = "r1", width = 8),
+    "ldrb r1, [r0]\neor r1, r1, #0x1\nstrb r1, [r0]"
+  );
+  assert_eq!(
+    mmio_toggle_bits!(addr_reg = "r0", mask = 0x40, scratch = "r1", width = 32),
+    "ldr r1, [r0]\neor r1, r1, #0x40\nstr r1, [r0]"
+  );
+
+  // masks that can't be an immediate are loaded first
+  let expected = concat!(
+    "ldr r2, =0x8001\n",
+    "ldrh r1, [r0]\n",
+    "orr r1, r1, r2\n",
+    "strh r1, [r0]",
+  );
+  let actual = mmio_set_bits!(
+    addr_reg = "r0",
+    mask = 0x8001,
+    scratch = "r1",
+    mask_scratch = "r2",
+    width = 16
+  );
+  assert_eq!(expected, actual);
+
+  assert_eq!(
+    __bracer_error_of!(mmio_set_bits!(
+      addr_reg = "r0",
+      mask = 0x8001,
+      scratch = "r1",
+      width = 16
+    )),
+    "the mask 0x8001 can't be an immediate, so it needs a `mask_scratch = \
+     \"rN\"` register"
+  );
+  assert_eq!(
+    __bracer_error_of!(mmio_set_bits!(
+      addr_reg = "r0",
+      mask = 0x100,
+      scratch = "r1",
+      width = 8
+    )),
+    "the mask 0x100 doesn't fit in 8 bits"
+  );
+  assert_eq!(
+    __bracer_error_of!(mmio_set_bits!(
+      addr_reg = "r0",
+      mask = 1,
+      scratch = "r0",
+      width = 8
+    )),
+    "`scratch` must differ from `addr_reg`"
+  );
+  assert_eq!(
+    __bracer_error_of!(mmio_set_bits!(
+      addr_reg = "r0",
+      mask = 1,
+      scratch = "r1",
+      width = 64
+    )),
+    "`width` must be 8, 16, or 32"
+  );
+}
+
+#[test]
+fn test_check_a32_imm() {
+  assert_eq!(check_a32_imm!(0x3F0), "");
+  assert_eq!(check_a32_imm!(255), "");
+  assert_eq!(check_a32_imm!(0xFF000000), "");
+  assert_eq!(check_a32_imm!(0b1111_0000_0000), "");
+  assert_eq!(check_a32_imm!(0xFFFFFF00, mvn), "");
+  assert_eq!(check_a32_imm!(-256, cmn), "");
+
+  assert_eq!(
+    __bracer_error_of!(check_a32_imm!(0x102)),
+    "`0x102` (0x102) can't be an `a32` immediate, it must be an 8-bit value \
+     rotated right by an even amount"
+  );
+  assert_eq!(
+    __bracer_error_of!(check_a32_imm!(0xFFFFFF00)),
+    "`0xFFFFFF00` (0xFFFFFF00) can't be an `a32` immediate, it must be an \
+     8-bit value rotated right by an even amount"
+  );
+  assert_eq!(
+    __bracer_error_of!(check_a32_imm!(-257)),
+    "`-257` (0xFFFFFEFF) can't be an `a32` immediate, it must be an 8-bit \
+     value rotated right by an even amount"
+  );
+  assert_eq!(
+    __bracer_error_of!(check_a32_imm!(0x102, cmn)),
+    "`0x102` (0x102) can't be an `a32` immediate, nor can its negation (for \
+     `cmn`), it must be an 8-bit value rotated right by an even amount"
+  );
+  assert_eq!(
+    __bracer_error_of!(check_a32_imm!(0x12345, mvn)),
+    "`0x12345` (0x12345) can't be an `a32` immediate, nor can its complement \
+     (for `mvn`), it must be an 8-bit value rotated right by an even amount"
+  );
+  assert_eq!(
+    __bracer_error_of!(check_a32_imm!(0x1_0000_0000)),
+    "`0x1_0000_0000` doesn't fit in 32 bits"
+  );
+  assert_eq!(
+    __bracer_error_of!(check_a32_imm!(1, mov)),
+    "the second argument must be `mvn` or `cmn`"
+  );
+}
+
+#[test]
+fn test_poll_with_timeout() {
+  let expected = concat!(
+    "ldr r3, =0x2710\n",
+    "4:\n",
+    "ldr r1, [r0]\n",
+    "tst r1, #1\n",
+    "bne 4f\n",
+    "subs r3, r3, #1\n",
+    "bne 4b\n",
+    "b timeout_handler\n",
+    "4:\n",
+  );
+  let actual = poll_with_timeout!(
+    count_reg = "r3",
+    limit = 10000,
+    [4],
+    on_timeout = "timeout_handler",
+    {
+      "ldr r1, [r0]",
+      "tst r1, #1",
+    },
+    until = ne
+  );
+  assert_eq!(expected, actual);
+
+  // a small limit is a `mov`, and without `on_timeout` it falls through
+  let expected = concat!(
+    "mov r2, #100\n",
+    "1:\n",
+    "ldrh r1, [r0]\n",
+    "cmp r1, #0\n",
+    "beq 1f\n",
+    "subs r2, r2, #1\n",
+    "bne 1b\n",
+    "1:\n",
+  );
+  let actual = poll_with_timeout!(
+    count_reg = "r2",
+    limit = 100,
+    until = eq,
+    [1],
+    { "ldrh r1, [r0]", "cmp r1, #0" }
+  );
+  assert_eq!(expected, actual);
+
+  assert_eq!(
+    __bracer_error_of!(poll_with_timeout!(
+      count_reg = "r2",
+      limit = 100,
+      until = zz,
+      [1],
+      {}
+    )),
+    "`until` must be a condition code, eg: `ne`"
+  );
+  assert_eq!(
+    __bracer_error_of!(poll_with_timeout!(
+      count_reg = "r2",
+      limit = 0,
+      until = ne,
+      [1],
+      {}
+    )),
+    "`limit` can't be zero"
+  );
+  assert_eq!(
+    __bracer_error_of!(poll_with_timeout!(
+      count_reg = "sp",
+      limit = 1,
+      until = ne,
+      [1],
+      {}
+    )),
+    "`count_reg` can't be `sp`"
+  );
+  assert_eq!(
+    __bracer_error_of!(poll_with_timeout!(
+      count_reg = "r2",
+      limit = 1,
+      until = ne,
+      on_timeout = "bad name",
+      [1],
+      {}
+    )),
+    "`bad name` is not a valid symbol name"
+  );
+  assert_eq!(
+    __bracer_error_of!(poll_with_timeout!(
+      count_reg = "r2",
+      limit = 1,
+      until = ne,
+      {}
+    )),
+    "expected a group for the label and a group for the body"
+  );
+}
+
+#[test]
+fn test_asm_block() {
+  let expected = concat!(
+    "movs r0, #1\n",
+    ".balignw 4, 0x46C0\n",
+    ".code 32\n",
+    "mrs r1, CPSR\n",
+    ".code 16\n",
+    // the scope macro ends with a newline, then another is added after it
+    "\n",
+    "adds r0, r0, r1\n",
+  );
+  let actual = asm_block!(start = t32, end = t32, {
+    "movs r0, #1",
+    t32_with_a32_scope! {
+      "mrs r1, CPSR",
+    },
+    "adds r0, r0, r1",
+  });
+  assert_eq!(expected, actual);
+
+  // switching with the other directives
+  assert_eq!(
+    asm_block!(start = a32, end = t32, { "mov r0, #1", ".thumb" }),
+    "mov r0, #1\n.thumb\n"
+  );
+  assert_eq!(
+    asm_block!(start = t32, end = a32, { ".ARM  @ to a32" }),
+    ".ARM  @ to a32\n"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(asm_block!(start = t32, end = t32, {
+      "movs r0, #1",
+      ".code 32",
+      "mov r0, #2",
+    })),
+    "the block should end as t32, but it ends as a32"
+  );
+  assert_eq!(
+    __bracer_error_of!(asm_block!(start = a32, end = t32, {
+      ".code 32",
+      "mov r0, #2",
+      ".code 16",
+    })),
+    "`.code 32` switches to a32, but the code is already a32"
+  );
+  // a nested scope macro in a32 code is caught too
+  assert_eq!(
+    __bracer_error_of!(asm_block!(start = a32, end = a32, {
+      t32_with_a32_scope! { "mov r0, #2" },
+    })),
+    "`.code 32` switches to a32, but the code is already a32"
+  );
+  assert_eq!(
+    __bracer_error_of!(asm_block!(start = arm, end = a32, {})),
+    "`start` must be `a32` or `t32`"
+  );
+}
+
+#[test]
+fn test_asm_consts() {
+  let expected = concat!(
+    "and r1, r0, #0x1F\n",
+    "cmp r1, #0x1F\n",
+    "orr r0, r0, #0x80\n",
+    "msr CPSR_c, r0\n",
+  );
+  let actual = asm_consts!({ MODE_MASK = 0x1F, IRQ_BIT = 0x80 }, {
+    "and r1, r0, #MODE_MASK",
+    "cmp r1, #MODE_MASK",
+    "orr r0, r0, #IRQ_BIT",
+    "msr CPSR_c, r0",
+  });
+  assert_eq!(expected, actual);
+
+  // several uses in one line, and the assembler's swaps are allowed
+  assert_eq!(
+    asm_consts!({ A = 1, NOT_A = 0xFFFFFFFE }, { "mov r0, #NOT_A;add r0, #A" }),
+    "mov r0, #0xFFFFFFFE;add r0, #0x1\n"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(asm_consts!({ A = 1 }, { "mov r0, #A", "add r0, #B" })),
+    "`#B` is used but never declared"
+  );
+  assert_eq!(
+    __bracer_error_of!(asm_consts!({ BIG = 0x102 }, { "cmp r0, #BIG" })),
+    "`BIG` is 0x102, which can't be an immediate of `cmp`"
+  );
+  // `adc` and `sbc` swap with the complement, not the negation
+  assert_eq!(
+    asm_consts!({ K = 0xFFFFFBFF }, { "adc r0, r0, #K" }),
+    "adc r0, r0, #0xFFFFFBFF\n"
+  );
+  assert_eq!(
+    __bracer_error_of!(asm_consts!({ K = 0xFFFFFC00 }, { "adc r0, r0, #K" })),
+    "`K` is 0xFFFFFC00, which can't be an immediate of `adc`"
+  );
+  assert_eq!(
+    __bracer_error_of!(asm_consts!({ A = 1, A = 2 }, {})),
+    "`A` is declared twice"
+  );
+  // unused names are only an error when asked for
+  assert_eq!(asm_consts!({ A = 1 }, { "nop" }), "nop\n");
+  assert_eq!(
+    __bracer_error_of!(asm_consts!(deny_unused = true, { A = 1, B = 2 }, {
+      "nop"
+    })),
+    "`A` is never used\n`B` is never used"
+  );
+}
+
+#[test]
+fn test_with_masked_interrupts() {
+  assert_eq!(
+    with_masked_interrupts!("r12", temp = "r3", {
+      "ldr r0, [r1]",
+      "add r0, r0, #1",
+      "str r0, [r1]",
+    }),
+    "mrs r12, CPSR\norr r3, r12, #0xC0\nmsr CPSR_c, r3\nldr r0, [r1]\nadd r0, \
+     r0, #1\nstr r0, [r1]\nmsr CPSR_c, r12\n"
+  );
+  assert_eq!(
+    with_masked_interrupts!(r12, temp = "r3", irq_only, { "str r0, [r1]" }),
+    "mrs r12, CPSR\norr r3, r12, #0x80\nmsr CPSR_c, r3\nstr r0, [r1]\nmsr \
+     CPSR_c, r12\n"
+  );
+  assert_eq!(
+    with_masked_interrupts!("r12", temp = "r3", fiq_only, { "nop" }),
+    "mrs r12, CPSR\norr r3, r12, #0x40\nmsr CPSR_c, r3\nnop\nmsr CPSR_c, r12\n"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(with_masked_interrupts!("r12", { "nop" })),
+    "missing `temp = \"rN\"`, a second register to build the masked CPSR in, \
+     since the saved CPSR has to be kept as it is"
+  );
+  assert_eq!(
+    __bracer_error_of!(with_masked_interrupts!("r12", temp = "ip", { "nop" })),
+    "`temp` must differ from the register that saves the CPSR"
+  );
+  assert_eq!(
+    __bracer_error_of!(with_masked_interrupts!("sp", temp = "r3", { "nop" })),
+    "can't use `sp` as the scratch register, using `sp` or `pc` with \
+     `mrs`/`msr` is unpredictable"
+  );
+  assert_eq!(
+    __bracer_error_of!(with_masked_interrupts!(
+      "r12",
+      temp = "r3",
+      irq_only,
+      fiq_only,
+      { "nop" }
+    )),
+    "only one of `irq_only` or `fiq_only` can be given"
+  );
+  assert_eq!(
+    __bracer_error_of!(with_masked_interrupts!("r12", temp = "r3")),
+    "missing the body"
+  );
+}
+
+#[test]
+fn test_exception_return() {
+  assert_eq!(exception_return!(Irq), "subs pc, lr, #4");
+  assert_eq!(exception_return!(Fiq), "subs pc, lr, #4");
+  assert_eq!(exception_return!(Swi), "movs pc, lr");
+  assert_eq!(exception_return!(Svc), "movs pc, lr");
+  assert_eq!(exception_return!(Undefined), "movs pc, lr");
+  assert_eq!(exception_return!(PrefetchAbort), "subs pc, lr, #4");
+  assert_eq!(exception_return!(DataAbort), "subs pc, lr, #8");
+
+  assert_eq!(
+    __bracer_error_of!(exception_return!(Reset)),
+    "unknown exception kind `Reset`, expected one of: `Irq`, `Fiq`, \
+     `Swi`/`Svc`, `Undefined`, `PrefetchAbort`, `DataAbort`"
+  );
+  assert_eq!(
+    __bracer_error_of!(exception_return!("Irq")),
+    "expected one exception kind, eg: `Irq`"
+  );
+}
+
+#[test]
+fn test_irq_handler_prologue_epilogue() {
+  assert_eq!(irq_handler_prologue!(), "push {{r0-r3, r12, lr}}");
+  assert_eq!(irq_handler_epilogue!(), "pop {{r0-r3, r12, lr}}");
+  assert_eq!(
+    irq_handler_prologue!(save = "r0 - r3,r12 , lr", spsr = "r3"),
+    "push {{r0-r3, r12, lr}}\nmrs r3, SPSR\npush {{r3}}"
+  );
+
+  // the epilogue is the prologue's steps undone in reverse
+  let prologue = irq_handler_prologue!(
+    save = "r0-r5, r12, lr",
+    spsr = "r4",
+    sys_mode = true,
+    fiq_masked = true
+  );
+  let epilogue = irq_handler_epilogue!(
+    save = "r0-r5, r12, lr",
+    spsr = "r4",
+    sys_mode = true,
+    fiq_masked = true
+  );
+  let prologue_lines: Vec<&str> = prologue.lines().collect();
+  let epilogue_lines: Vec<&str> = epilogue.lines().rev().collect();
+  assert_eq!(prologue_lines.len(), 5);
+  assert_eq!(epilogue_lines.len(), 5);
+  let pairs = [
+    ("push {{r0-r5, r12, lr}}", "pop {{r0-r5, r12, lr}}"),
+    ("mrs r4, SPSR", "msr SPSR_cxsf, r4"),
+    ("push {{r4}}", "pop {{r4}}"),
+    ("msr CPSR_c, #0b11011111", "msr CPSR_c, #0b11010010"),
+    ("push {{lr}}", "pop {{lr}}"),
+  ];
+  for (i, (save, restore)) in pairs.into_iter().enumerate() {
+    assert_eq!(prologue_lines[i], save);
+    assert_eq!(epilogue_lines[i], restore);
+  }
+
+  assert_eq!(
+    __bracer_error_of!(irq_handler_prologue!(spsr = "r4")),
+    "`r4` must also be in the `save` list, or the interrupted code's value \
+     of it is lost"
+  );
+  assert_eq!(
+    __bracer_error_of!(irq_handler_prologue!(spsr = "lr")),
+    "`spsr` can't be `lr`, it must be one of `r0`-`r12`"
+  );
+  assert_eq!(
+    __bracer_error_of!(irq_handler_prologue!(save = "r0-r3, sp")),
+    "the `save` list can't hold `sp` or `pc`"
+  );
+  assert_eq!(
+    __bracer_error_of!(irq_handler_epilogue!(save = "r0-r3, r2")),
+    "`r2` is in the `save` list twice"
+  );
+  assert_eq!(
+    __bracer_error_of!(irq_handler_epilogue!(save = "r3-r0")),
+    "the range `r3-r0` in the `save` list is backwards"
+  );
+  assert_eq!(
+    __bracer_error_of!(irq_handler_epilogue!(save = "r0, x")),
+    "`x` in the `save` list is not a register or a range of registers, eg: \
+     `r0-r3`"
+  );
+}
+
+#[test]
+fn test_set_mode_stacks() {
+  let expected = concat!(
+    "msr CPSR_c, #0b11010010\n",
+    "ldr sp, =0x03007FA0\n",
+    "msr CPSR_c, #0b11010011\n",
+    "ldr sp, =0x03007FE0\n",
+    "msr CPSR_c, #0b11011111\n",
+    "ldr sp, =0x03007F00",
+  );
+  assert_eq!(
+    set_mode_stacks!(
+      irq = "=0x03007FA0",
+      svc = "=0x03007FE0",
+      sys = "=0x03007F00"
+    ),
+    expected
+  );
+
+  // ending in another mode, with long names and registers
+  assert_eq!(
+    set_mode_stacks!(FIQ = "r0", Abort = "=abort_stack", end_in = System),
+    "msr CPSR_c, #0b11010001\nmov sp, r0\nmsr CPSR_c, #0b11010111\nldr sp, \
+     =abort_stack\nmsr CPSR_c, #0b11011111"
+  );
+  // `end_in` that's already the last mode adds nothing
+  assert_eq!(
+    set_mode_stacks!(und = "{x}", end_in = und),
+    "msr CPSR_c, #0b11011011\nmov sp, {x}"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(set_mode_stacks!(irq = "=1", hyp = "=2")),
+    "unknown mode `hyp`, expected one of: `User`/`usr`, `FIQ`/`fiq`, \
+     `IRQ`/`irq`, `Supervisor`/`svc`, `Abort`/`abt`, `Undefined`/`und`, \
+     `System`/`sys`"
+  );
+  assert_eq!(
+    __bracer_error_of!(set_mode_stacks!(irq = "=1", IRQ = "=2")),
+    "the stack for `IRQ` is given twice"
+  );
+  assert_eq!(
+    __bracer_error_of!(set_mode_stacks!(usr = "=1")),
+    "User mode can't switch to other modes, set its stack with `sys`, which \
+     shares `sp` with User mode"
+  );
+  assert_eq!(
+    __bracer_error_of!(set_mode_stacks!(irq = "r8")),
+    "`r8` is banked in some modes, use one of `r0`-`r7`"
+  );
+  assert_eq!(
+    __bracer_error_of!(set_mode_stacks!(irq = "0x03007FA0")),
+    "`irq` must be `=value` to load from a literal pool, or a register, eg: \
+     `\"=0x03007FA0\"` or `\"r0\"`"
+  );
+  assert_eq!(
+    __bracer_error_of!(set_mode_stacks!(end_in = sys)),
+    "no stacks to set, give at least one `mode = value`, eg: `irq = \
+     \"=0x03007FA0\"`"
+  );
+}
+
+#[test]
+fn test_vector_table() {
+  let expected = concat!(
+    "b _start\n",
+    "b undef_handler\n",
+    "b swi_handler\n",
+    "b pabt_handler\n",
+    "b dabt_handler\n",
+    "b reserved_handler\n",
+    "b irq_handler\n",
+    "b fiq_handler",
+  );
+  // given out of order, the table is still in the CPU's order
+  let table = vector_table!(
+    fiq = "fiq_handler",
+    irq = "irq_handler",
+    reserved = "reserved_handler",
+    data_abort = "dabt_handler",
+    prefetch_abort = "pabt_handler",
+    svc = "swi_handler",
+    undef = "undef_handler",
+    reset = "_start",
+    style = b
+  );
+  assert_eq!(table, expected);
+  assert_eq!(table.lines().count(), 8);
+
+  assert_eq!(
+    vector_table!(reset = "_start", irq = "irq_handler"),
+    "ldr pc, =_start\nb .\nb .\nb .\nb .\nb .\nldr pc, =irq_handler\nb ."
+  );
+  assert_eq!(
+    vector_table!(undefined = "und", default = "hang", style = ldr),
+    "ldr pc, =hang\nldr pc, =und\nldr pc, =hang\nldr pc, =hang\nldr pc, \
+     =hang\nldr pc, =hang\nldr pc, =hang\nldr pc, =hang"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(vector_table!(swi = "a", svc = "b")),
+    "the target for `svc` is given twice"
+  );
+  assert_eq!(
+    __bracer_error_of!(vector_table!(hyp = "a")),
+    "unknown vector `hyp`, expected one of: `reset`, `undefined`, `swi`, \
+     `prefetch_abort`, `data_abort`, `reserved`, `irq`, `fiq`, or `default`"
+  );
+  assert_eq!(
+    __bracer_error_of!(vector_table!(reset = "_start", style = bl)),
+    "`style` must be `ldr` or `b`"
+  );
+  assert_eq!(
+    __bracer_error_of!(vector_table!(reset = "1st")),
+    "`1st` is not a valid symbol name"
+  );
+}
+
+#[test]
+fn test_swi_call() {
+  assert_eq!(swi_call!(0x04, t32), "swi #0x04");
+  assert_eq!(swi_call!(0x04, a32), "swi #0x040000");
+  assert_eq!(swi_call!(11, t32), "swi #0x0B");
+  assert_eq!(swi_call!(11, a32), "swi #0x0B0000");
+  assert_eq!(swi_call!(0b1111_1111, t32), "swi #0xFF");
+  assert_eq!(swi_call!(0xFF, a32), "swi #0xFF0000");
+  assert_eq!(swi_call!(0, a32), "swi #0x000000");
+
+  assert_eq!(
+    __bracer_error_of!(swi_call!(0x04)),
+    "pick a state with `a32` or `t32`, the call number is placed differently \
+     in each"
+  );
+  assert_eq!(
+    __bracer_error_of!(swi_call!(0x100, t32)),
+    "the call number must be 0 to 0xFF, which is all that fits in the t32 \
+     comment field, and the a32 one after `lsl #16`, got 256"
+  );
+  assert_eq!(
+    __bracer_error_of!(swi_call!(0x100, a32)),
+    "the call number must be 0 to 0xFF, which is all that fits in the t32 \
+     comment field, and the a32 one after `lsl #16`, got 256"
+  );
+  assert_eq!(
+    __bracer_error_of!(swi_call!(-1, t32)),
+    "the call number must be 0 to 0xFF, which is all that fits in the t32 \
+     comment field, and the a32 one after `lsl #16`, got -1"
+  );
+  assert_eq!(
+    __bracer_error_of!(swi_call!(4, thumb)),
+    "the state must be `a32` or `t32`"
+  );
+}
+
+#[test]
+fn test_nested_irq() {
+  let expected = concat!(
+    "mrs r12, SPSR\n",
+    "push {{r12, lr}}\n",
+    "msr CPSR_c, #0b00011111\n",
+    "push {{r12, lr}}",
+  );
+  assert_eq!(nested_irq_enter!(scratch = "r12"), expected);
+
+  let expected = concat!(
+    "pop {{r12, lr}}\n",
+    "msr CPSR_c, #0b10010010\n",
+    "pop {{r12, lr}}\n",
+    "msr SPSR_cxsf, r12",
+  );
+  assert_eq!(nested_irq_exit!(scratch = "r12"), expected);
+
+  // the exit is the enter's steps undone in reverse
+  let enter = nested_irq_enter!(scratch = "r3", fiq_masked = true);
+  let exit = nested_irq_exit!(scratch = "r3", fiq_masked = true);
+  let enter_lines: Vec<&str> = enter.lines().collect();
+  let exit_lines: Vec<&str> = exit.lines().rev().collect();
+  assert_eq!(enter_lines.len(), exit_lines.len());
+  assert_eq!(enter_lines[0], "mrs r3, SPSR");
+  assert_eq!(exit_lines[0], "msr SPSR_cxsf, r3");
+  assert_eq!(enter_lines[1], "push {{r3, lr}}");
+  assert_eq!(exit_lines[1], "pop {{r3, lr}}");
+  assert_eq!(enter_lines[2], "msr CPSR_c, #0b01011111");
+  assert_eq!(exit_lines[2], "msr CPSR_c, #0b11010010");
+  assert_eq!(enter_lines[3], "push {{r3, lr}}");
+  assert_eq!(exit_lines[3], "pop {{r3, lr}}");
+
+  assert_eq!(
+    __bracer_error_of!(nested_irq_enter!(scratch = "sp")),
+    "`scratch` can't be `sp`, it must be one of `r0`-`r12`"
+  );
+  assert_eq!(
+    __bracer_error_of!(nested_irq_exit!()),
+    "missing `scratch = \"rN\"`"
+  );
+}
+
+#[test]
+fn test_cp15_read_write() {
+  assert_eq!(cp15_read!("r0", ControlRegister), "mrc p15, 0, r0, c1, c0, 0");
+  assert_eq!(cp15_write!(r0, Control), "mcr p15, 0, r0, c1, c0, 0");
+  assert_eq!(cp15_read!("r1", Midr), "mrc p15, 0, r1, c0, c0, 0");
+  assert_eq!(cp15_read!("r2", CacheType), "mrc p15, 0, r2, c0, c0, 1");
+  assert_eq!(cp15_write!("r3", Ttb), "mcr p15, 0, r3, c2, c0, 0");
+  assert_eq!(cp15_read!("r4", Far), "mrc p15, 0, r4, c6, c0, 0");
+  assert_eq!(
+    cp15_write!("r0", CleanInvalidateDCacheLine),
+    "mcr p15, 0, r0, c7, c14, 1"
+  );
+  assert_eq!(cp15_write!("{x}", DtcmRegion), "mcr p15, 0, {x}, c9, c1, 0");
+
+  // raw registers
+  assert_eq!(cp15_read!("r0", c1, c0, 0), "mrc p15, 0, r0, c1, c0, 0");
+  assert_eq!(cp15_write!("r5", c15, c12, 7), "mcr p15, 0, r5, c15, c12, 7");
+
+  assert_eq!(
+    __bracer_error_of!(cp15_read!("pc", Control)),
+    "`pc` can't be used, it's the program counter"
+  );
+  assert_eq!(
+    __bracer_error_of!(cp15_read!("r0", DrainWriteBuffer)),
+    "`DrainWriteBuffer` can't be read"
+  );
+  assert_eq!(
+    __bracer_error_of!(cp15_write!("r0", CacheType)),
+    "`CacheType` can't be written"
+  );
+  assert_eq!(
+    __bracer_error_of!(cp15_read!("r0", c16, c0, 0)),
+    "`CRn` must be one of `c0`-`c15`"
+  );
+  assert_eq!(
+    __bracer_error_of!(cp15_read!("r0", c1, c0, 8)),
+    "`opcode2` must be an integer from 0 to 7"
+  );
+  assert!(__bracer_error_of!(cp15_read!("r0", Mmu))
+    .starts_with("unknown CP15 register `Mmu`, expected one of: `MainId`, "));
+  assert_eq!(__bracer_error_of!(cp15_read!("r0")), "missing the CP15 register");
+}
+
+#[test]
+fn test_swp_swap() {
+  assert_eq!(swp_swap!("r0", "r1", ["r2"]), "swp r0, r1, [r2]");
+  assert_eq!(swp_swap!(byte "r0", "r1", ["r2"]), "swpb r0, r1, [r2]");
+  assert_eq!(swp_swap!(r0, r0, [r12]), "swp r0, r0, [r12]");
+  assert_eq!(swp_swap!(byte "{a}", "{b}", ["{p}"]), "swpb {a}, {b}, [{p}]");
+
+  assert_eq!(
+    __bracer_error_of!(swp_swap!("r0", "r1", ["r0"])),
+    "the address register `r0` can't also be the destination register, the \
+     result of that is unpredictable"
+  );
+  assert_eq!(
+    __bracer_error_of!(swp_swap!(byte "r0", "r12", ["ip"])),
+    "the address register `ip` can't also be the source register, the result \
+     of that is unpredictable"
+  );
+  assert_eq!(
+    __bracer_error_of!(swp_swap!("{x}", "r1", ["{x}"])),
+    "the address register `{x}` can't also be the destination register, the \
+     result of that is unpredictable"
+  );
+  assert_eq!(
+    __bracer_error_of!(swp_swap!("pc", "r1", ["r2"])),
+    "can't use `pc` as the destination register of a `swp`"
+  );
+  assert_eq!(
+    __bracer_error_of!(swp_swap!("r0", "sp", ["r2"])),
+    "can't use `sp` as the source register of a `swp`"
+  );
+  assert_eq!(
+    __bracer_error_of!(swp_swap!("r0", "r1", ["r13"])),
+    "can't use `r13` as the address register of a `swp`"
+  );
+  assert_eq!(
+    __bracer_error_of!(swp_swap!("r0", "r1", "r2")),
+    "the address register must be in brackets, eg: `[\"r2\"]`"
+  );
+}
+
+#[test]
+fn test_a32_with_mode() {
+  assert_eq!(
+    a32_with_mode!(FIQ, scratch = "r7", {
+      "mov sp, r0",
+      "mov r8, #0",
+    }),
+    "mrs r7, CPSR\nmsr CPSR_c, #0b11010001\nmov sp, r0\nmov r8, #0\nmsr \
+     CPSR_c, r7\n"
+  );
+  assert_eq!(
+    a32_with_mode!(und, scratch = "{s}", { "mov sp, r0" }),
+    "mrs {s}, CPSR\nmsr CPSR_c, #0b11011011\nmov sp, r0\nmsr CPSR_c, {s}\n"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(a32_with_mode!(FIQ, scratch = "r12", { "nop" })),
+    "`scratch` can't be `r12`, it must be one of `r0`-`r7` so that it's the \
+     same register in every mode"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_with_mode!(usr, scratch = "r0", { "nop" })),
+    "can't come back from User mode, since it can't change the mode itself"
+  );
+  assert!(__bracer_error_of!(a32_with_mode!(hyp, scratch = "r0", { "nop" }))
+    .starts_with("the mode must be one of: `User`/`usr`, "));
+  assert_eq!(
+    __bracer_error_of!(a32_with_mode!(IRQ, { "nop" })),
+    "missing `scratch = \"rN\"`"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_with_mode!(IRQ, scratch = "r0")),
+    "missing the body"
+  );
+}
+
+#[test]
+fn test_bkpt_debug() {
+  // the proc-macro is built with the same `debug_assertions` as the tests.
+  let expected = if cfg!(debug_assertions) { "bkpt #0x07" } else { "" };
+  assert_eq!(bkpt_debug!(0x07), expected);
+  assert_eq!(bkpt_debug!(7, t32), expected);
+  assert_eq!(bkpt_debug!(0x07, debug_only = true), expected);
+
+  assert_eq!(bkpt_debug!(0x07, debug_only = false), "bkpt #0x07");
+  assert_eq!(bkpt_debug!(0xFFFF, a32, debug_only = false), "bkpt #0xFFFF");
+  assert_eq!(bkpt_debug!(0b1111_1111, t32, debug_only = false), "bkpt #0xFF");
+  assert_eq!(bkpt_debug!(0, debug_only = false), "bkpt #0x00");
+
+  assert_eq!(
+    __bracer_error_of!(bkpt_debug!(0x100, t32)),
+    "the breakpoint number must be 0 to 0xFF in t32, got 256"
+  );
+  assert_eq!(
+    __bracer_error_of!(bkpt_debug!(0x10000)),
+    "the breakpoint number must be 0 to 0xFFFF in a32, got 65536"
+  );
+  assert_eq!(
+    __bracer_error_of!(bkpt_debug!(1, thumb)),
+    "expected `a32`, `t32`, or `debug_only = bool`"
+  );
+}
+
+#[test]
+fn test_literal_pool() {
+  assert_eq!(emit_literal_pool!(), ".ltorg\n");
+  assert_eq!(
+    __bracer_error_of!(emit_literal_pool!(x)),
+    "this macro takes no input"
+  );
+
+  assert_eq!(
+    with_pool!("ldr r0, =0x04000208", "str r1, [r0]", "bx lr"),
+    "ldr r0, =0x04000208\nstr r1, [r0]\nbx lr\n.ltorg\n"
+  );
+  // comments and blank lines aren't counted, other expressions count as one
+  assert_eq!(
+    with_pool!(
+      max_lines = 3,
+      "ldr r0, =1 @ one\n\n// nothing\nldr r1, =2",
+      concat!("bx ", "lr"),
+    ),
+    "ldr r0, =1 @ one\n\n// nothing\nldr r1, =2\nbx lr\n.ltorg\n"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(with_pool!(max_lines = 2, "ldr r0, =1\nnop", "bx lr")),
+    "the body is 3 lines, more than `max_lines` (2), so an `ldr` near the \
+     start might not reach the literal pool at the end, split it up with \
+     `emit_literal_pool!()` or raise `max_lines`"
+  );
+  assert_eq!(
+    __bracer_error_of!(with_pool!(max_lines = 2)),
+    "missing the body lines"
+  );
+}
+
+#[test]
+fn test_load_const() {
+  // mov
+  assert_eq!(load_const!("r0", 0x04000000), "mov r0, #0x4000000");
+  assert_eq!(load_const!(r1, 255), "mov r1, #0xFF");
+  assert_eq!(load_const!("r2", 0xF000000F), "mov r2, #0xF000000F");
+  // mvn
+  assert_eq!(load_const!("r0", 0xFFFFFF00), "mvn r0, #0xFF");
+  assert_eq!(load_const!("r0", -1), "mvn r0, #0x0");
+  // mov + orr
+  assert_eq!(
+    load_const!("r0", 0x04000208),
+    "mov r0, #0x208\norr r0, r0, #0x4000000"
+  );
+  assert_eq!(
+    load_const!("r3", 0x0000FFFF),
+    "mov r3, #0xFF\norr r3, r3, #0xFF00"
+  );
+  // ldr
+  assert_eq!(load_const!("r0", 0x12345678), "ldr r0, =0x12345678");
+  assert_eq!(load_const!("{x}", 0x0101_0101), "ldr {x}, =0x1010101");
+
+  assert_eq!(
+    load_const!("r0", 0x04000000, strategy = pool),
+    "ldr r0, =0x4000000"
+  );
+  assert_eq!(
+    load_const!("r0", 0x04000208, strategy = inline),
+    "mov r0, #0x208\norr r0, r0, #0x4000000"
+  );
+  assert_eq!(
+    __bracer_error_of!(load_const!("r0", 0x12345678, strategy = inline)),
+    "`0x12345678` (0x12345678) can't be loaded inline, it isn't an `a32` \
+     immediate, the complement of one, or two of them `orr`ed together"
+  );
+  assert_eq!(
+    __bracer_error_of!(load_const!("pc", 1)),
+    "`pc` can't be used, it's the program counter"
+  );
+  assert_eq!(
+    __bracer_error_of!(load_const!("r0", 0x1_0000_0000)),
+    "`0x1_0000_0000` doesn't fit in 32 bits"
+  );
+}
+
+#[test]
+fn test_def_asm_const() {
+  assert_eq!(
+    def_asm_const!("REG_DISPCNT", 0x04000000),
+    ".equ REG_DISPCNT, 0x4000000\n"
+  );
+  assert_eq!(def_asm_const!("ten", 10), ".equ ten, 0xA\n");
+  assert_eq!(def_asm_const!("m", 0b1000_0000), ".equ m, 0x80\n");
+  assert_eq!(def_asm_const!("neg", -1), ".equ neg, 0xFFFFFFFF\n");
+  assert_eq!(
+    def_asm_const! {
+      "REG_DISPCNT" = 0x04000000,
+      "REG_IME" = 0x04000208,
+      ".L_local" = 0o17,
+    },
+    ".equ REG_DISPCNT, 0x4000000\n.equ REG_IME, 0x4000208\n.equ .L_local, \
+     0xF\n"
+  );
+  assert_eq!(def_asm_const!("ONE" = 1), ".equ ONE, 0x1\n");
+  assert_eq!(def_asm_const!("a.b$c" = 2), ".equ a.b$c, 0x2\n");
+
+  assert_eq!(
+    __bracer_error_of!(def_asm_const!("1st", 1)),
+    "`1st` can't be an assembler symbol, it must be letters, digits, `_`, \
+     `.`, and `$`, and not start with a digit"
+  );
+  assert_eq!(
+    __bracer_error_of!(def_asm_const!("a-b" = 1)),
+    "`a-b` can't be an assembler symbol, it must be letters, digits, `_`, \
+     `.`, and `$`, and not start with a digit"
+  );
+  assert_eq!(
+    __bracer_error_of!(def_asm_const!("A" = 1, "A" = 2)),
+    "`A` is defined twice"
+  );
+  assert_eq!(
+    __bracer_error_of!(def_asm_const!("A", 0x1_0000_0000)),
+    "`A` must be a 32-bit integer literal"
+  );
+}
+
+#[test]
+fn test_a32_fn_header() {
+  assert_eq!(
+    a32_fn_header!("my_handler"),
+    ".global my_handler\n.type my_handler, %function\n.balign 4\nmy_handler:"
+  );
+  assert_eq!(
+    a32_fn_header!("helper", global = false, align = 8),
+    ".type helper, %function\n.balign 8\nhelper:"
+  );
+
+  // as part of a whole `global_asm!` string
+  let full = concat!(
+    put_fn_in_section!(".text.my_handler"),
+    "\n",
+    ".arm\n",
+    a32_fn_header!("my_handler"),
+    "\n",
+    "bx lr\n",
+  );
+  assert_eq!(
+    full,
+    ".section .text.my_handler,\"ax\",%progbits\n.arm\n.global \
+     my_handler\n.type my_handler, %function\n.balign 4\nmy_handler:\nbx lr\n"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(a32_fn_header!("1st")),
+    "`1st` is not a valid symbol name"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_fn_header!("f", align = 2)),
+    "`align` must be a power of two of at least 4 for a32 code, got 2"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_fn_header!("f", align = 12)),
+    "`align` must be a power of two of at least 4 for a32 code, got 12"
+  );
+  assert_eq!(
+    __bracer_error_of!(a32_fn_header!(f)),
+    "the first argument must be the function name, as a string literal"
+  );
+}
+
+#[test]
+fn test_t32_fn_header() {
+  assert_eq!(
+    t32_fn_header!("my_thumb_fn"),
+    ".global my_thumb_fn\n.type my_thumb_fn, %function\n.balign 2\n.thumb_func\n\
+     my_thumb_fn:"
+  );
+  assert_eq!(
+    t32_fn_header!("helper", global = false, align = 4),
+    ".type helper, %function\n.balign 4\n.thumb_func\nhelper:"
+  );
+
+  // the `.code 16` comes from elsewhere in the `global_asm!` string
+  let full = concat!(
+    put_fn_in_section!(".text.my_thumb_fn"),
+    "\n",
+    ".code 16\n",
+    t32_fn_header!("my_thumb_fn"),
+    "\n",
+    "bx lr\n",
+  );
+  assert_eq!(
+    full,
+    ".section .text.my_thumb_fn,\"ax\",%progbits\n.code 16\n.global \
+     my_thumb_fn\n.type my_thumb_fn, %function\n.balign 2\n.thumb_func\n\
+     my_thumb_fn:\nbx lr\n"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(t32_fn_header!("f", align = 1)),
+    "`align` must be a power of two of at least 2 for t32 code, got 1"
+  );
+  assert_eq!(
+    __bracer_error_of!(t32_fn_header!("a b")),
+    "`a b` is not a valid symbol name"
+  );
+}
+
+#[test]
+fn test_fn_footer() {
+  assert_eq!(fn_footer!("my_handler"), ".size my_handler, . - my_handler\n");
+  assert_eq!(
+    fn_footer!("my_handler", pool = true),
+    ".ltorg\n.size my_handler, . - my_handler\n"
+  );
+  assert_eq!(
+    __bracer_error_of!(fn_footer!("my handler")),
+    "`my handler` is not a valid symbol name"
+  );
+  assert_eq!(
+    __bracer_error_of!(fn_footer!("f", pool = 1)),
+    "`pool` must be set as `true` or `false`"
+  );
+}
+
+#[test]
+fn test_asm_fn() {
+  assert_eq!(
+    asm_fn!("my_handler", {
+      "mov r0, #0",
+      "bx lr",
+    }),
+    ".global my_handler\n.type my_handler, %function\n.balign 4\n\
+     my_handler:\nmov r0, #0\nbx lr\n.size my_handler, . - my_handler\n"
+  );
+  assert_eq!(
+    asm_fn!("f", t32, global = false, pool = true, { "ldr r0, =1", "bx lr" }),
+    ".type f, %function\n.balign 2\n.thumb_func\nf:\nldr r0, =1\nbx \
+     lr\n.ltorg\n.size f, . - f\n"
+  );
+  // it's the header, body, and footer one after the other
+  assert_eq!(
+    asm_fn!("g", align = 32, { "bx lr" }),
+    concat!(a32_fn_header!("g", align = 32), "\nbx lr\n", fn_footer!("g"))
+  );
+
+  assert_eq!(
+    __bracer_error_of!(asm_fn!("f", align = 3, { "bx lr" })),
+    "`align` must be a power of two of at least 4 for a32 code, got 3"
+  );
+  assert_eq!(
+    __bracer_error_of!(asm_fn!("f", size = 3, { "bx lr" })),
+    "unknown argument `size`"
+  );
+  assert_eq!(__bracer_error_of!(asm_fn!("f")), "missing the body");
+}
+
+#[test]
+fn test_balign() {
+  assert_eq!(balign!(4), ".balign 4\n");
+  assert_eq!(balign!(1), ".balign 1\n");
+  assert_eq!(balign!(0x8000), ".balign 32768\n");
+  assert_eq!(balign!(4, 0x00), ".balign 4, 0x00\n");
+  assert_eq!(balign!(32, 255, 16), ".balign 32, 0xFF, 16\n");
+  assert_eq!(balign!(8, , 4), ".balign 8,, 4\n");
+
+  assert_eq!(
+    __bracer_error_of!(balign!(3)),
+    "the alignment must be a power of two from 1 to 32768, got 3"
+  );
+  assert_eq!(
+    __bracer_error_of!(balign!(0)),
+    "the alignment must be a power of two from 1 to 32768, got 0"
+  );
+  assert_eq!(
+    __bracer_error_of!(balign!(65536)),
+    "the alignment must be a power of two from 1 to 32768, got 65536"
+  );
+  assert_eq!(
+    __bracer_error_of!(balign!(4, 0x100)),
+    "the fill must be a byte value, from 0 to 0xFF"
+  );
+  assert_eq!(
+    __bracer_error_of!(balign!(4, 0, 2, 1)),
+    "too many arguments, expected the alignment, fill, and max skip"
+  );
+}
+
+#[test]
+fn test_reserve_zeroed() {
+  let expected = concat!(
+    ".section .bss.iwram,\"aw\",%nobits\n",
+    ".balign 8\n",
+    ".global irq_stack\n",
+    "irq_stack:\n",
+    ".space 0x400\n",
+    ".size irq_stack, . - irq_stack",
+  );
+  assert_eq!(
+    reserve_zeroed!("irq_stack", 0x400, section = ".bss.iwram", align = 8),
+    expected
+  );
+  assert_eq!(
+    reserve_zeroed!("mix_buffer", 1024, flags = "awT"),
+    ".section .bss,\"awT\",%nobits\n.balign 4\n.global mix_buffer\nmix_buffer:\n\
+     .space 0x400\n.size mix_buffer, . - mix_buffer"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(reserve_zeroed!("buf", 0)),
+    "the size must be a positive integer literal that fits in 32 bits"
+  );
+  assert_eq!(
+    __bracer_error_of!(reserve_zeroed!("buf", 4, align = 6)),
+    "the alignment must be a power of two from 1 to 32768, got 6"
+  );
+  assert_eq!(
+    __bracer_error_of!(reserve_zeroed!("buf", 4, section = ".bss,x")),
+    "`.bss,x` is not a valid section name: ',' isn't allowed, only ascii \
+     alphanumerics and `.`, `_`, `-`, `$`"
+  );
+  assert_eq!(
+    __bracer_error_of!(reserve_zeroed!("buf", 4, flags = "awq")),
+    "'q' isn't a known section flag, expected some of `a`, `w`, `x`, `M`, \
+     `S`, `T`"
+  );
+  assert_eq!(__bracer_error_of!(reserve_zeroed!("buf")), "missing the size");
+}
+
+#[test]
+fn test_incbin() {
+  let expected = concat!(
+    ".section .rodata,\"a\",%progbits\n",
+    ".balign 4\n",
+    ".global tiles_data\n",
+    "tiles_data:\n",
+    ".incbin \"assets/tiles.bin\"\n",
+    ".global tiles_data_end\n",
+    "tiles_data_end:\n",
+    ".size tiles_data, tiles_data_end - tiles_data",
+  );
+  assert_eq!(
+    incbin!(
+      "assets/tiles.bin",
+      symbol = "tiles_data",
+      section = ".rodata",
+      align = 4
+    ),
+    expected
+  );
+  // the defaults are the same
+  assert_eq!(incbin!("assets/tiles.bin", symbol = "tiles_data"), expected);
+  assert_eq!(
+    incbin!(
+      "/abs/song.raw",
+      symbol = "song",
+      section = ".rodata.ewram",
+      align = 2
+    ),
+    ".section .rodata.ewram,\"a\",%progbits\n.balign 2\n.global song\nsong:\n\
+     .incbin \"/abs/song.raw\"\n.global song_end\nsong_end:\n.size song, \
+     song_end - song"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(incbin!("a\"b.bin", symbol = "x")),
+    "\"a\\\"b.bin\" can't be used as an `.incbin` path, it must be non-empty \
+     and can't have `\"`, `\\`, or newlines"
+  );
+  assert_eq!(
+    __bracer_error_of!(incbin!("tiles.bin")),
+    "missing `symbol = \"name\"`"
+  );
+  assert_eq!(
+    __bracer_error_of!(incbin!("tiles.bin", symbol = "9tiles")),
+    "`9tiles` is not a valid symbol name"
+  );
+}
+
+#[test]
+fn test_emit_data() {
+  assert_eq!(emit_words!(0x100, 0x200, 0x300), ".word 256, 512, 768\n");
+  assert_eq!(emit_words!(0xFFFF_FFFF, -1), ".word 4294967295, -1\n");
+  assert_eq!(emit_halfwords!(0xFFFF, -0x8000), ".hword 65535, -32768\n");
+  assert_eq!(emit_bytes!(1, 2, 3, 255), ".byte 1, 2, 3, 255\n");
+  assert_eq!(emit_bytes!(0b1010, 0o17, 1_0, -128), ".byte 10, 15, 10, -128\n");
+  assert_eq!(emit_words!(), "");
+  assert_eq!(emit_bytes!(,), "");
+
+  // wrapping onto more lines
+  assert_eq!(
+    emit_bytes!(0, 1, 2, 3, 4, 5, 6, 7, 8, 9),
+    ".byte 0, 1, 2, 3, 4, 5, 6, 7\n.byte 8, 9\n"
+  );
+  assert_eq!(
+    emit_words!(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17),
+    ".word 1, 2, 3, 4, 5, 6, 7, 8\n.word 9, 10, 11, 12, 13, 14, 15, 16\n.word \
+     17\n"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(emit_bytes!(1, 2, 3, 256)),
+    "the value at index 3, `256`, doesn't fit in a byte (-128 to 255)"
+  );
+  assert_eq!(
+    __bracer_error_of!(emit_halfwords!(0x1_0000, -0x8001)),
+    "the value at index 0, `0x1_0000`, doesn't fit in a halfword (-32768 to \
+     65535)\nthe value at index 1, `-0x8001`, doesn't fit in a halfword \
+     (-32768 to 65535)"
+  );
+  assert_eq!(
+    __bracer_error_of!(emit_words!(0x1_0000_0000)),
+    "the value at index 0, `0x1_0000_0000`, doesn't fit in a word \
+     (-2147483648 to 4294967295)"
+  );
+  assert_eq!(
+    __bracer_error_of!(emit_words!(1, x)),
+    "the value at index 1 must be an integer literal"
+  );
+}
+
+#[test]
+fn test_asciz() {
+  assert_eq!(asciz!("hello: %d\n"), ".asciz \"hello: %d\\n\"\n");
+  assert_eq!(
+    asciz!("say \"hi\"\tC:\\dir\r"),
+    ".asciz \"say \\\"hi\\\"\\tC:\\\\dir\\r\"\n"
+  );
+  assert_eq!(asciz!(r"\x41"), ".asciz \"\\\\x41\"\n");
+  assert_eq!(asciz!(""), ".asciz \"\"\n");
+  // braces are escaped for the `asm!` template
+  assert_eq!(asciz!("{x} = {}"), ".asciz \"{{x}} = {{}}\"\n");
+  // control characters and UTF-8 bytes become octal escapes
+  assert_eq!(asciz!("\x01\x7Fé"), ".asciz \"\\001\\177\\303\\251\"\n");
+  assert_eq!(
+    asciz!("msg_hello", "hello"),
+    "msg_hello:\n.asciz \"hello\"\n.size msg_hello, . - msg_hello\n"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(asciz!("a\0b")),
+    "the text can't contain a NUL, `.asciz` adds one at the end"
+  );
+  assert_eq!(
+    __bracer_error_of!(asciz!("1msg", "hello")),
+    "`1msg` is not a valid symbol name"
+  );
+  assert_eq!(
+    __bracer_error_of!(asciz!(5)),
+    "the text must be a string literal"
+  );
+  assert_eq!(
+    __bracer_error_of!(asciz!("a", "b", "c")),
+    "expected at most a label and the text"
+  );
+}
+
+#[test]
+fn test_weak_symbol() {
+  assert_eq!(
+    weak_symbol!("default_irq_handler"),
+    ".weak default_irq_handler\n"
+  );
+  assert_eq!(weak_symbol!("a", "b", "c",), ".weak a\n.weak b\n.weak c\n");
+  assert_eq!(symbol_visibility!("helper", hidden), ".hidden helper\n");
+  assert_eq!(symbol_visibility!("helper", protected), ".protected helper\n");
+  assert_eq!(symbol_visibility!("helper", internal), ".internal helper\n");
+
+  assert_eq!(__bracer_error_of!(weak_symbol!()), "Not enough input");
+  assert_eq!(
+    __bracer_error_of!(weak_symbol!("a", "2b", "c d")),
+    "`2b` is not a valid symbol name\n`c d` is not a valid symbol name"
+  );
+  assert_eq!(
+    __bracer_error_of!(symbol_visibility!("helper", private)),
+    "unknown visibility `private`, expected one of: `hidden`, `protected`, \
+     `internal`"
+  );
+  assert_eq!(
+    __bracer_error_of!(symbol_visibility!("helper")),
+    "expected a symbol name and a visibility"
+  );
+  assert_eq!(
+    __bracer_error_of!(symbol_visibility!(helper, hidden)),
+    "symbol names must be string literals"
+  );
+}
+
+#[test]
+fn test_with_reg_aliases() {
+  assert_eq!(
+    with_reg_aliases! {
+      "count" = "r4", "src" = "r1";
+      "mov count, #0",
+      "ldr r0, [src]",
+    },
+    "count .req r4\nsrc .req r1\nmov count, #0\nldr r0, [src]\n.unreq \
+     src\n.unreq count\n"
+  );
+  assert_eq!(
+    with_reg_aliases! {
+      "tmp" = "{t}";
+      "mov tmp, #1",
+      a32_read_cpsr_to!("r0"),
+    },
+    "tmp .req {t}\nmov tmp, #1\nmrs r0, CPSR\n.unreq tmp\n"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(with_reg_aliases! { "count" = "r4", "COUNT" = "r5"; }),
+    "the alias `COUNT` is given more than once"
+  );
+  assert_eq!(
+    __bracer_error_of!(with_reg_aliases! { "ip" = "r4", "v1" = "r5"; }),
+    "`ip` is already a register name\n`v1` is already a register name"
+  );
+  assert_eq!(
+    __bracer_error_of!(with_reg_aliases! { "1st" = "r4", "x" = "q4"; }),
+    "`1st` is not a valid alias name\n`q4` is not a register name or an \
+     `asm!` substitution like `{temp}`"
+  );
+  assert_eq!(
+    __bracer_error_of!(with_reg_aliases! { "count" = "r4" }),
+    "expected `;` between the aliases and the body lines"
+  );
+  assert_eq!(
+    __bracer_error_of!(with_reg_aliases! { ; "nop" }),
+    "expected at least one `\"alias\" = \"reg\"` before the `;`"
+  );
+}
+
+#[test]
+fn test_set_symbol() {
+  assert_eq!(
+    set_symbol!("__aeabi_memcpy", "fast_memcpy"),
+    ".global __aeabi_memcpy\n.set __aeabi_memcpy, fast_memcpy\n"
+  );
+  assert_eq!(
+    set_symbol!("local_copy", "fast_memcpy", global = false),
+    ".set local_copy, fast_memcpy\n"
+  );
+  assert_eq!(
+    set_symbol!("__aeabi_memcpy", "fast_memcpy", function = true),
+    ".global __aeabi_memcpy\n.type __aeabi_memcpy, %function\n.set \
+     __aeabi_memcpy, fast_memcpy\n"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(set_symbol!("fast_memcpy", "fast_memcpy")),
+    "`fast_memcpy` can't be set as an alias of itself"
+  );
+  assert_eq!(
+    __bracer_error_of!(set_symbol!("a-b", "9c")),
+    "`a-b` is not a valid symbol name\n`9c` is not a valid symbol name"
+  );
+  assert_eq!(__bracer_error_of!(set_symbol!("a")), "Not enough input");
+  assert_eq!(
+    __bracer_error_of!(set_symbol!("a", "b", global = 1)),
+    "`global` must be set as `true` or `false`"
+  );
+}
+
+#[test]
+fn test_gas_macro() {
+  assert_eq!(
+    gas_macro!("save_regs", params = ["first", "last"], {
+      "stmfd sp!, {\\first-\\last}",
+      r"add \first\(), \first, #\@",
+    }),
+    ".macro save_regs first last\nstmfd sp!, {{\\first-\\last}}\nadd \
+     \\first\\(), \\first, #\\@\n.endm\n"
+  );
+  assert_eq!(
+    gas_macro!("halt", { "1:", "b 1b" }),
+    ".macro halt\n1:\nb 1b\n.endm\n"
+  );
+  assert_eq!(
+    gas_macro!("two", params = [], { "nop\nnop" }),
+    ".macro two\nnop\nnop\n.endm\n"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(gas_macro!("save_regs", params = ["first"], {
+      r"stmfd sp!, {\first-\last}",
+    })),
+    "`\\last` is used, but `last` is not a parameter"
+  );
+  assert_eq!(
+    __bracer_error_of!(gas_macro!("m", params = ["1st", "x", "x"], {})),
+    "`1st` is not a valid parameter name\nthe parameter `x` is given more \
+     than once"
+  );
+  assert_eq!(
+    __bracer_error_of!(gas_macro!("m", { emit_literal_pool!() })),
+    "the body lines must be string literals, so that the parameter uses can \
+     be checked"
+  );
+  assert_eq!(__bracer_error_of!(gas_macro!("m")), "missing the body");
+}
+
+#[test]
+fn test_irp_each() {
+  assert_eq!(
+    irp_each!("reg", ["r0", "r1", "r2", "r3"], {
+      "mov \\reg, #0",
+    }),
+    ".irp reg, r0, r1, r2, r3\nmov \\reg, #0\n.endr\n"
+  );
+  assert_eq!(
+    irp_each!("offset", [0, 4, 0x10, -8], {
+      r"str r1, [r0, #\offset]",
+      "push {r1}",
+    }),
+    ".irp offset, 0, 4, 16, -8\nstr r1, [r0, #\\offset]\npush {{r1}}\n.endr\n"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(irp_each!("reg", [], { "nop" })),
+    "the list can't be empty"
+  );
+  assert_eq!(
+    __bracer_error_of!(irp_each!("2reg", ["r0"], { "nop" })),
+    "`2reg` is not a valid loop variable name"
+  );
+  assert_eq!(
+    __bracer_error_of!(irp_each!("reg", ["r0, r1", ""], { "nop" })),
+    "list items must be integer literals or string literals without commas \
+     or whitespace\nlist items must be integer literals or string literals \
+     without commas or whitespace"
+  );
+  assert_eq!(
+    __bracer_error_of!(irp_each!("reg", ["r0"], { emit_literal_pool!() })),
+    "the body lines must be string literals, other macros can't be used \
+     since their output can't use `\\reg`"
+  );
+}
+
+#[test]
+fn test_cfi_fn() {
+  assert_eq!(
+    cfi_fn!(saves = "r4-r7, lr", frame = 16, {
+      "push {{r4-r7, lr}}",
+      "sub sp, sp, #16",
+    }),
+    ".cfi_startproc\n.cfi_def_cfa_offset 36\n.cfi_offset r4, -20\n.cfi_offset \
+     r5, -16\n.cfi_offset r6, -12\n.cfi_offset r7, -8\n.cfi_offset r14, \
+     -4\npush {{r4-r7, lr}}\nsub sp, sp, #16\n.cfi_endproc\n"
+  );
+  // the order of the list doesn't matter, the push order is by number
+  assert_eq!(
+    cfi_fn!(saves = "lr, r8, r4", { "push {{r4, r8, lr}}" }),
+    ".cfi_startproc\n.cfi_def_cfa_offset 12\n.cfi_offset r4, -12\n.cfi_offset \
+     r8, -8\n.cfi_offset r14, -4\npush {{r4, r8, lr}}\n.cfi_endproc\n"
+  );
+  assert_eq!(
+    cfi_fn!(frame = 8, { "sub sp, sp, #8" }),
+    ".cfi_startproc\n.cfi_def_cfa_offset 8\nsub sp, sp, #8\n.cfi_endproc\n"
+  );
+  assert_eq!(cfi_fn!({ "bx lr" }), ".cfi_startproc\nbx lr\n.cfi_endproc\n");
+
+  assert_eq!(
+    __bracer_error_of!(cfi_fn!(frame = 6, { "bx lr" })),
+    "`frame` must be a multiple of 4, got 6"
+  );
+  assert_eq!(
+    __bracer_error_of!(cfi_fn!(saves = "r4, sp", { "bx lr" })),
+    "the `saves` list can't hold `sp` or `pc`"
+  );
+  assert_eq!(__bracer_error_of!(cfi_fn!(saves = "r4")), "missing the body");
+}
+
+#[test]
+fn test_assert_code_size_le() {
+  assert_eq!(
+    assert_code_size_le!("my_stub", 32),
+    ".if (. - my_stub) > 32\n.error \"my_stub exceeds 32 bytes\"\n.endif\n"
+  );
+  assert_eq!(
+    assert_code_size_le!("vector_stub", 0x20),
+    ".if (. - vector_stub) > 32\n.error \"vector_stub exceeds 32 \
+     bytes\"\n.endif\n"
+  );
+  assert_eq!(size_marker!("my_stub"), "my_stub:\n");
+
+  assert_eq!(
+    __bracer_error_of!(assert_code_size_le!("my stub", 0)),
+    "`my stub` is not a valid symbol name\nthe size must be a positive \
+     integer literal"
+  );
+  assert_eq!(
+    __bracer_error_of!(assert_code_size_le!("my_stub")),
+    "expected a symbol name and a size, eg: `\"my_stub\", 32`"
+  );
+  assert_eq!(
+    __bracer_error_of!(size_marker!("1abc")),
+    "`1abc` is not a valid symbol name"
+  );
+}
+
+#[test]
+fn test_arch_directive() {
+  assert_eq!(arch_directive!(arch = "armv4t"), ".arch armv4t\n");
+  assert_eq!(arch_directive!(cpu = "arm7tdmi"), ".cpu arm7tdmi\n");
+  assert_eq!(arch_directive!(fpu = "softvfp"), ".fpu softvfp\n");
+  assert_eq!(
+    arch_directive!(arch = "armv5te", cpu = "arm946e-s", fpu = "vfpv2"),
+    ".arch armv5te\n.cpu arm946e-s\n.fpu vfpv2\n"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(arch_directive!(arch = "armv4tt")),
+    "unknown arch name `armv4tt`, did you mean `armv4t`?"
+  );
+  assert_eq!(
+    __bracer_error_of!(arch_directive!(cpu = "arm7tdm1")),
+    "unknown cpu name `arm7tdm1`, did you mean `arm7tdmi`?"
+  );
+  assert_eq!(
+    __bracer_error_of!(arch_directive!(arch = "armv4t", arch = "armv5te")),
+    "`arch` is given more than once"
+  );
+  assert_eq!(
+    __bracer_error_of!(arch_directive!(isa = "a32")),
+    "unknown argument `isa`, expected `arch`, `cpu`, or `fpu`"
+  );
+  assert_eq!(__bracer_error_of!(arch_directive!()), "Not enough input");
+}
+
+#[test]
+fn test_jump_table() {
+  assert_eq!(
+    jump_table!(
+      "r0",
+      ["case_zero", "case_one", "case_two"],
+      default = "case_bad"
+    ),
+    "cmp r0, #3\naddlo pc, pc, r0, lsl #2\nb case_bad\nb case_zero\nb \
+     case_one\nb case_two"
+  );
+  assert_eq!(
+    jump_table!(r4, ["only"], default = "bad", a32),
+    "cmp r4, #1\naddlo pc, pc, r4, lsl #2\nb bad\nb only"
+  );
+
+  assert_eq!(
+    __bracer_error_of!(jump_table!("pc", ["a"], default = "b")),
+    "`pc` can't be used, it's the program counter"
+  );
+  assert_eq!(
+    __bracer_error_of!(jump_table!("sp", ["a"], default = "b")),
+    "`sp` can't be used, it's the stack pointer"
+  );
+  assert_eq!(
+    __bracer_error_of!(jump_table!("r0", ["a", "1b"], default = "c d")),
+    "`1b` is not a valid symbol name\n`c d` is not a valid symbol name"
+  );
+  assert_eq!(
+    __bracer_error_of!(jump_table!("r0", [], default = "b")),
+    "the case list can't be empty"
+  );
+  assert_eq!(
+    __bracer_error_of!(jump_table!("r0", ["a"])),
+    "missing `default = \"label\"`, where an out of range index branches to"
+  );
+  assert_eq!(
+    __bracer_error_of!(jump_table!("r0", ["a"], default = "b", t32)),
+    "`t32` isn't supported yet, there's no `add pc` with a shifted register \
+     in t32, so the table needs a different approach"
+  );
+}
+
+#[test]
+fn test_clz_emulate() {
+  assert_eq!(
+    clz_emulate!("r0" = "r1", scratch = "r2"),
+    "mov r2, r1\nmov r0, #0\ncmp r2, #0x10000\nmovlo r2, r2, lsl #16\naddlo \
+     r0, r0, #16\ncmp r2, #0x1000000\nmovlo r2, r2, lsl #8\naddlo r0, r0, \
+     #8\ncmp r2, #0x10000000\nmovlo r2, r2, lsl #4\naddlo r0, r0, #4\ncmp r2, \
+     #0x40000000\nmovlo r2, r2, lsl #2\naddlo r0, r0, #2\ncmp r2, \
+     #0x80000000\naddlo r0, r0, #1\ncmp r2, #0\naddeq r0, r0, #1"
+  );
+  assert_eq!(clz_emulate!("r0" = "r1", arch = v5), "clz r0, r1");
+  assert_eq!(clz_emulate!(r3 = r3, scratch = "r2", arch = v5), "clz r3, r3");
+
+  assert_eq!(
+    __bracer_error_of!(clz_emulate!("r0" = "r1", scratch = "r1")),
+    "`scratch` must differ from the source and destination"
+  );
+  assert_eq!(
+    __bracer_error_of!(clz_emulate!("r0" = "r0", scratch = "r2")),
+    "the destination and source must differ, `r0` is the count while the \
+     source is still needed"
+  );
+  assert_eq!(
+    __bracer_error_of!(clz_emulate!("r0" = "sp", scratch = "r2")),
+    "`sp` can't be used, it's the stack pointer"
+  );
+  assert_eq!(
+    __bracer_error_of!(clz_emulate!("r0" = "r1")),
+    "missing `scratch = \"rN\"`, which is needed unless `arch = v5`"
+  );
+  assert_eq!(
+    __bracer_error_of!(clz_emulate!("r0" = "r1", arch = v6)),
+    "`arch` must be `v4` or `v5`"
+  );
+}
+
+#[test]
+fn test_byte_reverse() {
+  assert_eq!(
+    byte_reverse!("r0" = "r1", scratch = "r2"),
+    "eor r2, r1, r1, ror #16\nbic r2, r2, #0xFF0000\nmov r0, r1, ror #8\neor \
+     r0, r0, r2, lsr #8"
+  );
+  // in place
+  assert_eq!(
+    byte_reverse!("r4", scratch = "r12"),
+    "eor r12, r4, r4, ror #16\nbic r12, r12, #0xFF0000\nmov r4, r4, ror \
+     #8\neor r4, r4, r12, lsr #8"
+  );
+  assert_eq!(byte_reverse!(r0 = r1, arch = v6), "rev r0, r1");
+  assert_eq!(
+    byte_reverse16!("r0" = "r1", scratch = "r2"),
+    "and r2, r1, #0xFF\nmov r0, r1, lsr #8\nand r0, r0, #0xFF\norr r0, r0, \
+     r2, lsl #8"
+  );
+  assert_eq!(
+    byte_reverse16!("r3", scratch = "r2"),
+    "and r2, r3, #0xFF\nmov r3, r3, lsr #8\nand r3, r3, #0xFF\norr r3, r3, \
+     r2, lsl #8"
+  );
+  assert_eq!(byte_reverse16!("r3", arch = v6), "rev16 r3, r3");
+
+  assert_eq!(
+    __bracer_error_of!(byte_reverse!("r0" = "r1", scratch = "r0")),
+    "`scratch` must differ from the source and destination"
+  );
+  assert_eq!(
+    __bracer_error_of!(byte_reverse16!("sp", scratch = "r2")),
+    "`sp` can't be used, it's the stack pointer"
+  );
+  assert_eq!(
+    __bracer_error_of!(byte_reverse!("r0" = "pc", scratch = "r2")),
+    "`pc` can't be used, it's the program counter"
+  );
+  assert_eq!(
+    __bracer_error_of!(byte_reverse!("r0")),
+    "missing `scratch = \"rN\"`, which is needed unless `arch = v6`"
+  );
+}
+
+#[test]
+fn test_output_spans() {
+  // the output literal carries the span of the input it came from, so an
+  // assembler error about it points at the user's code.
+  assert_eq!(__bracer_span_of!(a32_read_spsr_to!("r0")), "\"r0\"");
+  assert_eq!(
+    __bracer_span_of!(put_fn_in_section!(".iwram.text")),
+    "\".iwram.text\""
+  );
+  assert_eq!(
+    __bracer_span_of!(when!(("r0" == "r1")[1]{ "nop" })),
+    "(\"r0\" == \"r1\")"
+  );
+  assert_eq!(
+    __bracer_span_of!(align_dispatch!(
+      ptr = "r0",
+      other = "r1",
+      scratch = "r2",
+      labels = { word = 1, half = 2 }
+    )),
+    "ptr"
+  );
+  assert_eq!(
+    __bracer_span_of!(sat_add_s32!(
+      dst = "r0",
+      a = "r1",
+      b = "r2",
+      arch = v5te
+    )),
+    "dst"
+  );
+  assert_eq!(__bracer_span_of!(reg_abs!("r3")), "\"r3\"");
+  assert_eq!(__bracer_span_of!(swap_regs!("r0", "r1")), "\"r0\"");
+  assert_eq!(
+    __bracer_span_of!(clz_emulate!("r0" = "r1", scratch = "r2")),
+    "\"r0\""
+  );
+  assert_eq!(__bracer_span_of!(byte_reverse!("r4", scratch = "r12")), "\"r4\"");
+}